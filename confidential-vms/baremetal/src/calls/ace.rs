@@ -6,5 +6,5 @@ use crate::error::Error;
 pub const KVM_ACE_EXTID: usize = 0x509999;
 
 pub fn load_all_pages() -> Result<usize, Error> {
-    super::ecall(KVM_ACE_EXTID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::LoadAllPagesFailed())
+    super::ecall(KVM_ACE_EXTID, 0, 0, 0, 0, 0, 0, 0).map_err(|_| Error::LoadAllPagesFailed())
 }
\ No newline at end of file
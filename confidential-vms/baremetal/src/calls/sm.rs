@@ -3,16 +3,386 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::error::Error;
 
-const ACE_EXTID: usize = 0x510000;
+// shares the definition of the security monitor, see ACE_EXT_ID in the top-level Makefile
+#[path = "../../../../security-monitor/src/build_config.rs"]
+mod build_config;
+pub use build_config::ACE_EXT_ID as ACE_EXTID;
 
 const ACE_ESM_FID: usize = 1000;
 const ACE_SHARE_PAGE_FID: usize = 2000;
+const ACE_MEMORY_REGIONS_FID: usize = 2001;
+const ACE_ATTESTATION_KEY_FID: usize = 2002;
+const ACE_CONVERT_TO_PRIVATE_FID: usize = 2003;
+const ACE_FLUSH_GUEST_TLB_FID: usize = 2004;
+const ACE_FLUSH_GUEST_TLB_ALL_FID: usize = 2005;
+const ACE_SET_SECURE_TIMER_FID: usize = 2006;
+const ACE_EXPIRED_SECURE_TIMERS_FID: usize = 2007;
+const ACE_ACCEPT_PAGE_FID: usize = 2008;
+const ACE_SEAL_FID: usize = 2009;
+const ACE_UNSEAL_FID: usize = 2010;
+const ACE_VM_INFO_FID: usize = 2011;
+const ACE_REMAP_PAGE_FID: usize = 2012;
+const ACE_MEASUREMENTS_FID: usize = 2013;
+const ACE_PAGE_STATUS_FID: usize = 2015;
+const ACE_SHARED_MEMCPY_FID: usize = 2016;
+const ACE_ACKNOWLEDGE_REVOCATION_FID: usize = 2017;
+const ACE_FAULT_RECORDS_FID: usize = 2018;
+const ACE_LOG_EVENT_FID: usize = 2019;
+const ACE_EVENT_LOG_FID: usize = 2020;
+const ACE_PIN_PRIVATE_FID: usize = 2021;
+const ACE_UNPIN_PRIVATE_FID: usize = 2022;
+const ACE_READ_COUNTER_FID: usize = 2023;
+const ACE_INCREMENT_COUNTER_FID: usize = 2024;
+const ACE_SHARE_BARRIER_FID: usize = 2025;
+const ACE_CLEAR_EXTENDED_STATE_FID: usize = 2026;
 
-pub fn esm() -> Result<usize, Error> {
-    super::ecall(ACE_EXTID, ACE_ESM_FID, 0, 0, 0, 0, 0).map_err(|_| Error::EsmError())
+// pages are copied to the confidential memory when accessed for the first time
+pub const ESM_DEFERRED_IMPORT: usize = 1 << 0;
+
+// the upper half of the flags is the number of 4KiB pages of the confidential memory reserved for the VM at its creation
+pub const ESM_RESERVED_PAGES_SHIFT: usize = 32;
+pub const ESM_RESERVED_PAGES: usize = 256;
+// the largest reservation the security monitor admits unless the hypervisor approves another limit
+pub const ESM_MAX_RESERVED_PAGES: usize = 4096;
+
+// maximum number of pages shared with the hypervisor at the same time, 0 selects the security monitor's default
+pub const ESM_MAX_SHARED_PAGES: usize = 32;
+
+// maximum number of harts of a confidential VM supported by the security monitor
+pub const MAX_HARTS_PER_VM: usize = 32;
+
+// magic value starting the image manifest signed by the VM owner
+pub const IMAGE_MANIFEST_MAGIC: u64 = 0x4143_452d_494d_4147;
+
+// pages can be shared only within the region given to the ESM call, a region of size 0 permits sharing any page.
+// The VM supplying the image manifest (0 for none) is launched only if its pages match the manifest. The device tree
+// (0 for none) is measured at the creation, it is passed in t0 because the other arguments occupy all argument registers.
+pub fn esm(flags: usize, max_shared_pages: usize, shareable_paddr: usize, shareable_size: usize, number_of_harts: usize, image_manifest_paddr: usize, fdt_paddr: usize) -> Result<usize, Error> {
+    let (error, value): (usize, usize);
+    unsafe { core::arch::asm!("ecall", in("a0") flags, in("a1") max_shared_pages, in("a2") shareable_paddr, in("a3") shareable_size, in("a4") number_of_harts, in("a5") image_manifest_paddr, in("t0") fdt_paddr, in("a6") ACE_ESM_FID, in("a7") ACE_EXTID, lateout("a0") error, lateout("a1") value) };
+    match error {
+        0 => Ok(value),
+        _ => Err(Error::EsmError()),
+    }
 }
 
 pub fn share_page(paddr: usize, number_of_pages: usize) -> Result<usize, Error> {
-    super::ecall(ACE_EXTID, ACE_SHARE_PAGE_FID, paddr, number_of_pages, 0, 0, 0).map_err(|_| Error::SharePageError())
+    super::ecall(ACE_EXTID, ACE_SHARE_PAGE_FID, paddr, number_of_pages, 0, 0, 0, 0).map_err(|_| Error::SharePageError())
+}
+
+pub fn memory_regions(buffer_paddr: usize, number_of_entries: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_MEMORY_REGIONS_FID, buffer_paddr, number_of_entries, 0, 0, 0, 0).map_err(|_| Error::MemoryRegionsError())
+}
+
+pub fn attestation_key(buffer_paddr: usize, buffer_size: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_ATTESTATION_KEY_FID, buffer_paddr, buffer_size, 0, 0, 0, 0).map_err(|_| Error::AttestationKeyError())
+}
+
+pub fn convert_to_private(paddr: usize, number_of_pages: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_CONVERT_TO_PRIVATE_FID, paddr, number_of_pages, 0, 0, 0, 0).map_err(|_| Error::ConvertToPrivateError())
+}
+
+pub fn flush_guest_tlb(paddr: usize, size: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_FLUSH_GUEST_TLB_FID, paddr, size, 0, 0, 0, 0).map_err(|_| Error::FlushGuestTlbError())
+}
+
+pub fn flush_guest_tlb_all() -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_FLUSH_GUEST_TLB_ALL_FID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::FlushGuestTlbError())
+}
+
+// secure timers are maintained by the security monitor, the deadline usize::MAX cancels the timer
+pub const MAX_SECURE_TIMERS: usize = 4;
+
+pub fn set_secure_timer(index: usize, deadline: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_SET_SECURE_TIMER_FID, index, deadline, 0, 0, 0, 0).map_err(|_| Error::SecureTimerError())
+}
+
+// returns the flags of secure timers that expired since the last call, bit i denoting the i-th timer
+pub fn expired_secure_timers() -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_EXPIRED_SECURE_TIMERS_FID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::SecureTimerError())
+}
+
+// pages imported on the first access must be accepted before the confidential VM relies on their content
+pub fn accept_page(paddr: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_ACCEPT_PAGE_FID, paddr, 0, 0, 0, 0, 0).map_err(|_| Error::AcceptPageError())
+}
+
+// the sealed blob is larger than the data by the nonce and the authentication tag
+pub const SEAL_OVERHEAD: usize = 24 + 16;
+
+// returns the size of the sealed blob, nothing is written if it does not fit in the output buffer
+pub fn seal(plaintext_paddr: usize, len: usize, out_paddr: usize, out_cap: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_SEAL_FID, plaintext_paddr, len, out_paddr, out_cap, 0, 0).map_err(|_| Error::SealError())
+}
+
+// fails if the blob was modified or sealed by a confidential VM with different measurements
+pub fn unseal(blob_paddr: usize, len: usize, out_paddr: usize, out_cap: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_UNSEAL_FID, blob_paddr, len, out_paddr, out_cap, 0, 0).map_err(|_| Error::UnsealError())
 }
 
+// moves the confidential page to an address that is not mapped, the content of the page is not copied
+pub fn remap_page(old_paddr: usize, new_paddr: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_REMAP_PAGE_FID, old_paddr, new_paddr, 0, 0, 0, 0).map_err(|_| Error::RemapPageError())
+}
+
+// the security monitor extends measurement registers with SHA-256 or SHA-512, selected when it is built
+pub const MAX_MEASUREMENT_SIZE: usize = 64;
+pub const MEASUREMENT_ALGORITHM_SHA256: usize = 0x000B;
+pub const MEASUREMENT_ALGORITHM_SHA512: usize = 0x000D;
+
+// the algorithm is recorded in the page owned by the security monitor
+pub fn measurement_algorithm() -> usize {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    const MEASUREMENT_ALGORITHM_OFFSET: usize = 96;
+    unsafe { ((MONITOR_PAGE_ADDRESS + MEASUREMENT_ALGORITHM_OFFSET) as *const u64).read_volatile() as usize }
+}
+
+pub fn measurement_size() -> usize {
+    match measurement_algorithm() {
+        MEASUREMENT_ALGORITHM_SHA256 => 32,
+        _ => MAX_MEASUREMENT_SIZE,
+    }
+}
+
+// returns the measurement register extended with the given data like the security monitor does
+pub fn extend_measurement(measurement: &[u8], data: &[&[u8]]) -> alloc::vec::Vec<u8> {
+    use sha2::{Digest, Sha256, Sha512};
+    match measurement_algorithm() {
+        MEASUREMENT_ALGORITHM_SHA256 => {
+            let mut hasher = Sha256::new();
+            hasher.update(measurement);
+            data.iter().for_each(|chunk| hasher.update(chunk));
+            hasher.finalize().to_vec()
+        },
+        _ => {
+            let mut hasher = Sha512::new();
+            hasher.update(measurement);
+            data.iter().for_each(|chunk| hasher.update(chunk));
+            hasher.finalize().to_vec()
+        },
+    }
+}
+
+// the report of the measurement registers starts with the tag of the measurement algorithm
+pub const MEASUREMENT_REPORT_HEADER_SIZE: usize = 8;
+
+// returns the measurement register with the given index from the report
+pub fn report_measurement(report: &[u8], index: usize) -> &[u8] {
+    let size = measurement_size();
+    &report[MEASUREMENT_REPORT_HEADER_SIZE + index * size..][..size]
+}
+
+// returns the size of the report of all measurement registers, nothing is written if it does not fit in the buffer
+pub fn measurements(buffer_paddr: usize, buffer_size: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_MEASUREMENTS_FID, buffer_paddr, buffer_size, 0, 0, 0, 0).map_err(|_| Error::MeasurementsError())
+}
+
+// on request, the measurement registers are followed by the size of the event log as a 64-bit value and the event log
+pub const MEASUREMENTS_INCLUDE_EVENT_LOG: usize = 1;
+
+pub fn measurements_with_event_log(buffer_paddr: usize, buffer_size: usize) -> Result<usize, Error> {
+    let flags = MEASUREMENTS_INCLUDE_EVENT_LOG;
+    super::ecall(ACE_EXTID, ACE_MEASUREMENTS_FID, buffer_paddr, buffer_size, flags, 0, 0, 0)
+        .map_err(|_| Error::MeasurementsError())
+}
+
+// status of the page containing the address, the hypervisor can access only shared pages
+pub const PAGE_STATUS_UNMAPPED: usize = 0;
+pub const PAGE_STATUS_PRIVATE: usize = 1;
+pub const PAGE_STATUS_SHARED: usize = 2;
+
+pub fn page_status(paddr: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_PAGE_STATUS_FID, paddr, 0, 0, 0, 0, 0).map_err(|_| Error::PageStatusError())
+}
+
+// the security monitor copies at most this many bytes in one call
+pub const MAX_SHARED_MEMCPY_SIZE: usize = 64 * 1024;
+
+// both buffers must be shared, the destination writable. Overlapping buffers are copied like memmove
+pub fn shared_memcpy(dst_paddr: usize, src_paddr: usize, len: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_SHARED_MEMCPY_FID, dst_paddr, src_paddr, len, 0, 0, 0).map_err(|_| Error::SharedMemcpyError())
+}
+
+// returns the confidential VM id, the calling confidential hart id as seen by the security monitor, and how many times
+// the calling confidential hart returned to the hypervisor
+pub fn vm_info() -> Result<(usize, usize, usize), Error> {
+    let (error, vm_id, hart_id, hart_exits): (usize, usize, usize, usize);
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a6") ACE_VM_INFO_FID,
+            in("a7") ACE_EXTID,
+            lateout("a0") error,
+            lateout("a1") vm_id,
+            lateout("a2") hart_id,
+            lateout("a3") hart_exits,
+        )
+    };
+    match error {
+        0 => Ok((vm_id, hart_id, hart_exits)),
+        _ => Err(Error::VmInfoError()),
+    }
+}
+
+// error code returned by the security monitor when it rejects a call
+pub const SM_ERROR_CODE: usize = 0x1000;
+
+// calls in the ACE extension are serviced by the security monitor, which rejects unknown function ids
+pub fn ace_call(fid: usize) -> Result<usize, usize> {
+    super::ecall(ACE_EXTID, fid, 0, 0, 0, 0, 0, 0)
+}
+
+// calls in extensions not virtualized by the security monitor are serviced by the hypervisor
+pub fn hypervisor_call(extid: usize, fid: usize) -> Result<usize, usize> {
+    super::ecall(extid, fid, 0, 0, 0, 0, 0, 0)
+}
+
+// the hypervisor sets the VS-level timer interrupt pending in hvip when the timer expires
+const SBI_TIME_EXTID: usize = 0x54494D45;
+const SBI_TIME_SET_TIMER_FID: usize = 0;
+
+pub fn set_timer(stime_value: usize) -> Result<usize, Error> {
+    super::ecall(SBI_TIME_EXTID, SBI_TIME_SET_TIMER_FID, stime_value, 0, 0, 0, 0, 0).map_err(|_| Error::MaskedInterruptError())
+}
+
+// legacy SBI v0.1 calls that the security monitor translates into calls to the extensions replacing them or rejects
+const SBI_LEGACY_CONSOLE_PUTCHAR_EXTID: usize = 0x01;
+const SBI_LEGACY_CONSOLE_GETCHAR_EXTID: usize = 0x02;
+pub const SBI_ERR_NOT_SUPPORTED: usize = -2isize as usize;
+
+pub fn legacy_console_putchar(character: u8) -> Result<usize, usize> {
+    super::ecall(SBI_LEGACY_CONSOLE_PUTCHAR_EXTID, 0, character as usize, 0, 0, 0, 0, 0)
+}
+
+pub fn legacy_console_getchar() -> Result<usize, usize> {
+    super::ecall(SBI_LEGACY_CONSOLE_GETCHAR_EXTID, 0, 0, 0, 0, 0, 0, 0)
+}
+
+// the security monitor answers the SBI base extension calls of confidential VMs with its own identity
+const SBI_BASE_EXTID: usize = 0x10;
+const SBI_BASE_GET_IMPL_ID_FID: usize = 1;
+const SBI_BASE_PROBE_EXTENSION_FID: usize = 3;
+const SBI_BASE_GET_MVENDORID_FID: usize = 4;
+pub const ACE_IMPL_ID: usize = 0x414345;
+
+pub fn sbi_impl_id() -> Result<usize, Error> {
+    super::ecall(SBI_BASE_EXTID, SBI_BASE_GET_IMPL_ID_FID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::SbiIdentityError())
+}
+
+// returns a non-zero value if the extension is available
+pub fn sbi_probe_extension(extid: usize) -> Result<usize, Error> {
+    super::ecall(SBI_BASE_EXTID, SBI_BASE_PROBE_EXTENSION_FID, extid, 0, 0, 0, 0, 0).map_err(|_| Error::AceExtensionError())
+}
+
+pub fn sbi_mvendorid() -> Result<usize, Error> {
+    super::ecall(SBI_BASE_EXTID, SBI_BASE_GET_MVENDORID_FID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::SbiIdentityError())
+}
+
+// the page owned by the security monitor records the lowest revoked shared page that is not yet acknowledged
+pub fn pending_revocation() -> Option<usize> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    const PENDING_REVOCATION_OFFSET: usize = 104;
+    let value = unsafe { ((MONITOR_PAGE_ADDRESS + PENDING_REVOCATION_OFFSET) as *const u64).read_volatile() as usize };
+    match value & 1 {
+        0 => None,
+        _ => Some(value & !1),
+    }
+}
+
+// the guest must stop using the revoked shared page before acknowledging, the page is unmapped afterwards
+pub fn acknowledge_revocation(paddr: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_ACKNOWLEDGE_REVOCATION_FID, paddr, 0, 0, 0, 0, 0).map_err(|_| Error::SharedPageRevocationError())
+}
+
+// the security monitor rejects harts the confidential VM did not declare in the ESM call, all others reach the hypervisor
+const SBI_HSM_EXTID: usize = 0x48534D;
+const SBI_HSM_HART_START_FID: usize = 0;
+pub const SBI_ERR_INVALID_PARAM: usize = -3isize as usize;
+pub const SBI_ERR_ALREADY_AVAILABLE: usize = -6isize as usize;
+
+pub fn hart_start(hart_id: usize, start_addr: usize, opaque: usize) -> Result<usize, usize> {
+    super::ecall(SBI_HSM_EXTID, SBI_HSM_HART_START_FID, hart_id, start_addr, opaque, 0, 0, 0)
+}
+
+// faults detected by the security monitor while completing operations asynchronously and security violations, each
+// record is a triple of (code, address, context) 64-bit values
+pub const FAULT_SHARE_PAGE_FAILED: u64 = 1;
+pub const FAULT_INVALID_HYPERVISOR_PAGE: u64 = 2;
+pub const FAULT_SHARE_PAGE_REJECTED: u64 = 3;
+pub const FAULT_SHARED_PAGE_REVOKED: u64 = 4;
+pub const FAULT_ACCESS_VIOLATION: u64 = 5;
+
+pub fn fault_records(buffer: &mut [[u64; 3]]) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_FAULT_RECORDS_FID, buffer.as_mut_ptr() as usize, buffer.len(), 0, 0, 0, 0)
+        .map_err(|_| Error::FaultRecordsError())
+}
+
+// the confidential VM can extend any measurement register, the runtime measurement register is reserved for its events.
+// Every event in the log is serialized as the register index and the description size, both 64-bit little-endian
+// values, followed by the description
+pub const RUNTIME_MEASUREMENT: usize = 3;
+pub const NUMBER_OF_MEASUREMENTS: usize = 4;
+
+pub fn log_event(description: &[u8], measurement_index: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_LOG_EVENT_FID, description.as_ptr() as usize, description.len(), measurement_index, 0, 0, 0)
+        .map_err(|_| Error::EventLogError())
+}
+
+// returns the size of the event log, nothing is written if it does not fit in the buffer
+pub fn event_log(buffer: &mut [u8]) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_EVENT_LOG_FID, buffer.as_mut_ptr() as usize, buffer.len(), 0, 0, 0, 0).map_err(|_| Error::EventLogError())
+}
+
+// a pinned confidential page can neither be shared nor moved away until it is unpinned
+pub fn pin_private(paddr: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_PIN_PRIVATE_FID, paddr, 0, 0, 0, 0, 0).map_err(|_| Error::PinPrivateError())
+}
+
+pub fn unpin_private(paddr: usize) -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_UNPIN_PRIVATE_FID, paddr, 0, 0, 0, 0, 0).map_err(|_| Error::PinPrivateError())
+}
+
+// returns the value of the monotonic counter and whether it survives recreating the VM and rebooting the platform
+pub fn read_counter(index: usize) -> Result<(u64, bool), Error> {
+    counter_call(ACE_READ_COUNTER_FID, index)
+}
+
+// returns the incremented value of the monotonic counter and whether it survives recreating the VM and rebooting the
+// platform
+pub fn increment_counter(index: usize) -> Result<(u64, bool), Error> {
+    counter_call(ACE_INCREMENT_COUNTER_FID, index)
+}
+
+fn counter_call(fid: usize, index: usize) -> Result<(u64, bool), Error> {
+    let (error, value, is_persistent): (usize, usize, usize);
+    unsafe { core::arch::asm!("ecall", inlateout("a0") index => error, lateout("a1") value, lateout("a2") is_persistent, in("a6") fid, in("a7") ACE_EXTID) };
+    match error {
+        0 => Ok((value as u64, is_persistent != 0)),
+        _ => Err(Error::MonotonicCounterError()),
+    }
+}
+
+// returns the number of shares that other harts requested and the hypervisor has not answered yet
+pub fn share_barrier() -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_SHARE_BARRIER_FID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::ShareBarrierError())
+}
+
+// zeroes the floating-point registers of the calling hart, e.g., before yielding after a cryptographic operation
+pub fn clear_extended_state() -> Result<usize, Error> {
+    super::ecall(ACE_EXTID, ACE_CLEAR_EXTENDED_STATE_FID, 0, 0, 0, 0, 0, 0).map_err(|_| Error::ClearExtendedStateError())
+}
+
+// the security monitor writes the byte passed in a0 to its console without forwarding the call to the hypervisor
+const SBI_DBCN_EXTID: usize = 0x4442434E;
+const SBI_DBCN_WRITE_FID: usize = 0;
+const SBI_DBCN_WRITE_BYTE_FID: usize = 2;
+
+pub fn console_write_byte(byte: u8) -> Result<usize, Error> {
+    super::ecall(SBI_DBCN_EXTID, SBI_DBCN_WRITE_BYTE_FID, byte as usize, 0, 0, 0, 0, 0)
+        .map_err(|_| Error::ConsoleWriteByteError())
+}
+
+// the hypervisor reads the buffer, so the security monitor rejects buffers that are not entirely shared
+pub fn console_write(paddr: usize, len: usize) -> Result<usize, usize> {
+    super::ecall(SBI_DBCN_EXTID, SBI_DBCN_WRITE_FID, len, paddr, 0, 0, 0, 0)
+}
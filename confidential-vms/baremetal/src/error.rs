@@ -15,4 +15,112 @@ pub enum Error {
     DmaNotInitialized(),
     #[error("Load all pages failed")]
     LoadAllPagesFailed(),
+    #[error("Memory regions call failed")]
+    MemoryRegionsError(),
+    #[error("Attestation key call failed")]
+    AttestationKeyError(),
+    #[error("Counter is not monotonic")]
+    CounterNotMonotonic(),
+    #[error("Memory layout does not match the security monitor's view")]
+    MemoryLayoutMismatch(),
+    #[error("Monitor page is not mapped or can be remapped")]
+    MonitorPageError(),
+    #[error("Address overflow was not detected")]
+    AddressOverflowNotDetected(),
+    #[error("Deferred page was not imported")]
+    DeferredImportError(),
+    #[error("Convert to private call failed")]
+    ConvertToPrivateError(),
+    #[error("Sharing beyond the limit was not rejected")]
+    ShareLimitNotEnforced(),
+    #[error("SBI implementation identity is not virtualized")]
+    SbiIdentityError(),
+    #[error("Sharing policy is not enforced or not measured")]
+    SharingPolicyError(),
+    #[error("Hart limit is not enforced")]
+    HartLimitError(),
+    #[error("Guest TLB flush failed or accepted an invalid range")]
+    FlushGuestTlbError(),
+    #[error("Image not matching the manifest was launched")]
+    ImageManifestNotEnforced(),
+    #[error("Ecall was routed to the wrong handler")]
+    EcallRoutingError(),
+    #[error("Masked interrupt was delivered or not kept pending")]
+    MaskedInterruptError(),
+    #[error("Secure timers did not expire in the order of their deadlines")]
+    SecureTimerError(),
+    #[error("Accepting the page did not follow its import")]
+    AcceptPageError(),
+    #[error("No trap frames for hart {0}")]
+    TrapFrameError(usize),
+    #[error("Sealing the data failed")]
+    SealError(),
+    #[error("Unsealing the data failed")]
+    UnsealError(),
+    #[error("ESM accepted invalid arguments")]
+    EsmArgumentsError(),
+    #[error("VM info does not match the confidential VM")]
+    VmInfoError(),
+    #[error("Load into the zero register modified the registers")]
+    ZeroRegisterError(),
+    #[error("Call accepted a buffer that is not entirely confidential")]
+    PartiallyMappedBufferError(),
+    #[error("Remapping the page failed")]
+    RemapPageError(),
+    #[error("Interrupt enable bits changed across a call to the hypervisor")]
+    InterruptEnableRoundTripError(),
+    #[error("Confidential VM admitted with a reservation exceeding the confidential memory")]
+    MemoryReservationError(),
+    #[error("Started hart found the hart id {0} instead of its id within the confidential VM")]
+    HartIdError(usize),
+    #[error("Shared info page does not match the layout written by the security monitor")]
+    SharedInfoError(),
+    #[error("Page owned by the confidential VM was shared in place")]
+    ShareConfidentialPageError(),
+    #[error("Repeated MMIO load returned an inconsistent value")]
+    RepeatedMmioLoadError(),
+    #[error("Security monitor accessed memory it must not access")]
+    GuestAccessError(),
+    #[error("Storing the zero register wrote a non-zero value")]
+    StoreFromZeroRegisterError(),
+    #[error("Measurement registers error")]
+    MeasurementsError(),
+    #[error("FDT measurement error")]
+    FdtMeasurementError(),
+    #[error("Time is not monotonic")]
+    TimeNotMonotonic(),
+    #[error("Page status error")]
+    PageStatusError(),
+    #[error("ACE extension error")]
+    AceExtensionError(),
+    #[error("Legacy SBI error")]
+    LegacySbiError(),
+    #[error("Measurement algorithm error")]
+    MeasurementAlgorithmError(),
+    #[error("Shared memcpy error")]
+    SharedMemcpyError(),
+    #[error("Shared page revocation error")]
+    SharedPageRevocationError(),
+    #[error("Page scrubbing error")]
+    PageScrubbingError(),
+    #[error("Illegal instruction emulation error")]
+    IllegalInstructionEmulationError(),
+    #[error("Hart start error")]
+    HartStartError(),
+    #[error("Fault records error")]
+    FaultRecordsError(),
+    #[error("Event log error")]
+    EventLogError(),
+    #[error("Pin private error")]
+    PinPrivateError(),
+    #[error("Paging mode change error")]
+    PagingModeChangeError(),
+    #[error("Monotonic counter error")]
+    MonotonicCounterError(),
+    #[error("Share barrier error")]
+    ShareBarrierError(),
+    #[error("Clear extended state error")]
+    ClearExtendedStateError(),
+    #[error("Console write byte error")]
+    ConsoleWriteByteError(),
 }
\ No newline at end of file
@@ -35,7 +35,31 @@ static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::<32>::empty();
 static mut DMA_PADDR: Option<AtomicUsize> = None;
 static mut SCRATCH_PAGE: Option<crate::hal::ScratchPage> = None;
 const UART_BASE_ADDRESS: usize = 0x1000_0000;
-pub static mut TRAP_FRAME: [[trap::TrapFrame; 8]; 4] = [[trap::TrapFrame::zero(); 8]; 4];
+// the import mode is selected at build time, so both modes can be tested with the same tests
+const ESM_IMPORT_MODE: usize = match cfg!(feature = "deferred_import") {
+    true => crate::calls::sm::ESM_DEFERRED_IMPORT,
+    false => 0,
+};
+const DEFERRED_PAGE_PATTERN: u64 = 0xdefe_44ed_0000_0000;
+// this page is not accessed before the deferred import test, so its content is still in the hypervisor's memory
+static mut DEFERRED_PAGE: DeferredPage = DeferredPage([DEFERRED_PAGE_PATTERN; 512]);
+
+#[repr(C, align(4096))]
+struct DeferredPage([u64; 512]);
+
+const REMAPPED_PAGE_PATTERN: u64 = 0x4e4d_4150_0000_0000;
+// the test moves this page to another address and back, so it must not share its page with other data
+static mut REMAPPED_PAGE: RemappedPage = RemappedPage([0; 512]);
+
+#[repr(C, align(4096))]
+struct RemappedPage([u64; 512]);
+
+// root of the Sv39 page table that identity maps the lower half of the address space with 1 GiB pages, except for the
+// gigapage below the code, which aliases the code
+static mut SV39_ROOT_PAGE_TABLE: Sv39PageTable = Sv39PageTable([0; 512]);
+
+#[repr(C, align(4096))]
+struct Sv39PageTable([u64; 512]);
 
 extern "C" {
     fn _stack_start();
@@ -46,37 +70,143 @@ extern "C" {
     fn _dma_end();
     fn _heap_start();
     fn _heap_size();
+    fn _secondary_start();
 }
 
+// a hart started with the SBI HSM extension stores the hart id and the opaque value it finds in a0 and a1
+#[no_mangle]
+static STARTED_HART_ID: AtomicUsize = AtomicUsize::new(usize::MAX);
+#[no_mangle]
+static STARTED_HART_OPAQUE: AtomicUsize = AtomicUsize::new(0);
+
 #[no_mangle]
-extern "C" fn init(_hart_id: usize, fdt_paddr: usize) {
+extern "C" fn init(hart_id: usize, fdt_paddr: usize) {
     let mut uart = Uart::new(UART_BASE_ADDRESS);
     init_memory(&mut uart);    
-    init_trap();
+    init_trap(hart_id).expect("Trap initialization failed");
 
-    crate::calls::sm::esm().expect("ESM failed");
+    let (shareable_paddr, shareable_size) = unsafe { (_dma_start as usize, _dma_end as usize - _dma_start as usize) };
+    let esm_flags = |reserved_pages: usize| ESM_IMPORT_MODE | reserved_pages << crate::calls::sm::ESM_RESERVED_PAGES_SHIFT;
+    // pages imported on the first access cannot be measured at the creation, so only the eagerly copied VM passes its
+    // device tree. The hypervisor maps the pages of the device tree when the VM touches them.
+    let measured_fdt_paddr = match cfg!(feature = "deferred_import") {
+        true => 0,
+        false => {
+            fdt(fdt_paddr).iter().step_by(4096).for_each(|byte| unsafe { core::ptr::read_volatile(byte); });
+            fdt_paddr
+        },
+    };
+    let esm = |number_of_harts| crate::calls::sm::esm(esm_flags(crate::calls::sm::ESM_RESERVED_PAGES), crate::calls::sm::ESM_MAX_SHARED_PAGES, shareable_paddr, shareable_size, number_of_harts, 0, measured_fdt_paddr);
+    // the security monitor rejects the manifest that is not signed by the owner. The self-test of the security monitor
+    // checks that a correctly signed manifest is accepted and that flipping a single byte of it is detected.
+    let tampered_image = tampered_image_manifest().and_then(|manifest_paddr| {
+        crate::calls::sm::esm(0, crate::calls::sm::ESM_MAX_SHARED_PAGES, shareable_paddr, shareable_size, 1, manifest_paddr, 0)
+    });
+    // the security monitor rejects a confidential VM with more harts than it supports, so the VM is still not confidential
+    let over_hart_limit = esm(crate::calls::sm::MAX_HARTS_PER_VM + 1);
+    // the security monitor does not admit a confidential VM whose reservation exceeds the free confidential memory
+    let over_reservation = crate::calls::sm::esm(esm_flags(u32::MAX as usize), crate::calls::sm::ESM_MAX_SHARED_PAGES, shareable_paddr, shareable_size, 1, 0, 0);
+    // nor a confidential VM whose reservation exceeds the limit approved by the hypervisor, even if the memory is free
+    let over_limit = crate::calls::sm::esm(esm_flags(crate::calls::sm::ESM_MAX_RESERVED_PAGES + 1), crate::calls::sm::ESM_MAX_SHARED_PAGES, shareable_paddr, shareable_size, 1, 0, 0);
+    // the security monitor rejects malformed arguments before it copies the VM to the confidential memory, and an
+    // address that does not point to a device tree after the copy
+    let invalid_esm_arguments = [
+        crate::calls::sm::esm(1 << 31, crate::calls::sm::ESM_MAX_SHARED_PAGES, shareable_paddr, shareable_size, 1, 0, 0),
+        crate::calls::sm::esm(0, crate::calls::sm::ESM_MAX_SHARED_PAGES, usize::MAX - 0xfff, 0x2000, 1, 0, 0),
+        crate::calls::sm::esm(0, crate::calls::sm::ESM_MAX_SHARED_PAGES, shareable_paddr, shareable_size, 1, 0, init as usize),
+    ];
+    esm(crate::calls::sm::MAX_HARTS_PER_VM).expect("ESM failed");
 
     uart.println("Hello IBM from confidential VM!");
+    uart.println(&format!("Deferred import: {}", cfg!(feature = "deferred_import")));
 
     test_exception_delegation(&mut uart);
 
-    match test_base_sbi(&mut uart) {
-        Ok(_) => uart.println("SBI base test: success"),
-        Err(error) => {
-            uart.println(&format!("Error: {:?}", error));
-            uart.println("SBI base test: failed");
-        },
-    };
+    run_test(&mut uart, "Memory regions", test_memory_regions);
+    run_test(&mut uart, "Attestation key", test_attestation_key);
+    run_test(&mut uart, "Virtual counters", test_virtual_counters);
+    run_test(&mut uart, "Deferred import", test_deferred_import);
+
+    // only pages imported on the first access await the acceptance
+    if cfg!(feature = "deferred_import") {
+        run_test(&mut uart, "Accept page", test_accept_page);
+    }
+
+    run_test(&mut uart, "Address overflow", test_address_overflow);
+    run_test(&mut uart, "Monitor page", test_monitor_page);
+    run_test(&mut uart, "Convert to private", test_convert_to_private);
+    run_test(&mut uart, "Flush guest TLB", test_flush_guest_tlb);
+    run_test(&mut uart, "Share limit", test_share_limit);
+    run_test(&mut uart, "Sharing policy", |uart| test_sharing_policy(uart, shareable_paddr, shareable_size));
+    run_test(&mut uart, "Hart limit", |uart| test_hart_limit(uart, over_hart_limit));
+    run_test(&mut uart, "ESM arguments", |uart| test_esm_arguments(uart, &invalid_esm_arguments));
+    run_test(&mut uart, "Image manifest", |uart| test_image_manifest(uart, tampered_image));
+    run_test(&mut uart, "Ecall routing", test_ecall_routing);
+    run_test(&mut uart, "Masked interrupt", test_masked_interrupt);
+    run_test(&mut uart, "Secure timers", test_secure_timers);
+    run_test(&mut uart, "Trap frames", |uart| test_trap_frames(uart, hart_id));
+    run_test(&mut uart, "Sealing", test_sealing);
+    run_test(&mut uart, "Console write byte", test_console_write_byte);
+    run_test(&mut uart, "Clear extended state", test_clear_extended_state);
+    run_test(&mut uart, "Share barrier", test_share_barrier);
+    run_test(&mut uart, "Monotonic counters", test_monotonic_counters);
+    run_test(&mut uart, "Paging mode change", test_paging_mode_change);
+    run_test(&mut uart, "Pin private", test_pin_private);
+    run_test(&mut uart, "Event log", test_event_log);
+
+    // only pages imported on the first access are rejected after the hypervisor allocated the shared page
+    if cfg!(feature = "deferred_import") {
+        run_test(&mut uart, "Fault records", test_fault_records);
+    }
+
+    run_test(&mut uart, "Hart start", |uart| test_hart_start(uart, hart_id));
+    run_test(&mut uart, "Illegal instruction emulation", test_illegal_instruction_emulation);
+    run_test(&mut uart, "Page scrubbing", test_page_scrubbing);
+    run_test(&mut uart, "Shared page revocation", test_shared_page_revocation);
+    run_test(&mut uart, "Shared memcpy", test_shared_memcpy);
+    run_test(&mut uart, "Measurement algorithm", test_measurement_algorithm);
+    run_test(&mut uart, "Legacy SBI", test_legacy_sbi);
+    run_test(&mut uart, "ACE extension", test_ace_extension);
+    run_test(&mut uart, "Page status", test_page_status);
+    run_test(&mut uart, "Monotonic time", test_monotonic_time);
+    run_test(&mut uart, "FDT measurement", |uart| test_fdt_measurement(uart, measured_fdt_paddr));
+    run_test(&mut uart, "Measurements", test_measurements);
+    run_test(&mut uart, "Store from zero register", test_store_from_zero_register);
+    run_test(&mut uart, "Guest access", test_guest_access);
+    run_test(&mut uart, "Repeated MMIO load", test_repeated_mmio_load);
+    run_test(&mut uart, "Share confidential page", test_share_confidential_page);
+    run_test(&mut uart, "Shared info", test_shared_info);
+    run_test(&mut uart, "Stable hart id", |uart| test_stable_hart_id(uart, hart_id));
+    run_test(&mut uart, "Memory reservation", |uart| test_memory_reservation(uart, over_reservation, over_limit));
+    run_test(&mut uart, "Interrupt enable round trip", test_interrupt_enable_round_trip);
+    run_test(&mut uart, "Remap page", test_remap_page);
+    run_test(&mut uart, "Partially mapped buffer", test_partially_mapped_buffer);
+    run_test(&mut uart, "Load into zero register", test_load_into_zero_register);
+    run_test(&mut uart, "VM info", test_vm_info);
+    run_test(&mut uart, "SBI identity", test_sbi_identity);
+    run_test(&mut uart, "SBI base", test_base_sbi);
+    run_test(&mut uart, "Virtio blk", |_| test_virtio(fdt_paddr));
+
+    sbi::system_reset::system_reset(sbi::system_reset::ResetType::Shutdown, sbi::system_reset::ResetReason::NoReason).expect("system reset failed");
+}
 
-    match test_virtio(fdt_paddr) {
-        Ok(_) => uart.println("Virtio blk test: success"),
+// runs the test and prints its result
+fn run_test<E: core::fmt::Debug, F: FnOnce(&mut Uart) -> Result<(), E>>(uart: &mut Uart, name: &str, test: F) {
+    match test(uart) {
+        Ok(_) => uart.println(&format!("{} test: success", name)),
         Err(error) => {
             uart.println(&format!("Error: {:?}", error));
-            uart.println("Virtio blk test: failed");
+            uart.println(&format!("{} test: failed", name));
         },
     };
+}
 
-    sbi::system_reset::system_reset(sbi::system_reset::ResetType::Shutdown, sbi::system_reset::ResetReason::NoReason).expect("system reset failed");
+// takes the given number of consecutive pages from the memory the VM can share with the hypervisor
+fn allocate_dma_pages(number_of_pages: usize) -> Result<usize, Error> {
+    match unsafe { &crate::DMA_PADDR } {
+        Some(v) => Ok(v.fetch_add(4096 * number_of_pages, core::sync::atomic::Ordering::SeqCst)),
+        None => Err(Error::DmaNotInitialized()),
+    }
 }
 
 fn test_exception_delegation(uart: &mut Uart) {
@@ -88,6 +218,1122 @@ fn test_exception_delegation(uart: &mut Uart) {
     uart.println("Success");
 }
 
+fn test_memory_regions(uart: &mut Uart) -> Result<(), Error> {
+    const MAX_REGIONS: usize = 16;
+    let mut regions = [[0u64; 2]; MAX_REGIONS];
+    let number_of_regions = crate::calls::sm::memory_regions(regions.as_mut_ptr() as usize, MAX_REGIONS)?;
+    for region in regions.iter().take(number_of_regions) {
+        uart.println(&format!("Confidential memory 0x{:x}-0x{:x}", region[0], region[0] + region[1]));
+    }
+    // the memory this VM was created with must be reported as confidential by the security monitor.
+    let (memory_start, memory_end) = (_memory_start as usize as u64, _memory_end as usize as u64);
+    let covered = regions.iter().take(number_of_regions).any(|region| region[0] <= memory_start && memory_end <= region[0] + region[1]);
+    match covered {
+        true => Ok(()),
+        false => Err(Error::MemoryLayoutMismatch()),
+    }
+}
+
+fn test_attestation_key(uart: &mut Uart) -> Result<(), Error> {
+    // the first call learns the required buffer size, the second one reads the key
+    let required_size = crate::calls::sm::attestation_key(0, 0)?;
+    let mut buffer = alloc::vec![0u8; required_size];
+    let size = crate::calls::sm::attestation_key(buffer.as_mut_ptr() as usize, buffer.len())?;
+    uart.println(&format!("Attestation public key: {:x?}", &buffer[..core::cmp::min(size, 32)]));
+    match size == required_size && buffer.iter().any(|b| *b != 0) {
+        true => Ok(()),
+        false => Err(Error::AttestationKeyError()),
+    }
+}
+
+fn test_virtual_counters(uart: &mut Uart) -> Result<(), Error> {
+    // reading counters traps in the security monitor that returns values scoped to this confidential VM
+    let read_instret = || {
+        let value: usize;
+        unsafe { core::arch::asm!("rdinstret {0}", out(reg) value) };
+        value
+    };
+    let first = read_instret();
+    let second = read_instret();
+    uart.println(&format!("instret: {} -> {}", first, second));
+    match first <= second {
+        true => Ok(()),
+        false => Err(Error::CounterNotMonotonic()),
+    }
+}
+
+fn test_deferred_import(uart: &mut Uart) -> Result<(), Error> {
+    let page = unsafe { core::ptr::addr_of_mut!(DEFERRED_PAGE.0) as *mut u64 };
+    if cfg!(feature = "deferred_import") {
+        // the first access traps in the security monitor that imports the page with the content provided at the
+        // creation. The page stays unmapped until this VM accepts it, so the load faults. The load is not compressed,
+        // because the trap handler skips 4 bytes.
+        let access_faults = crate::trap::ACCESS_FAULTS.load(core::sync::atomic::Ordering::SeqCst);
+        unsafe {
+            core::arch::asm!(".option push", ".option norvc", "ld {0}, 0({1})", ".option pop", out(reg) _, in(reg) page)
+        };
+        if crate::trap::ACCESS_FAULTS.load(core::sync::atomic::Ordering::SeqCst) != access_faults + 1 {
+            return Err(Error::DeferredImportError());
+        }
+        crate::calls::sm::accept_page(page as usize)?;
+    }
+    let first = unsafe { page.read_volatile() };
+    uart.println(&format!("Deferred page 0x{:x}: 0x{:x}", page as usize, first));
+    if (0..512).any(|i| unsafe { page.add(i).read_volatile() } != DEFERRED_PAGE_PATTERN) {
+        return Err(Error::DeferredImportError());
+    }
+    // the page is now resident in the confidential memory, so stores do not reach the hypervisor
+    (0..512).for_each(|i| unsafe { page.add(i).write_volatile(i as u64) });
+    match (0..512).all(|i| unsafe { page.add(i).read_volatile() } == i as u64) {
+        true => Ok(()),
+        false => Err(Error::DeferredImportError()),
+    }
+}
+
+fn test_accept_page(uart: &mut Uart) -> Result<(), Error> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    // the deferred import test accepted the page, so it cannot be accepted again
+    let page = unsafe { core::ptr::addr_of!(DEFERRED_PAGE) as usize };
+    let accepted_twice = crate::calls::sm::accept_page(page);
+    uart.println(&format!("Accepted page 0x{:x} again: {:?}", page, accepted_twice));
+    // the page owned by the security monitor is never imported
+    let never_imported = crate::calls::sm::accept_page(MONITOR_PAGE_ADDRESS);
+    uart.println(&format!("Never imported page: {:?}", never_imported));
+    match (accepted_twice, never_imported) {
+        (Err(_), Err(_)) => Ok(()),
+        _ => Err(Error::AcceptPageError()),
+    }
+}
+
+fn test_address_overflow(uart: &mut Uart) -> Result<(), Error> {
+    // writing the second value of the region entry to this buffer would wrap around the address space
+    let buffer_paddr = usize::MAX & !(core::mem::size_of::<u64>() - 1);
+    match crate::calls::sm::memory_regions(buffer_paddr, 1) {
+        Ok(_) => Err(Error::AddressOverflowNotDetected()),
+        Err(_) => {
+            uart.println(&format!("Buffer at 0x{:x} rejected", buffer_paddr));
+            Ok(())
+        }
+    }
+}
+
+fn test_monitor_page(uart: &mut Uart) -> Result<(), Error> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    const MONITOR_PAGE_MAGIC: u64 = 0x4143_452d_4d4f_4e49;
+    // the security monitor maps its read-only page at a fixed address in every confidential VM
+    let info = unsafe { core::slice::from_raw_parts(MONITOR_PAGE_ADDRESS as *const u64, 3) };
+    uart.println(&format!("Monitor page: magic 0x{:x}, confidential VM id {}, harts {}", info[0], info[1], info[2]));
+    if info[0] != MONITOR_PAGE_MAGIC {
+        return Err(Error::MonitorPageError());
+    }
+    // neither the confidential VM nor the hypervisor can replace the monitor page with a shared page
+    match crate::calls::sm::share_page(MONITOR_PAGE_ADDRESS, 1) {
+        Ok(_) => Err(Error::MonitorPageError()),
+        Err(_) => Ok(()),
+    }
+}
+
+fn test_convert_to_private(uart: &mut Uart) -> Result<(), Error> {
+    let pages_to_convert = 2;
+    let paddr = allocate_dma_pages(pages_to_convert)?;
+    for i in 0..pages_to_convert {
+        crate::calls::sm::share_page(paddr + i * 4096, 1)?;
+    }
+    let pages = unsafe { core::slice::from_raw_parts_mut(paddr as *mut u64, 512 * pages_to_convert) };
+    pages.iter_mut().for_each(|v| unsafe { (v as *mut u64).write_volatile(0xdead_beef) });
+    // the security monitor does not write confidential data into pages shared with the hypervisor
+    let required_size = crate::calls::sm::attestation_key(0, 0)?;
+    if crate::calls::sm::attestation_key(paddr, required_size).is_ok() {
+        return Err(Error::ConvertToPrivateError());
+    }
+    // a request for more pages than a confidential VM can share is rejected before the security monitor allocates memory
+    if crate::calls::sm::convert_to_private(paddr, usize::MAX / 4096).is_ok() {
+        return Err(Error::ConvertToPrivateError());
+    }
+
+    crate::calls::sm::convert_to_private(paddr, pages_to_convert)?;
+    uart.println(&format!("Converted {} shared pages at 0x{:x} to private", pages_to_convert, paddr));
+    // fresh confidential pages are zeroed and the security monitor accepts them as confidential memory
+    if pages.iter().any(|v| unsafe { (v as *const u64).read_volatile() } != 0) {
+        return Err(Error::ConvertToPrivateError());
+    }
+    crate::calls::sm::attestation_key(paddr, required_size)?;
+    // converting the same pages again fails because they are no longer shared
+    match crate::calls::sm::convert_to_private(paddr, pages_to_convert) {
+        Ok(_) => Err(Error::ConvertToPrivateError()),
+        Err(_) => Ok(()),
+    }
+}
+
+fn test_flush_guest_tlb(uart: &mut Uart) -> Result<(), Error> {
+    let paddr = allocate_dma_pages(1)?;
+    crate::calls::sm::share_page(paddr, 1)?;
+    crate::calls::sm::convert_to_private(paddr, 1)?;
+    // the page was remapped, so the stale translations are removed before the page is used again
+    crate::calls::sm::flush_guest_tlb(paddr, 4096)?;
+    let page = unsafe { core::slice::from_raw_parts(paddr as *const u64, 512) };
+    if page.iter().any(|v| unsafe { (v as *const u64).read_volatile() } != 0) {
+        return Err(Error::FlushGuestTlbError());
+    }
+    // empty ranges and ranges outside the guest address space are rejected
+    if crate::calls::sm::flush_guest_tlb(paddr, 0).is_ok() || crate::calls::sm::flush_guest_tlb(paddr, usize::MAX).is_ok() {
+        return Err(Error::FlushGuestTlbError());
+    }
+    crate::calls::sm::flush_guest_tlb_all()?;
+    uart.println(&format!("Flushed guest TLB after remapping 0x{:x}", paddr));
+    Ok(())
+}
+
+fn test_share_limit(uart: &mut Uart) -> Result<(), Error> {
+    let max_shared_pages = crate::calls::sm::ESM_MAX_SHARED_PAGES;
+    let paddr = allocate_dma_pages(max_shared_pages + 1)?;
+    // other tests returned their shared pages, so the entire quota is available
+    for i in 0..max_shared_pages {
+        crate::calls::sm::share_page(paddr + i * 4096, 1)?;
+    }
+    let extra_paddr = paddr + max_shared_pages * 4096;
+    if crate::calls::sm::share_page(extra_paddr, 1).is_ok() {
+        return Err(Error::ShareLimitNotEnforced());
+    }
+    uart.println(&format!("Sharing page {} rejected", max_shared_pages + 1));
+    // converting a shared page back to private frees quota for a subsequent share
+    crate::calls::sm::convert_to_private(paddr, 1)?;
+    crate::calls::sm::share_page(extra_paddr, 1)?;
+    // return the quota so the virtio driver can share its buffers
+    crate::calls::sm::convert_to_private(paddr + 4096, max_shared_pages)?;
+    Ok(())
+}
+
+fn test_sharing_policy(uart: &mut Uart, shareable_paddr: usize, shareable_size: usize) -> Result<(), Error> {
+    use crate::calls::sm::MAX_MEASUREMENT_SIZE;
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    const SHARING_POLICY_MEASUREMENT_OFFSET: usize = 3 * core::mem::size_of::<u64>();
+    // a page within the declared region can be shared
+    let paddr = allocate_dma_pages(1)?;
+    crate::calls::sm::share_page(paddr, 1)?;
+    crate::calls::sm::convert_to_private(paddr, 1)?;
+    // a page outside the declared region cannot be shared
+    let outside_paddr = unsafe { core::ptr::addr_of!(DEFERRED_PAGE) as usize };
+    if crate::calls::sm::share_page(outside_paddr, 1).is_ok() {
+        return Err(Error::SharingPolicyError());
+    }
+    // the measurement exposed by the security monitor covers the declared region
+    let measurement_size = crate::calls::sm::measurement_size();
+    let expected = crate::calls::sm::extend_measurement(
+        &[0u8; MAX_MEASUREMENT_SIZE][..measurement_size],
+        &[&(shareable_paddr as u64).to_le_bytes(), &((shareable_paddr + shareable_size) as u64).to_le_bytes()],
+    );
+    let measurement = unsafe { core::slice::from_raw_parts((MONITOR_PAGE_ADDRESS + SHARING_POLICY_MEASUREMENT_OFFSET) as *const u8, measurement_size) };
+    uart.println(&format!("Sharing policy measurement: {:x?}", &measurement[..8]));
+    match measurement == expected.as_slice() {
+        true => Ok(()),
+        false => Err(Error::SharingPolicyError()),
+    }
+}
+
+fn test_hart_limit(uart: &mut Uart, over_hart_limit: Result<usize, Error>) -> Result<(), Error> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    if over_hart_limit.is_ok() {
+        return Err(Error::HartLimitError());
+    }
+    // the confidential VM was created with the maximum number of harts
+    let number_of_harts = unsafe { ((MONITOR_PAGE_ADDRESS + 2 * core::mem::size_of::<u64>()) as *const u64).read_volatile() };
+    uart.println(&format!("Confidential VM harts: {}", number_of_harts));
+    match number_of_harts as usize == crate::calls::sm::MAX_HARTS_PER_VM {
+        true => Ok(()),
+        false => Err(Error::HartLimitError()),
+    }
+}
+
+// Builds the image manifest listing a page whose content was modified after it had been hashed. Launching a valid image
+// requires the manifest signed with the owner key provisioned in the security monitor, which is not available here.
+fn tampered_image_manifest() -> Result<usize, Error> {
+    use sha2::{Digest, Sha256};
+    let paddr = allocate_dma_pages(2)?;
+    let page_paddr = paddr + 4096;
+    let page = unsafe { core::slice::from_raw_parts_mut(page_paddr as *mut u8, 4096) };
+    page.fill(0xa5);
+    // the security monitor is built with the default SHA-256 measurement algorithm, which hashes the listed pages
+    let hash = Sha256::digest(&page[..]);
+    page[0] ^= 0xff;
+    let manifest = unsafe { core::slice::from_raw_parts_mut(paddr as *mut u8, 4096) };
+    manifest.fill(0);
+    manifest[0..8].copy_from_slice(&crate::calls::sm::IMAGE_MANIFEST_MAGIC.to_le_bytes());
+    manifest[8..16].copy_from_slice(&1u64.to_le_bytes());
+    manifest[16..24].copy_from_slice(&(page_paddr as u64).to_le_bytes());
+    manifest[24..32].copy_from_slice(&4096u64.to_le_bytes());
+    manifest[32..64].copy_from_slice(hash.as_slice());
+    // the signature following the entry stays zeroed
+    Ok(paddr)
+}
+
+fn test_esm_arguments(uart: &mut Uart, invalid_esm_arguments: &[Result<usize, Error>]) -> Result<(), Error> {
+    // unknown flags and a shareable region overflowing the address space are rejected, the valid ESM call succeeded
+    uart.println(&format!("ESM with invalid arguments: {:?}", invalid_esm_arguments));
+    match invalid_esm_arguments.iter().all(|result| result.is_err()) {
+        true => Ok(()),
+        false => Err(Error::EsmArgumentsError()),
+    }
+}
+
+fn test_image_manifest(uart: &mut Uart, tampered_image: Result<usize, Error>) -> Result<(), Error> {
+    uart.println(&format!("ESM with a tampered image: {:?}", tampered_image));
+    match tampered_image {
+        Ok(_) => Err(Error::ImageManifestNotEnforced()),
+        Err(_) => Ok(()),
+    }
+}
+
+fn test_ecall_routing(uart: &mut Uart) -> Result<(), Error> {
+    const UNKNOWN_FID: usize = 2999;
+    const SBI_TIME_EXTID: usize = 0x54494D45;
+    // the security monitor rejects an unknown function of its own extension
+    let ace_result = crate::calls::sm::ace_call(UNKNOWN_FID);
+    // the hypervisor, not the security monitor, rejects an unknown function of the standard extension
+    let hypervisor_result = crate::calls::sm::hypervisor_call(SBI_TIME_EXTID, UNKNOWN_FID);
+    uart.println(&format!("ACE call: {:?}, hypervisor call: {:?}", ace_result, hypervisor_result));
+    match (ace_result, hypervisor_result) {
+        (Err(crate::calls::sm::SM_ERROR_CODE), Err(error)) if error != crate::calls::sm::SM_ERROR_CODE => Ok(()),
+        _ => Err(Error::EcallRoutingError()),
+    }
+}
+
+fn test_masked_interrupt(uart: &mut Uart) -> Result<(), Error> {
+    const SIE_BIT: usize = 1 << 1;
+    const STIP_BIT: usize = 1 << 5;
+    const SBI_HSM_EXTID: usize = 0x48534D;
+    const SBI_HSM_HART_GET_STATUS_FID: usize = 2;
+    const MAX_RESUMES: usize = 1000;
+    let read_sip = || {
+        let value: usize;
+        unsafe { core::arch::asm!("csrr {0}, sip", out(reg) value) };
+        value
+    };
+    // mask the timer interrupt globally and locally, so the hardware must not deliver it
+    unsafe { core::arch::asm!("csrc sstatus, {0}", "csrc sie, {1}", in(reg) SIE_BIT, in(reg) STIP_BIT) };
+    crate::calls::sm::set_timer(0)?;
+    // the hypervisor injects the expired timer when it resumes this confidential hart after any call it services
+    let pending = (0..MAX_RESUMES).any(|_| {
+        let _ = crate::calls::sm::hypervisor_call(SBI_HSM_EXTID, SBI_HSM_HART_GET_STATUS_FID);
+        read_sip() & STIP_BIT != 0
+    });
+    // enabling the timer interrupt while interrupts are globally disabled leaves it pending
+    unsafe { core::arch::asm!("csrs sie, {0}", in(reg) STIP_BIT) };
+    let still_pending = read_sip() & STIP_BIT != 0;
+    // reprogramming the timer is the only way to clear the pending timer interrupt
+    unsafe { core::arch::asm!("csrc sie, {0}", in(reg) STIP_BIT) };
+    crate::calls::sm::set_timer(usize::MAX)?;
+    unsafe { core::arch::asm!("csrs sstatus, {0}", in(reg) SIE_BIT) };
+    uart.println(&format!("Timer interrupt pending while masked: {}, after enabling in sie: {}", pending, still_pending));
+    match pending && still_pending {
+        true => Ok(()),
+        false => Err(Error::MaskedInterruptError()),
+    }
+}
+
+fn test_secure_timers(uart: &mut Uart) -> Result<(), Error> {
+    const SSIP_BIT: usize = 1 << 1;
+    const DELTA: usize = 1_000_000;
+    let read_time = || {
+        let value: usize;
+        unsafe { core::arch::asm!("rdtime {0}", out(reg) value) };
+        value
+    };
+    // the security monitor rejects timers it does not maintain
+    if crate::calls::sm::set_secure_timer(crate::calls::sm::MAX_SECURE_TIMERS, 0).is_ok() {
+        return Err(Error::SecureTimerError());
+    }
+    let now = read_time();
+    // the timer registered first has the farther deadline
+    crate::calls::sm::set_secure_timer(0, now + 2 * DELTA)?;
+    crate::calls::sm::set_secure_timer(1, now + DELTA)?;
+    let mut expired = 0;
+    let mut first_expired = 0;
+    while expired != 0b11 && read_time() < now + 4 * DELTA {
+        let flags = crate::calls::sm::expired_secure_timers()?;
+        if first_expired == 0 {
+            first_expired = flags;
+        }
+        expired |= flags;
+    }
+    // the expiry also raised the software interrupt, which is masked in this test
+    unsafe { core::arch::asm!("csrc sip, {0}", in(reg) SSIP_BIT) };
+    uart.println(&format!("First expired timers: 0b{:b}, all expired timers: 0b{:b}", first_expired, expired));
+    match first_expired == 0b10 && expired == 0b11 {
+        true => Ok(()),
+        false => Err(Error::SecureTimerError()),
+    }
+}
+
+fn test_trap_frames(uart: &mut Uart, hart_id: usize) -> Result<(), Error> {
+    let frames = trap::trap_frame_for(hart_id)?.len();
+    // harts beyond the reserved trap frames are rejected instead of sharing frames with other harts
+    let rejected = matches!(trap::trap_frame_for(trap::NUM_HARTS), Err(Error::TrapFrameError(_)));
+    uart.println(&format!("Hart {} has {} trap frames, hart {} rejected: {}", hart_id, frames, trap::NUM_HARTS, rejected));
+    match frames == trap::NESTED_TRAPS && rejected {
+        true => Ok(()),
+        false => Err(Error::TrapFrameError(trap::NUM_HARTS)),
+    }
+}
+
+fn test_sealing(uart: &mut Uart) -> Result<(), Error> {
+    // sealing fails if the boot loader did not provide the sealing secret to the security monitor
+    let secret = b"secret persisted by the confidential VM";
+    let mut blob = alloc::vec![0u8; secret.len() + crate::calls::sm::SEAL_OVERHEAD];
+    let sealed_size = crate::calls::sm::seal(secret.as_ptr() as usize, secret.len(), blob.as_mut_ptr() as usize, blob.len())?;
+    let mut unsealed = alloc::vec![0u8; secret.len()];
+    let unsealed_size = crate::calls::sm::unseal(blob.as_ptr() as usize, sealed_size, unsealed.as_mut_ptr() as usize, unsealed.len())?;
+    uart.println(&format!("Sealed {} bytes into {} bytes, unsealed {} bytes", secret.len(), sealed_size, unsealed_size));
+    if sealed_size != blob.len() || unsealed_size != secret.len() || unsealed[..] != secret[..] {
+        return Err(Error::UnsealError());
+    }
+    // flipping a bit of the ciphertext fails the authentication
+    blob[sealed_size / 2] ^= 1;
+    match crate::calls::sm::unseal(blob.as_ptr() as usize, sealed_size, unsealed.as_mut_ptr() as usize, unsealed.len()) {
+        Err(_) => Ok(()),
+        Ok(_) => Err(Error::UnsealError()),
+    }
+}
+
+fn test_console_write_byte(_uart: &mut Uart) -> Result<(), Error> {
+    const SBI_HSM_EXTID: usize = 0x48534D;
+    const SBI_HSM_HART_GET_STATUS_FID: usize = 2;
+    // the security monitor writes every byte to its console sink before it reports success, and it services the call
+    // without returning to the hypervisor, so the exit count of this hart does not change. The security monitor's
+    // console sink self-test checks that the byte in the register is the one that reaches the sink.
+    let (_, _, exits_before) = crate::calls::sm::vm_info()?;
+    let is_written = b"fast path\n".iter().all(|byte| matches!(crate::calls::sm::console_write_byte(*byte), Ok(0)));
+    let (_, _, exits_after) = crate::calls::sm::vm_info()?;
+    // a call forwarded to the hypervisor is counted, so an unchanged count is not due to a stale value
+    let _ = crate::calls::sm::hypervisor_call(SBI_HSM_EXTID, SBI_HSM_HART_GET_STATUS_FID);
+    let (_, _, exits_forwarded) = crate::calls::sm::vm_info()?;
+    match is_written && exits_after == exits_before && exits_forwarded != exits_after {
+        true => Ok(()),
+        false => Err(Error::ConsoleWriteByteError()),
+    }
+}
+
+fn test_clear_extended_state(uart: &mut Uart) -> Result<(), Error> {
+    const SSTATUS_FS_MASK: usize = 0b11 << 13;
+    const SSTATUS_FS_INITIAL: usize = 0b01 << 13;
+    const SSTATUS_FS_CLEAN: usize = 0b10 << 13;
+    const SECRET: usize = 0x5ec2_e75e_c2e7_5ec2;
+    // the floating-point unit is disabled at boot
+    unsafe {
+        core::arch::asm!(
+            "csrs sstatus, {fs}",
+            "fmv.d.x f0, {secret}",
+            "fmv.d.x f10, {secret}",
+            "fmv.d.x f31, {secret}",
+            fs = in(reg) SSTATUS_FS_INITIAL,
+            secret = in(reg) SECRET,
+            out("f0") _,
+            out("f10") _,
+            out("f31") _,
+        )
+    };
+    crate::calls::sm::clear_extended_state()?;
+    let (f0, f10, f31, sstatus): (usize, usize, usize, usize);
+    unsafe {
+        core::arch::asm!(
+            "csrr {sstatus}, sstatus",
+            "fmv.x.d {f0}, f0",
+            "fmv.x.d {f10}, f10",
+            "fmv.x.d {f31}, f31",
+            sstatus = out(reg) sstatus,
+            f0 = out(reg) f0,
+            f10 = out(reg) f10,
+            f31 = out(reg) f31,
+        )
+    };
+    uart.println(&format!("Clear extended state: f0={:x} f10={:x} f31={:x} sstatus={:x}", f0, f10, f31, sstatus));
+    match f0 == 0 && f10 == 0 && f31 == 0 && sstatus & SSTATUS_FS_MASK == SSTATUS_FS_CLEAN {
+        true => Ok(()),
+        false => Err(Error::ClearExtendedStateError()),
+    }
+}
+
+fn test_share_barrier(_uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{convert_to_private, page_status, share_barrier, share_page, PAGE_STATUS_SHARED};
+    const NUMBER_OF_PAGES: usize = 3;
+    const MAX_POLLS: usize = 1000;
+    let paddr = allocate_dma_pages(NUMBER_OF_PAGES)?;
+    (0..NUMBER_OF_PAGES).try_for_each(|page| share_page(paddr + page * 4096, 1).map(|_| ()))?;
+    // the barrier does not wait for shares of other harts, so it is polled until the hypervisor answered all of them
+    let is_drained = (0..MAX_POLLS).any(|_| matches!(share_barrier(), Ok(0)));
+    let is_shared = (0..NUMBER_OF_PAGES).all(|page| matches!(page_status(paddr + page * 4096), Ok(PAGE_STATUS_SHARED)));
+    // return the quota so the virtio driver can share its buffers
+    convert_to_private(paddr, NUMBER_OF_PAGES)?;
+    match is_drained && is_shared {
+        true => Ok(()),
+        false => Err(Error::ShareBarrierError()),
+    }
+}
+
+fn test_monotonic_counters(_uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{increment_counter, read_counter};
+    const COUNTER: usize = 1;
+    let (initial, is_persistent) = read_counter(COUNTER)?;
+    // counters that are not persistent start from zero whenever the confidential VM is created
+    let is_fresh = is_persistent || initial == 0;
+    let (first, _) = increment_counter(COUNTER)?;
+    let (second, _) = increment_counter(COUNTER)?;
+    let (latest, _) = read_counter(COUNTER)?;
+    // a counter that does not exist must be rejected instead of being created
+    let is_index_checked = read_counter(usize::MAX).is_err();
+    match is_fresh && initial < first && first < second && latest == second && is_index_checked {
+        true => Ok(()),
+        false => Err(Error::MonotonicCounterError()),
+    }
+}
+
+fn test_paging_mode_change(uart: &mut Uart) -> Result<(), Error> {
+    // valid, readable, writable, executable, accessed, and dirty
+    const PTE_FLAGS: u64 = 0xcf;
+    const SATP_MODE_SV39: usize = 8 << 60;
+    const GIGAPAGE_SHIFT: usize = 30;
+    const GIGAPAGE_PPN_SHIFT: usize = 18;
+    const PTE_PPN_SHIFT: usize = 10;
+    // the line status register reports an empty transmitter
+    const UART_LSR_THRE: usize = 1 << 5;
+    let code_gigapage = read_uart_line_status as usize >> GIGAPAGE_SHIFT;
+    let alias_gigapage = code_gigapage - 1;
+    let root = unsafe { core::ptr::addr_of_mut!(SV39_ROOT_PAGE_TABLE) };
+    // Sv39 requires the virtual address to be sign-extended from bit 38, so only the lower half is mapped.
+    for index in 0..256 {
+        let gigapage = if index == alias_gigapage { code_gigapage } else { index };
+        unsafe { (*root).0[index] = (((gigapage << GIGAPAGE_PPN_SHIFT) << PTE_PPN_SHIFT) as u64) | PTE_FLAGS };
+    }
+    let aliased_address = read_uart_line_status as usize - (1 << GIGAPAGE_SHIFT);
+    let aliased_read: fn() -> usize = unsafe { core::mem::transmute(aliased_address) };
+    let sv39 = SATP_MODE_SV39 | (root as usize >> 12);
+    unsafe { core::arch::asm!("csrw satp, {0}", "sfence.vma", in(reg) sv39) };
+    // the load executes at a virtual address that is not the confidential VM's physical address of the instruction,
+    // so the security monitor emulates it only if it translates the address with the Sv39 page table.
+    let status_with_sv39 = aliased_read();
+    unsafe { core::arch::asm!("csrw satp, zero", "sfence.vma") };
+    // the same instruction executed at its physical address must be emulated after paging is disabled.
+    let status_without_paging = read_uart_line_status();
+    uart.println(&format!("UART line status with Sv39: 0x{:x}, without paging: 0x{:x}", status_with_sv39, status_without_paging));
+    match status_with_sv39 & UART_LSR_THRE != 0 && status_without_paging & UART_LSR_THRE != 0 {
+        true => Ok(()),
+        false => Err(Error::PagingModeChangeError()),
+    }
+}
+
+// loads from the uart, so the security monitor reads this instruction to emulate the load
+#[inline(never)]
+fn read_uart_line_status() -> usize {
+    const UART_LSR_OFFSET: usize = 5;
+    let status: usize;
+    unsafe { core::arch::asm!("lbu {0}, 0({1})", out(reg) status, in(reg) UART_BASE_ADDRESS + UART_LSR_OFFSET) };
+    status
+}
+
+fn test_pin_private(_uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{convert_to_private, pin_private, remap_page, share_page, unpin_private};
+    let paddr = allocate_dma_pages(2)?;
+    let new_paddr = paddr + 4096;
+    // converting the shared page to private places a confidential page at an address the sharing policy permits
+    share_page(paddr, 1)?;
+    convert_to_private(paddr, 1)?;
+    pin_private(paddr)?;
+    // the pinned page can be neither shared nor moved away, which would leave its address free to be shared
+    let pinned_share = share_page(paddr, 1);
+    let pinned_remap = remap_page(paddr, new_paddr);
+    unpin_private(paddr)?;
+    let unpinned_remap = remap_page(paddr, new_paddr);
+    let unpinned_share = share_page(paddr, 1);
+    if unpinned_share.is_ok() {
+        convert_to_private(paddr, 1)?;
+    }
+    match (pinned_share, pinned_remap, unpinned_remap, unpinned_share) {
+        (Err(_), Err(_), Ok(_), Ok(_)) => Ok(()),
+        _ => Err(Error::PinPrivateError()),
+    }
+}
+
+fn test_event_log(uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{event_log, extend_measurement, log_event, measurements, measurements_with_event_log};
+    use crate::calls::sm::{report_measurement, MAX_MEASUREMENT_SIZE, MEASUREMENT_REPORT_HEADER_SIZE};
+    use crate::calls::sm::{NUMBER_OF_MEASUREMENTS, RUNTIME_MEASUREMENT};
+    let measurement = |index: usize| -> Result<alloc::vec::Vec<u8>, Error> {
+        let buffer = [0u8; MEASUREMENT_REPORT_HEADER_SIZE + NUMBER_OF_MEASUREMENTS * MAX_MEASUREMENT_SIZE];
+        measurements(buffer.as_ptr() as usize, buffer.len())?;
+        Ok(report_measurement(&buffer, index).to_vec())
+    };
+    // the events extend both the runtime measurement register and a register measured at the creation
+    let events: [(&[u8], usize); 2] = [(b"loaded the application", RUNTIME_MEASUREMENT), (b"opened the database", 0)];
+    let initial = [measurement(RUNTIME_MEASUREMENT)?, measurement(0)?];
+    events.iter().try_for_each(|(event, index)| log_event(event, *index).map(|_| ()))?;
+    let rejected = log_event(events[0].0, NUMBER_OF_MEASUREMENTS).is_err();
+    // every register is extended with the hash of the description logged for it
+    let is_extended = events.iter().zip(initial.iter()).try_fold(true, |is_extended, ((event, index), initial)| {
+        let expected = extend_measurement(initial, &[&extend_measurement(&[], &[*event])]);
+        Ok::<bool, Error>(is_extended && measurement(*index)? == expected)
+    })?;
+    let mut log = alloc::vec![0u8; event_log(&mut [])?];
+    event_log(&mut log)?;
+    let expected_log: alloc::vec::Vec<u8> = events
+        .iter()
+        .flat_map(|(event, index)| {
+            [&(*index as u64).to_le_bytes()[..], &(event.len() as u64).to_le_bytes()[..], *event].concat()
+        })
+        .collect();
+    // the report ends with the size of the event log followed by the log, so a verifier can replay it
+    let mut report = alloc::vec![0u8; measurements_with_event_log(0, 0)?];
+    measurements_with_event_log(report.as_mut_ptr() as usize, report.len())?;
+    let is_reported = report.ends_with(&[&(log.len() as u64).to_le_bytes()[..], &log[..]].concat());
+    uart.println(&format!("Event log of {} bytes, reported: {}, rejected: {}", log.len(), is_reported, rejected));
+    match rejected && is_extended && is_reported && log.ends_with(&expected_log) {
+        true => Ok(()),
+        false => Err(Error::EventLogError()),
+    }
+}
+
+fn test_fault_records(uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{fault_records, FAULT_SHARE_PAGE_REJECTED};
+    let mut records = [[0u64; 3]; 4];
+    // drain the records posted by the earlier tests
+    while fault_records(&mut records)? > 0 {}
+    let paddr = allocate_dma_pages(1)?;
+    // the access imports the page, which then awaits the acceptance. The load is not compressed, because the trap
+    // handler skips 4 bytes.
+    unsafe {
+        core::arch::asm!(".option push", ".option norvc", "ld {0}, 0({1})", ".option pop", out(reg) _, in(reg) paddr)
+    };
+    // the security monitor rejects sharing the imported page only after the hypervisor allocated the page
+    let rejected = crate::calls::sm::share_page(paddr, 1);
+    let number_of_records = fault_records(&mut records)?;
+    crate::calls::sm::accept_page(paddr)?;
+    uart.println(&format!("Rejected: {}, fault records: {:x?}", rejected.is_err(), &records[..number_of_records]));
+    match (rejected, number_of_records, records[0]) {
+        (Err(_), 1, [FAULT_SHARE_PAGE_REJECTED, address, _]) if address == paddr as u64 => Ok(()),
+        _ => Err(Error::FaultRecordsError()),
+    }
+}
+
+fn test_hart_start(uart: &mut Uart, hart_id: usize) -> Result<(), Error> {
+    use crate::calls::sm::{hart_start, MAX_HARTS_PER_VM, SBI_ERR_ALREADY_AVAILABLE, SBI_ERR_INVALID_PARAM};
+    // the VM declared MAX_HARTS_PER_VM harts, so starting the running hart reaches the hypervisor, which reports it as
+    // already started, while the hart beyond the declared ones does not exist
+    let declared = hart_start(hart_id, 0, 0);
+    let undeclared = hart_start(MAX_HARTS_PER_VM, 0, 0);
+    uart.println(&format!("Hart start declared {:x?}, undeclared {:x?}", declared, undeclared));
+    match (declared, undeclared) {
+        (Err(SBI_ERR_ALREADY_AVAILABLE), Err(SBI_ERR_INVALID_PARAM)) => Ok(()),
+        _ => Err(Error::HartStartError()),
+    }
+}
+
+fn test_illegal_instruction_emulation(uart: &mut Uart) -> Result<(), Error> {
+    use crate::trap::ILLEGAL_INSTRUCTIONS;
+    use core::sync::atomic::Ordering;
+    let illegal_instructions = || ILLEGAL_INSTRUCTIONS.load(Ordering::SeqCst);
+    let before = illegal_instructions();
+    // stopi belongs to the virtual interrupt file, which the security monitor emulates when the hardware lacks it
+    let stopi: usize;
+    unsafe { core::arch::asm!("csrr {0}, 0xdb0", out(reg) stopi) };
+    let is_emulated = illegal_instructions() == before;
+    // unimp, i.e., the write to the read-only cycle CSR, has no emulation and raises the exception in the guest
+    unsafe { core::arch::asm!(".4byte 0xc0001073") };
+    let is_raised = illegal_instructions() == before + 1;
+    uart.println(&format!("Illegal instruction stopi 0x{:x} emulated {}, unimp raised {}", stopi, is_emulated, is_raised));
+    match is_emulated && is_raised {
+        true => Ok(()),
+        false => Err(Error::IllegalInstructionEmulationError()),
+    }
+}
+
+fn test_page_scrubbing(uart: &mut Uart) -> Result<(), Error> {
+    let pages_to_scrub = 4;
+    let paddr = allocate_dma_pages(pages_to_scrub)?;
+    for i in 0..pages_to_scrub {
+        crate::calls::sm::share_page(paddr + i * 4096, 1)?;
+    }
+    let size = 4096 * pages_to_scrub;
+    (0..size).for_each(|i| unsafe { ((paddr + i) as *mut u8).write_volatile(0xff) });
+    // the private pages replacing the shared ones are scrubbed by the security monitor, every byte must read back as zero
+    crate::calls::sm::convert_to_private(paddr, pages_to_scrub)?;
+    let non_zero_bytes = (0..size).filter(|i| unsafe { ((paddr + i) as *const u8).read_volatile() } != 0).count();
+    uart.println(&format!("Scrubbed {} pages at 0x{:x}, non-zero bytes {}", pages_to_scrub, paddr, non_zero_bytes));
+    match non_zero_bytes {
+        0 => Ok(()),
+        _ => Err(Error::PageScrubbingError()),
+    }
+}
+
+fn test_shared_page_revocation(uart: &mut Uart) -> Result<(), Error> {
+    // only the hypervisor revokes shared pages, so the guest checks that it cannot acknowledge revocations that never happened
+    let paddr = allocate_dma_pages(2)?;
+    crate::calls::sm::share_page(paddr, 1)?;
+    let no_pending_revocation = crate::calls::sm::pending_revocation().is_none();
+    let rejected = crate::calls::sm::acknowledge_revocation(paddr).is_err()
+        && crate::calls::sm::acknowledge_revocation(paddr + 4096).is_err();
+    // the page stays shared after the rejected acknowledgements
+    let still_shared = crate::calls::sm::page_status(paddr)? == crate::calls::sm::PAGE_STATUS_SHARED;
+    uart.println(&format!("Shared page revocation none pending {}, rejected {}, still shared {}", no_pending_revocation, rejected, still_shared));
+    crate::calls::sm::convert_to_private(paddr, 1)?;
+    match no_pending_revocation && rejected && still_shared {
+        true => Ok(()),
+        false => Err(Error::SharedPageRevocationError()),
+    }
+}
+
+fn test_shared_memcpy(uart: &mut Uart) -> Result<(), Error> {
+    const SIZE: usize = 256;
+    const SHIFT: usize = 16;
+    let shared_pages = 2;
+    // the page following the shared pages stays private
+    let paddr = allocate_dma_pages(shared_pages + 1)?;
+    for i in 0..shared_pages {
+        crate::calls::sm::share_page(paddr + i * 4096, 1)?;
+    }
+    let read = |address: usize| unsafe { (address as *const u8).read_volatile() };
+    let write = |address: usize, value: u8| unsafe { (address as *mut u8).write_volatile(value) };
+    (0..SIZE).for_each(|i| write(paddr + i, i as u8));
+    // the copy between the shared pages crosses the page boundary
+    let dst_paddr = paddr + 4096 - SIZE / 2;
+    let copied = crate::calls::sm::shared_memcpy(dst_paddr, paddr, SIZE)?;
+    let non_overlapping = copied == SIZE && (0..SIZE).all(|i| read(dst_paddr + i) == i as u8);
+    // the destination follows the source, so the bytes must be copied before they are overwritten
+    let copied = crate::calls::sm::shared_memcpy(paddr + SHIFT, paddr, SIZE)?;
+    let overlapping = copied == SIZE && (0..SIZE).all(|i| read(paddr + SHIFT + i) == i as u8);
+    // a range reaching the private page is rejected and nothing is copied
+    let partially_shared_paddr = paddr + shared_pages * 4096 - SIZE / 2;
+    (0..SIZE / 2).for_each(|i| write(partially_shared_paddr + i, 0xa5));
+    let rejected = crate::calls::sm::shared_memcpy(partially_shared_paddr, paddr, SIZE).is_err()
+        && crate::calls::sm::shared_memcpy(paddr, partially_shared_paddr, SIZE).is_err()
+        && (0..SIZE / 2).all(|i| read(partially_shared_paddr + i) == 0xa5);
+    // copies larger than the limit are rejected before any page is translated
+    let too_large = crate::calls::sm::MAX_SHARED_MEMCPY_SIZE + 1;
+    let rejected = rejected && crate::calls::sm::shared_memcpy(paddr, paddr, too_large).is_err();
+    uart.println(&format!("Shared memcpy non-overlapping {}, overlapping {}, rejected {}", non_overlapping, overlapping, rejected));
+    crate::calls::sm::convert_to_private(paddr, shared_pages)?;
+    match non_overlapping && overlapping && rejected {
+        true => Ok(()),
+        false => Err(Error::SharedMemcpyError()),
+    }
+}
+
+fn test_measurement_algorithm(uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{MAX_MEASUREMENT_SIZE, MEASUREMENT_ALGORITHM_SHA256, MEASUREMENT_ALGORITHM_SHA512};
+    use crate::calls::sm::{MEASUREMENT_REPORT_HEADER_SIZE, NUMBER_OF_MEASUREMENTS};
+    let algorithm = crate::calls::sm::measurement_algorithm();
+    let measurement_size = crate::calls::sm::measurement_size();
+    // every measurement register has the output length of the recorded algorithm, which is also tagged in the report
+    let buffer = [0u8; MEASUREMENT_REPORT_HEADER_SIZE + NUMBER_OF_MEASUREMENTS * MAX_MEASUREMENT_SIZE];
+    let size = crate::calls::sm::measurements(buffer.as_ptr() as usize, buffer.len())?;
+    let tag = u64::from_le_bytes(buffer[..MEASUREMENT_REPORT_HEADER_SIZE].try_into().unwrap()) as usize;
+    let extended = crate::calls::sm::extend_measurement(&[0u8; MAX_MEASUREMENT_SIZE][..measurement_size], &[]);
+    uart.println(&format!("Measurement algorithm 0x{:x}, tag 0x{:x}, register size {}", algorithm, tag, measurement_size));
+    let known_algorithm = algorithm == MEASUREMENT_ALGORITHM_SHA256 || algorithm == MEASUREMENT_ALGORITHM_SHA512;
+    let is_valid_size = size == MEASUREMENT_REPORT_HEADER_SIZE + NUMBER_OF_MEASUREMENTS * measurement_size;
+    match known_algorithm && tag == algorithm && is_valid_size && extended.len() == measurement_size {
+        true => Ok(()),
+        false => Err(Error::MeasurementAlgorithmError()),
+    }
+}
+
+fn test_legacy_sbi(uart: &mut Uart) -> Result<(), Error> {
+    // the security monitor writes the legacy putchar to its own console and rejects the legacy getchar
+    let putchar = b"legacy\n".iter().try_for_each(|character| crate::calls::sm::legacy_console_putchar(*character).map(|_| ()));
+    let getchar = crate::calls::sm::legacy_console_getchar();
+    uart.println(&format!("Legacy putchar: {:?}, getchar: {:?}", putchar, getchar));
+    match (putchar, getchar) {
+        (Ok(_), Err(crate::calls::sm::SBI_ERR_NOT_SUPPORTED)) => Ok(()),
+        _ => Err(Error::LegacySbiError()),
+    }
+}
+
+fn test_ace_extension(uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::ACE_EXTID;
+    // the security monitor answers the probe only for the id it was built with, so the ids of both sides match
+    let availability = crate::calls::sm::sbi_probe_extension(ACE_EXTID)?;
+    let neighbour_availability = crate::calls::sm::sbi_probe_extension(ACE_EXTID + 1)?;
+    uart.println(&format!("ACE extension 0x{:x}: {}, extension 0x{:x}: {}", ACE_EXTID, availability, ACE_EXTID + 1, neighbour_availability));
+    match availability != 0 && neighbour_availability == 0 {
+        true => Ok(()),
+        false => Err(Error::AceExtensionError()),
+    }
+}
+
+fn test_page_status(uart: &mut Uart) -> Result<(), Error> {
+    use crate::calls::sm::{PAGE_STATUS_PRIVATE, PAGE_STATUS_SHARED, PAGE_STATUS_UNMAPPED};
+    const MAX_REGIONS: usize = 16;
+    let mut regions = [[0u64; 2]; MAX_REGIONS];
+    let number_of_regions = crate::calls::sm::memory_regions(regions.as_mut_ptr() as usize, MAX_REGIONS)?;
+    let memory_end = regions.iter().take(number_of_regions).map(|region| region[0] + region[1]).max().ok_or(Error::MemoryLayoutMismatch())? as usize;
+    let shared_paddr = allocate_dma_pages(1)?;
+    crate::calls::sm::share_page(shared_paddr, 1)?;
+    // the stack is private, pages beyond the end of the memory are not mapped unless they were remapped there
+    let private = crate::calls::sm::page_status(regions.as_ptr() as usize)?;
+    let shared = crate::calls::sm::page_status(shared_paddr + 8)?;
+    let unmapped = crate::calls::sm::page_status(memory_end + 16 * 4096)?;
+    crate::calls::sm::convert_to_private(shared_paddr, 1)?;
+    let converted = crate::calls::sm::page_status(shared_paddr)?;
+    uart.println(&format!("Page status private: {}, shared: {}, unmapped: {}, converted: {}", private, shared, unmapped, converted));
+    match (private, shared, unmapped, converted) {
+        (PAGE_STATUS_PRIVATE, PAGE_STATUS_SHARED, PAGE_STATUS_UNMAPPED, PAGE_STATUS_PRIVATE) => Ok(()),
+        _ => Err(Error::PageStatusError()),
+    }
+}
+
+fn test_monotonic_time(uart: &mut Uart) -> Result<(), Error> {
+    const ROUNDS: usize = 64;
+    const SBI_TIME_EXTID: usize = 0x54494D45;
+    const UNKNOWN_FID: usize = 2999;
+    let read_time = || {
+        let value: usize;
+        unsafe { core::arch::asm!("rdtime {0}", out(reg) value) };
+        value
+    };
+    // every call serviced by the hypervisor lets it schedule this hart on another physical hart, whose time counter
+    // might be skewed, but the security monitor never exposes a lower time
+    let first = read_time();
+    let mut previous = first;
+    let mut is_monotonic = true;
+    for _ in 0..ROUNDS {
+        let _ = crate::calls::sm::hypervisor_call(SBI_TIME_EXTID, UNKNOWN_FID);
+        let time = read_time();
+        is_monotonic &= time >= previous;
+        previous = time;
+    }
+    uart.println(&format!("time: {} -> {}", first, previous));
+    match is_monotonic {
+        true => Ok(()),
+        false => Err(Error::TimeNotMonotonic()),
+    }
+}
+
+// returns the device tree whose total size is the big-endian 32-bit value following the magic value
+fn fdt(fdt_paddr: usize) -> &'static [u8] {
+    let fdt_size = u32::from_be(unsafe { ((fdt_paddr + 4) as *const u32).read_volatile() }) as usize;
+    unsafe { core::slice::from_raw_parts(fdt_paddr as *const u8, fdt_size) }
+}
+
+fn test_fdt_measurement(uart: &mut Uart, fdt_paddr: usize) -> Result<(), Error> {
+    use crate::calls::sm::{MAX_MEASUREMENT_SIZE, MEASUREMENT_REPORT_HEADER_SIZE};
+    const FDT_MEASUREMENT: usize = 2;
+    const MAX_MEASUREMENTS: usize = 8;
+    let measurement_size = crate::calls::sm::measurement_size();
+    let mut buffer = [0u8; MEASUREMENT_REPORT_HEADER_SIZE + MAX_MEASUREMENTS * MAX_MEASUREMENT_SIZE];
+    crate::calls::sm::measurements(buffer.as_mut_ptr() as usize, buffer.len())?;
+    let initial = [0u8; MAX_MEASUREMENT_SIZE];
+    // the device tree the VM sees is the one measured at the creation, a VM that passed no device tree has none measured
+    let expected = match fdt_paddr {
+        0 => initial[..measurement_size].to_vec(),
+        _ => crate::calls::sm::extend_measurement(&initial[..measurement_size], &[fdt(fdt_paddr)]),
+    };
+    let measurement = crate::calls::sm::report_measurement(&buffer, FDT_MEASUREMENT);
+    uart.println(&format!("FDT at 0x{:x} measured: {:x?}", fdt_paddr, &measurement[..8]));
+    match measurement == expected.as_slice() {
+        true => Ok(()),
+        false => Err(Error::FdtMeasurementError()),
+    }
+}
+
+fn test_measurements(uart: &mut Uart) -> Result<(), Error> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    const MEASUREMENT_OFFSET: usize = 24;
+    const MEMORY_MEASUREMENT: usize = 0;
+    const SHARING_POLICY_MEASUREMENT: usize = 1;
+    const MAX_MEASUREMENTS: usize = 8;
+    use crate::calls::sm::{report_measurement, MAX_MEASUREMENT_SIZE, MEASUREMENT_REPORT_HEADER_SIZE};
+    let measurement_size = crate::calls::sm::measurement_size();
+    let mut buffer = [0u8; MEASUREMENT_REPORT_HEADER_SIZE + MAX_MEASUREMENTS * MAX_MEASUREMENT_SIZE];
+    // the empty buffer only returns the required size
+    let required_size = crate::calls::sm::measurements(buffer.as_mut_ptr() as usize, 0)?;
+    let untouched = buffer.iter().all(|byte| *byte == 0);
+    let size = crate::calls::sm::measurements(buffer.as_mut_ptr() as usize, buffer.len())?;
+    let registers_size = size.saturating_sub(MEASUREMENT_REPORT_HEADER_SIZE);
+    let number_of_measurements = registers_size / measurement_size;
+    uart.println(&format!("Required size {}, number of measurement registers {}", required_size, number_of_measurements));
+    // the sharing policy measurement is also published in the page owned by the security monitor
+    let sharing_policy = report_measurement(&buffer, SHARING_POLICY_MEASUREMENT);
+    let published = unsafe { core::slice::from_raw_parts((MONITOR_PAGE_ADDRESS + MEASUREMENT_OFFSET) as *const u8, measurement_size) };
+    // the memory is measured at the creation in both import modes
+    let memory = report_measurement(&buffer, MEMORY_MEASUREMENT);
+    let memory_measured = memory.iter().any(|byte| *byte != 0);
+    let is_valid_size = required_size == size && size <= buffer.len() && registers_size % measurement_size == 0;
+    match untouched && is_valid_size && sharing_policy == published && memory_measured {
+        true => Ok(()),
+        false => Err(Error::MeasurementsError()),
+    }
+}
+
+fn test_store_from_zero_register(uart: &mut Uart) -> Result<(), Error> {
+    const UART_SCRATCH_OFFSET: usize = 7;
+    const PATTERN: u8 = 0xa5;
+    let scratch = (UART_BASE_ADDRESS + UART_SCRATCH_OFFSET) as *mut u8;
+    unsafe { scratch.write_volatile(PATTERN) };
+    let before = unsafe { scratch.read_volatile() };
+    // the security monitor must store zero, not the value it keeps in the slot of the zero register
+    unsafe { core::arch::asm!("sb x0, 0({0})", in(reg) scratch) };
+    let after = unsafe { scratch.read_volatile() };
+    uart.println(&format!("UART scratch register before: 0x{:x}, after storing x0: 0x{:x}", before, after));
+    match before == PATTERN && after == 0 {
+        true => Ok(()),
+        false => Err(Error::StoreFromZeroRegisterError()),
+    }
+}
+
+fn test_guest_access(uart: &mut Uart) -> Result<(), Error> {
+    const MAX_REGIONS: usize = 16;
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    let paddr = allocate_dma_pages(1)?;
+    unsafe { (paddr as *mut u64).write_volatile(0) };
+    let mut regions = [[0u64; 2]; MAX_REGIONS];
+    let number_of_regions = crate::calls::sm::memory_regions(regions.as_mut_ptr() as usize, MAX_REGIONS)?;
+    let memory_end = regions.iter().take(number_of_regions).map(|region| region[0] + region[1]).max().ok_or(Error::MemoryLayoutMismatch())? as usize;
+    // the security monitor writes through the mapping built by moving the page, not through the identity mapping
+    let new_paddr = memory_end + 3 * 4096;
+    crate::calls::sm::remap_page(paddr, new_paddr)?;
+    crate::calls::sm::memory_regions(new_paddr, 1)?;
+    let written = unsafe { [(new_paddr as *const u64).read_volatile(), ((new_paddr + 8) as *const u64).read_volatile()] };
+    // neither the address left unmapped nor the read-only page owned by the security monitor can be written
+    let unmapped = crate::calls::sm::memory_regions(paddr, 1);
+    let read_only = crate::calls::sm::memory_regions(MONITOR_PAGE_ADDRESS, 1);
+    uart.println(&format!("Wrote {:x?} at 0x{:x}, unmapped: {:?}, read-only: {:?}", written, new_paddr, unmapped, read_only));
+    match written == regions[0] && unmapped.is_err() && read_only.is_err() {
+        true => Ok(()),
+        false => Err(Error::GuestAccessError()),
+    }
+}
+
+fn test_repeated_mmio_load(uart: &mut Uart) -> Result<(), Error> {
+    const UART_LSR_OFFSET: usize = 5;
+    const ITERATIONS: usize = 32;
+    const SENTINEL: usize = 0x5a5a_5a5a;
+    for _ in 0..ITERATIONS {
+        let (signed, unsigned, sentinel): (usize, usize, usize);
+        // after the first iteration, the security monitor finds both loads in its instruction cache. The cached entries
+        // must keep the destination register and the sign extension of every instruction apart.
+        unsafe { core::arch::asm!("lb {0}, 0({3})", "lbu {1}, 0({3})", out(reg) signed, out(reg) unsigned, inout("t2") SENTINEL => sentinel, in(reg) UART_BASE_ADDRESS + UART_LSR_OFFSET) };
+        let is_sign_extended = signed == signed as u8 as i8 as isize as usize;
+        if !is_sign_extended || unsigned > u8::MAX as usize || sentinel != SENTINEL {
+            uart.println(&format!("Loaded lb=0x{:x}, lbu=0x{:x}, t2=0x{:x}", signed, unsigned, sentinel));
+            return Err(Error::RepeatedMmioLoadError());
+        }
+    }
+    uart.println(&format!("Emulated {} pairs of MMIO loads", ITERATIONS));
+    Ok(())
+}
+
+fn test_share_confidential_page(uart: &mut Uart) -> Result<(), Error> {
+    const MAX_REGIONS: usize = 16;
+    const PATTERN: u64 = 0x5ec2_e700_0000_0000;
+    let paddr = allocate_dma_pages(1)?;
+    // accessing the page imports it, so the page is owned by the confidential VM and cannot be shared in place
+    unsafe { (paddr as *mut u64).write_volatile(PATTERN) };
+    let rejected = crate::calls::sm::share_page(paddr, 1).is_err();
+    // moving the page away leaves the address not mapped, so it can be shared
+    let mut regions = [[0u64; 2]; MAX_REGIONS];
+    let number_of_regions = crate::calls::sm::memory_regions(regions.as_mut_ptr() as usize, MAX_REGIONS)?;
+    let memory_end = regions.iter().take(number_of_regions).map(|region| region[0] + region[1]).max().ok_or(Error::MemoryLayoutMismatch())? as usize;
+    let new_paddr = memory_end + 2 * 4096;
+    crate::calls::sm::remap_page(paddr, new_paddr)?;
+    crate::calls::sm::share_page(paddr, 1)?;
+    let moved = unsafe { (new_paddr as *const u64).read_volatile() };
+    uart.println(&format!("Sharing confidential page 0x{:x} rejected: {}, moved to 0x{:x} and shared", paddr, rejected, new_paddr));
+    // return the quota so the virtio driver can share its buffers
+    crate::calls::sm::convert_to_private(paddr, 1)?;
+    match rejected && moved == PATTERN {
+        true => Ok(()),
+        false => Err(Error::ShareConfidentialPageError()),
+    }
+}
+
+fn test_shared_info(uart: &mut Uart) -> Result<(), Error> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    const VM_ID_OFFSET: usize = 8;
+    const VERSION_OFFSET: usize = 88;
+    const HART_RECORDS_OFFSET: usize = 112;
+    const HART_RECORD_SIZE: usize = 24;
+    const SHARED_INFO_VERSION: usize = 3;
+    const SSIP_BIT: usize = 1 << 1;
+    const DELTA: usize = 1_000_000;
+    let read = |offset: usize| unsafe { ((MONITOR_PAGE_ADDRESS + offset) as *const u64).read_volatile() as usize };
+    let read_time = || {
+        let value: usize;
+        unsafe { core::arch::asm!("rdtime {0}", out(reg) value) };
+        value
+    };
+    let (vm_id, hart_id, _) = crate::calls::sm::vm_info()?;
+    let record = HART_RECORDS_OFFSET + hart_id * HART_RECORD_SIZE;
+    let layout_matches = read(VERSION_OFFSET) == SHARED_INFO_VERSION && read(VM_ID_OFFSET) == vm_id && read(record) == hart_id;
+    // the security monitor publishes the expired timer when it resumes this hart after the timer interrupt, so the
+    // confidential VM observes the expiry without calling the security monitor
+    let now = read_time();
+    crate::calls::sm::set_secure_timer(0, now + DELTA)?;
+    let mut published = 0;
+    while published == 0 && read_time() < now + 4 * DELTA {
+        published = read(record + 8);
+    }
+    let expired = crate::calls::sm::expired_secure_timers()?;
+    unsafe { core::arch::asm!("csrc sip, {0}", in(reg) SSIP_BIT) };
+    uart.println(&format!("Shared info version {}, hart {} published expired timers 0b{:b}", read(VERSION_OFFSET), read(record), published));
+    // the page is read-only, so the confidential VM cannot share it with the hypervisor or move it
+    let immutable = crate::calls::sm::remap_page(MONITOR_PAGE_ADDRESS, MONITOR_PAGE_ADDRESS - 0x1000).is_err();
+    match layout_matches && published == 0b1 && expired == 0b1 && immutable {
+        true => Ok(()),
+        false => Err(Error::SharedInfoError()),
+    }
+}
+
+fn test_stable_hart_id(uart: &mut Uart, hart_id: usize) -> Result<(), Error> {
+    use core::sync::atomic::Ordering;
+    const SBI_HSM_EXTID: usize = 0x48534D;
+    const SBI_HSM_HART_GET_STATUS_FID: usize = 2;
+    const OPAQUE: usize = 0xace0_0ace;
+    const POLLS: usize = 1000;
+    // the started hart runs on a physical hart other than this one, but it still finds its id within the VM
+    let started_hart_id = (hart_id + 1) % crate::calls::sm::MAX_HARTS_PER_VM;
+    let start_addr = _secondary_start as usize;
+    crate::calls::sm::hart_start(started_hart_id, start_addr, OPAQUE).map_err(|_| Error::HartStartError())?;
+    // every call serviced by the hypervisor gives it a chance to run the started hart
+    let echoed = (0..POLLS).find_map(|_| {
+        let _ = crate::calls::sm::hypervisor_call(SBI_HSM_EXTID, SBI_HSM_HART_GET_STATUS_FID);
+        match STARTED_HART_ID.load(Ordering::SeqCst) {
+            usize::MAX => None,
+            id => Some((id, STARTED_HART_OPAQUE.load(Ordering::SeqCst))),
+        }
+    });
+    uart.println(&format!("Hart {} started with {:x?}", started_hart_id, echoed));
+    match echoed {
+        Some((id, OPAQUE)) if id == started_hart_id => Ok(()),
+        Some((id, _)) => Err(Error::HartIdError(id)),
+        None => Err(Error::HartStartError()),
+    }
+}
+
+fn test_memory_reservation(uart: &mut Uart, over_reservation: Result<usize, Error>, over_limit: Result<usize, Error>) -> Result<(), Error> {
+    let pages_to_convert = 4;
+    if over_reservation.is_ok() || over_limit.is_ok() {
+        return Err(Error::MemoryReservationError());
+    }
+    // pages converted to private after the creation are allocated from the memory reserved for this confidential VM
+    let paddr = allocate_dma_pages(pages_to_convert)?;
+    for i in 0..pages_to_convert {
+        crate::calls::sm::share_page(paddr + i * 4096, 1)?;
+    }
+    crate::calls::sm::convert_to_private(paddr, pages_to_convert)?;
+    uart.println(&format!("Over-sized reservation rejected, converted {} pages at 0x{:x} to private", pages_to_convert, paddr));
+    Ok(())
+}
+
+fn test_interrupt_enable_round_trip(uart: &mut Uart) -> Result<(), Error> {
+    const SIE_BIT: usize = 1 << 1;
+    const SUPERVISOR_INTERRUPTS: usize = 0b001000100010;
+    const SBI_HSM_EXTID: usize = 0x48534D;
+    const SBI_HSM_HART_GET_STATUS_FID: usize = 2;
+    const ROUND_TRIPS: usize = 100;
+    let read_sie = || {
+        let value: usize;
+        unsafe { core::arch::asm!("csrr {0}, sie", out(reg) value) };
+        value
+    };
+    let original_sie = read_sie();
+    // sie aliases the VS-level bits of hie, so the security monitor must preserve them when switching to the hypervisor
+    // and back. Interrupts are masked globally, so enabling them locally does not deliver them.
+    unsafe { core::arch::asm!("csrc sstatus, {0}", "csrw sie, {1}", in(reg) SIE_BIT, in(reg) SUPERVISOR_INTERRUPTS) };
+    let before = read_sie();
+    let preserved = (0..ROUND_TRIPS).all(|_| {
+        let _ = crate::calls::sm::hypervisor_call(SBI_HSM_EXTID, SBI_HSM_HART_GET_STATUS_FID);
+        read_sie() == before
+    });
+    let after = read_sie();
+    unsafe { core::arch::asm!("csrw sie, {0}", "csrs sstatus, {1}", in(reg) original_sie, in(reg) SIE_BIT) };
+    uart.println(&format!("sie before: 0x{:x}, after {} round trips: 0x{:x}", before, ROUND_TRIPS, after));
+    match before != 0 && preserved {
+        true => Ok(()),
+        false => Err(Error::InterruptEnableRoundTripError()),
+    }
+}
+
+fn test_remap_page(uart: &mut Uart) -> Result<(), Error> {
+    const MAX_REGIONS: usize = 16;
+    let mut regions = [[0u64; 2]; MAX_REGIONS];
+    let number_of_regions = crate::calls::sm::memory_regions(regions.as_mut_ptr() as usize, MAX_REGIONS)?;
+    // the first page after the confidential memory of this VM is not mapped
+    let new_paddr = regions.iter().take(number_of_regions).map(|region| region[0] + region[1]).max().ok_or(Error::MemoryLayoutMismatch())? as usize;
+    let old_paddr = unsafe { core::ptr::addr_of_mut!(REMAPPED_PAGE) as usize };
+    unsafe { (old_paddr as *mut u64).write_volatile(REMAPPED_PAGE_PATTERN) };
+    crate::calls::sm::remap_page(old_paddr, new_paddr)?;
+    let moved = unsafe { (new_paddr as *const u64).read_volatile() };
+    // the old address is no longer mapped, so it cannot be moved again
+    let remapped_twice = crate::calls::sm::remap_page(old_paddr, new_paddr + 4096);
+    crate::calls::sm::remap_page(new_paddr, old_paddr)?;
+    let restored = unsafe { (old_paddr as *const u64).read_volatile() };
+    uart.println(&format!("Moved page 0x{:x} to 0x{:x}: 0x{:x}, moved again: {:?}", old_paddr, new_paddr, moved, remapped_twice));
+    match moved == REMAPPED_PAGE_PATTERN && restored == REMAPPED_PAGE_PATTERN && remapped_twice.is_err() {
+        true => Ok(()),
+        false => Err(Error::RemapPageError()),
+    }
+}
+
+fn test_partially_mapped_buffer(uart: &mut Uart) -> Result<(), Error> {
+    const MAX_REGIONS: usize = 16;
+    let mut regions = [[0u64; 2]; MAX_REGIONS];
+    let number_of_regions = crate::calls::sm::memory_regions(regions.as_mut_ptr() as usize, MAX_REGIONS)?;
+    let memory_end = _memory_end as usize as u64;
+    let region = regions.iter().take(number_of_regions).find(|region| region[0] < memory_end && memory_end <= region[0] + region[1]).ok_or(Error::MemoryLayoutMismatch())?;
+    // the output buffer starts in the last confidential page and ends in the page that is not mapped
+    let secret = b"secret persisted by the confidential VM";
+    let output_paddr = (region[0] + region[1]) as usize - 16;
+    let output_size = secret.len() + crate::calls::sm::SEAL_OVERHEAD;
+    uart.println(&format!("Sealing into 0x{:x}-0x{:x}", output_paddr, output_paddr + output_size));
+    if crate::calls::sm::seal(secret.as_ptr() as usize, secret.len(), output_paddr, output_size).is_ok() {
+        return Err(Error::PartiallyMappedBufferError());
+    }
+    // the attestation calls validate their buffers in the same way when the buffer is large enough
+    let measurements_size = crate::calls::sm::measurements(0, 0)?;
+    if crate::calls::sm::measurements(output_paddr, measurements_size).is_ok() {
+        return Err(Error::PartiallyMappedBufferError());
+    }
+    // the hypervisor must not be asked to read a debug console buffer in the confidential memory
+    match crate::calls::sm::console_write(secret.as_ptr() as usize, secret.len()) {
+        Err(crate::calls::sm::SBI_ERR_INVALID_PARAM) => Ok(()),
+        _ => Err(Error::PartiallyMappedBufferError()),
+    }
+}
+
+fn test_load_into_zero_register(uart: &mut Uart) -> Result<(), Error> {
+    const UART_LSR_OFFSET: usize = 5;
+    const SENTINEL: usize = 0x5a5a_5a5a;
+    let (zero, sentinel): (usize, usize);
+    // the load from the UART traps to the security monitor, which must discard the result and skip the load instruction
+    unsafe { core::arch::asm!("lb x0, 0({0})", "mv {1}, x0", in(reg) UART_BASE_ADDRESS + UART_LSR_OFFSET, out(reg) zero, inout("t0") SENTINEL => sentinel) };
+    uart.println(&format!("After the load x0=0x{:x}, t0=0x{:x}", zero, sentinel));
+    match zero == 0 && sentinel == SENTINEL {
+        true => Ok(()),
+        false => Err(Error::ZeroRegisterError()),
+    }
+}
+
+fn test_vm_info(uart: &mut Uart) -> Result<(), Error> {
+    const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+    let (vm_id, hart_id, _) = crate::calls::sm::vm_info()?;
+    uart.println(&format!("Confidential VM id {}, confidential hart id {}", vm_id, hart_id));
+    // the boot hart became the first confidential hart, and the monitor page holds the confidential VM id
+    let monitor_page_vm_id = unsafe { ((MONITOR_PAGE_ADDRESS + core::mem::size_of::<u64>()) as *const u64).read_volatile() };
+    match vm_id == monitor_page_vm_id as usize && hart_id == 0 {
+        true => Ok(()),
+        false => Err(Error::VmInfoError()),
+    }
+}
+
+fn test_sbi_identity(uart: &mut Uart) -> Result<(), Error> {
+    // the security monitor, not the host's firmware, answers the SBI base extension calls
+    let impl_id = crate::calls::sm::sbi_impl_id()?;
+    let mvendorid = crate::calls::sm::sbi_mvendorid()?;
+    uart.println(&format!("Implementation id: 0x{:x}, mvendorid: 0x{:x}", impl_id, mvendorid));
+    match impl_id == crate::calls::sm::ACE_IMPL_ID && mvendorid == 0 {
+        true => Ok(()),
+        false => Err(Error::SbiIdentityError()),
+    }
+}
+
 fn test_base_sbi(uart: &mut Uart) -> Result<(), usize> {
     let spec_version = sbi::base::spec_version();
     uart.println(&format!("Spec version: {:?}", spec_version));
@@ -141,6 +1387,20 @@ fn test_virtio(fdt_paddr: usize) -> Result<(), Error> {
 }
 
 fn init_memory(uart: &mut Uart) {
+    use memory_layout::MemoryRegion;
+    let memory = unsafe { _memory_start as usize.._memory_end as usize };
+    let regions = unsafe {
+        [
+            MemoryRegion { name: "stack", range: _stack_start as usize.._stack_end as usize },
+            MemoryRegion { name: "DMA", range: _dma_start as usize.._dma_end as usize },
+            MemoryRegion { name: "heap", range: _heap_start as usize.._heap_start as usize + _heap_size as usize },
+        ]
+    };
+    // the heap is not initialized yet, so the error is printed without allocating
+    if let Err(error) = memory_layout::validate(&memory, &regions) {
+        println!("Invalid memory layout: {}", error);
+        panic!("Memory initialization failed");
+    }
     unsafe {
         HEAP_ALLOCATOR.lock().init(_heap_start as usize, _heap_size as usize);
         uart.println(&format!("Stack 0x{:x}-0x{:x}", _stack_start as usize, _stack_end as usize));
@@ -152,12 +1412,12 @@ fn init_memory(uart: &mut Uart) {
     crate::calls::ace::load_all_pages().expect("Load all pages call failed");
 }
 
-fn init_trap() {
-    let hart_id: usize = 0;
+fn init_trap(hart_id: usize) -> Result<(), Error> {
+    let ptr: usize = (&mut trap::trap_frame_for(hart_id)?[0] as *mut trap::TrapFrame) as usize;
     unsafe {
-        let ptr: usize = (&mut crate::TRAP_FRAME[hart_id][0] as *mut trap::TrapFrame) as usize;
 		core::arch::asm!("csrw sscratch, {0}", in(reg) ptr);
 	}
+    Ok(())
 }
 
 fn prepare_shared_memory() -> Result<(usize, usize), Error> {
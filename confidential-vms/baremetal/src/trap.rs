@@ -1,7 +1,20 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+use crate::error::Error;
 use core::ptr::null_mut;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// number of harts the VM can boot and the number of trap frames reserved for each of them to handle nested traps
+pub const NUM_HARTS: usize = 4;
+pub const NESTED_TRAPS: usize = 8;
+
+// number of illegal instruction exceptions this VM handled, so tests can check which instructions were emulated
+pub static ILLEGAL_INSTRUCTIONS: AtomicUsize = AtomicUsize::new(0);
+// number of load and store access faults this VM handled, so tests can check which accesses were rejected
+pub static ACCESS_FAULTS: AtomicUsize = AtomicUsize::new(0);
+
+static mut TRAP_FRAMES: [TrapFrame; NUM_HARTS * NESTED_TRAPS] = [TrapFrame::zero(); NUM_HARTS * NESTED_TRAPS];
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -22,6 +35,15 @@ impl TrapFrame {
 	}
 }
 
+// returns the trap frames of the given hart, the first one is used for the outermost trap
+pub fn trap_frame_for(hart_id: usize) -> Result<&'static mut [TrapFrame], Error> {
+    if hart_id >= NUM_HARTS {
+        return Err(Error::TrapFrameError(hart_id));
+    }
+    let start = hart_id * NESTED_TRAPS;
+    Ok(unsafe { &mut TRAP_FRAMES[start..start + NESTED_TRAPS] })
+}
+
 #[no_mangle]
 extern "C" fn trap_handler(
     epc: usize,
@@ -42,14 +64,17 @@ extern "C" fn trap_handler(
         match cause_num {
             2 => {
                 println!("Illegal instruction at 0x{:08x}: 0x{:08x}", epc, tval);
+                ILLEGAL_INSTRUCTIONS.fetch_add(1, Ordering::SeqCst);
                 return_pc += 4;
             }
             5 => {
                 println!("Illegal memory access from 0x{:08x}: 0x{:08x}", epc, tval);
+                ACCESS_FAULTS.fetch_add(1, Ordering::SeqCst);
                 return_pc += 4;
             }
             7 => {
                 println!("Illegal memory access from 0x{:08x}: 0x{:08x}", epc, tval);
+                ACCESS_FAULTS.fetch_add(1, Ordering::SeqCst);
                 return_pc += 4;
             },
             _ => panic!("Unhandled trap -> {}\n", cause_num),
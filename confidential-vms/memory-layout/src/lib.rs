@@ -0,0 +1,103 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+#![cfg_attr(not(test), no_std)]
+
+use core::fmt;
+use core::ops::Range;
+
+// a region of the VM's memory defined by the linker script
+pub struct MemoryRegion {
+    pub name: &'static str,
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LayoutError {
+    RegionOutOfMemory(&'static str),
+    OverlappingRegions(&'static str, &'static str),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::RegionOutOfMemory(name) => write!(f, "Memory region {} is outside the VM's memory", name),
+            LayoutError::OverlappingRegions(first, second) => {
+                write!(f, "Memory regions {} and {} overlap", first, second)
+            }
+        }
+    }
+}
+
+// fails if a region is not entirely within the memory or if two regions overlap, e.g., because of a linker script
+// mistake. Empty regions never overlap with other regions. It does not access memory, so it can check any layout.
+pub fn validate(memory: &Range<usize>, regions: &[MemoryRegion]) -> Result<(), LayoutError> {
+    for (i, region) in regions.iter().enumerate() {
+        if region.range.start > region.range.end || region.range.start < memory.start || region.range.end > memory.end {
+            return Err(LayoutError::RegionOutOfMemory(region.name));
+        }
+        let overlapping = regions[..i].iter().find(|other| {
+            !region.range.is_empty()
+                && !other.range.is_empty()
+                && region.range.start < other.range.end
+                && other.range.start < region.range.end
+        });
+        if let Some(other) = overlapping {
+            return Err(LayoutError::OverlappingRegions(other.name, region.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMORY: Range<usize> = 0x8000_0000..0x8010_0000;
+
+    fn layout(stack: Range<usize>, dma: Range<usize>, heap: Range<usize>) -> [MemoryRegion; 3] {
+        [
+            MemoryRegion { name: "stack", range: stack },
+            MemoryRegion { name: "DMA", range: dma },
+            MemoryRegion { name: "heap", range: heap },
+        ]
+    }
+
+    #[test]
+    fn accepts_disjoint_regions() {
+        let regions = layout(0x8000_0000..0x8001_0000, 0x8001_0000..0x8002_0000, 0x8002_0000..0x8010_0000);
+        assert_eq!(validate(&MEMORY, &regions), Ok(()));
+    }
+
+    #[test]
+    fn rejects_overlapping_regions() {
+        let regions = layout(0x8000_0000..0x8001_0000, 0x8001_0000..0x8002_0000, 0x8001_f000..0x8010_0000);
+        assert_eq!(validate(&MEMORY, &regions), Err(LayoutError::OverlappingRegions("DMA", "heap")));
+    }
+
+    #[test]
+    fn rejects_region_above_memory() {
+        let regions = layout(0x8000_0000..0x8001_0000, 0x8001_0000..0x8002_0000, 0x8002_0000..0x8020_0000);
+        assert_eq!(validate(&MEMORY, &regions), Err(LayoutError::RegionOutOfMemory("heap")));
+    }
+
+    #[test]
+    fn rejects_region_below_memory() {
+        let regions = layout(0x7fff_0000..0x8001_0000, 0x8001_0000..0x8002_0000, 0x8002_0000..0x8010_0000);
+        assert_eq!(validate(&MEMORY, &regions), Err(LayoutError::RegionOutOfMemory("stack")));
+    }
+
+    // an inverted region is what a linker script that swaps the start and end symbols defines.
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn rejects_inverted_region() {
+        let regions = layout(0x8001_0000..0x8000_0000, 0x8001_0000..0x8002_0000, 0x8002_0000..0x8010_0000);
+        assert_eq!(validate(&MEMORY, &regions), Err(LayoutError::RegionOutOfMemory("stack")));
+    }
+
+    #[test]
+    fn ignores_empty_regions() {
+        let regions = layout(0x8000_0000..0x8001_0000, 0x8001_0000..0x8001_0000, 0x8001_0000..0x8010_0000);
+        assert_eq!(validate(&MEMORY, &regions), Ok(()));
+    }
+}
@@ -20,6 +20,7 @@
 #include <sbi/sbi_ipi.h>
 #include <sbi/sbi_misaligned_ldst.h>
 #include <sbi/sbi_timer.h>
+#include <sbi/sbi_system.h>
 
 #include <sbi/sbi_ecall_interface.h>
 
@@ -0,0 +1,38 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+// This file is the single definition of the values provisioned at build time with environment variables. Confidential
+// VMs built in this repository include it with the `path` attribute, so it must not depend on any other module of the
+// security monitor.
+
+// TODO: replace with an identifier registered in the RISC-V fundation. The identifier is provisioned at build time with
+// the ACE_EXT_ID environment variable (a hexadecimal number), which the top-level Makefile also passes to confidential
+// VMs built with it, so both sides use the same identifier.
+pub const ACE_EXT_ID: usize = match option_env!("ACE_EXT_ID") {
+    Some(value) => parse_number(value, 16),
+    None => 0x510000,
+};
+const _: () = assert!(ACE_EXT_ID >> 32 == 0, "ACE_EXT_ID does not fit in 32 bits");
+
+/// Parses the number in the given radix, which is at most 16. Hexadecimal numbers can have the `0x` prefix. An invalid
+/// number breaks the build.
+pub const fn parse_number(value: &str, radix: usize) -> usize {
+    let digits = value.as_bytes();
+    let has_prefix = radix == 16 && digits.len() > 2 && digits[0] == b'0' && (digits[1] == b'x' || digits[1] == b'X');
+    let mut i = if has_prefix { 2 } else { 0 };
+    assert!(i < digits.len(), "the number provisioned at build time is empty");
+    let mut number: usize = 0;
+    while i < digits.len() {
+        let digit = match digits[i] {
+            b'0'..=b'9' => digits[i] - b'0',
+            b'a'..=b'f' => digits[i] - b'a' + 10,
+            b'A'..=b'F' => digits[i] - b'A' + 10,
+            _ => radix as u8,
+        } as usize;
+        assert!(digit < radix, "the number provisioned at build time has an invalid digit");
+        assert!(number <= (usize::MAX - digit) / radix, "the number provisioned at build time overflows");
+        number = number * radix + digit;
+        i += 1;
+    }
+    number
+}
@@ -1,9 +1,15 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::{ConfidentialVmId, ControlData, HardwareHart};
-use crate::core::transformations::{ExposeToConfidentialVm, PendingRequest, TrapReason};
+use crate::core::bounded_lock::{self, MAX_LOCK_ATTEMPTS};
+use crate::core::control_data::{ConfidentialVm, ConfidentialVmId, FaultRecord, HardwareHart, SbiPolicy};
+use crate::core::transformations::{
+    ConsoleWriteByteRequest, ExposeToConfidentialVm, FlushGuestTlbRequest, HartStartRequest, LegacySbiRequest,
+    PageImportRequest, PendingRequest, SbiBaseRequest, SecureTimerRequest, TrapReason,
+};
+use crate::error::{Error, Result};
 use crate::non_confidential_flow::NonConfidentialFlow;
+use spin::MutexGuard;
 
 extern "C" {
     fn exit_to_confidential_vm_asm(confidential_hart_address: usize) -> !;
@@ -20,39 +26,165 @@ impl<'a> ConfidentialFlow<'a> {
 
     pub fn route(self) -> ! {
         use crate::confidential_flow::handlers::{
-            guest_load_page_fault, guest_store_page_fault, hypercall, interrupt, invalid_call, share_page,
+            access_fault, console_write_byte, guest_load_page_fault, guest_store_page_fault, hart_start, hart_stop,
+            hypercall, illegal_instruction, interrupt, invalid_call, legacy_sbi, no_forward_progress, page_import,
+            sbi_base,
         };
         use crate::ACE_EXT_ID;
-        const SHARE_PAGE_FID: usize = 2000;
 
+        // after a panic on another physical hart, the confidential VM's state was cleared with the confidential memory.
+        if crate::core::panic::is_blocked(true, &self.hart.confidential_hart().trap_reason()) {
+            crate::core::panic::stop_hart();
+        }
+        self.hart.confidential_hart_mut().suspend();
+        if let Some((address, cause)) = self.hart.confidential_hart_mut().record_trap() {
+            no_forward_progress::handle(self, address, cause);
+        }
         let confidential_hart = self.hart.confidential_hart();
 
+        // fast path for frequent calls without side effects, whose answers do not require building a request.
+        if let Some(result) = confidential_hart.precomputed_sbi_result() {
+            self.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(result));
+        }
+
         match confidential_hart.trap_reason() {
             TrapReason::Interrupt => interrupt::handle(self),
-            TrapReason::VsEcall(ACE_EXT_ID, SHARE_PAGE_FID) => {
-                share_page::handle(confidential_hart.share_page_request(), self)
+            // calls in the ACE extension target the security monitor and are never forwarded to the hypervisor.
+            TrapReason::VsEcall(ACE_EXT_ID, function_id) => self.route_security_monitor_call(function_id),
+            // standard SBI extensions that the security monitor virtualizes for the confidential VM.
+            TrapReason::VsEcall(SbiBaseRequest::EXTID, _) => {
+                sbi_base::handle(confidential_hart.sbi_base_request(), self)
+            }
+            // legacy SBI v0.1 calls are translated into calls to the extensions that replaced them.
+            TrapReason::VsEcall(extension_id, _) if LegacySbiRequest::is_legacy(extension_id) => {
+                legacy_sbi::handle(confidential_hart.legacy_sbi_request(), self)
+            }
+            // single bytes written to the debug console are passed in a register, so the security monitor writes them
+            // to its own console without a shared buffer.
+            TrapReason::VsEcall(ConsoleWriteByteRequest::EXTID, ConsoleWriteByteRequest::WRITE_BYTE_FID) => {
+                console_write_byte::handle(confidential_hart.console_write_byte_request(), self)
+            }
+            // only harts declared when the confidential VM entered the secure mode can be started.
+            TrapReason::VsEcall(HartStartRequest::EXTID, HartStartRequest::HART_START_FID) => {
+                hart_start::handle(confidential_hart.hart_start_request(), self)
             }
-            TrapReason::VsEcall(ACE_EXT_ID, function_id) => invalid_call::handle(self, ACE_EXT_ID, function_id),
+            TrapReason::VsEcall(HartStartRequest::EXTID, HartStartRequest::HART_STOP_FID) => {
+                hart_stop::handle(confidential_hart.hypercall_request(), self)
+            }
+            // all other calls are hypercalls serviced by the hypervisor.
             TrapReason::VsEcall(_, _) => hypercall::handle(confidential_hart.hypercall_request(), self),
+            TrapReason::GuestInstructionPageFault
+            | TrapReason::GuestLoadPageFault
+            | TrapReason::GuestStorePageFault
+                if self.is_deferred_page(confidential_hart.page_import_request()) =>
+            {
+                page_import::handle(confidential_hart.page_import_request(), self)
+            }
+            TrapReason::GuestInstructionPageFault => invalid_call::handle(self, 0, 0),
+            // MMIO accesses are emulated only for instructions fetched from confidential pages, so the hypervisor
+            // cannot choose the emulated instruction by writing to a shared page.
             TrapReason::GuestLoadPageFault => {
-                guest_load_page_fault::handle(confidential_hart.guest_load_page_fault_request(), self)
+                let request = self
+                    .assure_confidential_instruction()
+                    .and_then(|_| self.hart.confidential_hart_mut().guest_load_page_fault_request());
+                guest_load_page_fault::handle(request, self)
             }
             TrapReason::GuestStorePageFault => {
-                guest_store_page_fault::handle(confidential_hart.guest_store_page_fault_request(), self)
+                let request = self
+                    .assure_confidential_instruction()
+                    .and_then(|_| self.hart.confidential_hart_mut().guest_store_page_fault_request());
+                guest_store_page_fault::handle(request, self)
+            }
+            // instructions the security monitor might emulate, e.g., accesses to CSRs it virtualizes.
+            TrapReason::IllegalInstruction | TrapReason::VirtualInstruction => {
+                illegal_instruction::handle(confidential_hart.illegal_instruction_request(), self)
             }
             TrapReason::Unknown(extension_id, function_id) => invalid_call::handle(self, extension_id, function_id),
             TrapReason::HsEcall(_, _) => {
                 panic!("Bug: Incorrect interrupt delegation configuration")
             }
-            TrapReason::StoreAccessFault => {
-                panic!("Bug: Incorrect interrupt delegation configuration")
+            // access faults are not delegated, so they are reported to the confidential VM instead of emulated as MMIO.
+            TrapReason::LoadAccessFault | TrapReason::StoreAccessFault => {
+                access_fault::handle(confidential_hart.access_fault_request(), self)
             }
         }
     }
 
+    /// Dispatches the calls that the confidential VM makes to the security monitor. Unknown function ids are rejected
+    /// by the security monitor, so the hypervisor never learns about them.
+    fn route_security_monitor_call(self, function_id: usize) -> ! {
+        use crate::confidential_flow::handlers::{
+            accept_page, acknowledge_revocation, attestation_key, clear_extended_state, convert_to_private, event_log,
+            expired_secure_timers, fault_records, flush_guest_tlb, increment_counter, invalid_call, log_event,
+            measurements, memory_regions, page_status, pin_private, read_counter, remap_page, seal, secure_timer,
+            share_barrier, share_page, shared_memcpy, unpin_private, unseal, vm_info,
+        };
+        use crate::ACE_EXT_ID;
+        const SHARE_PAGE_FID: usize = 2000;
+        const MEMORY_REGIONS_FID: usize = 2001;
+        const ATTESTATION_KEY_FID: usize = 2002;
+        const CONVERT_TO_PRIVATE_FID: usize = 2003;
+        const FLUSH_GUEST_TLB_FID: usize = 2004;
+        const FLUSH_GUEST_TLB_ALL_FID: usize = 2005;
+        const SET_SECURE_TIMER_FID: usize = 2006;
+        const EXPIRED_SECURE_TIMERS_FID: usize = 2007;
+        const ACCEPT_PAGE_FID: usize = 2008;
+        const SEAL_FID: usize = 2009;
+        const UNSEAL_FID: usize = 2010;
+        const VM_INFO_FID: usize = 2011;
+        const REMAP_PAGE_FID: usize = 2012;
+        const MEASUREMENTS_FID: usize = 2013;
+        const PAGE_STATUS_FID: usize = 2015;
+        const SHARED_MEMCPY_FID: usize = 2016;
+        const ACKNOWLEDGE_REVOCATION_FID: usize = 2017;
+        const FAULT_RECORDS_FID: usize = 2018;
+        const LOG_EVENT_FID: usize = 2019;
+        const EVENT_LOG_FID: usize = 2020;
+        const PIN_PRIVATE_FID: usize = 2021;
+        const UNPIN_PRIVATE_FID: usize = 2022;
+        const READ_COUNTER_FID: usize = 2023;
+        const INCREMENT_COUNTER_FID: usize = 2024;
+        const SHARE_BARRIER_FID: usize = 2025;
+        const CLEAR_EXTENDED_STATE_FID: usize = 2026;
+
+        let confidential_hart = self.hart.confidential_hart();
+        match function_id {
+            SHARE_PAGE_FID => share_page::handle(confidential_hart.share_page_request(), self),
+            MEMORY_REGIONS_FID => memory_regions::handle(confidential_hart.memory_regions_request(), self),
+            ATTESTATION_KEY_FID => attestation_key::handle(confidential_hart.attestation_key_request(), self),
+            CONVERT_TO_PRIVATE_FID => convert_to_private::handle(confidential_hart.convert_to_private_request(), self),
+            FLUSH_GUEST_TLB_FID => flush_guest_tlb::handle(confidential_hart.flush_guest_tlb_request(), self),
+            FLUSH_GUEST_TLB_ALL_FID => flush_guest_tlb::handle(Ok(FlushGuestTlbRequest::all()), self),
+            SET_SECURE_TIMER_FID => secure_timer::handle(confidential_hart.secure_timer_request(), self),
+            EXPIRED_SECURE_TIMERS_FID => expired_secure_timers::handle(self),
+            ACCEPT_PAGE_FID => accept_page::handle(confidential_hart.accept_page_request(), self),
+            SEAL_FID => seal::handle(confidential_hart.sealing_request(), self),
+            UNSEAL_FID => unseal::handle(confidential_hart.sealing_request(), self),
+            VM_INFO_FID => vm_info::handle(confidential_hart.vm_info_result(), self),
+            REMAP_PAGE_FID => remap_page::handle(confidential_hart.remap_page_request(), self),
+            MEASUREMENTS_FID => measurements::handle(confidential_hart.measurements_request(), self),
+            PAGE_STATUS_FID => page_status::handle(confidential_hart.page_status_request(), self),
+            SHARED_MEMCPY_FID => shared_memcpy::handle(confidential_hart.shared_memcpy_request(), self),
+            ACKNOWLEDGE_REVOCATION_FID => {
+                acknowledge_revocation::handle(confidential_hart.acknowledge_revocation_request(), self)
+            }
+            FAULT_RECORDS_FID => fault_records::handle(confidential_hart.fault_records_request(), self),
+            LOG_EVENT_FID => log_event::handle(confidential_hart.log_event_request(), self),
+            EVENT_LOG_FID => event_log::handle(confidential_hart.event_log_request(), self),
+            PIN_PRIVATE_FID => pin_private::handle(confidential_hart.pin_private_request(), self),
+            UNPIN_PRIVATE_FID => unpin_private::handle(confidential_hart.pin_private_request(), self),
+            READ_COUNTER_FID => read_counter::handle(confidential_hart.counter_request(), self),
+            INCREMENT_COUNTER_FID => increment_counter::handle(confidential_hart.counter_request(), self),
+            SHARE_BARRIER_FID => share_barrier::handle(self),
+            CLEAR_EXTENDED_STATE_FID => clear_extended_state::handle(self),
+            _ => invalid_call::handle(self, ACE_EXT_ID, function_id),
+        }
+    }
+
     pub fn finish_request(self) -> ! {
         use crate::confidential_flow::handlers::{
-            guest_load_page_fault_result, guest_store_page_fault_result, hypercall_result, share_page_result,
+            convert_to_private_result, guest_load_page_fault_result, guest_store_page_fault_result, hypercall_result,
+            share_page_result,
         };
 
         match self.hart.confidential_hart_mut().take_request() {
@@ -60,27 +192,45 @@ impl<'a> ConfidentialFlow<'a> {
             Some(PendingRequest::GuestLoadPageFault(request)) => {
                 guest_load_page_fault_result::handle(self.hart.guest_load_page_fault_result(request), self)
             }
-            Some(PendingRequest::GuestStorePageFault(request)) => guest_store_page_fault_result::handle(self, request),
+            Some(PendingRequest::GuestStorePageFault(request)) => {
+                let result = self.hart.guest_store_page_fault_result(&request);
+                guest_store_page_fault_result::handle(request, result, self)
+            }
             Some(PendingRequest::SharePage(request)) => {
                 share_page_result::handle(self.hart.share_page_result(), self, request)
             }
+            Some(PendingRequest::ConvertToPrivate()) => convert_to_private_result::handle(self),
             None => self.exit_to_confidential_vm(ExposeToConfidentialVm::Resume()),
         }
     }
 
     pub fn into_non_confidential_flow(self) -> NonConfidentialFlow<'a> {
-        let id = self.confidential_vm_id();
-        match ControlData::try_confidential_vm(id, |mut cvm| Ok(cvm.return_confidential_hart(self.hart))) {
+        // the hypervisor's timer must fire when the hypervisor expects it, regardless of the secure timers.
+        self.hart.program_timer(None);
+        let confidential_vm = self.hart.take_confidential_vm().expect("Bug: confidential flow without confidential VM");
+        let returned = bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || confidential_vm.try_lock())
+            .map(|mut cvm| cvm.return_confidential_hart(self.hart));
+        match returned {
             Ok(_) => {
                 crate::core::pmp::close_access_to_confidential_memory();
                 NonConfidentialFlow::create(self.hart)
             }
-            Err(error) => self.exit_to_confidential_vm(error.into_confidential_transformation()),
+            Err(error) => {
+                // the handle must not stay on the stack, which is discarded when resuming the confidential VM.
+                self.hart.cache_confidential_vm(confidential_vm);
+                self.exit_to_confidential_vm(error.into_confidential_transformation())
+            }
         }
     }
 
     pub fn exit_to_confidential_vm(self, transformation: ExposeToConfidentialVm) -> ! {
-        let confidential_hart_address = self.hart.confidential_hart_mut().apply(transformation);
+        let secure_timer_deadline = self.hart.confidential_hart().secure_timer_deadline();
+        self.hart.program_timer(secure_timer_deadline);
+        let confidential_hart = self.hart.confidential_hart_mut();
+        let confidential_hart_address = confidential_hart.apply(transformation).unwrap_or_else(|error| {
+            debug!("Rejected the transformation of the confidential hart: {:?}", error);
+            confidential_hart.address()
+        });
         unsafe { exit_to_confidential_vm_asm(confidential_hart_address) }
     }
 
@@ -88,6 +238,101 @@ impl<'a> ConfidentialFlow<'a> {
         self.hart.confidential_hart().confidential_vm_id()
     }
 
+    /// Returns an error if the instruction that trapped is not entirely located in confidential pages. The security
+    /// monitor reads the instruction using the confidential VM's address translation, so an instruction in a shared
+    /// page could be modified by the hypervisor before or while the security monitor decodes it.
+    fn assure_confidential_instruction(&self) -> Result<()> {
+        let (address, vsatp) = self.hart.confidential_hart().instruction_address();
+        self.try_confidential_vm(|confidential_vm| {
+            confidential_vm.root_page_table().assure_confidential_instruction(vsatp, address)
+        })
+        .map_err(|_| Error::ExecutingFromSharedMemory())
+    }
+
+    pub fn is_deferred_page(&self, request: PageImportRequest) -> bool {
+        let address = request.confidential_vm_virtual_address();
+        self.try_confidential_vm(|cvm| {
+            let root_page_table = cvm.root_page_table();
+            Ok(root_page_table.is_deferred(address) || root_page_table.is_imported(address))
+        })
+        .unwrap_or(false)
+    }
+
+    /// Posts the fault to the queue of the confidential VM executing on this hart.
+    pub fn post_fault(&self, record: FaultRecord) {
+        let _ = self.try_confidential_vm(|mut confidential_vm| {
+            confidential_vm.post_fault(record);
+            Ok(())
+        });
+    }
+
+    /// Locks the confidential VM executing on this hart. Unlike `ControlData::try_confidential_vm`, it uses the handle
+    /// kept by the hart and does not look up the confidential VM in the control data.
+    pub fn try_confidential_vm<F, O>(&self, op: O) -> Result<F>
+    where O: FnOnce(MutexGuard<'_, ConfidentialVm>) -> Result<F> {
+        self.hart.try_confidential_vm(op)
+    }
+
+    /// Records the access in the memory audit of the confidential VM. The access is forwarded to the hypervisor even if
+    /// it could not be recorded.
+    #[cfg(feature = "memory_audit")]
+    pub fn audit(&self, record: crate::core::control_data::AuditRecord) {
+        let recorded = self.try_confidential_vm(|mut cvm| {
+            cvm.audit(record);
+            Ok(())
+        });
+        if let Err(error) = recorded {
+            debug!("Could not record the memory access: {:?}", error);
+        }
+    }
+
+    pub fn read_virtual_csr(&self, csr: usize) -> Result<usize> {
+        self.hart.confidential_hart().read_virtual_csr(csr)
+    }
+
+    pub fn write_virtual_csr(&mut self, csr: usize) -> Result<usize> {
+        self.hart.confidential_hart_mut().write_virtual_csr(csr)
+    }
+
+    /// Resumes the confidential VM if the timer interrupt was caused by its secure timers and not by the hypervisor's
+    /// timer. Otherwise, returns the control flow, so the interrupt can be reflected to the hypervisor.
+    pub fn expire_secure_timers(self) -> Self {
+        if self.hart.confidential_hart_mut().expire_secure_timers() && !self.hart.is_hypervisor_timer_expired() {
+            self.exit_to_confidential_vm(ExposeToConfidentialVm::Resume());
+        }
+        self
+    }
+
+    pub fn set_secure_timer(&mut self, request: SecureTimerRequest) -> Result<()> {
+        self.hart.confidential_hart_mut().set_secure_timer(request)
+    }
+
+    pub fn hardware_time(&self, time: usize) -> usize {
+        self.hart.confidential_hart().hardware_time(time)
+    }
+
+    /// Returns true if the SBI policy of the confidential VM allows forwarding calls of the extension to the
+    /// hypervisor.
+    pub fn is_sbi_extension_allowed(&self, extension_id: usize) -> bool {
+        self.sbi_policy().allows(extension_id)
+    }
+
+    pub fn sbi_policy(&self) -> SbiPolicy {
+        self.hart.confidential_hart().sbi_policy()
+    }
+
+    pub fn clear_extended_state(&mut self) {
+        self.hart.confidential_hart_mut().clear_extended_state()
+    }
+
+    pub fn stop_confidential_hart(&mut self) {
+        self.hart.confidential_hart_mut().stop()
+    }
+
+    pub fn take_expired_secure_timers(&mut self) -> usize {
+        self.hart.confidential_hart_mut().take_expired_secure_timers()
+    }
+
     pub fn set_pending_request(self, request: PendingRequest) -> Self {
         if let Err(error) = self.hart.confidential_hart_mut().set_pending_request(request) {
             self.exit_to_confidential_vm(error.into_confidential_transformation());
@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{AcceptPageRequest, ExposeToConfidentialVm, SbiResult};
+
+/// Accepts the page imported on the first access into the confidential VM's private memory. Fails if the page is not
+/// awaiting acceptance, so the confidential VM learns about pages it did not expect to be imported.
+pub fn handle(request: AcceptPageRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut confidential_vm| {
+            confidential_vm.accept_page(request.confidential_vm_virtual_address())
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
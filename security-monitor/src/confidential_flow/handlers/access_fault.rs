@@ -0,0 +1,18 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::control_data::{FaultCode, FaultRecord};
+use crate::core::transformations::{AccessFaultRequest, ExposeToConfidentialVm};
+
+/// Raises the access fault in the confidential VM. The confidential VM accessed memory protected by the physical memory
+/// protection, e.g., memory of the security monitor that the hypervisor mapped as a shared page. This is a security
+/// violation, so the access is never emulated as MMIO by the hypervisor and the violation is posted to the fault queue
+/// of the confidential VM.
+pub fn handle(request: AccessFaultRequest, confidential_flow: ConfidentialFlow) -> ! {
+    debug!("Access fault {} of the confidential VM at 0x{:x}", request.cause(), request.address());
+    confidential_flow.post_fault(FaultRecord::new(FaultCode::AccessViolation, request.address(), request.cause()));
+    #[cfg(feature = "memory_audit")]
+    confidential_flow.audit(crate::core::control_data::AuditRecord::access_fault(&request));
+    confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::AccessFault(request))
+}
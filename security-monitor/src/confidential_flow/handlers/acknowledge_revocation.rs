@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{AcknowledgeRevocationRequest, ExposeToConfidentialVm, SbiResult};
+
+/// Confirms that the confidential VM stopped using the shared page revoked by the hypervisor, so the security monitor
+/// can unmap it when the hypervisor repeats the revocation. Fails if the hypervisor did not revoke the page.
+pub fn handle(request: AcknowledgeRevocationRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut confidential_vm| {
+            confidential_vm.acknowledge_revocation(request.confidential_vm_virtual_address())
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
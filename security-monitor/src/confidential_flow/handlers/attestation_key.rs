@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::attestation::{AttestationKey, ATTESTATION_KEY};
+use crate::core::transformations::{AttestationKeyRequest, ExposeToConfidentialVm, SbiResult};
+use crate::error::{Error, Result};
+use alloc::vec::Vec;
+
+/// Writes the public part of the attestation key followed by the certificate chain into the confidential VM's buffer.
+/// The confidential VM always learns the number of bytes required to store them. Nothing is written if the buffer is
+/// too small. The call fails if the security monitor could not generate the attestation key during its initialization,
+/// e.g., because the platform has no entropy source.
+pub fn handle(request: AttestationKeyRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = match copy_attestation_key(&request, &confidential_flow) {
+        Ok(required_size) => ExposeToConfidentialVm::SbiResult(SbiResult::success(required_size)),
+        Err(error) => error.into_confidential_transformation(),
+    };
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+fn copy_attestation_key(request: &AttestationKeyRequest, confidential_flow: &ConfidentialFlow) -> Result<usize> {
+    let attestation_key = ATTESTATION_KEY.get().ok_or(Error::AttestationKeyNotAvailable())?;
+    let data: Vec<u8> =
+        attestation_key.public_key().iter().chain(attestation_key.certificate_chain().iter()).copied().collect();
+    if data.len() <= request.buffer_size() {
+        confidential_flow.try_confidential_vm(|confidential_vm| {
+            confidential_vm.root_page_table().copy_to_confidential_vm(request.buffer_address(), &data)
+        })?;
+    }
+    Ok(data.len())
+}
@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult};
+
+/// Zeroes the floating-point registers of the calling confidential hart, e.g., after the confidential VM finished a
+/// cryptographic operation and before it yields to the hypervisor. The registers are zero when the call returns.
+pub fn handle(mut confidential_flow: ConfidentialFlow) -> ! {
+    confidential_flow.clear_extended_state();
+    confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(SbiResult::success(0)))
+}
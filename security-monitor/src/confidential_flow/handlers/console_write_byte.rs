@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ConsoleWriteByteRequest, ExposeToConfidentialVm, SbiResult};
+
+/// Writes the byte to the console sink of the security monitor and resumes the confidential VM without involving the
+/// hypervisor. Confidential VMs log one byte at a time during early boot, so this avoids the world switch and the
+/// shared buffer required by the other debug console calls. The write is recorded in the memory audit of the
+/// confidential VM because the byte leaves the confidential VM. The call fails with SBI_ERR_DENIED if the SBI policy of
+/// the confidential VM does not allow the debug console extension.
+pub fn handle(request: ConsoleWriteByteRequest, confidential_flow: ConfidentialFlow) -> ! {
+    if !confidential_flow.is_sbi_extension_allowed(ConsoleWriteByteRequest::EXTID) {
+        confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(SbiResult::denied()));
+    }
+    crate::debug::console_put(request.byte());
+    #[cfg(feature = "memory_audit")]
+    confidential_flow.audit(crate::core::control_data::AuditRecord::console_write());
+    confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(SbiResult::success(0)))
+}
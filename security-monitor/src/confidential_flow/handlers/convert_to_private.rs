@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ConvertToPrivateRequest, ExposeToHypervisor, PendingRequest, SbiRequest};
+use crate::error::Result;
+
+/// Replaces the shared pages with zeroed confidential pages and then informs the hypervisor that it can reclaim the
+/// memory that backed the shared pages. The confidential VM never observes the content of the shared pages at the
+/// converted addresses because the new mappings are in place before the hypervisor learns about the conversion.
+pub fn handle(request: Result<(ConvertToPrivateRequest, SbiRequest)>, confidential_flow: ConfidentialFlow) -> ! {
+    let confidential_vm_id = confidential_flow.confidential_vm_id();
+    let sbi_request = request.and_then(|(request, sbi_request)| {
+        debug!(
+            "Confidential VM[id={:?}] requested conversion of {} shared pages at {:x} to private",
+            confidential_vm_id,
+            request.number_of_pages(),
+            request.confidential_vm_virtual_address().usize()
+        );
+        confidential_flow.try_confidential_vm(|mut cvm| {
+            cvm.convert_to_private(request.confidential_vm_virtual_address(), request.number_of_pages())
+        })?;
+        Ok(sbi_request)
+    });
+
+    match sbi_request {
+        Ok(sbi_request) => confidential_flow
+            .set_pending_request(PendingRequest::ConvertToPrivate())
+            .into_non_confidential_flow()
+            .exit_to_hypervisor(ExposeToHypervisor::SbiRequest(sbi_request)),
+        Err(error) => confidential_flow.exit_to_confidential_vm(error.into_confidential_transformation()),
+    }
+}
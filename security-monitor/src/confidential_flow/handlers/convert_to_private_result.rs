@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult};
+
+/// The conversion completed before the hypervisor was notified, so the response of the hypervisor does not influence
+/// the result returned to the confidential VM.
+pub fn handle(confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = ExposeToConfidentialVm::SbiResult(SbiResult::success(0));
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -0,0 +1,26 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{EventLogRequest, ExposeToConfidentialVm, SbiResult};
+use crate::error::Result;
+
+/// Writes the confidential VM's event log into its buffer. The confidential VM always learns the number of bytes
+/// required to store the log. Nothing is written if the buffer is too small.
+pub fn handle(request: EventLogRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = match copy_event_log(&request, &confidential_flow) {
+        Ok(required_size) => ExposeToConfidentialVm::SbiResult(SbiResult::success(required_size)),
+        Err(error) => error.into_confidential_transformation(),
+    };
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+fn copy_event_log(request: &EventLogRequest, confidential_flow: &ConfidentialFlow) -> Result<usize> {
+    confidential_flow.try_confidential_vm(|confidential_vm| {
+        let data = confidential_vm.event_log().as_bytes();
+        if data.len() <= request.buffer_size() {
+            confidential_vm.root_page_table().copy_to_confidential_vm(request.buffer_address(), data)?;
+        }
+        Ok(data.len())
+    })
+}
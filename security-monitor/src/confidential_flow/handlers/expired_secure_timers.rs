@@ -0,0 +1,11 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult};
+
+/// Returns the flags of secure timers that expired since the last call, bit i denoting the i-th timer, and clears them.
+pub fn handle(mut confidential_flow: ConfidentialFlow) -> ! {
+    let expired = confidential_flow.take_expired_secure_timers();
+    confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(SbiResult::success(expired)))
+}
@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, FaultRecordsRequest, SbiResult};
+use crate::error::Result;
+
+/// Moves the oldest faults detected by the security monitor into the buffer of the confidential VM and returns their
+/// number. The confidential VM reads the queue until it is empty, i.e., until the call returns 0.
+pub fn handle(fault_records_request: Result<FaultRecordsRequest>, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = fault_records_request
+        .and_then(|request| {
+            confidential_flow.try_confidential_vm(|mut confidential_vm| confidential_vm.take_fault_records(&request))
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |number_of_records| ExposeToConfidentialVm::SbiResult(SbiResult::success(number_of_records)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
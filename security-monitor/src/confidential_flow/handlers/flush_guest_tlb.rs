@@ -0,0 +1,47 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::mmu::{hfence_gvma, hfence_gvma_vmid, PageSize};
+use crate::core::transformations::{ExposeToConfidentialVm, FlushGuestTlbRequest, SbiResult};
+use crate::error::{Error, Result};
+
+// Flushing more pages one by one takes longer than flushing all translations of the confidential VM.
+const MAX_SCOPED_FENCES: usize = 64;
+
+/// Removes cached second-stage translations of the confidential VM that issued the request. Only translations tagged
+/// with the VMID of this confidential VM are affected, so the confidential VM cannot influence other VMs.
+pub fn handle(request: Result<FlushGuestTlbRequest>, confidential_flow: ConfidentialFlow) -> ! {
+    let confidential_vm_id = confidential_flow.confidential_vm_id();
+    let transformation = request
+        .and_then(|request| {
+            confidential_flow.try_confidential_vm(|confidential_vm| {
+                let address_space_size = confidential_vm.root_page_table().paging_system().address_space_size();
+                let vmid = confidential_vm_id.usize();
+                match request.range() {
+                    Some(range) => {
+                        assure!(range.end <= address_space_size, Error::MemoryAccessAuthorization())?;
+                        flush_range(vmid, range.start, range.end);
+                    }
+                    None => hfence_gvma_vmid(vmid),
+                }
+                Ok(())
+            })
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+fn flush_range(vmid: usize, start: usize, end: usize) {
+    let page_size = PageSize::Size4KiB.in_bytes();
+    let first_page = start / page_size;
+    let number_of_pages = (end - 1) / page_size - first_page + 1;
+    match number_of_pages > MAX_SCOPED_FENCES {
+        // a fence for the entire VMID removes a superset of the requested translations
+        true => hfence_gvma_vmid(vmid),
+        false => (first_page..first_page + number_of_pages).for_each(|page| hfence_gvma(vmid, page * page_size)),
+    }
+}
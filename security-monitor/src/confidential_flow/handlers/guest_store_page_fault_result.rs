@@ -2,9 +2,23 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::confidential_flow::ConfidentialFlow;
-use crate::core::transformations::{ExposeToConfidentialVm, GuestStorePageFaultRequest, GuestStorePageFaultResult};
+use crate::core::transformations::{
+    ExposeToConfidentialVm, GuestStorePageFaultRequest, GuestStorePageFaultResult, PendingRequest,
+};
 
-pub fn handle(confidential_flow: ConfidentialFlow, request: GuestStorePageFaultRequest) -> ! {
-    let transformation = ExposeToConfidentialVm::GuestStorePageFaultResult(GuestStorePageFaultResult::new(request));
-    confidential_flow.exit_to_confidential_vm(transformation)
+/// Completes the store after the hypervisor emulated it. The store request remains pending until the result is applied,
+/// which checks it again against the confidential hart's state. A result that does not match the store request fails
+/// the hypervisor's resume call and drops the request, so the store faults again when the hypervisor resumes the
+/// confidential hart.
+pub fn handle(
+    request: GuestStorePageFaultRequest, result: GuestStorePageFaultResult, confidential_flow: ConfidentialFlow,
+) -> ! {
+    match request.assure_completed_by(&result) {
+        Ok(_) => confidential_flow
+            .set_pending_request(PendingRequest::GuestStorePageFault(request))
+            .exit_to_confidential_vm(ExposeToConfidentialVm::GuestStorePageFaultResult(result)),
+        Err(error) => confidential_flow
+            .into_non_confidential_flow()
+            .exit_to_hypervisor(error.into_non_confidential_transformation()),
+    }
 }
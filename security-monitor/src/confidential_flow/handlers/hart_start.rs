@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::handlers::hypercall;
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, HartStartRequest, SbiResult};
+
+/// Forwards the request to start a confidential hart to the hypervisor if the confidential VM was created with this
+/// hart. Other harts do not exist, so the call fails with SBI_ERR_INVALID_PARAM like for a hart id that the platform
+/// does not have. The started hart finds its id within the confidential VM in a0, whichever physical hart the
+/// hypervisor starts it on.
+pub fn handle(request: HartStartRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let prepared = confidential_flow.try_confidential_vm(|mut confidential_vm| {
+        confidential_vm.prepare_hart_start(request.confidential_hart_id(), request.start_address(), request.opaque())
+    });
+    match prepared {
+        Ok(_) => hypercall::handle(request.into_sbi_request(), confidential_flow),
+        Err(_) => {
+            confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(SbiResult::invalid_param()))
+        }
+    }
+}
@@ -0,0 +1,13 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::handlers::hypercall;
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::SbiRequest;
+
+/// Forwards the request of the confidential hart to stop itself to the hypervisor. The confidential hart is recorded as
+/// stopped, so it starts from the state set by the next request to start it.
+pub fn handle(sbi_request: SbiRequest, mut confidential_flow: ConfidentialFlow) -> ! {
+    confidential_flow.stop_confidential_hart();
+    hypercall::handle(sbi_request, confidential_flow)
+}
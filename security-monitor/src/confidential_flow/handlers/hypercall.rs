@@ -2,11 +2,52 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::confidential_flow::ConfidentialFlow;
-use crate::core::transformations::{ExposeToHypervisor, PendingRequest, SbiRequest};
+use crate::core::control_data::SbiPolicy;
+use crate::core::mmu::MemoryClass;
+use crate::core::transformations::{
+    ConfidentialVmVirtualAddress, ExposeToConfidentialVm, ExposeToHypervisor, PendingRequest, SbiRequest, SbiResult,
+};
 
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// Forwards the call to the hypervisor if the SBI policy of the confidential VM allows its extension. Otherwise, the
+/// call fails with SBI_ERR_DENIED without reaching the hypervisor. A debug console call fails with
+/// SBI_ERR_INVALID_PARAM unless its entire buffer is shared, so the hypervisor is never asked to access a page it
+/// cannot access. A timer deadline is expressed in the time of the confidential VM, which is shifted from the time of
+/// the physical hart after a migration, so it is converted before the hypervisor programs the timer.
 pub fn handle(sbi_request: SbiRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let is_shared = |address, size| {
+        confidential_flow
+            .try_confidential_vm(|confidential_vm| {
+                confidential_vm.root_page_table().validate_range(address, size, MemoryClass::Shared)
+            })
+            .is_ok()
+    };
+    let sbi_request = match authorize(sbi_request, confidential_flow.sbi_policy(), is_shared) {
+        Ok(sbi_request) => sbi_request,
+        Err(sbi_result) => confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(sbi_result)),
+    };
+    let sbi_request = match sbi_request.timer_deadline() {
+        Some(deadline) => sbi_request.with_timer_deadline(confidential_flow.hardware_time(deadline)),
+        None => sbi_request,
+    };
     confidential_flow
         .set_pending_request(PendingRequest::SbiRequest())
         .into_non_confidential_flow()
         .exit_to_hypervisor(ExposeToHypervisor::SbiRequest(sbi_request))
 }
+
+/// Returns the call that the security monitor forwards to the hypervisor, or the result with which the security
+/// monitor answers the call without reaching the hypervisor.
+fn authorize<F: Fn(ConfidentialVmVirtualAddress, usize) -> bool>(
+    sbi_request: SbiRequest, sbi_policy: SbiPolicy, is_shared: F,
+) -> Result<SbiRequest, SbiResult> {
+    if !sbi_policy.allows(sbi_request.extension_id()) {
+        return Err(SbiResult::denied());
+    }
+    match sbi_request.debug_console_buffer() {
+        Some((address, size)) if sbi_request.a2() != 0 || !is_shared(address, size) => Err(SbiResult::invalid_param()),
+        _ => Ok(sbi_request),
+    }
+}
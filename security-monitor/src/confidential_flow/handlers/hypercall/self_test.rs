@@ -0,0 +1,35 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::error::{Error, Result};
+
+/// Checks that a call of an extension the SBI policy denies is answered with SBI_ERR_DENIED, that a debug console write
+/// from a buffer that is not shared is answered with SBI_ERR_INVALID_PARAM, and that an allowed call is forwarded.
+pub fn run() -> Result<()> {
+    const HSM_EXTID: usize = 0x48534D;
+    const DBCN_EXTID: usize = 0x4442434E;
+    const HSM_HART_STATUS_FID: usize = 2;
+    const DBCN_WRITE_FID: usize = 0;
+    const SBI_ERR_DENIED: isize = -4;
+    const SBI_ERR_INVALID_PARAM: isize = -3;
+    let console_only = SbiPolicy::restricted(1 << 6);
+    let shared_buffer = 0x9000_0000;
+    let is_shared = |address: ConfidentialVmVirtualAddress, _| address.usize() == shared_buffer;
+    let hart_status = SbiRequest::new(HSM_EXTID, HSM_HART_STATUS_FID, 0, 0, 0, 0, 0, 0);
+    let is_denied = matches!(
+        authorize(hart_status, console_only, is_shared),
+        Err(result) if result.a0() == SBI_ERR_DENIED as usize
+    );
+    let private_write = SbiRequest::new(DBCN_EXTID, DBCN_WRITE_FID, 8, shared_buffer + 0x1000, 0, 0, 0, 0);
+    let is_rejected = matches!(
+        authorize(private_write, console_only, is_shared),
+        Err(result) if result.a0() == SBI_ERR_INVALID_PARAM as usize
+    );
+    let shared_write = SbiRequest::new(DBCN_EXTID, DBCN_WRITE_FID, 8, shared_buffer, 0, 0, 0, 0);
+    let is_forwarded = matches!(
+        authorize(shared_write, console_only, is_shared),
+        Ok(request) if request.extension_id() == DBCN_EXTID && request.a1() == shared_buffer
+    );
+    assure!(is_denied && is_rejected && is_forwarded, Error::SelfTestFailed("hypercall authorization"))
+}
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{CsrReadRequest, CsrReadResult, ExposeToConfidentialVm, IllegalInstructionRequest};
+use crate::error::{Error, Result};
+
+/// Emulates the instruction that the confidential VM cannot execute directly if the security monitor virtualizes it,
+/// e.g., reading a counter or a CSR of the virtual interrupt file that the hardware does not implement. All other
+/// instructions raise the illegal instruction exception in the confidential VM, like the hardware would do.
+pub fn handle(request: IllegalInstructionRequest, mut confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = match request.csr_read_request() {
+        Some(csr_read_request) => emulate_csr_access(*csr_read_request, &mut confidential_flow),
+        None => Err(Error::InvalidRiscvInstruction(request.instruction())),
+    }
+    .unwrap_or_else(|error| {
+        debug!("Illegal instruction 0x{:x} in the confidential VM: {:?}", request.instruction(), error);
+        ExposeToConfidentialVm::IllegalInstruction(request)
+    });
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+/// Returns the virtualized value of the CSR. Some CSRs are also written, e.g., writing stopei claims an interrupt.
+fn emulate_csr_access(
+    request: CsrReadRequest, confidential_flow: &mut ConfidentialFlow,
+) -> Result<ExposeToConfidentialVm> {
+    let value = match request.is_write() {
+        true => confidential_flow.write_virtual_csr(request.csr())?,
+        false => confidential_flow.read_virtual_csr(request.csr())?,
+    };
+    Ok(ExposeToConfidentialVm::CsrReadResult(CsrReadResult::new(request, value)))
+}
@@ -0,0 +1,17 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{CounterRequest, CounterResult, ExposeToConfidentialVm};
+
+/// Increments the monotonic counter and returns its new value the same way as reading the counter does. The counter is
+/// shared by all confidential harts of the confidential VM, so concurrent increments never return the same value.
+pub fn handle(request: CounterRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut confidential_vm| {
+            let counters = confidential_vm.monotonic_counters_mut();
+            Ok(CounterResult::new(counters.increment(request.index())?, counters.is_persistent()))
+        })
+        .map_or_else(|error| error.into_confidential_transformation(), ExposeToConfidentialVm::CounterResult);
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
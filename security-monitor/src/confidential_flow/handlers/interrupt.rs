@@ -30,6 +30,11 @@ pub fn handle(confidential_flow: ConfidentialFlow) -> ! {
     // TODO: handle interrupts targeted for confidential VM by reflecting them
     // directly to the confidential VM
     let mip = riscv::register::mip::read().bits();
+    // the timer interrupt might be caused by a secure timer of the confidential hart, which the hypervisor must not see
+    let confidential_flow = match mip & MTIP_MASK > 0 {
+        true => confidential_flow.expire_secure_timers(),
+        false => confidential_flow,
+    };
     let interrupt_code = if mip & MEIP_MASK > 0 {
         // TODO: clear the bit in mip
         Ok(MEIP - 2)
@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::handlers::{console_write_byte, hypercall};
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, LegacySbiRequest, SbiResult};
+
+/// Forwards the supported legacy SBI call to the hypervisor as the call to the extension that replaced it. The
+/// confidential VM receives the hypervisor's answer in a0 like from the legacy call. The legacy putchar is written to
+/// the console of the security monitor like the single-byte debug console write. Unsupported legacy calls fail with
+/// SBI_ERR_NOT_SUPPORTED without reaching the hypervisor.
+pub fn handle(request: LegacySbiRequest, confidential_flow: ConfidentialFlow) -> ! {
+    if let Some(console_write_byte_request) = request.console_write_byte_request() {
+        console_write_byte::handle(console_write_byte_request, confidential_flow);
+    }
+    match request.replacement() {
+        Some(sbi_request) => hypercall::handle(sbi_request, confidential_flow),
+        None => {
+            confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(SbiResult::not_supported()))
+        }
+    }
+}
@@ -0,0 +1,23 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, LogEventRequest, SbiResult};
+
+/// Appends the event to the confidential VM's event log and extends the referenced measurement register with the hash
+/// of the event description. Either both happen or none, so the log always replays to the register value.
+pub fn handle(request: LogEventRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut confidential_vm| {
+            confidential_vm.log_event(
+                request.description_address(),
+                request.description_size(),
+                request.measurement_index(),
+            )
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -0,0 +1,36 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, MeasurementsRequest, SbiResult};
+use crate::error::Result;
+
+/// Writes the report of the measurement registers into the confidential VM's buffer: the tag of the measurement
+/// algorithm as a 64-bit value, followed by the registers in the order of their indices. On request, the registers are
+/// followed by the size of the event log as a 64-bit value and the event log, so a verifier can replay the logged
+/// events against the registers they extended. The values are not signed because they never leave the confidential VM.
+/// The confidential VM always learns the number of bytes required to store them. Nothing is written if the buffer is
+/// too small.
+pub fn handle(measurements_request: Result<MeasurementsRequest>, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation =
+        measurements_request.and_then(|request| copy_measurements(&request, &confidential_flow)).map_or_else(
+            |error| error.into_confidential_transformation(),
+            |required_size| ExposeToConfidentialVm::SbiResult(SbiResult::success(required_size)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+fn copy_measurements(request: &MeasurementsRequest, confidential_flow: &ConfidentialFlow) -> Result<usize> {
+    confidential_flow.try_confidential_vm(|confidential_vm| {
+        let mut data = confidential_vm.measurements();
+        if request.include_event_log() {
+            let event_log = confidential_vm.event_log().as_bytes();
+            data.extend_from_slice(&(event_log.len() as u64).to_le_bytes());
+            data.extend_from_slice(event_log);
+        }
+        if data.len() <= request.buffer_size() {
+            confidential_vm.root_page_table().copy_to_confidential_vm(request.buffer_address(), &data)?;
+        }
+        Ok(data.len())
+    })
+}
@@ -0,0 +1,21 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, MemoryRegionsRequest, SbiResult};
+use crate::error::Result;
+
+/// Exposes to the confidential VM the regions of its physical address space that are backed by the confidential
+/// memory. The information comes from the control data and not from the hypervisor, so the confidential VM can use it
+/// to verify the memory layout described in the device tree provided by the hypervisor.
+pub fn handle(memory_regions_request: Result<MemoryRegionsRequest>, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = memory_regions_request
+        .and_then(|request| {
+            confidential_flow.try_confidential_vm(|confidential_vm| confidential_vm.write_memory_regions(&request))
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |number_of_regions| ExposeToConfidentialVm::SbiResult(SbiResult::success(number_of_regions)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -1,13 +1,47 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+pub mod accept_page;
+pub mod access_fault;
+pub mod acknowledge_revocation;
+pub mod attestation_key;
+pub mod clear_extended_state;
+pub mod console_write_byte;
+pub mod convert_to_private;
+pub mod convert_to_private_result;
+pub mod event_log;
+pub mod expired_secure_timers;
+pub mod fault_records;
+pub mod flush_guest_tlb;
 pub mod guest_load_page_fault;
 pub mod guest_load_page_fault_result;
 pub mod guest_store_page_fault;
 pub mod guest_store_page_fault_result;
+pub mod hart_start;
+pub mod hart_stop;
 pub mod hypercall;
 pub mod hypercall_result;
+pub mod illegal_instruction;
+pub mod increment_counter;
 pub mod interrupt;
 pub mod invalid_call;
+pub mod legacy_sbi;
+pub mod log_event;
+pub mod measurements;
+pub mod memory_regions;
+pub mod no_forward_progress;
+pub mod page_import;
+pub mod page_status;
+pub mod pin_private;
+pub mod read_counter;
+pub mod remap_page;
+pub mod sbi_base;
+pub mod seal;
+pub mod secure_timer;
+pub mod share_barrier;
 pub mod share_page;
 pub mod share_page_result;
+pub mod shared_memcpy;
+pub mod unpin_private;
+pub mod unseal;
+pub mod vm_info;
@@ -0,0 +1,18 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToHypervisor, SbiRequest};
+
+/// Function id under which the hypervisor learns that a confidential hart keeps trapping at the same instruction.
+const NO_FORWARD_PROGRESS_FID: usize = 3003;
+
+/// Yields the physical hart to the hypervisor instead of entering again the confidential hart that keeps trapping at
+/// the same instruction. The hypervisor receives the address of the instruction and the trap cause, so it can, e.g.,
+/// back the missing page or terminate the confidential VM. When the hypervisor resumes the confidential hart, it
+/// executes the same instruction again.
+pub fn handle(confidential_flow: ConfidentialFlow, address: usize, cause: usize) -> ! {
+    debug!("Confidential hart does not make forward progress at {:x}, cause {}", address, cause);
+    let notification = SbiRequest::new(crate::ACE_EXT_ID, NO_FORWARD_PROGRESS_FID, address, cause, 0, 0, 0, 0);
+    confidential_flow.into_non_confidential_flow().exit_to_hypervisor(ExposeToHypervisor::SbiRequest(notification))
+}
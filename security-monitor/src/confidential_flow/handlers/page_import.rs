@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, PageImportRequest};
+
+/// Imports the page that the confidential VM accessed for the first time and raises the access fault in the
+/// confidential VM. The page stays unmapped until the confidential VM accepts it, so every access before the acceptance
+/// faults.
+pub fn handle(page_import_request: PageImportRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut confidential_vm| {
+            confidential_vm.import_page(page_import_request.confidential_vm_virtual_address())
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::AccessFault(page_import_request.access_fault_request()),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, PageStatusRequest, SbiResult};
+
+/// Returns the status of the page containing the requested address, so the confidential VM can check that a buffer is
+/// shared with the hypervisor before passing it to a device. The status is a value of `PageStatus`.
+pub fn handle(request: PageStatusRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|confidential_vm| {
+            Ok(confidential_vm.root_page_table().page_status(request.confidential_vm_virtual_address()))
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |page_status| ExposeToConfidentialVm::SbiResult(SbiResult::success(page_status as usize)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, PinPrivateRequest, SbiResult};
+
+/// Pins the confidential page, so requests to share the page at this address or to move the page away fail until the
+/// confidential VM unpins it. Protects pages storing secrets against being exposed by a later buggy call.
+pub fn handle(request: PinPrivateRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut confidential_vm| {
+            confidential_vm.pin_private(request.confidential_vm_virtual_address())
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
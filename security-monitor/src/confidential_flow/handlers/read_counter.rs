@@ -0,0 +1,16 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{CounterRequest, CounterResult, ExposeToConfidentialVm};
+
+/// Returns the value of the monotonic counter in a1 and 1 in a2 if the counter survives reboots of the platform.
+pub fn handle(request: CounterRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|confidential_vm| {
+            let counters = confidential_vm.monotonic_counters();
+            Ok(CounterResult::new(counters.read(request.index())?, counters.is_persistent()))
+        })
+        .map_or_else(|error| error.into_confidential_transformation(), ExposeToConfidentialVm::CounterResult);
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
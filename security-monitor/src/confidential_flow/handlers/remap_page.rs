@@ -0,0 +1,34 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::mmu::hfence_gvma;
+use crate::core::transformations::{ExposeToConfidentialVm, RemapPageRequest, SbiResult};
+use crate::error::Result;
+
+/// Moves a confidential page of the confidential VM to another address. Cached translations of both addresses are
+/// removed before the confidential VM resumes, so it cannot reach the page through the old address.
+pub fn handle(request: Result<RemapPageRequest>, confidential_flow: ConfidentialFlow) -> ! {
+    let confidential_vm_id = confidential_flow.confidential_vm_id();
+    let transformation = request
+        .and_then(|request| {
+            debug!(
+                "Confidential VM[id={:?}] requested remapping of page {:x} to {:x}",
+                confidential_vm_id,
+                request.old_address().usize(),
+                request.new_address().usize()
+            );
+            confidential_flow.try_confidential_vm(|mut cvm| {
+                cvm.remap_confidential_page(request.old_address(), request.new_address())
+            })?;
+            let vmid = confidential_vm_id.usize();
+            hfence_gvma(vmid, request.old_address().usize());
+            hfence_gvma(vmid, request.new_address().usize());
+            Ok(())
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
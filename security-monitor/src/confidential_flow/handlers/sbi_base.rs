@@ -0,0 +1,11 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiBaseRequest};
+
+/// Handles calls to the SBI base extension with virtualized values instead of forwarding them to the hypervisor, which
+/// would reveal the identity of the host's firmware and processor.
+pub fn handle(request: SbiBaseRequest, confidential_flow: ConfidentialFlow) -> ! {
+    confidential_flow.exit_to_confidential_vm(ExposeToConfidentialVm::SbiResult(request.result()))
+}
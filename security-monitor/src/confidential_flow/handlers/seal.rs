@@ -0,0 +1,43 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::attestation::{SealingKey, ZeroizingVec, SEALING_KEY};
+use crate::core::mmu::MemoryClass;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult, SealingRequest};
+use crate::error::{Error, Result};
+
+/// Seals the data from the confidential VM's buffer with the key bound to the confidential VM's identity and writes the
+/// sealed blob into the confidential VM's output buffer. The confidential VM always learns the size of the sealed blob.
+/// Nothing is written if the output buffer is too small.
+pub fn handle(request: SealingRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = match seal(&request, &confidential_flow) {
+        Ok(sealed_size) => ExposeToConfidentialVm::SbiResult(SbiResult::success(sealed_size)),
+        Err(error) => error.into_confidential_transformation(),
+    };
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+fn seal(request: &SealingRequest, confidential_flow: &ConfidentialFlow) -> Result<usize> {
+    assure!(request.input_size() <= SealingRequest::MAX_INPUT_SIZE, Error::SealingFailed())?;
+    let sealing_key = SEALING_KEY.get().ok_or(Error::SealingKeyNotAvailable())?;
+    let sealed_size = request.input_size() + SealingKey::OVERHEAD;
+    let fits = sealed_size <= request.output_capacity();
+    confidential_flow.try_confidential_vm(|confidential_vm| {
+        let root_page_table = confidential_vm.root_page_table();
+        // both buffers are validated before sealing, so an invalid output buffer does not consume a nonce.
+        root_page_table.validate_range(request.input_address(), request.input_size(), MemoryClass::Confidential)?;
+        if fits {
+            root_page_table.validate_range(request.output_address(), sealed_size, MemoryClass::Confidential)?;
+        }
+        // the data is secret, so its copy is zeroed once it has been sealed.
+        let plaintext = ZeroizingVec::from(
+            root_page_table.copy_from_confidential_vm(request.input_address(), request.input_size())?,
+        );
+        let blob = sealing_key.seal(confidential_vm.sealing_identity(), &plaintext)?;
+        if fits {
+            root_page_table.copy_to_confidential_vm(request.output_address(), &blob)?;
+        }
+        Ok(blob.len())
+    })
+}
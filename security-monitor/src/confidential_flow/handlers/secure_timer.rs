@@ -0,0 +1,15 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult, SecureTimerRequest};
+
+/// Arms one of the secure timers of the calling confidential hart. The timer is never visible to the hypervisor or to
+/// other harts, so its expiry is attributed to the owning confidential hart only.
+pub fn handle(request: SecureTimerRequest, mut confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow.set_secure_timer(request).map_or_else(
+        |error| error.into_confidential_transformation(),
+        |_| ExposeToConfidentialVm::SbiResult(SbiResult::success(0)),
+    );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult};
+
+/// Orders the shares that completed before this call before the confidential VM's later accesses, e.g., a notification
+/// of the hypervisor. The security monitor maps a shared page before the share call returns and fences the second-stage
+/// translations whenever it enters the confidential VM, so the calling hart observes every completed share when the
+/// barrier returns. Confidential harts on other physical harts observe them at their next entry.
+///
+/// The barrier does not wait for shares that other confidential harts requested, because only the hypervisor can
+/// complete them and it might never answer. Instead, it returns in a1 the number of these shares, and a confidential VM
+/// that depends on them polls the barrier until it returns 0.
+pub fn handle(confidential_flow: ConfidentialFlow) -> ! {
+    let transformation =
+        confidential_flow.try_confidential_vm(|confidential_vm| Ok(confidential_vm.shares_in_progress())).map_or_else(
+            |error| error.into_confidential_transformation(),
+            |shares_in_progress| ExposeToConfidentialVm::SbiResult(SbiResult::success(shares_in_progress)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
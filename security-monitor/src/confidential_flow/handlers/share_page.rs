@@ -3,16 +3,23 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::confidential_flow::ConfidentialFlow;
 use crate::core::transformations::{ExposeToHypervisor, PendingRequest, SbiRequest, SharePageRequest};
-use crate::error::Error;
+use crate::error::Result;
 
-pub fn handle(
-    share_page_request: Result<(SharePageRequest, SbiRequest), Error>, confidential_flow: ConfidentialFlow,
-) -> ! {
+pub fn handle(share_page_request: Result<(SharePageRequest, SbiRequest)>, confidential_flow: ConfidentialFlow) -> ! {
+    // Rejecting the request before the hypervisor allocates the page bounds the number of requests the confidential VM
+    // can forward to the hypervisor. The limit is checked again when the shared page is mapped.
+    let confidential_vm_id = confidential_flow.confidential_vm_id();
+    let share_page_request = share_page_request.and_then(|(request, sbi_request)| {
+        confidential_flow.try_confidential_vm(|confidential_vm| {
+            confidential_vm.can_share_page(request.confidential_vm_virtual_address())
+        })?;
+        Ok((request, sbi_request))
+    });
     match share_page_request {
         Ok((request, sbi_request)) => {
             debug!(
                 "Confidential VM[id={:?}] requested a shared page mapped to {:x}",
-                confidential_flow.confidential_vm_id(),
+                confidential_vm_id,
                 &request.confidential_vm_virtual_address().usize()
             );
             confidential_flow
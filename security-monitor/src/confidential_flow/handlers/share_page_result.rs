@@ -2,21 +2,31 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::confidential_flow::ConfidentialFlow;
-use crate::core::control_data::ControlData;
+use crate::core::control_data::{FaultCode, FaultRecord};
 use crate::core::memory_tracker::SharedPage;
 use crate::core::transformations::{ExposeToConfidentialVm, SbiResult, SharePageRequest, SharePageResult};
 
+/// Maps the page allocated by the hypervisor into the confidential VM's address space. The confidential VM learns why
+/// sharing failed from the record posted to its fault queue.
 pub fn handle(share_page_result: SharePageResult, confidential_flow: ConfidentialFlow, request: SharePageRequest) -> ! {
+    let address = request.confidential_vm_virtual_address().usize();
+    let hypervisor_page_address = share_page_result.hypervisor_page_address();
     if share_page_result.is_error() {
         // hypervisor returned an error informing that it could not allocate shared
         // pages let's inform the confidential VM about it.
+        let record = FaultRecord::new(FaultCode::SharePageFailed, address, share_page_result.response_code());
+        confidential_flow.post_fault(record);
         let transformation = ExposeToConfidentialVm::SbiResult(SbiResult::failure(share_page_result.response_code()));
         confidential_flow.exit_to_confidential_vm(transformation);
     }
 
-    let shared_page = match SharedPage::new(share_page_result.hypervisor_page_address(), request) {
+    let shared_page = match SharedPage::new(hypervisor_page_address, share_page_result.is_read_only(), request) {
         Ok(v) => v,
-        Err(error) => confidential_flow.exit_to_confidential_vm(error.into_confidential_transformation()),
+        Err(error) => {
+            let record = FaultRecord::new(FaultCode::InvalidHypervisorPage, address, hypervisor_page_address);
+            confidential_flow.post_fault(record);
+            confidential_flow.exit_to_confidential_vm(error.into_confidential_transformation())
+        }
     };
 
     debug!(
@@ -24,12 +34,15 @@ pub fn handle(share_page_result: SharePageResult, confidential_flow: Confidentia
         share_page_result.hypervisor_page_address()
     );
 
-    let confidential_vm_id = confidential_flow.confidential_vm_id();
-    let transformation = ControlData::try_confidential_vm_mut(confidential_vm_id, |mut cvm| {
-        cvm.root_page_table_mut().map_shared_page(&shared_page)
-    })
-    .and_then(|_| Ok(ExposeToConfidentialVm::SbiResult(SbiResult::success(0))))
-    .unwrap_or_else(|error| error.into_confidential_transformation());
+    let transformation = confidential_flow
+        .try_confidential_vm(|mut cvm| {
+            cvm.map_shared_page(&shared_page).map_err(|error| {
+                cvm.post_fault(FaultRecord::new(FaultCode::SharePageRejected, address, hypervisor_page_address));
+                error
+            })
+        })
+        .and_then(|_| Ok(ExposeToConfidentialVm::SbiResult(SbiResult::success(0))))
+        .unwrap_or_else(|error| error.into_confidential_transformation());
 
     confidential_flow.exit_to_confidential_vm(transformation)
 }
@@ -0,0 +1,27 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult, SharedMemcpyRequest};
+use crate::error::Error;
+
+/// Copies data between two buffers that the confidential VM shares with the hypervisor, so bulk copies do not trap on
+/// every access to the shared memory. Returns the number of copied bytes. Nothing is copied if the size exceeds
+/// `SharedMemcpyRequest::MAX_SIZE`, any page of the buffers is not shared, or the destination is read-only.
+pub fn handle(request: SharedMemcpyRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = confidential_flow
+        .try_confidential_vm(|confidential_vm| {
+            let max_size = SharedMemcpyRequest::MAX_SIZE;
+            assure!(request.size() <= max_size, Error::SharedMemcpyTooLarge(request.size(), max_size))?;
+            confidential_vm.root_page_table().copy_within_shared_memory(
+                request.destination_address(),
+                request.source_address(),
+                request.size(),
+            )
+        })
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |size| ExposeToConfidentialVm::SbiResult(SbiResult::success(size)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
@@ -0,0 +1,42 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::attestation::{SealingKey, SEALING_KEY};
+use crate::core::mmu::MemoryClass;
+use crate::core::transformations::{ExposeToConfidentialVm, SbiResult, SealingRequest};
+use crate::error::{Error, Result};
+
+/// Authenticates and decrypts the sealed blob from the confidential VM's buffer and writes the recovered data into the
+/// confidential VM's output buffer. Fails if the blob was modified or sealed by a confidential VM with a different
+/// identity. The confidential VM always learns the size of the recovered data. Nothing is written if the output buffer
+/// is too small.
+pub fn handle(request: SealingRequest, confidential_flow: ConfidentialFlow) -> ! {
+    let transformation = match unseal(&request, &confidential_flow) {
+        Ok(plaintext_size) => ExposeToConfidentialVm::SbiResult(SbiResult::success(plaintext_size)),
+        Err(error) => error.into_confidential_transformation(),
+    };
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
+
+fn unseal(request: &SealingRequest, confidential_flow: &ConfidentialFlow) -> Result<usize> {
+    let max_blob_size = SealingRequest::MAX_INPUT_SIZE + SealingKey::OVERHEAD;
+    assure!(request.input_size() <= max_blob_size, Error::UnsealingFailed())?;
+    let plaintext_size = request.input_size().checked_sub(SealingKey::OVERHEAD).ok_or(Error::UnsealingFailed())?;
+    let fits = plaintext_size <= request.output_capacity();
+    let sealing_key = SEALING_KEY.get().ok_or(Error::SealingKeyNotAvailable())?;
+    confidential_flow.try_confidential_vm(|confidential_vm| {
+        let root_page_table = confidential_vm.root_page_table();
+        // the recovered data is secret, so it is written only if the entire output buffer is confidential memory.
+        root_page_table.validate_range(request.input_address(), request.input_size(), MemoryClass::Confidential)?;
+        if fits {
+            root_page_table.validate_range(request.output_address(), plaintext_size, MemoryClass::Confidential)?;
+        }
+        let blob = root_page_table.copy_from_confidential_vm(request.input_address(), request.input_size())?;
+        let plaintext = sealing_key.unseal(confidential_vm.sealing_identity(), &blob)?;
+        if fits {
+            root_page_table.copy_to_confidential_vm(request.output_address(), &plaintext)?;
+        }
+        Ok(plaintext.len())
+    })
+}
@@ -0,0 +1,18 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::confidential_flow::ConfidentialFlow;
+use crate::core::transformations::{ExposeToConfidentialVm, VmInfoResult};
+
+/// Returns the id of the confidential VM in a1, the id of the calling confidential hart in a2, and in a3 how many
+/// times the calling confidential hart returned to the hypervisor.
+pub fn handle(result: VmInfoResult, confidential_flow: ConfidentialFlow) -> ! {
+    let confidential_hart_id = result.confidential_hart_id();
+    let transformation = confidential_flow
+        .try_confidential_vm(|confidential_vm| confidential_vm.hart_exits(confidential_hart_id))
+        .map_or_else(
+            |error| error.into_confidential_transformation(),
+            |hart_exits| ExposeToConfidentialVm::VmInfoResult(result.with_hart_exits(hart_exits)),
+        );
+    confidential_flow.exit_to_confidential_vm(transformation)
+}
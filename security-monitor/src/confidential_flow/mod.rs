@@ -2,6 +2,8 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 pub use control_flow::ConfidentialFlow;
+#[cfg(feature = "init_self_test")]
+pub use handlers::hypercall::self_test as hypercall_self_test;
 
 mod context_switch;
 mod control_flow;
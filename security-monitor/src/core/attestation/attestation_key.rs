@@ -0,0 +1,95 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::{wipe, Zeroizing};
+use crate::error::{Error, InitializationErrorType, Result};
+use alloc::vec::Vec;
+use ed25519_compact::{KeyPair, Seed};
+use spin::Once;
+
+/// The attestation key is generated once during the initialization of the security monitor and never changes later --
+/// this is guaranteed by Once<>. The private part of the key never leaves the confidential memory.
+pub static ATTESTATION_KEY: Once<Ed25519AttestationKey> = Once::new();
+
+/// Set during the initialization to true if the device tree declares the Zkr extension. Reading the `seed` CSR traps on
+/// harts without Zkr, so the entropy source is not read before it is known to exist.
+pub static ENTROPY_SOURCE: Once<bool> = Once::new();
+
+/// Key material used by the security monitor to sign attestation reports. Verifiers use the public key and the
+/// certificate chain to check the authenticity of reports signed with this key.
+pub trait AttestationKey {
+    fn public_key(&self) -> &[u8];
+
+    fn certificate_chain(&self) -> &[u8];
+
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+pub struct Ed25519AttestationKey {
+    key_pair: KeyPair,
+}
+
+impl Ed25519AttestationKey {
+    // Certificate chain endorsing the attestation key. It is provisioned at build time.
+    const CERTIFICATE_CHAIN: Option<&'static str> = option_env!("ACE_ATTESTATION_CERTIFICATE_CHAIN");
+
+    /// Generates a new attestation key from the seed collected from the hardware entropy source.
+    pub fn generate() -> Result<Self> {
+        let entropy = read_entropy::<{ Seed::BYTES }>()?;
+        let mut seed = Seed::new(*entropy);
+        let key_pair = KeyPair::from_seed(seed);
+        // the seed is not zeroed by the library, so our copy is wiped once the key pair has been derived from it.
+        wipe(&mut seed[..]);
+        Ok(Self { key_pair })
+    }
+}
+
+impl AttestationKey for Ed25519AttestationKey {
+    fn public_key(&self) -> &[u8] {
+        &self.key_pair.pk[..]
+    }
+
+    fn certificate_chain(&self) -> &[u8] {
+        Self::CERTIFICATE_CHAIN.unwrap_or("").as_bytes()
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.key_pair.sk.sign(message, None).to_vec()
+    }
+}
+
+/// Reads the given even number of bytes from the entropy source exposed by the `seed` CSR (Zkr extension). Every
+/// successful read returns 16 bits of entropy. The returned buffer is zeroed when dropped because it seeds keys. Fails
+/// without reading the CSR if the platform does not implement Zkr.
+pub(super) fn read_entropy<const N: usize>() -> Result<Zeroizing<N>> {
+    const SEED_CSR_OPST_SHIFT: usize = 30;
+    const SEED_CSR_OPST_ES16: usize = 0b10;
+    const SEED_CSR_OPST_DEAD: usize = 0b11;
+    // The entropy source might be temporarily unavailable, so we retry a bounded number of times.
+    const ENTROPY_SOURCE_RETRIES: usize = 1024;
+
+    let is_available = ENTROPY_SOURCE.get().copied().unwrap_or(false);
+    assure!(is_available, Error::InitializationError(InitializationErrorType::NoEntropySource))?;
+    let mut seed = Zeroizing::<N>::zeroed();
+    let mut position = 0;
+    let mut retries = 0;
+    while position < seed.len() {
+        assure!(
+            retries < ENTROPY_SOURCE_RETRIES,
+            Error::InitializationError(InitializationErrorType::NoEntropySource)
+        )?;
+        let value: usize;
+        unsafe { core::arch::asm!("csrrw {0}, 0x015, x0", out(reg) value) };
+        match (value >> SEED_CSR_OPST_SHIFT) & 0b11 {
+            SEED_CSR_OPST_ES16 => {
+                seed[position..position + 2].copy_from_slice(&(value as u16).to_le_bytes());
+                position += 2;
+            }
+            SEED_CSR_OPST_DEAD => {
+                return Err(Error::InitializationError(InitializationErrorType::NoEntropySource));
+            }
+            _ => retries += 1,
+        }
+    }
+    Ok(seed)
+}
@@ -0,0 +1,145 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::{digest_range, MEASUREMENT_SIZE};
+use crate::core::memory_tracker::{Allocated, Page};
+use crate::core::mmu::{PageSize, RootPageTable};
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+use ed25519_compact::{PublicKey, Signature};
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// Describes the pages of the confidential VM's initial image authorized by the VM owner. Every entry consists of the
+/// confidential VM's physical address of a page, the page size, and the hash of the page content computed with the
+/// measurement algorithm selected at build time. The owner
+/// signs the manifest, so the hypervisor can neither modify the image nor the manifest without being detected.
+///
+/// The manifest is part of the confidential VM's image and starts at a 4KiB-aligned address. It is a header (the magic
+/// value and the number of entries as 64-bit little-endian values), followed by the entries (address and size as 64-bit
+/// little-endian values, followed by the hash), followed by the Ed25519 signature over the header and the entries. The
+/// rest of the last page of the manifest must be zeroed, so the signature determines the content of all its pages.
+pub struct ImageManifest {
+    entries: BTreeMap<usize, ImageManifestEntry>,
+    // pages of the confidential VM's physical address space holding the manifest.
+    region: Range<usize>,
+}
+
+pub struct ImageManifestEntry {
+    size: usize,
+    hash: [u8; ImageManifest::HASH_SIZE],
+}
+
+impl ImageManifest {
+    const MAGIC: u64 = 0x4143_452d_494d_4147; // "ACE-IMAG"
+    const VALUE_SIZE: usize = core::mem::size_of::<u64>();
+    const HEADER_SIZE: usize = 2 * Self::VALUE_SIZE;
+    const HASH_SIZE: usize = MEASUREMENT_SIZE;
+    const ENTRY_SIZE: usize = 2 * Self::VALUE_SIZE + Self::HASH_SIZE;
+    // bounds the amount of memory copied when parsing the manifest.
+    const MAX_ENTRIES: usize = 1 << 15;
+    // Ed25519 public key of the VM owner encoded as a hex string. It is provisioned at build time.
+    const OWNER_PUBLIC_KEY: Option<&'static str> = option_env!("ACE_OWNER_PUBLIC_KEY");
+
+    /// Reads the manifest from the confidential VM's image that has already been copied to the confidential memory and
+    /// verifies its signature with the owner's public key. The hypervisor cannot change the manifest after the
+    /// verification because it has no access to the confidential memory.
+    pub fn copy_from_confidential_vm(
+        root_page_table: &RootPageTable, address: ConfidentialVmVirtualAddress,
+    ) -> Result<Self> {
+        assure!(address.usize() % PageSize::Size4KiB.in_bytes() == 0, Error::ManifestVerificationFailed())?;
+        let header = root_page_table.copy_from_confidential_vm(address, Self::HEADER_SIZE)?;
+        let region_size = Self::region_size(&header)?;
+        let region_end = address.checked_add(region_size).map_err(|_| Error::ManifestVerificationFailed())?;
+        let manifest = root_page_table.copy_from_confidential_vm(address, region_size)?;
+        Self::parse(&manifest, &Self::owner_public_key()?, address.usize()..region_end.usize())
+    }
+
+    /// Returns the size of the 4KiB-aligned region holding the manifest that starts with the given header.
+    fn region_size(header: &[u8]) -> Result<usize> {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        assure!(header.len() >= Self::HEADER_SIZE, Error::ManifestVerificationFailed())?;
+        assure!(read_value(header, 0) == Self::MAGIC as usize, Error::ManifestVerificationFailed())?;
+        let number_of_entries = read_value(header, Self::VALUE_SIZE);
+        assure!(number_of_entries <= Self::MAX_ENTRIES, Error::ManifestVerificationFailed())?;
+        let manifest_size = Self::signed_size(number_of_entries) + Signature::BYTES;
+        Ok((manifest_size + page_size - 1) / page_size * page_size)
+    }
+
+    fn signed_size(number_of_entries: usize) -> usize {
+        Self::HEADER_SIZE + number_of_entries * Self::ENTRY_SIZE
+    }
+
+    /// Verifies the signature of the manifest with the public key and reads its entries. The manifest is the content of
+    /// the given region of the confidential VM's physical address space.
+    fn parse(manifest: &[u8], public_key: &PublicKey, region: Range<usize>) -> Result<Self> {
+        assure!(manifest.len() == Self::region_size(manifest)?, Error::ManifestVerificationFailed())?;
+        let number_of_entries = read_value(manifest, Self::VALUE_SIZE);
+        let signed_size = Self::signed_size(number_of_entries);
+        let manifest_size = signed_size + Signature::BYTES;
+        assure!(manifest[manifest_size..].iter().all(|byte| *byte == 0), Error::ManifestVerificationFailed())?;
+        let signature = Signature::from_slice(&manifest[signed_size..manifest_size])
+            .map_err(|_| Error::ManifestVerificationFailed())?;
+        public_key.verify(&manifest[..signed_size], &signature).map_err(|_| Error::ManifestVerificationFailed())?;
+
+        let mut entries = BTreeMap::new();
+        let mut end_of_previous_entry = 0;
+        for i in 0..number_of_entries {
+            let offset = Self::HEADER_SIZE + i * Self::ENTRY_SIZE;
+            let address = read_value(&manifest, offset);
+            let size = read_value(&manifest, offset + Self::VALUE_SIZE);
+            let hash_offset = offset + 2 * Self::VALUE_SIZE;
+            let mut hash = [0u8; Self::HASH_SIZE];
+            hash.copy_from_slice(&manifest[hash_offset..hash_offset + Self::HASH_SIZE]);
+            // entries are sorted and do not overlap, so every entry describes a distinct page.
+            assure!(size > 0 && address >= end_of_previous_entry, Error::ManifestVerificationFailed())?;
+            end_of_previous_entry = address.checked_add(size).ok_or(Error::ManifestVerificationFailed())?;
+            entries.insert(address, ImageManifestEntry { size, hash });
+        }
+        Ok(Self { entries, region })
+    }
+
+    /// Returns an error if the manifest does not list the page mapped at the given confidential VM's physical address
+    /// or if the size or the hash of the page content differ from the ones in the manifest. Pages holding the
+    /// manifest are not listed in it because their content has been verified with the signature.
+    pub fn verify_page(&self, address: usize, page: &Page<Allocated>) -> Result<()> {
+        if self.region.start <= address && address + page.size().in_bytes() <= self.region.end {
+            return Ok(());
+        }
+        let entry = self.entries.get(&address).ok_or(Error::ManifestVerificationFailed())?;
+        assure!(entry.size == page.size().in_bytes(), Error::ManifestVerificationFailed())?;
+        let hash = digest_range(page.address(), page.size().in_bytes())?;
+        assure!(hash == entry.hash, Error::ManifestVerificationFailed())
+    }
+
+    /// Returns the pages listed in the manifest sorted by their confidential VM's physical addresses.
+    pub fn entries(&self) -> impl Iterator<Item = (&usize, &ImageManifestEntry)> {
+        self.entries.iter()
+    }
+
+    fn owner_public_key() -> Result<PublicKey> {
+        let encoded_key = Self::OWNER_PUBLIC_KEY.ok_or(Error::ManifestVerificationFailed())?;
+        let key = (0..encoded_key.len())
+            .step_by(2)
+            .map(|i| encoded_key.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(Error::ManifestVerificationFailed())?;
+        PublicKey::from_slice(&key).map_err(|_| Error::ManifestVerificationFailed())
+    }
+}
+
+impl ImageManifestEntry {
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+}
+
+fn read_value(bytes: &[u8], offset: usize) -> usize {
+    let mut value = [0u8; ImageManifest::VALUE_SIZE];
+    value.copy_from_slice(&bytes[offset..offset + ImageManifest::VALUE_SIZE]);
+    u64::from_le_bytes(value) as usize
+}
@@ -0,0 +1,49 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::core::attestation::MeasurementHasher;
+use crate::core::memory_tracker::MemoryTracker;
+use ed25519_compact::{KeyPair, Seed};
+use sha2::Digest;
+
+/// Signs a manifest of a single page with a test key and checks that the signed manifest and the page are accepted,
+/// while flipping a single byte of the manifest, of its signature, or of the page makes the verification fail.
+pub fn run() -> Result<()> {
+    const PATTERN: u8 = 0xa5;
+    const PAGE_ADDRESS: usize = 0x1000;
+    let page_size = PageSize::Size4KiB.in_bytes();
+    let key_pair = KeyPair::from_seed(Seed::new([0x42; Seed::BYTES]));
+    let mut hasher = MeasurementHasher::new();
+    (0..page_size).for_each(|_| hasher.update([PATTERN]));
+    let mut manifest = alloc::vec![0u8; page_size];
+    manifest[..ImageManifest::VALUE_SIZE].copy_from_slice(&ImageManifest::MAGIC.to_le_bytes());
+    manifest[ImageManifest::VALUE_SIZE..ImageManifest::HEADER_SIZE].copy_from_slice(&1u64.to_le_bytes());
+    let entry = &mut manifest[ImageManifest::HEADER_SIZE..ImageManifest::HEADER_SIZE + ImageManifest::ENTRY_SIZE];
+    entry[..ImageManifest::VALUE_SIZE].copy_from_slice(&(PAGE_ADDRESS as u64).to_le_bytes());
+    entry[ImageManifest::VALUE_SIZE..2 * ImageManifest::VALUE_SIZE].copy_from_slice(&(page_size as u64).to_le_bytes());
+    entry[2 * ImageManifest::VALUE_SIZE..].copy_from_slice(&hasher.finalize());
+    let signed_size = ImageManifest::signed_size(1);
+    let signature = key_pair.sk.sign(&manifest[..signed_size], None);
+    manifest[signed_size..signed_size + Signature::BYTES].copy_from_slice(&signature[..]);
+
+    let parse = |manifest: &[u8]| ImageManifest::parse(manifest, &key_pair.pk, 0..page_size);
+    let image_manifest = parse(&manifest).map_err(|_| Error::SelfTestFailed("image manifest signature"))?;
+    let is_tampering_detected =
+        [ImageManifest::VALUE_SIZE, ImageManifest::HEADER_SIZE + ImageManifest::ENTRY_SIZE - 1, signed_size]
+            .iter()
+            .all(|offset| {
+                let mut tampered_manifest = manifest.clone();
+                tampered_manifest[*offset] ^= 1;
+                parse(&tampered_manifest).is_err()
+            });
+    assure!(is_tampering_detected, Error::SelfTestFailed("image manifest tampering"))?;
+
+    let page = MemoryTracker::acquire_continous_pages(1, PageSize::Size4KiB)?.remove(0).zeroize();
+    page.offsets().for_each(|offset| page.write::<u8>(offset, PATTERN));
+    let is_page_accepted = image_manifest.verify_page(PAGE_ADDRESS, &page).is_ok();
+    page.write::<u8>(page_size - 1, !PATTERN);
+    let is_page_rejected = image_manifest.verify_page(PAGE_ADDRESS, &page).is_err();
+    MemoryTracker::release_page(page.deallocate());
+    assure!(is_page_accepted && is_page_rejected, Error::SelfTestFailed("image manifest page"))
+}
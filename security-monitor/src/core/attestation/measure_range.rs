@@ -0,0 +1,71 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::{MeasurementHasher, MEASUREMENT_SIZE};
+use crate::core::memory_tracker::{ConfidentialMemoryAddress, NonConfidentialMemoryAddress};
+use crate::core::mmu::PageSize;
+use crate::error::Result;
+use sha2::Digest;
+
+/// Digest of the content of a single page of a confidential VM, computed with the measurement algorithm.
+pub type PageDigest = [u8; MEASUREMENT_SIZE];
+
+// bytes read from the confidential memory before they are passed to the hasher. It is small to not exhaust the stack.
+const BUFFER_SIZE: usize = 64;
+
+/// Feeds the content of the confidential memory range into the hasher. All measurements of the confidential memory
+/// go through this function, so the same content always produces the same digest. Fails if any part of the range is
+/// outside the confidential memory, in which case the hasher is left unchanged.
+pub fn measure_range<D: Digest>(start: ConfidentialMemoryAddress, size: usize, digest: &mut D) -> Result<()> {
+    if size > 0 {
+        // the confidential memory is a single contiguous region, so the range is in it if its last byte is.
+        start.checked_add(size - 1)?;
+    }
+    // Safety: the range was checked to be in the confidential memory.
+    unsafe { feed(start.usize(), size, digest) };
+    Ok(())
+}
+
+/// Returns the digest of the content of the range of the hypervisor's memory. The hypervisor can change the content
+/// while it is read, so the digest is only meaningful if the same content is later copied to the confidential memory
+/// and its digest is compared against this one.
+pub fn digest_non_confidential_range(start: NonConfidentialMemoryAddress, size: usize) -> Result<PageDigest> {
+    // the non-confidential memory is not contiguous, so every byte of the range must be checked.
+    NonConfidentialMemoryAddress::new_region(start.usize(), size)?;
+    let mut hasher = MeasurementHasher::new();
+    // Safety: the range was checked to be in the non-confidential memory.
+    unsafe { feed(start.usize(), size, &mut hasher) };
+    Ok(finalize(hasher))
+}
+
+/// Returns the digest of the content of the range of the confidential memory.
+pub fn digest_range(start: ConfidentialMemoryAddress, size: usize) -> Result<PageDigest> {
+    let mut hasher = MeasurementHasher::new();
+    measure_range(start, size, &mut hasher)?;
+    Ok(finalize(hasher))
+}
+
+fn finalize(hasher: MeasurementHasher) -> PageDigest {
+    let mut page_digest = [0u8; MEASUREMENT_SIZE];
+    page_digest.copy_from_slice(&hasher.finalize());
+    page_digest
+}
+
+/// Safety: the caller must ensure that the entire range is memory that the security monitor can read.
+unsafe fn feed<D: Digest>(start: usize, size: usize, digest: &mut D) {
+    let page_size = PageSize::Size4KiB.in_bytes();
+    let mut buffer = [0u8; BUFFER_SIZE];
+    let mut offset = 0;
+    while offset < size {
+        // chunks do not cross page boundaries, so every read is within a single page.
+        let chunk_size = core::cmp::min(size - offset, page_size - (start + offset) % page_size);
+        for chunk_offset in (offset..offset + chunk_size).step_by(BUFFER_SIZE) {
+            let length = core::cmp::min(BUFFER_SIZE, offset + chunk_size - chunk_offset);
+            buffer[..length].iter_mut().enumerate().for_each(|(i, byte)| {
+                *byte = ((start + chunk_offset + i) as *const u8).read_volatile();
+            });
+            digest.update(&buffer[..length]);
+        }
+        offset += chunk_size;
+    }
+}
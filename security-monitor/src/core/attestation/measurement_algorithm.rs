@@ -0,0 +1,63 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use alloc::vec::Vec;
+use sha2::digest::typenum::Unsigned;
+use sha2::digest::OutputSizeUser;
+use sha2::{Digest, Sha256, Sha512};
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// Hash algorithm that extends the measurement registers of confidential VMs. The hasher provides `update` and
+/// `finalize`, the output size is known at compile time, so measurements are stored in fixed-size arrays.
+pub trait MeasurementAlgorithm {
+    type Hasher: Digest;
+    const OUTPUT_SIZE: usize;
+    /// Identifier of the algorithm from the TCG algorithm registry. It is recorded together with the measurements, so
+    /// a verifier knows which algorithm produced them.
+    const TAG: u64;
+
+    /// Replaces the measurement register with the hash of its current value concatenated with the given data.
+    fn extend(register: &mut [u8], data: &[&[u8]]) {
+        let mut hasher = Self::Hasher::new();
+        hasher.update(&*register);
+        data.iter().for_each(|chunk| hasher.update(chunk));
+        register.copy_from_slice(&hasher.finalize());
+    }
+
+    /// Returns the report of the measurement registers: the tag of the algorithm as a 64-bit little-endian value,
+    /// followed by the registers in the order of their indices.
+    fn report<'a>(registers: impl Iterator<Item = &'a [u8]>) -> Vec<u8> {
+        Self::TAG.to_le_bytes().into_iter().chain(registers.flatten().copied()).collect()
+    }
+}
+
+pub struct Sha256Measurement;
+
+impl MeasurementAlgorithm for Sha256Measurement {
+    type Hasher = Sha256;
+    const OUTPUT_SIZE: usize = <Sha256 as OutputSizeUser>::OutputSize::USIZE;
+    const TAG: u64 = 0x000B; // TPM_ALG_SHA256
+}
+
+pub struct Sha512Measurement;
+
+impl MeasurementAlgorithm for Sha512Measurement {
+    type Hasher = Sha512;
+    const OUTPUT_SIZE: usize = <Sha512 as OutputSizeUser>::OutputSize::USIZE;
+    const TAG: u64 = 0x000D; // TPM_ALG_SHA512
+}
+
+/// The algorithm selected at build time with the measurement_sha512 feature. SHA-256 is the default.
+#[cfg(not(feature = "measurement_sha512"))]
+pub type SelectedMeasurementAlgorithm = Sha256Measurement;
+#[cfg(feature = "measurement_sha512")]
+pub type SelectedMeasurementAlgorithm = Sha512Measurement;
+
+/// Size in bytes of every measurement register.
+pub const MEASUREMENT_SIZE: usize = <SelectedMeasurementAlgorithm as MeasurementAlgorithm>::OUTPUT_SIZE;
+
+/// Hasher of the algorithm selected at build time. All hashes over the content or the identity of confidential VMs
+/// are computed with it.
+pub type MeasurementHasher = <SelectedMeasurementAlgorithm as MeasurementAlgorithm>::Hasher;
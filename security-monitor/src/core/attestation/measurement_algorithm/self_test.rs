@@ -0,0 +1,39 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::error::{Error, Result};
+
+/// Hashes the test vector from FIPS 180-2, so the measurement_sha512 feature is known to work on the platform.
+fn sha512() -> Result<()> {
+    const DIGEST: [u8; 64] = [
+        0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49, 0xae, 0x20, 0x41, 0x31, 0x12, 0xe6,
+        0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2, 0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55, 0xd3, 0x9a, 0x21, 0x92, 0x99, 0x2a,
+        0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba, 0x3c, 0x23, 0xa3, 0xfe, 0xeb, 0xbd, 0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c,
+        0xe8, 0x0e, 0x2a, 0x9a, 0xc9, 0x4f, 0xa5, 0x4c, 0xa4, 0x9f,
+    ];
+    assure!(Sha512::digest(b"abc")[..] == DIGEST[..], Error::SelfTestFailed("sha512 test vector"))
+}
+
+/// Extends a measurement register and builds the report of the measurement registers with every supported algorithm,
+/// not only the one selected at build time, and checks the size of the registers and the tag in the report.
+pub fn run() -> Result<()> {
+    sha512()?;
+    fn check<A: MeasurementAlgorithm>(output_size: usize, tag: u64) -> bool {
+        let mut register = alloc::vec![0u8; A::OUTPUT_SIZE];
+        A::extend(&mut register, &[b"ab", b"c"]);
+        let mut extended_data = alloc::vec![0u8; A::OUTPUT_SIZE];
+        extended_data.extend_from_slice(b"abc");
+        let is_extended = register[..] == A::Hasher::digest(&extended_data)[..];
+        let report = A::report([&register[..], &register[..]].into_iter());
+        let tag_size = core::mem::size_of::<u64>();
+        is_extended
+            && A::OUTPUT_SIZE == output_size
+            && report.len() == tag_size + 2 * output_size
+            && report[..tag_size] == tag.to_le_bytes()
+            && report[tag_size + output_size..] == register[..]
+    }
+    let is_sha256 = check::<Sha256Measurement>(32, 0x000B);
+    let is_sha512 = check::<Sha512Measurement>(64, 0x000D);
+    assure!(is_sha256 && is_sha512, Error::SelfTestFailed("measurement algorithms"))
+}
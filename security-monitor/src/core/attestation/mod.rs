@@ -0,0 +1,23 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+pub use attestation_key::{AttestationKey, Ed25519AttestationKey, ATTESTATION_KEY, ENTROPY_SOURCE};
+#[cfg(feature = "init_self_test")]
+pub use image_manifest::self_test as image_manifest_self_test;
+pub use image_manifest::ImageManifest;
+pub use measure_range::{digest_non_confidential_range, digest_range, measure_range, PageDigest};
+#[cfg(feature = "init_self_test")]
+pub use measurement_algorithm::self_test as measurement_algorithm_self_test;
+pub use measurement_algorithm::{
+    MeasurementAlgorithm, MeasurementHasher, SelectedMeasurementAlgorithm, Sha256Measurement, Sha512Measurement,
+    MEASUREMENT_SIZE,
+};
+pub use sealing_key::{SealingKey, SEALING_KEY};
+pub use zeroizing::{wipe, Zeroizing, ZeroizingVec};
+
+mod attestation_key;
+mod image_manifest;
+mod measure_range;
+mod measurement_algorithm;
+mod sealing_key;
+mod zeroizing;
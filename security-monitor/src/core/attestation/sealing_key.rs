@@ -0,0 +1,107 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::attestation_key::read_entropy;
+use crate::core::attestation::{wipe, MeasurementHasher, Zeroizing, ZeroizingVec, MEASUREMENT_SIZE};
+use crate::error::{Error, Result};
+use alloc::vec::Vec;
+use chacha20poly1305::{AeadInPlace, Key, KeyInit, Tag, XChaCha20Poly1305, XNonce};
+use core::sync::atomic::{AtomicU64, Ordering};
+use sha2::digest::generic_array::GenericArray;
+use sha2::Digest;
+use spin::Once;
+
+/// The sealing key is created once during the initialization of the security monitor and never changes later -- this
+/// is guaranteed by Once<>. It never leaves the confidential memory. It is not set if the boot loader did not provide
+/// the root secret.
+pub static SEALING_KEY: Once<SealingKey> = Once::new();
+
+/// Root secret from which the security monitor derives a key for every measured identity of a confidential VM. Data
+/// sealed by a confidential VM can only be unsealed by a confidential VM with the same identity. The root secret is
+/// provided by the boot loader and is the same on every boot of the security monitor on the same platform, so sealed
+/// data survives the reboot of the security monitor.
+pub struct SealingKey {
+    secret: Zeroizing<{ Self::SECRET_SIZE }>,
+    // the same key is used across reboots, so the nonces start with a random prefix chosen at every boot.
+    nonce_prefix: [u8; Self::NONCE_PREFIX_SIZE],
+    // every sealing consumes a nonce, so no two blobs are encrypted with the same key and nonce.
+    next_nonce: AtomicU64,
+}
+
+impl SealingKey {
+    pub const SECRET_SIZE: usize = 32;
+    // the key is derived with the measurement algorithm, whose output is at least as long as the key.
+    const DIGEST_SIZE: usize = MEASUREMENT_SIZE;
+    const NONCE_SIZE: usize = 24;
+    const NONCE_PREFIX_SIZE: usize = Self::NONCE_SIZE - core::mem::size_of::<u64>();
+    const TAG_SIZE: usize = 16;
+    // separates the sealing keys from other values derived from the same secret.
+    const DOMAIN: &'static [u8] = b"ACE-SEAL";
+    /// Number of bytes by which the sealed blob is larger than the plaintext: the nonce and the authentication tag.
+    pub const OVERHEAD: usize = Self::NONCE_SIZE + Self::TAG_SIZE;
+
+    /// Creates the sealing key from the root secret. The nonce prefix is collected from the hardware entropy source.
+    pub fn new(secret: Zeroizing<{ Self::SECRET_SIZE }>) -> Result<Self> {
+        let nonce_prefix = *read_entropy::<{ Self::NONCE_PREFIX_SIZE }>()?;
+        Ok(Self { secret, nonce_prefix, next_nonce: AtomicU64::new(0) })
+    }
+
+    /// Encrypts and authenticates the plaintext with the key derived for the given identity. Returns the sealed blob:
+    /// the nonce, followed by the ciphertext, followed by the authentication tag.
+    pub fn seal(&self, identity: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let counter = self
+            .next_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |counter| counter.checked_add(1))
+            .map_err(|_| Error::SealingFailed())?;
+        let mut nonce = [0u8; Self::NONCE_SIZE];
+        nonce[..Self::NONCE_PREFIX_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[Self::NONCE_PREFIX_SIZE..].copy_from_slice(&counter.to_le_bytes());
+        let mut blob = Vec::with_capacity(plaintext.len() + Self::OVERHEAD);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(plaintext);
+        let tag = match self.cipher(identity).encrypt_in_place_detached(
+            XNonce::from_slice(&nonce),
+            &[],
+            &mut blob[Self::NONCE_SIZE..],
+        ) {
+            Ok(tag) => tag,
+            Err(_) => {
+                // the blob might still hold the plaintext if the encryption failed.
+                wipe(&mut blob);
+                return Err(Error::SealingFailed());
+            }
+        };
+        blob.extend_from_slice(&tag);
+        Ok(blob)
+    }
+
+    /// Verifies the authentication tag of the sealed blob and decrypts it with the key derived for the given identity.
+    /// Fails if the blob was modified or sealed for a different identity. The recovered data is zeroed when dropped.
+    pub fn unseal(&self, identity: &[u8], blob: &[u8]) -> Result<ZeroizingVec> {
+        assure!(blob.len() >= Self::OVERHEAD, Error::UnsealingFailed())?;
+        let (nonce, sealed) = blob.split_at(Self::NONCE_SIZE);
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - Self::TAG_SIZE);
+        let mut plaintext = ZeroizingVec::from(ciphertext.to_vec());
+        self.cipher(identity)
+            .decrypt_in_place_detached(XNonce::from_slice(nonce), &[], &mut plaintext, Tag::from_slice(tag))
+            .map_err(|_| Error::UnsealingFailed())?;
+        Ok(plaintext)
+    }
+
+    /// Returns the cipher keyed for the given identity. The cipher zeroes its key when dropped.
+    fn cipher(&self, identity: &[u8]) -> XChaCha20Poly1305 {
+        let mut hasher = MeasurementHasher::new();
+        hasher.update(Self::DOMAIN);
+        hasher.update(&self.secret[..]);
+        hasher.update(identity);
+        // the derived key is zeroed after the cipher has been initialized with it.
+        let mut digest = Zeroizing::<{ Self::DIGEST_SIZE }>::zeroed();
+        hasher.finalize_into_reset(GenericArray::from_mut_slice(&mut digest[..]));
+        // the hasher does not zero its state, which buffers the secret, so it is overwritten with a fresh hasher.
+        // Safety: the hasher is a valid and aligned local variable that is not borrowed.
+        unsafe { core::ptr::write_volatile(&mut hasher, MeasurementHasher::new()) };
+        XChaCha20Poly1305::new(Key::from_slice(&digest[..Self::SECRET_SIZE]))
+    }
+}
+
+const _: () = assert!(SealingKey::DIGEST_SIZE >= SealingKey::SECRET_SIZE);
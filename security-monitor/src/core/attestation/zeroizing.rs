@@ -0,0 +1,75 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Buffer holding key material that is overwritten with zeros when it goes out of scope, so the key material does not
+/// linger in the stack or heap memory that is reused later. Moving the buffer copies its content, so buffers should be
+/// filled in place rather than passed around by value.
+pub struct Zeroizing<const N: usize>([u8; N]);
+
+impl<const N: usize> Zeroizing<N> {
+    pub fn zeroed() -> Self {
+        Self([0u8; N])
+    }
+}
+
+impl<const N: usize> Deref for Zeroizing<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for Zeroizing<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> Drop for Zeroizing<N> {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+/// Heap buffer holding secret data, e.g., plaintext recovered by unsealing, that is overwritten with zeros when it goes
+/// out of scope, also when an error is returned early.
+pub struct ZeroizingVec(Vec<u8>);
+
+impl From<Vec<u8>> for ZeroizingVec {
+    fn from(buffer: Vec<u8>) -> Self {
+        Self(buffer)
+    }
+}
+
+impl Deref for ZeroizingVec {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ZeroizingVec {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for ZeroizingVec {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+/// Overwrites the buffer with zeros. Volatile writes are not elided by the compiler even though the buffer is never
+/// read again.
+pub fn wipe(buffer: &mut [u8]) {
+    // Safety: every byte is valid and aligned, and the mutable borrow of the buffer grants exclusive access to it.
+    buffer.iter_mut().for_each(|byte| unsafe { core::ptr::write_volatile(byte, 0) });
+    compiler_fence(Ordering::SeqCst);
+}
@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::error::{Error, Result};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Number of attempts to acquire a lock protecting the control data or the memory tracker before giving up. Failing
+/// returns the control to the caller, which can retry the world switch, instead of spinning forever on a lock whose
+/// holder might never release it.
+pub const MAX_LOCK_ATTEMPTS: usize = 1 << 16;
+
+/// Set when any physical hart panics. A panicking hart never returns, so it never releases locks it holds.
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+/// Must be called by the panic handler before the panicking hart stops.
+pub fn mark_panicked() {
+    PANICKED.store(true, Ordering::SeqCst);
+}
+
+/// Calls `try_lock` until it returns the lock guard or `max_attempts` attempts fail. Returns
+/// `Error::ContentionTimeout` when the lock is still taken after the last attempt and `Error::LockHeldAcrossPanic` as
+/// soon as the lock is taken after a hart panicked, because then the lock might never be released.
+pub fn acquire<G, L>(max_attempts: usize, mut try_lock: L) -> Result<G>
+where L: FnMut() -> Option<G> {
+    for _ in 0..max_attempts {
+        if let Some(guard) = try_lock() {
+            return Ok(guard);
+        }
+        assure_not!(PANICKED.load(Ordering::SeqCst), Error::LockHeldAcrossPanic())?;
+        core::hint::spin_loop();
+    }
+    Err(Error::ContentionTimeout())
+}
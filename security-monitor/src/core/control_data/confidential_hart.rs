@@ -1,14 +1,31 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::ConfidentialVmId;
-use crate::core::hart::{FpRegisters, GpRegister, GpRegisters, HartState};
+use crate::core::control_data::instruction_cache::DecodedAccess;
+use crate::core::control_data::{
+    ConfidentialVmId, InstructionCache, PendingRequests, ProgressMonitor, SbiPolicy, SecureTimers, SharedInfo,
+    VirtualCounters, VirtualInterruptFile, VirtualTime,
+};
+use crate::core::hart::{Endianness, FpRegisters, GpRegister, GpRegisters, HartState};
+use crate::core::mmu::PagingSystem;
 use crate::core::transformations::{
-    ExposeToConfidentialVm, GuestLoadPageFaultRequest, GuestLoadPageFaultResult, GuestStorePageFaultRequest,
-    GuestStorePageFaultResult, MmioLoadRequest, MmioStoreRequest, PendingRequest, SbiRequest, SbiResult,
-    SharePageRequest, TrapReason,
+    AcceptPageRequest, AccessFaultRequest, AcknowledgeRevocationRequest, AttestationKeyRequest,
+    ConsoleWriteByteRequest, ConvertToPrivateRequest, CounterRequest, CounterResult, CsrReadRequest, CsrReadResult,
+    EventLogRequest, ExposeToConfidentialVm, FaultRecordsRequest, FlushGuestTlbRequest, GuestLoadPageFaultRequest,
+    GuestLoadPageFaultResult, GuestStorePageFaultRequest, GuestStorePageFaultResult, HartStartRequest,
+    IllegalInstructionRequest, LegacySbiRequest, LogEventRequest, MeasurementsRequest, MemoryRegionsRequest,
+    MmioLoadRequest, MmioStoreRequest, PageImportRequest, PageStatusRequest, PendingRequest, PinPrivateRequest,
+    RemapPageRequest, SbiBaseRequest, SbiRequest, SbiResult, SealingRequest, SecureTimerRequest, SharePageRequest,
+    SharedMemcpyRequest, TrapReason, VmInfoResult,
 };
-use crate::error::Error;
+use crate::error::{Error, Result};
+use alloc::sync::Arc;
+#[cfg(feature = "insecure_debug")]
+use alloc::vec::Vec;
+use riscv::register::hgatp::Hgatp;
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
 
 /// ConfidentialHart represents the dump state of the confidential VM's hart (aka
 /// vcpu). The only publicly exposed way to modify the virtual hart state
@@ -18,15 +35,67 @@ pub struct ConfidentialHart {
     // Safety: Careful, HardwareHart and ConfidentialHart must both start with the HartState element because based on
     // this we automatically calculate offsets of registers' and CSRs' for the asm code.
     confidential_hart_state: HartState,
-    pending_request: Option<PendingRequest>,
+    pending_requests: PendingRequests,
+    virtual_counters: VirtualCounters,
+    secure_timers: SecureTimers,
+    interrupt_file: VirtualInterruptFile,
+    instruction_cache: InstructionCache,
+    progress_monitor: ProgressMonitor,
+    // the page owned by the security monitor in which the confidential hart's record is updated on every resume.
+    shared_info: Option<SharedInfo>,
+    // extensions whose calls the security monitor forwards to the hypervisor.
+    sbi_policy: SbiPolicy,
     // a dummy virtual hart means that the confidential_hart is not associated with any confidential VM
     dummy: bool,
+    // a stopped confidential hart has not executed since it was created or stopped with the SBI HSM extension, so
+    // starting it can set its entry state.
+    is_stopped: bool,
 }
 
 impl ConfidentialHart {
+    // The context switch assembly accesses the hart state of the ConfidentialHart using the offsets calculated for the
+    // HardwareHart. Both must start with the hart state, which is checked at compile time.
+    pub(super) const HART_STATE_OFFSET: usize = memoffset::offset_of!(ConfidentialHart, confidential_hart_state);
+    // VS-level software, timer, and external interrupts.
+    const VS_INTERRUPTS: usize = 0b010001000100;
+    const VS_EXTERNAL_INTERRUPT: usize = 1 << 10;
+    const SSTATUS_SIE: usize = 1 << 1;
+    const SSTATUS_SPIE: usize = 1 << 5;
+    const SSTATUS_SPP: usize = 1 << 8;
+    // the same field of the mstatus and vsstatus tracks the state of the floating-point unit.
+    const STATUS_FS_MASK: usize = 0b11 << 13;
+    const STATUS_FS_CLEAN: usize = 0b10 << 13;
+    const MSTATUS_MPP_SHIFT: usize = 11;
+    const MSTATUS_MPP_MASK: usize = 0b11 << Self::MSTATUS_MPP_SHIFT;
+    const SUPERVISOR_MODE: usize = 1;
+    const INSTRUCTION_ACCESS_FAULT: usize = 1;
+    const ILLEGAL_INSTRUCTION: usize = 2;
+    const LOAD_ACCESS_FAULT: usize = 5;
+    const STORE_ACCESS_FAULT: usize = 7;
+    const GUEST_INSTRUCTION_PAGE_FAULT: usize = 20;
+    const GUEST_LOAD_PAGE_FAULT: usize = 21;
+    const CSR_STOPEI: usize = 0x15c;
+    const CSR_STOPI: usize = 0xdb0;
+    // number of times in a row the confidential hart can trap at the same instruction for the same reason before the
+    // security monitor yields the physical hart to the hypervisor. Raise it if emulated instructions legitimately
+    // trap more often without the security monitor moving the confidential hart forward.
+    const MAX_IDENTICAL_TRAPS: usize = 1024;
+
     pub fn dummy(id: usize) -> Self {
         let confidential_hart_state = HartState::empty(id);
-        Self { confidential_hart_state, pending_request: None, dummy: true }
+        Self {
+            confidential_hart_state,
+            pending_requests: PendingRequests::new(),
+            virtual_counters: VirtualCounters::new(),
+            secure_timers: SecureTimers::new(),
+            interrupt_file: VirtualInterruptFile::new(),
+            instruction_cache: InstructionCache::new(),
+            progress_monitor: ProgressMonitor::new(Self::MAX_IDENTICAL_TRAPS),
+            shared_info: None,
+            sbi_policy: SbiPolicy::unrestricted(),
+            dummy: true,
+            is_stopped: true,
+        }
     }
 
     pub fn from_vm_hart_reset(id: usize, from: &HartState) -> Self {
@@ -34,20 +103,45 @@ impl ConfidentialHart {
 
         // delegate VS-level interrupts directly to the confidential VM. All other
         // interrupts will trap in the security monitor.
-        confidential_hart_state.mideleg = 0b010001000100;
+        confidential_hart_state.mideleg = Self::VS_INTERRUPTS;
         confidential_hart_state.hideleg = confidential_hart_state.mideleg;
+        // no interrupts are pending until the hypervisor injects them.
+        confidential_hart_state.hvip = 0;
 
-        // delegate exceptions that can be handled directly in the confidential VM
-        confidential_hart_state.medeleg = 0b1011001111111111;
+        // delegate exceptions that can be handled directly in the confidential VM. Load and store access faults trap in
+        // the security monitor, so they are never mistaken for MMIO. Illegal instructions trap in the security monitor,
+        // so it can emulate the ones it virtualizes before reflecting the others to the confidential VM.
+        confidential_hart_state.medeleg = 0b1011001101011011;
         confidential_hart_state.hedeleg = confidential_hart_state.medeleg;
 
-        Self { confidential_hart_state, pending_request: None, dummy: false }
+        // reading counters traps in the security monitor, which exposes values virtualized per confidential hart
+        confidential_hart_state.hcounteren = 0;
+
+        // like a hart started with the SBI HSM extension, the confidential hart finds its id in a0. This is the id
+        // within the confidential VM, which does not change when the hypervisor schedules the confidential hart
+        // on another physical hart.
+        confidential_hart_state.set_gpr(GpRegister::a0, id);
+
+        Self {
+            confidential_hart_state,
+            pending_requests: PendingRequests::new(),
+            virtual_counters: VirtualCounters::new(),
+            secure_timers: SecureTimers::new(),
+            interrupt_file: VirtualInterruptFile::new(),
+            instruction_cache: InstructionCache::new(),
+            progress_monitor: ProgressMonitor::new(Self::MAX_IDENTICAL_TRAPS),
+            shared_info: None,
+            sbi_policy: SbiPolicy::unrestricted(),
+            dummy: false,
+            is_stopped: true,
+        }
     }
 
-    pub fn from_vm_hart(id: usize, from: &HartState) -> Self {
+    pub fn from_vm_hart(id: usize, from: &HartState) -> Result<Self> {
         let mut confidential_hart = Self::from_vm_hart_reset(id, from);
-        GpRegisters::iter().for_each(|x| {
-            confidential_hart.confidential_hart_state.gprs.0[x] = from.gprs.0[x];
+        // the slot of the zero register (x0) is not copied, so it never holds a value left by the hypervisor.
+        GpRegisters::iter().filter_map(GpRegister::from_index).for_each(|register| {
+            confidential_hart.confidential_hart_state.set_gpr(register, from.gpr(register));
         });
         FpRegisters::iter().for_each(|x| {
             confidential_hart.confidential_hart_state.fprs.0[x] = from.fprs.0[x];
@@ -59,52 +153,325 @@ impl ConfidentialHart {
         // the hypervisor to let him know that this VM become an confidential VM.
         // The hypervisor should then return to the confidential VM providing it
         // with the result of this transformation.
-        confidential_hart.pending_request = Some(PendingRequest::SbiRequest());
-        confidential_hart
+        confidential_hart.push_request(PendingRequest::SbiRequest())?;
+        confidential_hart.is_stopped = false;
+        Ok(confidential_hart)
+    }
+
+    /// Returns the guest virtual address of the instruction that trapped and the vsatp that translates it. The vsatp is
+    /// the one saved when the hart trapped, so the address translation follows the paging mode that the confidential VM
+    /// might have changed at any time since it started, e.g., when enabling paging during its boot.
+    pub fn instruction_address(&self) -> (usize, usize) {
+        (self.confidential_hart_state.mepc, self.confidential_hart_state.vsatp)
     }
 
     pub fn confidential_vm_id(&self) -> ConfidentialVmId {
-        ConfidentialVmId::new(riscv::register::hgatp::Hgatp::from(self.confidential_hart_state.hgatp).vmid())
+        ConfidentialVmId::new(Hgatp::from(self.confidential_hart_state.hgatp).vmid())
+    }
+
+    /// Fails unless the root page table that the hardware uses to translate the confidential VM's physical addresses
+    /// when the confidential hart executes is the given one.
+    pub(super) fn assure_root_page_table(&self, root_page_table_address: usize) -> Result<()> {
+        assure!(
+            Hgatp::from(self.confidential_hart_state.hgatp).address() == root_page_table_address,
+            Error::HgatpNotPointingToConfidentialRoot()
+        )
     }
 
     pub(super) fn confidential_hart_id(&self) -> usize {
         self.confidential_hart_state.id
     }
 
+    /// Returns the GPRs x0-x31 followed by mepc, mstatus, vsstatus, vsie, vstvec, vsepc, vscause, vstval, vsatp, hvip,
+    /// and the number of load and store instructions decoded because they were not in the instruction cache.
+    #[cfg(feature = "insecure_debug")]
+    pub fn dump_state(&self) -> Vec<usize> {
+        let state = &self.confidential_hart_state;
+        let csrs = [
+            state.mepc,
+            state.mstatus,
+            state.vsstatus,
+            state.vsie,
+            state.vstvec,
+            state.vsepc,
+            state.vscause,
+            state.vstval,
+            state.vsatp,
+            state.hvip,
+            self.instruction_cache.decodes(),
+        ];
+        GpRegisters::iter()
+            .filter_map(GpRegister::from_index)
+            .map(|register| state.gpr(register))
+            .chain(csrs.iter().copied())
+            .collect()
+    }
+
+    /// Removes and returns the oldest request awaiting the hypervisor's response.
     pub fn take_request(&mut self) -> Option<PendingRequest> {
-        self.pending_request.take()
+        self.pending_requests.take_next()
+    }
+
+    /// Returns true if a share request of this confidential hart awaits the hypervisor's response.
+    pub fn is_sharing(&self) -> bool {
+        self.pending_requests.contains(|request| matches!(request, PendingRequest::SharePage(_)))
     }
 
     pub fn is_dummy(&self) -> bool {
         self.dummy
     }
 
-    pub fn set_pending_request(&mut self, request: PendingRequest) -> Result<(), Error> {
-        assure!(self.pending_request.is_none(), Error::PendingRequest())?;
-        self.pending_request = Some(request);
-        Ok(())
+    /// Makes the request the only one awaiting the hypervisor's response. Fails if another request is pending.
+    pub fn set_pending_request(&mut self, request: PendingRequest) -> Result<()> {
+        assure!(self.pending_requests.is_empty(), Error::PendingRequest())?;
+        self.push_request(request)
+    }
+
+    /// Enqueues the request after the pending ones, so a handler can chain a follow-up request to the current one.
+    /// Fails if the queue of pending requests is full.
+    pub fn push_request(&mut self, request: PendingRequest) -> Result<()> {
+        self.pending_requests.push(request)
+    }
+
+    /// Must be called whenever the confidential hart traps in the security monitor.
+    pub fn suspend(&mut self) {
+        self.virtual_counters.suspend();
+    }
+
+    /// Records the exception that has just trapped in the security monitor. Returns the address of the instruction and
+    /// the cause if the confidential hart does not make forward progress. Interrupts are not counted, because they do
+    /// not depend on the executed instruction.
+    pub fn record_trap(&mut self) -> Option<(usize, usize)> {
+        let mcause = riscv::register::mcause::read();
+        if mcause.is_interrupt() {
+            return None;
+        }
+        let (address, cause) = (self.confidential_hart_state.mepc, mcause.code());
+        self.progress_monitor.record_trap(address, cause).then_some((address, cause))
+    }
+
+    pub fn set_secure_timer(&mut self, request: SecureTimerRequest) -> Result<()> {
+        self.secure_timers.set(request.index(), request.deadline())
+    }
+
+    /// Returns the flags of secure timers that expired since the confidential VM last read them and clears them.
+    /// Zeroes the floating-point registers and marks the floating-point state clean, so secrets that the confidential
+    /// VM kept in these registers do not remain in the confidential hart state. The floating-point unit stays disabled
+    /// if the confidential VM disabled it. The hart state has no vector registers to clear.
+    pub fn clear_extended_state(&mut self) {
+        let state = &mut self.confidential_hart_state;
+        state.fprs = FpRegisters::empty();
+        state.fcsr = 0;
+        [&mut state.mstatus, &mut state.vsstatus]
+            .into_iter()
+            .filter(|status| **status & Self::STATUS_FS_MASK != 0)
+            .for_each(|status| *status = (*status & !Self::STATUS_FS_MASK) | Self::STATUS_FS_CLEAN);
+    }
+
+    pub fn take_expired_secure_timers(&mut self) -> usize {
+        self.expire_secure_timers();
+        self.secure_timers.take_expired()
+    }
+
+    /// Returns the nearest deadline of the armed secure timers in the time of the physical hart.
+    pub fn secure_timer_deadline(&self) -> Option<usize> {
+        self.secure_timers.nearest_deadline().map(|deadline| self.hardware_time(deadline))
+    }
+
+    /// Converts the time exposed to the confidential VM to the time of the physical hart, e.g., to forward a timer
+    /// deadline set by the confidential VM to the hypervisor.
+    pub fn hardware_time(&self, time: usize) -> usize {
+        self.virtual_counters.hardware_time(time)
+    }
+
+    /// Must be called whenever the hypervisor schedules the confidential hart on a physical hart.
+    pub fn migrate(&mut self) {
+        self.virtual_counters.migrate();
+        self.is_stopped = false;
+    }
+
+    /// Sets the state in which the stopped confidential hart starts, as defined by the SBI HSM extension: it executes
+    /// from the start address with translation disabled and finds its id within the confidential VM in a0 and the
+    /// opaque value in a1. The id does not depend on the physical hart on which the hypervisor starts it. A
+    /// confidential hart that is not stopped keeps its state, so the hypervisor reports it as already started.
+    pub fn prepare_start(&mut self, start_address: usize, opaque: usize) {
+        if self.is_stopped {
+            // the result of the call that stopped the confidential hart must not overwrite its entry state.
+            self.pending_requests = PendingRequests::new();
+            let id = self.confidential_hart_id();
+            self.confidential_hart_state.mepc = start_address;
+            self.confidential_hart_state.vsatp = 0;
+            self.confidential_hart_state.set_gpr(GpRegister::a0, id);
+            self.confidential_hart_state.set_gpr(GpRegister::a1, opaque);
+        }
+    }
+
+    /// Records that the confidential hart asked to be stopped with the SBI HSM extension, so it can be started again.
+    pub fn stop(&mut self) {
+        self.is_stopped = true;
+    }
+
+    /// Returns the value of the CSR as seen by the confidential VM.
+    pub fn read_virtual_csr(&self, csr: usize) -> Result<usize> {
+        const CSR_CYCLE: usize = 0xc00;
+        const CSR_TIME: usize = 0xc01;
+        const CSR_INSTRET: usize = 0xc02;
+        const CSR_CYCLEH: usize = 0xc80;
+        const CSR_TIMEH: usize = 0xc81;
+        const CSR_INSTRETH: usize = 0xc82;
+        const HIGH_HALF_SHIFT: usize = 32;
+
+        match csr {
+            CSR_CYCLE => Ok(self.virtual_counters.cycle()),
+            CSR_TIME => Ok(self.virtual_counters.time()),
+            CSR_INSTRET => Ok(self.virtual_counters.instret()),
+            // the upper halves of counters are only accessible to 32-bit software.
+            CSR_CYCLEH => Ok(self.virtual_counters.cycle() >> HIGH_HALF_SHIFT),
+            CSR_TIMEH => Ok(self.virtual_counters.time() >> HIGH_HALF_SHIFT),
+            CSR_INSTRETH => Ok(self.virtual_counters.instret() >> HIGH_HALF_SHIFT),
+            Self::CSR_STOPEI => Ok(self.interrupt_file.stopei()),
+            Self::CSR_STOPI => Ok(self.interrupt_file.stopi()),
+            _ => Err(Error::NotSupportedCsr(csr)),
+        }
+    }
+
+    /// Emulates the write to the CSR and returns the value of the CSR from before the write. Writing stopei claims the
+    /// highest-priority pending external interrupt regardless of the written value.
+    pub fn write_virtual_csr(&mut self, csr: usize) -> Result<usize> {
+        match csr {
+            Self::CSR_STOPEI => {
+                let stopei = self.interrupt_file.claim();
+                if !self.interrupt_file.is_pending() {
+                    self.confidential_hart_state.hvip &= !Self::VS_EXTERNAL_INTERRUPT;
+                }
+                Ok(stopei)
+            }
+            _ => Err(Error::NotSupportedCsr(csr)),
+        }
     }
 }
 
 // functions to inject information to a confidential VM.
 impl ConfidentialHart {
-    pub fn set_hgatp(&mut self, hgatp: usize) {
-        self.confidential_hart_state.hgatp = hgatp;
+    /// Sets the hgatp that the hardware uses to translate the confidential VM's physical addresses. The hgatp must
+    /// point to a root page table located in the confidential memory, use a paging mode supported by the security
+    /// monitor, and carry the VMID of the confidential VM this hart belongs to.
+    pub fn set_hgatp(&mut self, hgatp: Hgatp, confidential_vm_id: ConfidentialVmId) -> Result<()> {
+        use crate::core::memory_tracker::CONFIDENTIAL_MEMORY_RANGE;
+        use crate::error::NOT_INITIALIZED_CONFIDENTIAL_MEMORY;
+        let paging_mode = hgatp.mode().ok_or(Error::InvalidHgatp())?;
+        PagingSystem::from(&paging_mode).ok_or(Error::InvalidHgatp())?;
+        let confidential_memory = CONFIDENTIAL_MEMORY_RANGE.get().expect(NOT_INITIALIZED_CONFIDENTIAL_MEMORY);
+        assure!(confidential_memory.contains(&hgatp.address()), Error::InvalidHgatp())?;
+        assure!(hgatp.vmid() == confidential_vm_id.usize(), Error::InvalidHgatp())?;
+        self.confidential_hart_state.hgatp = hgatp.bits();
+        Ok(())
+    }
+
+    pub fn set_shared_info(&mut self, shared_info: SharedInfo) {
+        self.shared_info = Some(shared_info);
+    }
+
+    pub fn set_sbi_policy(&mut self, sbi_policy: SbiPolicy) {
+        self.sbi_policy = sbi_policy;
+    }
+
+    pub fn sbi_policy(&self) -> SbiPolicy {
+        self.sbi_policy
+    }
+
+    pub fn set_virtual_time(&mut self, virtual_time: Arc<VirtualTime>) {
+        self.virtual_counters.set_virtual_time(virtual_time);
+    }
+
+    /// Marks the given VS-level interrupts pending in hvip and clears the others. The security monitor never redirects
+    /// the confidential hart to its trap vector. Instead, the hardware delivers the interrupt once the confidential VM
+    /// enables it in sstatus and sie, so the interrupt stays pending while the confidential VM masks it.
+    pub fn inject_interrupts(&mut self, hvip: usize) {
+        self.confidential_hart_state.hvip = hvip & Self::VS_INTERRUPTS | self.secure_timers.pending_interrupt();
+        if self.interrupt_file.is_pending() {
+            self.confidential_hart_state.hvip |= Self::VS_EXTERNAL_INTERRUPT;
+        }
+    }
+
+    /// Marks the external interrupt pending in the confidential hart's virtual interrupt file. The confidential VM
+    /// claims it via stopei.
+    pub fn inject_external_interrupt(&mut self, identity: usize) {
+        self.interrupt_file.set_pending(identity);
+        if self.interrupt_file.is_pending() {
+            self.confidential_hart_state.hvip |= Self::VS_EXTERNAL_INTERRUPT;
+        }
+    }
+
+    /// Marks the secure timers whose deadline passed as expired and raises the interrupt notifying the confidential
+    /// VM. Returns true if any timer expired.
+    pub fn expire_secure_timers(&mut self) -> bool {
+        let expired = self.secure_timers.expire(self.virtual_counters.time());
+        self.confidential_hart_state.hvip |= self.secure_timers.pending_interrupt();
+        expired
     }
 
-    pub fn apply(&mut self, transformation: ExposeToConfidentialVm) -> usize {
-        match transformation {
-            ExposeToConfidentialVm::SbiResult(v) => self.apply_sbi_result(v),
-            ExposeToConfidentialVm::GuestLoadPageFaultResult(v) => self.apply_guest_load_page_fault_result(v),
+    /// Applies the transformation to the confidential hart state and returns the address of the state, from which the
+    /// context switch restores the confidential hart. A rejected transformation leaves the state unchanged, so the
+    /// confidential hart re-executes the instruction that trapped.
+    pub fn apply(&mut self, transformation: ExposeToConfidentialVm) -> Result<usize> {
+        let mepc = self.confidential_hart_state.mepc;
+        let result = match transformation {
+            ExposeToConfidentialVm::SbiResult(v) => {
+                self.apply_sbi_result(v);
+                Ok(())
+            }
+            ExposeToConfidentialVm::GuestLoadPageFaultResult(v) => {
+                self.apply_guest_load_page_fault_result(v);
+                Ok(())
+            }
             ExposeToConfidentialVm::GuestStorePageFaultResult(v) => self.apply_guest_store_page_fault_result(v),
-            ExposeToConfidentialVm::Resume() => {}
+            ExposeToConfidentialVm::CsrReadResult(v) => {
+                self.apply_csr_read_result(v);
+                Ok(())
+            }
+            ExposeToConfidentialVm::VmInfoResult(v) => {
+                self.apply_vm_info_result(v);
+                Ok(())
+            }
+            ExposeToConfidentialVm::CounterResult(v) => {
+                self.apply_counter_result(v);
+                Ok(())
+            }
+            ExposeToConfidentialVm::AccessFault(v) => {
+                self.apply_access_fault(v);
+                Ok(())
+            }
+            ExposeToConfidentialVm::IllegalInstruction(v) => {
+                self.apply_illegal_instruction(v);
+                Ok(())
+            }
+            ExposeToConfidentialVm::Resume() => Ok(()),
+        };
+        // the confidential hart will execute after this transformation is applied.
+        if self.confidential_hart_state.mepc != mepc {
+            self.progress_monitor.record_progress();
         }
+        self.virtual_counters.resume();
+        if let Some(shared_info) = self.shared_info {
+            let pending_interrupts = self.confidential_hart_state.hvip & Self::VS_INTERRUPTS;
+            shared_info.write_hart_record(
+                self.confidential_hart_id(),
+                self.secure_timers.expired(),
+                pending_interrupts,
+            );
+        }
+        result.map(|_| self.address())
+    }
+
+    pub fn address(&self) -> usize {
         core::ptr::addr_of!(self.confidential_hart_state) as usize
     }
 
     fn apply_sbi_result(&mut self, result: SbiResult) {
         self.confidential_hart_state.set_gpr(GpRegister::a0, result.a0());
         self.confidential_hart_state.set_gpr(GpRegister::a1, result.a1());
+        result.additional_values().for_each(|(register, value)| self.confidential_hart_state.set_gpr(register, value));
         self.confidential_hart_state.mepc += result.pc_offset();
     }
 
@@ -113,9 +480,72 @@ impl ConfidentialHart {
         self.confidential_hart_state.mepc += result.instruction_length();
     }
 
-    fn apply_guest_store_page_fault_result(&mut self, result: GuestStorePageFaultResult) {
+    /// The store has completed only if a store request is still pending, the confidential hart still executes the
+    /// store instruction that trapped, and the hypervisor emulated an instruction of the length recorded when the
+    /// confidential hart faulted. Otherwise, mepc would skip an instruction whose store never reached the hypervisor.
+    fn apply_guest_store_page_fault_result(&mut self, result: GuestStorePageFaultResult) -> Result<()> {
+        assure!(
+            matches!(self.pending_requests.next(), Some(PendingRequest::GuestStorePageFault(_))),
+            Error::UnexpectedResult()
+        )?;
+        // a mismatched store request is dropped, the store faults again when the confidential hart re-executes it.
+        let request = match self.pending_requests.take_next() {
+            Some(PendingRequest::GuestStorePageFault(request)) => request,
+            _ => return Err(Error::UnexpectedResult()),
+        };
+        assure!(request.mepc() == self.confidential_hart_state.mepc, Error::UnexpectedResult())?;
+        request.assure_completed_by(&result)?;
+        self.confidential_hart_state.mepc += request.instruction_length();
+        Ok(())
+    }
+
+    fn apply_csr_read_result(&mut self, result: CsrReadResult) {
+        self.confidential_hart_state.set_gpr(result.result_gpr(), result.value());
         self.confidential_hart_state.mepc += result.instruction_length();
     }
+
+    fn apply_vm_info_result(&mut self, result: VmInfoResult) {
+        let additional_values = [result.confidential_hart_id(), result.hart_exits()];
+        self.apply_sbi_result(
+            SbiResult::success(result.confidential_vm_id()).with_additional_values(&additional_values),
+        );
+    }
+
+    fn apply_counter_result(&mut self, result: CounterResult) {
+        let additional_values = [result.is_persistent() as usize];
+        self.apply_sbi_result(SbiResult::success(result.value()).with_additional_values(&additional_values));
+    }
+
+    fn apply_access_fault(&mut self, request: AccessFaultRequest) {
+        self.raise_exception(request.cause(), request.address());
+    }
+
+    /// The confidential VM observes the illegal instruction exception also when the instruction caused the virtual
+    /// instruction exception, which is meant for the hypervisor.
+    fn apply_illegal_instruction(&mut self, request: IllegalInstructionRequest) {
+        self.raise_exception(Self::ILLEGAL_INSTRUCTION, request.instruction());
+    }
+
+    /// Redirects the confidential hart to its VS-level trap handler like the hardware does for a delegated exception.
+    fn raise_exception(&mut self, cause: usize, trap_value: usize) {
+        let state = &mut self.confidential_hart_state;
+        let from_supervisor =
+            (state.mstatus & Self::MSTATUS_MPP_MASK) >> Self::MSTATUS_MPP_SHIFT == Self::SUPERVISOR_MODE;
+        let interrupts_enabled = state.vsstatus & Self::SSTATUS_SIE != 0;
+        state.vsepc = state.mepc;
+        state.vscause = cause;
+        state.vstval = trap_value;
+        state.vsstatus &= !(Self::SSTATUS_SIE | Self::SSTATUS_SPIE | Self::SSTATUS_SPP);
+        if interrupts_enabled {
+            state.vsstatus |= Self::SSTATUS_SPIE;
+        }
+        if from_supervisor {
+            state.vsstatus |= Self::SSTATUS_SPP;
+        }
+        // the trap handler executes in the VS-mode and exceptions always jump to the base address of the trap vector.
+        state.mstatus = (state.mstatus & !Self::MSTATUS_MPP_MASK) | (Self::SUPERVISOR_MODE << Self::MSTATUS_MPP_SHIFT);
+        state.mepc = state.vstvec & !0b11;
+    }
 }
 
 // functions to expose portions of confidential virtual hart state
@@ -128,34 +558,76 @@ impl ConfidentialHart {
         SbiRequest::from_hart_state(&self.confidential_hart_state)
     }
 
-    pub fn guest_load_page_fault_request(&self) -> Result<(GuestLoadPageFaultRequest, MmioLoadRequest), Error> {
+    /// Returns the answer to the SBI call whose result is a constant, so the security monitor can answer it before
+    /// dispatching the trap to a handler. Returns None for all other traps.
+    pub fn precomputed_sbi_result(&self) -> Option<SbiResult> {
+        match self.trap_reason() {
+            TrapReason::VsEcall(SbiBaseRequest::EXTID, function_id) => SbiBaseRequest::precomputed_result(function_id),
+            _ => None,
+        }
+    }
+
+    pub fn hart_start_request(&self) -> HartStartRequest {
+        HartStartRequest::new(
+            self.confidential_hart_state.gpr(GpRegister::a0),
+            self.confidential_hart_state.gpr(GpRegister::a1),
+            self.confidential_hart_state.gpr(GpRegister::a2),
+            self.hypercall_request(),
+        )
+    }
+
+    pub fn legacy_sbi_request(&self) -> LegacySbiRequest {
+        LegacySbiRequest::new(
+            self.confidential_hart_state.gpr(GpRegister::a7),
+            self.confidential_hart_state.gpr(GpRegister::a0),
+        )
+    }
+
+    pub fn console_write_byte_request(&self) -> ConsoleWriteByteRequest {
+        ConsoleWriteByteRequest::new(self.confidential_hart_state.gpr(GpRegister::a0))
+    }
+
+    pub fn sbi_base_request(&self) -> SbiBaseRequest {
+        SbiBaseRequest::new(
+            self.confidential_hart_state.gpr(GpRegister::a6),
+            self.confidential_hart_state.gpr(GpRegister::a0),
+            self.sbi_policy,
+        )
+    }
+
+    pub fn guest_load_page_fault_request(&mut self) -> Result<(GuestLoadPageFaultRequest, MmioLoadRequest)> {
         let mcause = riscv::register::mcause::read().code();
         let (instruction, instruction_length) = self.read_instruction();
-        let gpr = read_result_gpr(instruction)?;
+        let (gpr, access_width, is_signed, instruction_length) =
+            self.decode_memory_access(instruction, instruction_length)?;
+        let endianness = Endianness::of_trapped_access(&self.confidential_hart_state);
         let mtval = self.confidential_hart_state.mtval;
         let mtval2 = self.confidential_hart_state.mtval2;
 
-        let load_fault_request = GuestLoadPageFaultRequest::new(instruction_length, gpr);
+        let load_fault_request =
+            GuestLoadPageFaultRequest::new(instruction_length, gpr, access_width, is_signed, endianness);
         let mmio_load_request = MmioLoadRequest::new(mcause, mtval, mtval2, instruction);
 
         Ok((load_fault_request, mmio_load_request))
     }
 
-    pub fn guest_store_page_fault_request(&self) -> Result<(GuestStorePageFaultRequest, MmioStoreRequest), Error> {
+    pub fn guest_store_page_fault_request(&mut self) -> Result<(GuestStorePageFaultRequest, MmioStoreRequest)> {
         let mcause = riscv::register::mcause::read().code();
         let (instruction, instruction_length) = self.read_instruction();
-        let gpr = read_result_gpr(instruction)?;
-        let gpr_value = self.confidential_hart_state.gpr(gpr);
+        let (gpr, access_width, _, instruction_length) = self.decode_memory_access(instruction, instruction_length)?;
+        let endianness = Endianness::of_trapped_access(&self.confidential_hart_state);
+        let gpr_value = endianness.to_canonical(self.confidential_hart_state.gpr(gpr), access_width);
         let mtval = self.confidential_hart_state.mtval;
         let mtval2 = self.confidential_hart_state.mtval2;
 
-        let guest_store_page_fault_request = GuestStorePageFaultRequest::new(instruction_length);
-        let mmio_store_request = MmioStoreRequest::new(mcause, mtval, mtval2, instruction, gpr, gpr_value);
+        let mepc = self.confidential_hart_state.mepc;
+        let guest_store_page_fault_request = GuestStorePageFaultRequest::new(instruction_length, mepc);
+        let mmio_store_request = MmioStoreRequest::new(mcause, mtval, mtval2, instruction, gpr, gpr_value, mepc);
 
         Ok((guest_store_page_fault_request, mmio_store_request))
     }
 
-    pub fn share_page_request(&self) -> Result<(SharePageRequest, SbiRequest), Error> {
+    pub fn share_page_request(&self) -> Result<(SharePageRequest, SbiRequest)> {
         let shared_page_address = self.confidential_hart_state.gpr(GpRegister::a0);
         let share_page_request = SharePageRequest::new(shared_page_address)?;
         let sbi_request = SbiRequest::kvm_ace_page_in(shared_page_address);
@@ -163,6 +635,148 @@ impl ConfidentialHart {
         Ok((share_page_request, sbi_request))
     }
 
+    pub fn convert_to_private_request(&self) -> Result<(ConvertToPrivateRequest, SbiRequest)> {
+        let address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let number_of_pages = self.confidential_hart_state.gpr(GpRegister::a1);
+        let convert_to_private_request = ConvertToPrivateRequest::new(address, number_of_pages)?;
+        let sbi_request = SbiRequest::kvm_ace_page_out(address, number_of_pages);
+
+        Ok((convert_to_private_request, sbi_request))
+    }
+
+    pub fn remap_page_request(&self) -> Result<RemapPageRequest> {
+        let old_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let new_address = self.confidential_hart_state.gpr(GpRegister::a1);
+        RemapPageRequest::new(old_address, new_address)
+    }
+
+    pub fn flush_guest_tlb_request(&self) -> Result<FlushGuestTlbRequest> {
+        let address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let size = self.confidential_hart_state.gpr(GpRegister::a1);
+        FlushGuestTlbRequest::new(address, size)
+    }
+
+    pub fn secure_timer_request(&self) -> SecureTimerRequest {
+        let index = self.confidential_hart_state.gpr(GpRegister::a0);
+        let deadline = self.confidential_hart_state.gpr(GpRegister::a1);
+        SecureTimerRequest::new(index, deadline)
+    }
+
+    pub fn accept_page_request(&self) -> AcceptPageRequest {
+        AcceptPageRequest::new(self.confidential_hart_state.gpr(GpRegister::a0))
+    }
+
+    pub fn acknowledge_revocation_request(&self) -> AcknowledgeRevocationRequest {
+        AcknowledgeRevocationRequest::new(self.confidential_hart_state.gpr(GpRegister::a0))
+    }
+
+    pub fn pin_private_request(&self) -> PinPrivateRequest {
+        PinPrivateRequest::new(self.confidential_hart_state.gpr(GpRegister::a0))
+    }
+
+    pub fn access_fault_request(&self) -> AccessFaultRequest {
+        let cause = riscv::register::mcause::read().code();
+        AccessFaultRequest::new(cause, self.confidential_hart_state.mtval)
+    }
+
+    pub fn page_import_request(&self) -> PageImportRequest {
+        // mtval2 holds the faulting confidential VM's physical address shifted right by 2 bits. The lowest bits are the
+        // same as in the virtual address stored in mtval.
+        let address = (self.confidential_hart_state.mtval2 << 2) | (self.confidential_hart_state.mtval & 0b11);
+        // the confidential VM observes the access to a page it has not accepted as an access fault of the same kind.
+        let access_fault_cause = match riscv::register::mcause::read().code() {
+            Self::GUEST_INSTRUCTION_PAGE_FAULT => Self::INSTRUCTION_ACCESS_FAULT,
+            Self::GUEST_LOAD_PAGE_FAULT => Self::LOAD_ACCESS_FAULT,
+            _ => Self::STORE_ACCESS_FAULT,
+        };
+        PageImportRequest::new(address, access_fault_cause, self.confidential_hart_state.mtval)
+    }
+
+    pub fn memory_regions_request(&self) -> Result<MemoryRegionsRequest> {
+        let buffer_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let number_of_entries = self.confidential_hart_state.gpr(GpRegister::a1);
+        MemoryRegionsRequest::new(buffer_address, number_of_entries)
+    }
+
+    pub fn fault_records_request(&self) -> Result<FaultRecordsRequest> {
+        let buffer_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let number_of_entries = self.confidential_hart_state.gpr(GpRegister::a1);
+        FaultRecordsRequest::new(buffer_address, number_of_entries)
+    }
+
+    pub fn attestation_key_request(&self) -> AttestationKeyRequest {
+        let buffer_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let buffer_size = self.confidential_hart_state.gpr(GpRegister::a1);
+        AttestationKeyRequest::new(buffer_address, buffer_size)
+    }
+
+    pub fn measurements_request(&self) -> Result<MeasurementsRequest> {
+        let buffer_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let buffer_size = self.confidential_hart_state.gpr(GpRegister::a1);
+        let flags = self.confidential_hart_state.gpr(GpRegister::a2);
+        MeasurementsRequest::new(buffer_address, buffer_size, flags)
+    }
+
+    pub fn log_event_request(&self) -> LogEventRequest {
+        let description_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let description_size = self.confidential_hart_state.gpr(GpRegister::a1);
+        let measurement_index = self.confidential_hart_state.gpr(GpRegister::a2);
+        LogEventRequest::new(description_address, description_size, measurement_index)
+    }
+
+    pub fn event_log_request(&self) -> EventLogRequest {
+        let buffer_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let buffer_size = self.confidential_hart_state.gpr(GpRegister::a1);
+        EventLogRequest::new(buffer_address, buffer_size)
+    }
+
+    pub fn counter_request(&self) -> CounterRequest {
+        CounterRequest::new(self.confidential_hart_state.gpr(GpRegister::a0))
+    }
+
+    pub fn page_status_request(&self) -> PageStatusRequest {
+        PageStatusRequest::new(self.confidential_hart_state.gpr(GpRegister::a0))
+    }
+
+    pub fn shared_memcpy_request(&self) -> SharedMemcpyRequest {
+        let destination_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let source_address = self.confidential_hart_state.gpr(GpRegister::a1);
+        let size = self.confidential_hart_state.gpr(GpRegister::a2);
+        SharedMemcpyRequest::new(destination_address, source_address, size)
+    }
+
+    pub fn sealing_request(&self) -> SealingRequest {
+        let input_address = self.confidential_hart_state.gpr(GpRegister::a0);
+        let input_size = self.confidential_hart_state.gpr(GpRegister::a1);
+        let output_address = self.confidential_hart_state.gpr(GpRegister::a2);
+        let output_capacity = self.confidential_hart_state.gpr(GpRegister::a3);
+        SealingRequest::new(input_address, input_size, output_address, output_capacity)
+    }
+
+    pub fn vm_info_result(&self) -> VmInfoResult {
+        VmInfoResult::new(self.confidential_vm_id(), self.confidential_hart_id())
+    }
+
+    pub fn illegal_instruction_request(&self) -> IllegalInstructionRequest {
+        let (instruction, instruction_length) = self.read_instruction();
+        IllegalInstructionRequest::new(instruction, self.csr_read_request(instruction, instruction_length).ok())
+    }
+
+    fn csr_read_request(&self, instruction: usize, instruction_length: usize) -> Result<CsrReadRequest> {
+        use riscv_decode::Instruction::{Csrrs, Csrrw};
+        let to_gpr =
+            |index: u32| GpRegister::from_index(index as usize).ok_or(Error::InvalidRiscvInstruction(instruction));
+        match riscv_decode::decode(instruction as u32)? {
+            // csrr rd, csr is the pseudo instruction for csrrs rd, csr, x0
+            Csrrs(i) if i.rs1() == 0 => Ok(CsrReadRequest::new(i.csr() as usize, to_gpr(i.rd())?, instruction_length)),
+            // the value written to stopei is ignored, the write only claims the interrupt.
+            Csrrw(i) if i.csr() as usize == Self::CSR_STOPEI => {
+                Ok(CsrReadRequest::new_write(i.csr() as usize, to_gpr(i.rd())?, instruction_length))
+            }
+            _ => Err(Error::InvalidRiscvInstruction(instruction)),
+        }
+    }
+
     fn read_instruction(&self) -> (usize, usize) {
         // mepc stores the virtual address of the instruction that caused trap. Setting
         // mstatus.MPRV bit allows reading the faulting instruction in memory using the
@@ -184,10 +798,66 @@ impl ConfidentialHart {
 
         (instruction, instruction_length)
     }
+
+    /// Decodes the load or store instruction of the given length that accessed MMIO, looking it up in the instruction
+    /// cache first.
+    fn decode_memory_access(&mut self, instruction: usize, instruction_length: usize) -> Result<DecodedAccess> {
+        // mepc advances by the instruction length after the emulation, so a decoded form of a different length would
+        // resume the confidential hart in the middle of an instruction.
+        assure_instruction_length(instruction, instruction_length)?;
+        if let Some(decoded_access) = self.instruction_cache.get(instruction) {
+            return Ok(decoded_access);
+        }
+        let gpr = read_result_gpr(instruction)?;
+        let (access_width, is_signed) = read_access_width(instruction)?;
+        let decoded_access = (gpr, access_width, is_signed, instruction_length);
+        self.instruction_cache.insert(instruction, decoded_access);
+        Ok(decoded_access)
+    }
+}
+
+/// Returns the number of bytes accessed by the load or store instruction and whether the loaded value is sign-extended.
+pub(super) fn read_access_width(instruction: usize) -> Result<(usize, bool)> {
+    use riscv_decode::Instruction::{Lb, Lbu, Ld, Lh, Lhu, Lw, Lwu, Sb, Sd, Sh, Sw};
+    match riscv_decode::decode(instruction as u32) {
+        Ok(Lb(_)) => Ok((1, true)),
+        Ok(Lbu(_)) | Ok(Sb(_)) => Ok((1, false)),
+        Ok(Lh(_)) => Ok((2, true)),
+        Ok(Lhu(_)) | Ok(Sh(_)) => Ok((2, false)),
+        Ok(Lw(_)) => Ok((4, true)),
+        Ok(Lwu(_)) | Ok(Sw(_)) => Ok((4, false)),
+        Ok(Ld(_)) | Ok(Sd(_)) => Ok((8, false)),
+        _ => {
+            // compressed instructions: c.lw and c.lwsp load a sign-extended word, c.sw and c.swsp store a word, and
+            // c.ld, c.ldsp, c.sd, c.sdsp access a double word.
+            const INSN_MASK_C_MEMORY_ACCESS: usize = 0xe003;
+            match instruction & INSN_MASK_C_MEMORY_ACCESS {
+                0x4000 | 0x4002 => Ok((4, true)),
+                0xc000 | 0xc002 => Ok((4, false)),
+                0x6000 | 0x6002 | 0xe000 | 0xe002 => Ok((8, false)),
+                _ => Err(Error::InvalidRiscvInstruction(instruction)),
+            }
+        }
+    }
+}
+
+/// Fails if the length bits of the opcode do not encode the given instruction length or the instruction has bits set
+/// beyond this length. Only 2-byte compressed and 4-byte instructions are decoded, so longer encodings are rejected.
+fn assure_instruction_length(instruction: usize, instruction_length: usize) -> Result<()> {
+    const LENGTH_BITS_MASK: usize = 0b11;
+    const LONGER_LENGTH_BITS_MASK: usize = 0b11100;
+    let encoded_length = match instruction & LENGTH_BITS_MASK {
+        LENGTH_BITS_MASK if instruction & LONGER_LENGTH_BITS_MASK == LONGER_LENGTH_BITS_MASK => None,
+        LENGTH_BITS_MASK => Some(4),
+        _ => Some(2),
+    };
+    let is_trimmed = instruction.checked_shr(8 * instruction_length as u32).unwrap_or(0) == 0;
+    let is_consistent = encoded_length == Some(instruction_length) && is_trimmed;
+    assure!(is_consistent, Error::InconsistentInstruction(instruction))
 }
 
 // TODO: remove below once riscv_decode supports compressed instructions
-fn read_result_gpr(mtinst: usize) -> Result<GpRegister, Error> {
+fn read_result_gpr(mtinst: usize) -> Result<GpRegister> {
     use riscv_decode::Instruction::{Lb, Lbu, Ld, Lh, Lhu, Lw, Lwu, Sb, Sd, Sh, Sw};
     let register_index = match riscv_decode::decode(mtinst as u32) {
         Ok(Sb(i)) => Ok(i.rs2()),
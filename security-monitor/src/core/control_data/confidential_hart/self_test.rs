@@ -0,0 +1,136 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::core::memory_tracker::CONFIDENTIAL_MEMORY_RANGE;
+use riscv::register::hgatp::HgatpMode;
+
+/// Applies the result of a call that returns values in all registers a0-a5 and checks that every register holds
+/// its value afterwards. Then checks that clearing the extended state zeroes the dirty floating-point registers.
+pub fn run() -> Result<()> {
+    let mut confidential_hart = ConfidentialHart::dummy(0);
+    let result = SbiResult::success(1).with_additional_values(&[2, 3, 4, 5]);
+    confidential_hart.apply(ExposeToConfidentialVm::SbiResult(result))?;
+    let registers = [GpRegister::a0, GpRegister::a1, GpRegister::a2, GpRegister::a3, GpRegister::a4, GpRegister::a5];
+    let is_applied = registers
+        .iter()
+        .enumerate()
+        .all(|(value, register)| confidential_hart.confidential_hart_state.gpr(*register) == value);
+    assure!(is_applied, Error::SelfTestFailed("sbi result"))?;
+
+    let state = &mut confidential_hart.confidential_hart_state;
+    FpRegisters::iter().for_each(|x| state.fprs.0[x] = x + 1);
+    state.vsstatus |= ConfidentialHart::STATUS_FS_MASK;
+    confidential_hart.clear_extended_state();
+    let state = &confidential_hart.confidential_hart_state;
+    let is_zeroed = FpRegisters::iter().all(|x| state.fprs.0[x] == 0);
+    let is_clean = state.vsstatus & ConfidentialHart::STATUS_FS_MASK == ConfidentialHart::STATUS_FS_CLEAN;
+    assure!(is_zeroed && is_clean, Error::SelfTestFailed("clearing extended state"))?;
+
+    // a follow-up request is taken after the request it was chained to, and a full queue rejects further requests.
+    let request = GuestStorePageFaultRequest::new(4, confidential_hart.confidential_hart_state.mepc);
+    confidential_hart.set_pending_request(PendingRequest::GuestStorePageFault(request))?;
+    let is_single_slot =
+        matches!(confidential_hart.set_pending_request(PendingRequest::SbiRequest()), Err(Error::PendingRequest()));
+    confidential_hart.push_request(PendingRequest::SbiRequest())?;
+    let is_ordered = matches!(confidential_hart.take_request(), Some(PendingRequest::GuestStorePageFault(_)))
+        && matches!(confidential_hart.take_request(), Some(PendingRequest::SbiRequest()))
+        && confidential_hart.take_request().is_none();
+    (0..PendingRequests::CAPACITY).try_for_each(|_| confidential_hart.push_request(PendingRequest::SbiRequest()))?;
+    let is_overflow_rejected =
+        matches!(confidential_hart.push_request(PendingRequest::SbiRequest()), Err(Error::PendingRequestQueueFull()));
+    while confidential_hart.take_request().is_some() {}
+    assure!(is_single_slot && is_ordered && is_overflow_rejected, Error::SelfTestFailed("pending requests"))?;
+
+    // lw a0, 0(a1) and c.lw a0, 0(a1) decode only with the length encoded in their opcodes.
+    let is_lw_decoded =
+        matches!(confidential_hart.decode_memory_access(0x0005a503, 4), Ok((GpRegister::a0, 4, true, 4)));
+    let is_c_lw_decoded = matches!(confidential_hart.decode_memory_access(0x4188, 2), Ok((GpRegister::a0, 4, true, 2)));
+    let is_inconsistent = |instruction, length| {
+        matches!(assure_instruction_length(instruction, length), Err(Error::InconsistentInstruction(_)))
+    };
+    let is_mismatch_rejected = is_inconsistent(0x0005a503, 2)
+        && is_inconsistent(0x4188, 4)
+        && is_inconsistent(0x1_0000_4188, 2)
+        && is_inconsistent(0x003f, 4)
+        && matches!(confidential_hart.decode_memory_access(0x0005a503, 2), Err(Error::InconsistentInstruction(_)));
+    let is_decoded = is_lw_decoded && is_c_lw_decoded;
+    assure!(is_decoded && is_mismatch_rejected, Error::SelfTestFailed("instruction length"))?;
+
+    let root = CONFIDENTIAL_MEMORY_RANGE.get().ok_or(Error::SelfTestFailed("confidential memory"))?.start;
+    let valid_hgatp = Hgatp::new(root, HgatpMode::Sv57x4, 1);
+    let is_accepted = confidential_hart.set_hgatp(valid_hgatp, ConfidentialVmId::new(1)).is_ok();
+    // a Bare mode, a root page table in the non-confidential memory, a VMID of another confidential VM, and an id
+    // that overflows the VMID field.
+    let invalid_hgatps = [
+        (Hgatp::from(valid_hgatp.bits() & !(0b1111 << 60)), 1),
+        (Hgatp::new(0, HgatpMode::Sv57x4, 1), 1),
+        (Hgatp::new(root, HgatpMode::Sv57x4, 2), 1),
+        (Hgatp::new(root, HgatpMode::Sv57x4, 0x4001), 0x4001),
+    ];
+    let are_rejected = invalid_hgatps.iter().all(|(hgatp, id)| {
+        matches!(confidential_hart.set_hgatp(*hgatp, ConfidentialVmId::new(*id)), Err(Error::InvalidHgatp()))
+    });
+    let is_unchanged = confidential_hart.confidential_hart_state.hgatp == valid_hgatp.bits();
+    assure!(is_accepted && are_rejected && is_unchanged, Error::SelfTestFailed("hgatp validation"))?;
+    // the confidential hart enters the confidential VM only on the confidential root page table, never on the
+    // hypervisor's one.
+    let is_confidential_root_allowed = confidential_hart.assure_root_page_table(root).is_ok();
+    let host_root = 0x8000_0000;
+    confidential_hart.confidential_hart_state.hgatp = Hgatp::new(host_root, HgatpMode::Sv57x4, 1).bits();
+    let is_host_root_rejected =
+        matches!(confidential_hart.assure_root_page_table(root), Err(Error::HgatpNotPointingToConfidentialRoot()));
+    confidential_hart.confidential_hart_state.hgatp = valid_hgatp.bits();
+    assure!(is_confidential_root_allowed && is_host_root_rejected, Error::SelfTestFailed("hgatp root"))?;
+
+    // a store result is applied only while its store request is pending, the store instruction did not change, and
+    // the hypervisor emulated an instruction of the recorded length.
+    let mepc = confidential_hart.confidential_hart_state.mepc;
+    let request = || GuestStorePageFaultRequest::new(4, mepc);
+    // the hypervisor reports the emulated instruction by advancing its sepc past the store instruction.
+    let result_of_length = |instruction_length| {
+        let mut hypervisor_hart_state = HartState::empty(0);
+        hypervisor_hart_state.sepc = mepc + instruction_length;
+        let result = GuestStorePageFaultResult::new(&hypervisor_hart_state, &request());
+        ExposeToConfidentialVm::GuestStorePageFaultResult(result)
+    };
+    let result = || result_of_length(4);
+    let is_unrequested_rejected = confidential_hart.apply(result()).is_err();
+    confidential_hart.set_pending_request(PendingRequest::GuestStorePageFault(request()))?;
+    let is_wrong_length_rejected =
+        confidential_hart.apply(result_of_length(2)).is_err() && confidential_hart.confidential_hart_state.mepc == mepc;
+    confidential_hart.set_pending_request(PendingRequest::GuestStorePageFault(request()))?;
+    confidential_hart.confidential_hart_state.mepc = mepc + 2;
+    let is_mismatch_rejected =
+        confidential_hart.apply(result()).is_err() && confidential_hart.confidential_hart_state.mepc == mepc + 2;
+    confidential_hart.confidential_hart_state.mepc = mepc;
+    confidential_hart.set_pending_request(PendingRequest::GuestStorePageFault(request()))?;
+    let is_applied =
+        confidential_hart.apply(result()).is_ok() && confidential_hart.confidential_hart_state.mepc == mepc + 4;
+    let is_rejected = is_unrequested_rejected && is_wrong_length_rejected && is_mismatch_rejected;
+    assure!(is_rejected && is_applied, Error::SelfTestFailed("store result"))?;
+
+    // access faults are decoded separately from guest page faults and raised in the confidential VM's trap handler
+    // instead of being emulated as MMIO.
+    let state = &confidential_hart.confidential_hart_state;
+    let is_decoded = matches!(TrapReason::from_exception(5, state), TrapReason::LoadAccessFault)
+        && matches!(TrapReason::from_exception(7, state), TrapReason::StoreAccessFault)
+        && matches!(TrapReason::from_exception(21, state), TrapReason::GuestLoadPageFault)
+        && matches!(TrapReason::from_exception(23, state), TrapReason::GuestStorePageFault);
+    let state = &mut confidential_hart.confidential_hart_state;
+    let faulting_pc = state.mepc;
+    state.vstvec = 0x8000_1001;
+    state.vsstatus |= ConfidentialHart::SSTATUS_SIE;
+    state.mstatus = (state.mstatus & !ConfidentialHart::MSTATUS_MPP_MASK)
+        | (ConfidentialHart::SUPERVISOR_MODE << ConfidentialHart::MSTATUS_MPP_SHIFT);
+    confidential_hart.apply(ExposeToConfidentialVm::AccessFault(AccessFaultRequest::new(7, 0x9000_0008)))?;
+    let state = &confidential_hart.confidential_hart_state;
+    let is_raised = state.vsepc == faulting_pc
+        && state.vscause == 7
+        && state.vstval == 0x9000_0008
+        && state.mepc == 0x8000_1000
+        && state.vsstatus
+            & (ConfidentialHart::SSTATUS_SIE | ConfidentialHart::SSTATUS_SPIE | ConfidentialHart::SSTATUS_SPP)
+            == ConfidentialHart::SSTATUS_SPIE | ConfidentialHart::SSTATUS_SPP;
+    assure!(is_decoded && is_raised, Error::SelfTestFailed("access fault"))
+}
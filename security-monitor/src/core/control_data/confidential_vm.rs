@@ -1,13 +1,44 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::{ConfidentialHart, HardwareHart};
-use crate::core::mmu::RootPageTable;
-use crate::error::Error;
+use crate::core::attestation::{
+    ImageManifest, MeasurementAlgorithm, MeasurementHasher, SelectedMeasurementAlgorithm, MEASUREMENT_SIZE,
+};
+#[cfg(feature = "memory_audit")]
+use crate::core::control_data::{AuditRecord, MemoryAudit};
+use crate::core::control_data::{
+    ConfidentialHart, EventLog, FaultCode, FaultQueue, FaultRecord, HardwareHart, MonotonicCounters, SbiPolicy,
+    SharedInfo, SharedPageRevocations, SharingPolicy, VirtualTime, TIMEBASE_FREQUENCY,
+};
+use crate::core::hart::MAX_NUMBER_OF_HARTS;
+use crate::core::memory_tracker::{MemoryTracker, SharedPage};
+use crate::core::mmu::{PageSize, RootPageTable};
+use crate::core::transformations::{ConfidentialVmVirtualAddress, FaultRecordsRequest, MemoryRegionsRequest};
+use crate::error::{Error, Result};
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::ops::Range;
 use riscv::register::hgatp::Hgatp;
+use sha2::Digest;
 
-const MAX_HASH_SIZE: usize = 512; // 512b for SHA-512
+// measurement register extended with the content of pages imported into the confidential memory or, when the VM owner
+// supplied the image manifest, with the hashes of pages listed in the manifest.
+const MEMORY_MEASUREMENT: usize = 0;
+// measurement register extended with the sharing and SBI policies declared at the confidential VM creation.
+const SHARING_POLICY_MEASUREMENT: usize = 1;
+// measurement register extended at the creation with the device tree that the confidential VM received at boot.
+const FDT_MEASUREMENT: usize = 2;
+// the device tree header starts with the magic value and the total size of the device tree as big-endian 32-bit values.
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_HEADER_SIZE: usize = 40;
+// bounds the amount of memory copied when measuring the device tree.
+const MAX_FDT_SIZE: usize = 1 << 20;
+
+// Every confidential VM has at least the boot hart. A confidential hart executes only when a physical hart steals it,
+// so the security monitor never runs more confidential harts of a VM at the same time than there are physical harts.
+const _: () = assert!(ConfidentialVm::MAX_HARTS_PER_VM > 0);
+const _: () = assert!(ConfidentialVm::MAX_HARTS_PER_VM <= MAX_NUMBER_OF_HARTS);
 
 #[derive(PartialEq, Eq, Hash, Debug, PartialOrd, Ord, Copy, Clone)]
 pub struct ConfidentialVmId(usize);
@@ -23,18 +54,362 @@ impl ConfidentialVmId {
 
 pub struct ConfidentialVm {
     id: ConfidentialVmId,
-    _measurements: [Measurement; 4],
+    measurements: [Measurement; 4],
+    // measurements at the confidential VM creation. Data sealed by the confidential VM is bound to them, so
+    // measurements extended later do not prevent the confidential VM from unsealing its data.
+    sealing_identity: Measurement,
     confidential_harts: Vec<ConfidentialHart>,
     root_page_table: RootPageTable,
+    // regions of the confidential VM's physical address space backed by the confidential memory at the VM creation.
+    memory_regions: Vec<Range<usize>>,
+    // bounds the page table memory and the hypervisor notifications the confidential VM can induce by sharing pages.
+    shared_pages: usize,
+    sharing_policy: SharingPolicy,
+    // shared pages the hypervisor wants to take back, published to the confidential VM via the shared info page.
+    revocations: SharedPageRevocations,
+    shared_info: SharedInfo,
+    // events describing the extends of the runtime measurement register, so a verifier can replay them.
+    event_log: EventLog,
+    // faults detected while completing operations of the confidential VM asynchronously.
+    faults: FaultQueue,
+    // counters that only grow, so the confidential VM can detect rollbacks of its state.
+    monotonic_counters: MonotonicCounters,
+    // physical harts executing the confidential harts, indexed by the confidential hart id.
+    physical_hart_ids: Vec<Option<usize>>,
+    // number of times every confidential hart returned to the hypervisor, indexed by the confidential hart id.
+    hart_exits: Vec<usize>,
+    // when set, the hypervisor cannot schedule confidential harts of this VM.
+    is_quiesced: bool,
+    // addresses of confidential pages that the confidential VM pinned, so they are never shared or moved away.
+    pinned_private: BTreeSet<usize>,
+    #[cfg(feature = "memory_audit")]
+    memory_audit: MemoryAudit,
 }
 
 impl ConfidentialVm {
+    /// Maximum number of harts of a single confidential VM.
+    pub const MAX_HARTS_PER_VM: usize = 32;
+    /// Maximum number of confidential pages a single confidential VM can pin at the same time.
+    pub const MAX_PINNED_PAGES: usize = 1024;
+    // a node of the tree storing the pinned pages is at least half full, so a pinned page takes at most 4 heap words.
+    const PINNED_PAGE_HEAP_SIZE: usize = 4 * core::mem::size_of::<usize>();
+
     pub fn new(
-        id: ConfidentialVmId, mut confidential_harts: Vec<ConfidentialHart>, root_page_table: RootPageTable,
-    ) -> Self {
+        id: ConfidentialVmId, mut confidential_harts: Vec<ConfidentialHart>, mut root_page_table: RootPageTable,
+        sharing_policy: SharingPolicy, sbi_policy: SbiPolicy, image_manifest: Option<&ImageManifest>,
+        fdt_address: Option<ConfidentialVmVirtualAddress>,
+    ) -> Result<Self> {
+        assure!(confidential_harts.len() <= Self::MAX_HARTS_PER_VM, Error::TooManyHarts())?;
+        let timebase_frequency = TIMEBASE_FREQUENCY.get().copied().ok_or(Error::TimebaseFrequencyNotAvailable())?;
+        // The id of the confidential VM does not fit the VMID field of hgatp after 2^14 confidential VMs were created.
+        // Then, the VMID decoded from hgatp differs from the id and the harts reject the hgatp, so two confidential VMs
+        // never share a VMID.
         let hgatp = Hgatp::new(root_page_table.address().usize(), root_page_table.paging_system().hgatp_mode(), id.0);
-        confidential_harts.iter_mut().for_each(|confidential_hart| confidential_hart.set_hgatp(hgatp.bits()));
-        Self { id, _measurements: [Measurement::empty(); 4], confidential_harts, root_page_table }
+        confidential_harts.iter_mut().try_for_each(|confidential_hart| confidential_hart.set_hgatp(hgatp, id))?;
+        let mut measurements = [Measurement::empty(); 4];
+        sharing_policy.measure(&mut measurements[SHARING_POLICY_MEASUREMENT]);
+        sbi_policy.measure(&mut measurements[SHARING_POLICY_MEASUREMENT]);
+        confidential_harts.iter_mut().for_each(|confidential_hart| confidential_hart.set_sbi_policy(sbi_policy));
+        if let Some(address) = fdt_address {
+            measurements[FDT_MEASUREMENT].extend(&[&Self::copy_fdt(&root_page_table, address)?]);
+        }
+        image_manifest.iter().flat_map(|image_manifest| image_manifest.entries()).for_each(|(address, entry)| {
+            measurements[MEMORY_MEASUREMENT].extend(&[&address.to_le_bytes(), entry.hash()]);
+        });
+        // every page is measured at its physical address in the order of the addresses, so the measurement is the same
+        // whether the pages were copied eagerly or are imported later in the order the confidential VM accesses them.
+        root_page_table.measure_pages(|address, page_digest| {
+            measurements[MEMORY_MEASUREMENT].extend(&[&address.to_le_bytes(), page_digest]);
+        })?;
+        let mut sealing_identity = Measurement::empty();
+        sealing_identity.extend(&measurements.iter().map(|measurement| &measurement.value[..]).collect::<Vec<_>>());
+        let monotonic_counters = MonotonicCounters::new(&sealing_identity.value[..]);
+        let monitor_page = MemoryTracker::acquire_continous_pages(1, PageSize::Size4KiB)?.remove(0).zeroize();
+        let shared_info = SharedInfo::new(
+            &monitor_page,
+            id.usize(),
+            confidential_harts.len(),
+            &measurements[SHARING_POLICY_MEASUREMENT].value,
+        );
+        root_page_table.map_monitor_page(monitor_page)?;
+        confidential_harts.iter_mut().for_each(|confidential_hart| confidential_hart.set_shared_info(shared_info));
+        let virtual_time = Arc::new(VirtualTime::new());
+        confidential_harts
+            .iter_mut()
+            .for_each(|confidential_hart| confidential_hart.set_virtual_time(virtual_time.clone()));
+        let memory_regions = root_page_table.confidential_memory_regions();
+        let physical_hart_ids = confidential_harts.iter().map(|_| None).collect();
+        let hart_exits = confidential_harts.iter().map(|_| 0).collect();
+        Ok(Self {
+            id,
+            measurements,
+            sealing_identity,
+            confidential_harts,
+            root_page_table,
+            memory_regions,
+            shared_pages: 0,
+            sharing_policy,
+            revocations: SharedPageRevocations::new(timebase_frequency),
+            shared_info,
+            event_log: EventLog::new(),
+            faults: FaultQueue::new(),
+            monotonic_counters,
+            physical_hart_ids,
+            hart_exits,
+            is_quiesced: false,
+            pinned_private: BTreeSet::new(),
+            #[cfg(feature = "memory_audit")]
+            memory_audit: MemoryAudit::new(),
+        })
+    }
+
+    /// Writes the confidential memory regions registered at the confidential VM creation into the buffer located in
+    /// the confidential VM's memory. Returns the total number of regions, which might be larger than the number of
+    /// entries the buffer can hold.
+    pub fn write_memory_regions(&self, request: &MemoryRegionsRequest) -> Result<usize> {
+        let value_size = core::mem::size_of::<u64>();
+        self.memory_regions.iter().take(request.number_of_entries()).enumerate().try_for_each(|(i, region)| {
+            let entry_offset = i.checked_mul(MemoryRegionsRequest::ENTRY_SIZE).ok_or(Error::AddressOverflow())?;
+            let entry_address = request.buffer_address().checked_add(entry_offset)?;
+            [region.start, region.end - region.start].iter().enumerate().try_for_each(|(j, value)| {
+                let value_offset = j.checked_mul(value_size).ok_or(Error::AddressOverflow())?;
+                let address = entry_address.checked_add(value_offset)?;
+                self.root_page_table.write_guest::<u64>(address, *value as u64)
+            })
+        })?;
+        Ok(self.memory_regions.len())
+    }
+
+    /// Copies the page containing the given address from the hypervisor's memory to the confidential memory. The page
+    /// was measured at the confidential VM creation and is imported only if its content did not change since then, so
+    /// the measurements are not extended. The page stays unmapped until the confidential VM accepts it.
+    pub fn import_page(&mut self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        let page = self.root_page_table.import_page(address)?;
+        let page_address = address.usize() & !(page.size().in_bytes() - 1);
+        debug!("Imported page 0x{:x} of size 0x{:x}", page_address, page.size().in_bytes());
+        Ok(())
+    }
+
+    /// Maps the imported page containing the given address, so the confidential VM can access it. Fails if the page
+    /// was not imported or has already been accepted.
+    pub fn accept_page(&mut self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        self.root_page_table.accept_page(address)
+    }
+
+    /// Pins the confidential page containing the given address, so it cannot be shared or moved away until the
+    /// confidential VM unpins it. Fails if the address is not mapped to a confidential page, or if the confidential VM
+    /// already pinned the maximum number of pages. Every pinned page is charged to the heap reservation.
+    pub fn pin_private(&mut self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        assure!(self.root_page_table.is_confidential(address), Error::CannotPinNonConfidentialPage())?;
+        let page_address = address.usize() & !(PageSize::Size4KiB.in_bytes() - 1);
+        if !self.pinned_private.contains(&page_address) {
+            assure!(self.pinned_private.len() < Self::MAX_PINNED_PAGES, Error::PinLimitExceeded())?;
+            self.root_page_table.charge_heap(Self::PINNED_PAGE_HEAP_SIZE)?;
+            self.pinned_private.insert(page_address);
+        }
+        Ok(())
+    }
+
+    pub fn unpin_private(&mut self, address: ConfidentialVmVirtualAddress) {
+        if self.pinned_private.remove(&(address.usize() & !(PageSize::Size4KiB.in_bytes() - 1))) {
+            self.root_page_table.refund_heap(Self::PINNED_PAGE_HEAP_SIZE);
+        }
+    }
+
+    fn is_pinned_private(&self, address: ConfidentialVmVirtualAddress) -> bool {
+        self.pinned_private.contains(&(address.usize() & !(PageSize::Size4KiB.in_bytes() - 1)))
+    }
+
+    /// Returns an error if the page at the given address is pinned as private, if the sharing policy does not permit
+    /// sharing it, if the page is owned by the confidential VM, or if sharing it would exceed the number of pages this
+    /// confidential VM can share at the same time. Sharing an already shared page again does not count.
+    pub fn can_share_page(&self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        assure_not!(self.is_pinned_private(address), Error::PagePinnedPrivate())?;
+        assure!(self.sharing_policy.allows(address), Error::ShareOutsidePolicy())?;
+        assure_not!(self.root_page_table.is_confidential(address), Error::CannotShareConfidentialLeaf())?;
+        let is_new_shared_page = !self.root_page_table.is_shared(address);
+        let max_shared_pages = self.sharing_policy.max_shared_pages();
+        assure!(!is_new_shared_page || self.shared_pages < max_shared_pages, Error::ShareLimitExceeded())
+    }
+
+    /// Returns the number of confidential harts whose share request awaits the hypervisor's response. The count is
+    /// derived from the requests pending in the confidential harts, so a request is no longer counted once the
+    /// confidential hart drops it, e.g., when the hypervisor restarts the confidential hart instead of answering.
+    pub fn shares_in_progress(&self) -> usize {
+        self.confidential_harts.iter().filter(|confidential_hart| confidential_hart.is_sharing()).count()
+    }
+
+    pub fn map_shared_page(&mut self, shared_page: &SharedPage) -> Result<()> {
+        let address = shared_page.confidential_vm_virtual_address();
+        self.can_share_page(address)?;
+        let is_new_shared_page = !self.root_page_table.is_shared(address);
+        self.root_page_table.map_shared_page(shared_page)?;
+        if is_new_shared_page {
+            self.shared_pages += 1;
+        }
+        #[cfg(feature = "memory_audit")]
+        self.memory_audit.record(AuditRecord::share(shared_page));
+        Ok(())
+    }
+
+    /// Converts shared pages back to confidential pages, which returns them to the pool of pages the confidential VM
+    /// can share. The page table converts the pages only if all of them are shared, so each of them is accounted in the
+    /// number of shared pages, and an error here reveals a bug in the accounting.
+    pub fn convert_to_private(&mut self, address: ConfidentialVmVirtualAddress, number_of_pages: usize) -> Result<()> {
+        self.root_page_table.convert_to_private(address, number_of_pages)?;
+        self.shared_pages = self.shared_pages.checked_sub(number_of_pages).ok_or(Error::SharedPagesMiscounted())?;
+        let end_address = address.usize() + number_of_pages * PageSize::Size4KiB.in_bytes();
+        self.revocations.remove(address.usize(), end_address);
+        self.shared_info.write_pending_revocation(self.revocations.first_unacknowledged());
+        #[cfg(feature = "memory_audit")]
+        self.memory_audit.record(AuditRecord::unshare(address, number_of_pages));
+        Ok(())
+    }
+
+    /// Handles the hypervisor's request to take back the shared page containing the given address. The first request
+    /// notifies the confidential VM via the shared info page. The page is unmapped once the confidential VM
+    /// acknowledged the revocation or the timeout passed. Confidential harts running on other physical harts might
+    /// use cached translations of the page until the security monitor flushes the second-level TLB, which it does
+    /// before resuming them. Thus, the request returns true only after all confidential harts running when the page
+    /// was unmapped returned to the hypervisor, so the hypervisor repeats the request, preempting these harts if
+    /// needed, until it returns true.
+    /// Fails if the page is neither shared nor unmapped by an earlier request, e.g., because the confidential VM
+    /// converted it to private in the meantime.
+    pub fn revoke_shared_page(&mut self, address: ConfidentialVmVirtualAddress, now: usize) -> Result<bool> {
+        let page_address = address.usize() & !(PageSize::Size4KiB.in_bytes() - 1);
+        if let Some(have_exited) = self.revocations.take_unmapped(page_address, &self.hart_exits) {
+            return Ok(have_exited);
+        }
+        assure!(self.root_page_table.is_shared(address), Error::PageNotShared())?;
+        let mut is_reclaimable = false;
+        if self.revocations.request(page_address, now) {
+            let is_forced = self.revocations.is_pending_acknowledgement(page_address);
+            self.root_page_table.unmap_shared_page(address)?;
+            if is_forced {
+                self.post_fault(FaultRecord::new(FaultCode::SharedPageRevoked, page_address, 0));
+            }
+            self.shared_pages = self.shared_pages.checked_sub(1).ok_or(Error::SharedPagesMiscounted())?;
+            let running_harts: Vec<_> = (0..self.physical_hart_ids.len())
+                .filter(|id| self.physical_hart_ids[*id].is_some())
+                .map(|id| (id, self.hart_exits[id]))
+                .collect();
+            is_reclaimable = running_harts.is_empty();
+            self.revocations.unmap(page_address, running_harts);
+            #[cfg(feature = "memory_audit")]
+            self.memory_audit.record(AuditRecord::unshare(address, 1));
+        }
+        self.shared_info.write_pending_revocation(self.revocations.first_unacknowledged());
+        Ok(is_reclaimable)
+    }
+
+    /// Confirms that the confidential VM stopped using the revoked shared page containing the given address.
+    pub fn acknowledge_revocation(&mut self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        let page_address = address.usize() & !(PageSize::Size4KiB.in_bytes() - 1);
+        self.revocations.acknowledge(page_address)?;
+        self.shared_info.write_pending_revocation(self.revocations.first_unacknowledged());
+        Ok(())
+    }
+
+    /// Records the fault detected while completing an operation of the confidential VM asynchronously.
+    pub fn post_fault(&mut self, record: FaultRecord) {
+        self.faults.post(record);
+    }
+
+    /// Writes the oldest fault records into the buffer located in the confidential VM's memory and removes them from
+    /// the queue. Returns the number of written records. Records are removed only if all of them have been written.
+    pub fn take_fault_records(&mut self, request: &FaultRecordsRequest) -> Result<usize> {
+        let value_size = core::mem::size_of::<u64>();
+        let records: Vec<_> = self.faults.records().take(request.number_of_entries()).map(|r| r.values()).collect();
+        records.iter().enumerate().try_for_each(|(i, values)| {
+            let entry_address = request.buffer_address().checked_add(i * FaultRecordsRequest::ENTRY_SIZE)?;
+            values.iter().enumerate().try_for_each(|(j, value)| {
+                let address = entry_address.checked_add(j * value_size)?;
+                self.root_page_table.write_guest::<u64>(address, *value as u64)
+            })
+        })?;
+        self.faults.remove(records.len());
+        Ok(records.len())
+    }
+
+    /// Moves the confidential page to the new address. A pinned page cannot be moved, because its address could then be
+    /// shared.
+    pub fn remap_confidential_page(
+        &mut self, old_address: ConfidentialVmVirtualAddress, new_address: ConfidentialVmVirtualAddress,
+    ) -> Result<()> {
+        assure_not!(self.is_pinned_private(old_address), Error::PagePinnedPrivate())?;
+        self.root_page_table.remap_confidential_page(old_address, new_address)
+    }
+
+    #[cfg(feature = "memory_audit")]
+    pub fn memory_audit(&self) -> &MemoryAudit {
+        &self.memory_audit
+    }
+
+    /// Records an access of the confidential VM to the hypervisor's memory that the security monitor forwards.
+    #[cfg(feature = "memory_audit")]
+    pub fn audit(&mut self, record: AuditRecord) {
+        self.memory_audit.record(record);
+    }
+
+    /// Copies the device tree starting at the given address, so it can be measured when the confidential VM is created.
+    /// The device tree is read after it has been copied to the confidential memory, so the hypervisor cannot change it
+    /// after it has been measured. Like the image manifest, it cannot be in pages imported on the first access.
+    fn copy_fdt(root_page_table: &RootPageTable, address: ConfidentialVmVirtualAddress) -> Result<Vec<u8>> {
+        let header = root_page_table.copy_from_confidential_vm(address, FDT_HEADER_SIZE)?;
+        let read_header_value = |offset: usize| u32::from_be_bytes([0, 1, 2, 3].map(|i| header[offset + i]));
+        assure!(read_header_value(0) == FDT_MAGIC, Error::InvalidFdt())?;
+        let fdt_size = read_header_value(4) as usize;
+        assure!(FDT_HEADER_SIZE <= fdt_size && fdt_size <= MAX_FDT_SIZE, Error::InvalidFdt())?;
+        root_page_table.copy_from_confidential_vm(address, fdt_size)
+    }
+
+    /// Appends the event described by the data located in the confidential VM's memory to the event log and extends the
+    /// given measurement register with the hash of the description. Any register can be extended, because an extend
+    /// cannot undo the measurements of the initial state and the log tells the verifier what every extend represented.
+    /// The log is charged to the confidential VM's heap reservation. Nothing changes if the log is full or the
+    /// reservation is exhausted.
+    pub fn log_event(
+        &mut self, address: ConfidentialVmVirtualAddress, size: usize, measurement_index: usize,
+    ) -> Result<()> {
+        assure!(measurement_index < self.measurements.len(), Error::InvalidMeasurementIndex(measurement_index))?;
+        assure!(0 < size && size <= EventLog::MAX_EVENT_SIZE, Error::InvalidEventSize())?;
+        let description = self.root_page_table.copy_from_confidential_vm(address, size)?;
+        let event_size = EventLog::event_size(&description);
+        self.root_page_table.charge_heap(event_size)?;
+        if let Err(error) = self.event_log.append(measurement_index, &description) {
+            self.root_page_table.refund_heap(event_size);
+            return Err(error);
+        }
+        self.measurements[measurement_index].extend(&[&MeasurementHasher::digest(&description)]);
+        Ok(())
+    }
+
+    pub fn event_log(&self) -> &EventLog {
+        &self.event_log
+    }
+
+    pub fn monotonic_counters(&self) -> &MonotonicCounters {
+        &self.monotonic_counters
+    }
+
+    pub fn monotonic_counters_mut(&mut self) -> &mut MonotonicCounters {
+        &mut self.monotonic_counters
+    }
+
+    /// Returns the report of the current values of all measurement registers, which starts with the tag of the
+    /// measurement algorithm.
+    pub fn measurements(&self) -> Vec<u8> {
+        SelectedMeasurementAlgorithm::report(self.measurements.iter().map(|measurement| &measurement.value[..]))
+    }
+
+    /// Returns the identity to which the data sealed by this confidential VM is bound. Two confidential VMs have the
+    /// same identity only if they were created with the same measurements.
+    pub fn sealing_identity(&self) -> &[u8] {
+        &self.sealing_identity.value
+    }
+
+    pub fn root_page_table(&self) -> &RootPageTable {
+        &self.root_page_table
     }
 
     pub fn root_page_table_mut(&mut self) -> &mut RootPageTable {
@@ -43,35 +418,96 @@ impl ConfidentialVm {
 
     pub fn steal_confidential_hart(
         &mut self, confidential_hart_id: usize, hardware_hart: &mut HardwareHart,
-    ) -> Result<(), Error> {
+    ) -> Result<()> {
         let confidential_hart = self.confidential_harts.get(confidential_hart_id).ok_or(Error::InvalidHartId())?;
         // The hypervisor might try to schedule the same confidential_hart on different harts. We detect it because
         // after a confidential_hart is scheduled for the first time, its token is stolen and the ConfidentialVM is left
         // with a dummy confidential_hart.
         assure_not!(confidential_hart.is_dummy(), Error::RunningVHart())?;
+        assure_not!(self.is_quiesced, Error::ConfidentialVmQuiesced())?;
+        // The confidential hart must never execute on the page table created by the hypervisor, which the hypervisor
+        // can still modify. This is checked on every entry, so a missed or wrong rewrite of hgatp cannot go unnoticed.
+        confidential_hart.assure_root_page_table(self.root_page_table.address().usize())?;
         core::mem::swap(&mut hardware_hart.confidential_hart, &mut self.confidential_harts[confidential_hart_id]);
+        // the physical hart's time counter might be skewed with respect to the one that executed the confidential hart
+        // before, so the confidential hart recomputes its offset to the shared time base of the confidential VM.
+        hardware_hart.confidential_hart.migrate();
+        self.physical_hart_ids[confidential_hart_id] = Some(riscv::register::mhartid::read());
         Ok(())
     }
 
+    /// Returns how many times the confidential hart returned to the hypervisor. The count wraps around on overflow.
+    pub fn hart_exits(&self, confidential_hart_id: usize) -> Result<usize> {
+        self.hart_exits.get(confidential_hart_id).copied().ok_or(Error::InvalidHartId())
+    }
+
     pub fn return_confidential_hart(&mut self, hardware_hart: &mut HardwareHart) {
         assert!(!hardware_hart.confidential_hart.is_dummy());
         assert!(self.id == hardware_hart.confidential_hart().confidential_vm_id());
         let confidential_hart_id = hardware_hart.confidential_hart.confidential_hart_id();
         core::mem::swap(&mut hardware_hart.confidential_hart, &mut self.confidential_harts[confidential_hart_id]);
+        self.physical_hart_ids[confidential_hart_id] = None;
+        self.hart_exits[confidential_hart_id] = self.hart_exits[confidential_hart_id].wrapping_add(1);
+    }
+
+    /// Returns the registers of the confidential hart for debugging. Fails if the confidential hart executes on a
+    /// physical hart because its state is then not stored in the confidential VM.
+    #[cfg(feature = "insecure_debug")]
+    pub fn dump_confidential_hart(&self, confidential_hart_id: usize) -> Result<Vec<usize>> {
+        let confidential_hart = self.confidential_harts.get(confidential_hart_id).ok_or(Error::InvalidHartId())?;
+        assure_not!(confidential_hart.is_dummy(), Error::RunningVHart())?;
+        Ok(confidential_hart.dump_state())
+    }
+
+    /// Returns the number of confidential harts declared when the confidential VM entered the secure mode. It never
+    /// changes, so confidential hart ids are always lower than this number.
+    pub fn number_of_harts(&self) -> usize {
+        self.confidential_harts.len()
+    }
+
+    /// Sets the state in which the given confidential hart starts. A confidential hart that is executing on a physical
+    /// hart is not stopped, so its state is not changed. Fails if the confidential VM has no such hart.
+    pub fn prepare_hart_start(
+        &mut self, confidential_hart_id: usize, start_address: usize, opaque: usize,
+    ) -> Result<()> {
+        let confidential_hart = self.confidential_harts.get_mut(confidential_hart_id).ok_or(Error::InvalidHartId())?;
+        if !confidential_hart.is_dummy() {
+            confidential_hart.prepare_start(start_address, opaque);
+        }
+        Ok(())
     }
 
     pub fn is_running(&self) -> bool {
         self.confidential_harts.iter().filter(|confidential_hart| confidential_hart.is_dummy()).count() > 0
     }
+
+    /// Prevents the hypervisor from scheduling confidential harts of this VM and returns the ids of physical harts that
+    /// currently execute them. These harts stop executing the confidential VM when they next enter the security
+    /// monitor, e.g., on an interrupt.
+    pub fn begin_quiesce(&mut self) -> Vec<usize> {
+        self.is_quiesced = true;
+        self.physical_hart_ids.iter().filter_map(|physical_hart_id| *physical_hart_id).collect()
+    }
+
+    /// Allows the hypervisor to schedule confidential harts of this VM again.
+    pub fn end_quiesce(&mut self) {
+        self.is_quiesced = false;
+    }
 }
 
+/// Measurement register extended with the measurement algorithm selected at build time.
 #[derive(Clone, Copy)]
 pub struct Measurement {
-    pub value: [u8; MAX_HASH_SIZE / 8],
+    pub value: [u8; MEASUREMENT_SIZE],
 }
 
 impl Measurement {
     pub const fn empty() -> Measurement {
-        Self { value: [0u8; MAX_HASH_SIZE / 8] }
+        Self { value: [0u8; MEASUREMENT_SIZE] }
+    }
+
+    /// Replaces the measurement with the hash of the current measurement concatenated with the given data.
+    pub fn extend(&mut self, data: &[&[u8]]) {
+        SelectedMeasurementAlgorithm::extend(&mut self.value, data);
     }
 }
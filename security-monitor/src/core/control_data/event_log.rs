@@ -0,0 +1,43 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::error::{Error, Result};
+use alloc::vec::Vec;
+
+/// EventLog is the append-only log of events describing what the confidential VM extended its measurement registers
+/// with. A verifier replays the log to recompute the registers and learns what every extend represented. The log is
+/// stored in the security monitor's memory, so the hypervisor cannot tamper with it. Every event is serialized as the
+/// measurement register index and the description size, both 64-bit little-endian values, followed by the description.
+pub struct EventLog {
+    data: Vec<u8>,
+}
+
+impl EventLog {
+    /// Bounds the memory that a single confidential VM consumes for its log.
+    const MAX_SIZE: usize = 16 * 1024;
+    pub const MAX_EVENT_SIZE: usize = 1024;
+    const EVENT_HEADER_SIZE: usize = 2 * core::mem::size_of::<u64>();
+
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    /// Returns the number of bytes the event with the given description occupies in the log.
+    pub fn event_size(description: &[u8]) -> usize {
+        Self::EVENT_HEADER_SIZE + description.len()
+    }
+
+    /// Appends the event to the log. Fails if the log cannot store it. Then the log remains unchanged.
+    pub fn append(&mut self, measurement_index: usize, description: &[u8]) -> Result<()> {
+        let event_size = Self::event_size(description);
+        assure!(self.data.len() + event_size <= Self::MAX_SIZE, Error::EventLogFull())?;
+        self.data.extend_from_slice(&(measurement_index as u64).to_le_bytes());
+        self.data.extend_from_slice(&(description.len() as u64).to_le_bytes());
+        self.data.extend_from_slice(description);
+        Ok(())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
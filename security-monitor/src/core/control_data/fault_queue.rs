@@ -0,0 +1,76 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use alloc::collections::VecDeque;
+
+/// FaultQueue holds the faults that the security monitor detected while completing operations of the confidential VM
+/// asynchronously, e.g., after the hypervisor answered a request, and the security violations it detected. The SBI
+/// result of such an operation cannot explain why it failed, so the confidential VM reads the records to learn it. When
+/// the queue is full, the oldest records are dropped, so a confidential VM that never reads the queue does not consume
+/// more memory.
+pub struct FaultQueue {
+    records: VecDeque<FaultRecord>,
+}
+
+impl FaultQueue {
+    const CAPACITY: usize = 32;
+
+    pub fn new() -> Self {
+        Self { records: VecDeque::new() }
+    }
+
+    pub fn post(&mut self, record: FaultRecord) {
+        if self.records.len() == Self::CAPACITY {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Returns the records ordered from the oldest one without removing them.
+    pub fn records(&self) -> impl Iterator<Item = &FaultRecord> {
+        self.records.iter()
+    }
+
+    /// Removes the given number of the oldest records after the confidential VM has read them.
+    pub fn remove(&mut self, number_of_records: usize) {
+        self.records.drain(..core::cmp::min(number_of_records, self.records.len()));
+    }
+}
+
+#[repr(usize)]
+#[derive(Clone, Copy)]
+pub enum FaultCode {
+    // the hypervisor could not allocate the shared page, the context is the hypervisor's error code.
+    SharePageFailed = 1,
+    // the hypervisor answered with a page that cannot be shared, the context is the address of this page.
+    InvalidHypervisorPage = 2,
+    // the security monitor could not map the page allocated by the hypervisor, e.g., because the mapping would widen
+    // the permissions of the already shared page. The context is the address of the hypervisor's page.
+    SharePageRejected = 3,
+    // the hypervisor took back the shared page that the confidential VM did not acknowledge to stop using in time.
+    SharedPageRevoked = 4,
+    // the confidential VM accessed memory protected by the physical memory protection, e.g., memory of the security
+    // monitor mapped by the hypervisor. The address is the confidential VM's virtual address, the context is the
+    // exception code.
+    AccessViolation = 5,
+}
+
+/// A single fault: what failed, the confidential VM's physical address of the affected page, and the context that
+/// depends on the fault code.
+#[derive(Clone, Copy)]
+pub struct FaultRecord {
+    code: FaultCode,
+    address: usize,
+    context: usize,
+}
+
+impl FaultRecord {
+    pub fn new(code: FaultCode, address: usize, context: usize) -> Self {
+        Self { code, address, context }
+    }
+
+    /// Returns the record encoded as 64-bit values in the order they are written to the confidential VM's memory.
+    pub fn values(&self) -> [usize; 3] {
+        [self.code as usize, self.address, self.context]
+    }
+}
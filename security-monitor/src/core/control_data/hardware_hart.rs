@@ -1,14 +1,23 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::ConfidentialHart;
+use crate::core::bounded_lock::{self, MAX_LOCK_ATTEMPTS};
+use crate::core::control_data::{ConfidentialHart, ConfidentialVm};
 use crate::core::hart::{GpRegister, HartState};
 use crate::core::memory_tracker::{Allocated, Page, UnAllocated};
+#[cfg(feature = "insecure_debug")]
+use crate::core::transformations::DumpHartStateRequest;
+#[cfg(feature = "memory_audit")]
+use crate::core::transformations::MemoryAuditRequest;
 use crate::core::transformations::{
-    EsmRequest, ExposeToHypervisor, GuestLoadPageFaultRequest, GuestLoadPageFaultResult, InterruptRequest,
-    MmioLoadRequest, MmioStoreRequest, OpensbiRequest, ResumeRequest, SbiRequest, SbiResult, SbiVmRequest,
-    SharePageResult, TerminateRequest, TrapReason,
+    EsmRequest, ExposeToHypervisor, GuestLoadPageFaultRequest, GuestLoadPageFaultResult, GuestStorePageFaultRequest,
+    GuestStorePageFaultResult, InterruptRequest, MemorySnapshotRequest, MmioLoadRequest, MmioStoreRequest,
+    OpensbiRequest, ReservationLimitRequest, ResumeRequest, RevokeSharedPageRequest, SbiRequest, SbiResult,
+    SbiVmRequest, SharePageResult, TerminateRequest, TrapReason,
 };
+use crate::error::{Error, Result};
+use alloc::sync::Arc;
+use spin::{Mutex, MutexGuard};
 
 #[repr(C)]
 pub struct HardwareHart {
@@ -24,11 +33,19 @@ pub struct HardwareHart {
     // data structures and our security monitor also uses mscratch to keep track of the address of the hart state
     // in memory.
     previous_mscratch: usize,
+    // deadline of the timer that the hypervisor programmed on this hart via the SBI call. The security monitor
+    // restores it after programming an earlier deadline of a confidential hart's secure timer.
+    hypervisor_timer: usize,
+    secure_timer_armed: bool,
     // we keep the virtual hart that is associated with this hardware hart. The virtual hart can be 1) a dummy hart
     // in case there is any confidential VM's virtual hart associated to it, or 2) an confidential VM's virtual hart.
     // In the latter case, the hardware hart and confidential VM's control data swap their virtual harts (a dummy
     // hart with the confidential VM's virtual hart)
     pub(super) confidential_hart: ConfidentialHart,
+    // the confidential VM owning the confidential hart that executes on this hart. Caching it spares the lookup in the
+    // control data on every trap. It is set when the confidential hart is stolen and cleared when it is returned, so
+    // the confidential VM cannot be removed while this hart holds it.
+    confidential_vm: Option<Arc<Mutex<ConfidentialVm>>>,
 }
 
 impl HardwareHart {
@@ -38,7 +55,10 @@ impl HardwareHart {
             stack_address: stack.end_address().usize(),
             stack: stack.zeroize(),
             previous_mscratch: 0,
+            hypervisor_timer: usize::MAX,
+            secure_timer_armed: false,
             confidential_hart: ConfidentialHart::dummy(id),
+            confidential_vm: None,
         }
     }
 
@@ -55,6 +75,49 @@ impl HardwareHart {
         self.previous_mscratch = current_mscratch;
     }
 
+    /// Records the deadline that the hypervisor requests with the SBI set_timer call before OpenSBI programs it.
+    pub fn record_hypervisor_timer(&mut self) {
+        self.hypervisor_timer = self.non_confidential_hart_state.gpr(GpRegister::a0);
+    }
+
+    /// Returns true if the hypervisor's timer expired, so the timer interrupt must be reflected to the hypervisor.
+    pub fn is_hypervisor_timer_expired(&self) -> bool {
+        riscv::register::time::read() >= self.hypervisor_timer
+    }
+
+    /// Programs the hardware timer with the nearest of the hypervisor's deadline and the deadlines of secure timers of
+    /// the confidential hart executing on this hart. The hypervisor's deadline is restored once the secure timers are
+    /// no longer armed or the confidential hart stops executing. Only the SBI timer is supported, a hypervisor writing
+    /// stimecmp directly (Sstc) does not go through the security monitor.
+    pub fn program_timer(&mut self, secure_timer_deadline: Option<usize>) {
+        let deadline = secure_timer_deadline.map_or(self.hypervisor_timer, |v| v.min(self.hypervisor_timer));
+        if deadline != self.hypervisor_timer || self.secure_timer_armed {
+            self.swap_mscratch();
+            unsafe { opensbi_sys::sbi_timer_event_start(deadline as u64) };
+            self.swap_mscratch();
+            self.secure_timer_armed = deadline != self.hypervisor_timer;
+        }
+    }
+
+    /// Sends a machine-level software interrupt to the physical hart with the given hart id (mhartid), making it enter
+    /// the security monitor. OpenSBI addresses harts by their index in the platform's hart list, which differs from the
+    /// hart id on platforms with sparse hart ids, so the hart id is translated first. Unknown hart ids are ignored.
+    pub fn interrupt_physical_hart(&mut self, physical_hart_id: usize) {
+        let Ok(physical_hart_id) = u32::try_from(physical_hart_id) else { return };
+        self.swap_mscratch();
+        // Safety: after the swap, mscratch points to the OpenSBI's scratch structure of this hart, which contains the
+        // address of the platform description that OpenSBI uses to translate hart ids to hart indices.
+        unsafe {
+            let scratch = &*(riscv::register::mscratch::read() as *const opensbi_sys::sbi_scratch);
+            let platform = scratch.platform_addr as *const opensbi_sys::sbi_platform;
+            let hart_index = opensbi_sys::sbi_platform_hart_index(platform, physical_hart_id);
+            if hart_index != u32::MAX {
+                opensbi_sys::sbi_ipi_raw_send(hart_index);
+            }
+        }
+        self.swap_mscratch();
+    }
+
     pub fn confidential_hart(&self) -> &ConfidentialHart {
         &self.confidential_hart
     }
@@ -62,6 +125,21 @@ impl HardwareHart {
     pub fn confidential_hart_mut(&mut self) -> &mut ConfidentialHart {
         &mut self.confidential_hart
     }
+
+    pub fn cache_confidential_vm(&mut self, confidential_vm: Arc<Mutex<ConfidentialVm>>) {
+        self.confidential_vm = Some(confidential_vm);
+    }
+
+    pub fn take_confidential_vm(&mut self) -> Option<Arc<Mutex<ConfidentialVm>>> {
+        self.confidential_vm.take()
+    }
+
+    /// Locks the confidential VM whose confidential hart executes on this hart without accessing the control data.
+    pub fn try_confidential_vm<F, O>(&self, op: O) -> Result<F>
+    where O: FnOnce(MutexGuard<'_, ConfidentialVm>) -> Result<F> {
+        let confidential_vm = self.confidential_vm.as_ref().ok_or(Error::InvalidConfidentialVmId())?;
+        op(bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || confidential_vm.try_lock())?)
+    }
 }
 
 impl HardwareHart {
@@ -79,6 +157,9 @@ impl HardwareHart {
     fn apply_sbi_result(&mut self, result: &SbiResult) {
         self.non_confidential_hart_state.set_gpr(GpRegister::a0, result.a0());
         self.non_confidential_hart_state.set_gpr(GpRegister::a1, result.a1());
+        result
+            .additional_values()
+            .for_each(|(register, value)| self.non_confidential_hart_state.set_gpr(register, value));
         self.non_confidential_hart_state.mepc += result.pc_offset();
     }
 
@@ -153,6 +234,8 @@ impl HardwareHart {
         self.non_confidential_hart_state.stval = request.stval();
         self.non_confidential_hart_state.htval = request.htval();
         self.non_confidential_hart_state.set_gpr(request.gpr(), request.gpr_value());
+        // KVM advances sepc by the length of the emulated instruction before it resumes the confidential hart.
+        self.non_confidential_hart_state.sepc = request.sepc();
 
         // hack: we do not allow the hypervisor to look into the guest memory
         // but we have to inform him about the instruction that caused exception.
@@ -207,7 +290,7 @@ impl HardwareHart {
         self.non_confidential_hart_state.trap_reason()
     }
 
-    pub fn esm_request(&self) -> EsmRequest {
+    pub fn esm_request(&self) -> Result<EsmRequest> {
         EsmRequest::new(&self.non_confidential_hart_state)
     }
 
@@ -219,6 +302,10 @@ impl HardwareHart {
         GuestLoadPageFaultResult::new(&self.non_confidential_hart_state, request)
     }
 
+    pub fn guest_store_page_fault_result(&self, request: &GuestStorePageFaultRequest) -> GuestStorePageFaultResult {
+        GuestStorePageFaultResult::new(&self.non_confidential_hart_state, request)
+    }
+
     pub fn sbi_vm_request(&self) -> SbiVmRequest {
         SbiVmRequest::from_hart_state(&self.non_confidential_hart_state)
     }
@@ -226,7 +313,27 @@ impl HardwareHart {
     pub fn resume_request(&self) -> ResumeRequest {
         let confidential_vm_id = self.non_confidential_hart_state.gpr(GpRegister::t0);
         let confidential_hart_id = self.non_confidential_hart_state.gpr(GpRegister::t1);
-        ResumeRequest::new(confidential_vm_id, confidential_hart_id)
+        // the hypervisor injects interrupts to the confidential hart the same way as to a regular VM, i.e., via hvip
+        let pending_interrupts = self.non_confidential_hart_state.hvip;
+        let external_interrupt = self.non_confidential_hart_state.gpr(GpRegister::t2);
+        ResumeRequest::new(confidential_vm_id, confidential_hart_id, pending_interrupts, external_interrupt)
+    }
+
+    #[cfg(feature = "insecure_debug")]
+    pub fn dump_hart_state_request(&self) -> DumpHartStateRequest {
+        let confidential_vm_id = self.non_confidential_hart_state.gpr(GpRegister::t0);
+        let confidential_hart_id = self.non_confidential_hart_state.gpr(GpRegister::t1);
+        let buffer_address = self.non_confidential_hart_state.gpr(GpRegister::t2);
+        let buffer_size = self.non_confidential_hart_state.gpr(GpRegister::t3);
+        DumpHartStateRequest::new(confidential_vm_id, confidential_hart_id, buffer_address, buffer_size)
+    }
+
+    #[cfg(feature = "memory_audit")]
+    pub fn memory_audit_request(&self) -> MemoryAuditRequest {
+        let confidential_vm_id = self.non_confidential_hart_state.gpr(GpRegister::t0);
+        let buffer_address = self.non_confidential_hart_state.gpr(GpRegister::t1);
+        let buffer_size = self.non_confidential_hart_state.gpr(GpRegister::t2);
+        MemoryAuditRequest::new(confidential_vm_id, buffer_address, buffer_size)
     }
 
     pub fn terminate_request(&self) -> TerminateRequest {
@@ -234,6 +341,22 @@ impl HardwareHart {
         TerminateRequest::new(confidential_vm_id)
     }
 
+    pub fn revoke_shared_page_request(&self) -> RevokeSharedPageRequest {
+        let confidential_vm_id = self.non_confidential_hart_state.gpr(GpRegister::t0);
+        let address = self.non_confidential_hart_state.gpr(GpRegister::t1);
+        RevokeSharedPageRequest::new(confidential_vm_id, address)
+    }
+
+    pub fn memory_snapshot_request(&self) -> MemorySnapshotRequest {
+        let buffer_address = self.non_confidential_hart_state.gpr(GpRegister::t0);
+        let buffer_size = self.non_confidential_hart_state.gpr(GpRegister::t1);
+        MemorySnapshotRequest::new(buffer_address, buffer_size)
+    }
+
+    pub fn reservation_limit_request(&self) -> ReservationLimitRequest {
+        ReservationLimitRequest::new(self.non_confidential_hart_state.gpr(GpRegister::t0))
+    }
+
     pub fn share_page_result(&self) -> SharePageResult {
         let is_error = self.non_confidential_hart_state.gpr(GpRegister::a0);
         let hypervisor_page_address = self.non_confidential_hart_state.gpr(GpRegister::a1);
@@ -0,0 +1,46 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::hart::GpRegister;
+
+/// Load or store instruction decoded for emulating an MMIO access: the register holding the loaded or stored value,
+/// the number of accessed bytes, whether the loaded value is sign-extended, and the length of the instruction.
+pub type DecodedAccess = (GpRegister, usize, bool, usize);
+
+/// InstructionCache maps raw load and store instructions to their decoded form. Decoding is a pure function of the
+/// instruction, and device drivers access MMIO registers in loops executing the same few instructions, so most MMIO
+/// traps find the instruction in the cache. Every confidential hart owns its cache, so no state is shared between
+/// confidential VMs.
+pub struct InstructionCache {
+    // entries ordered from the most to the least recently used.
+    entries: [Option<(usize, DecodedAccess)>; Self::CAPACITY],
+    // number of instructions that were decoded because they were not in the cache.
+    decodes: usize,
+}
+
+impl InstructionCache {
+    const CAPACITY: usize = 8;
+
+    pub fn new() -> Self {
+        Self { entries: [None; Self::CAPACITY], decodes: 0 }
+    }
+
+    /// Returns the decoded form of the given instruction and marks it as the most recently used one.
+    pub fn get(&mut self, instruction: usize) -> Option<DecodedAccess> {
+        let index = self.entries.iter().position(|entry| matches!(entry, Some((raw, _)) if *raw == instruction))?;
+        self.entries[..=index].rotate_right(1);
+        self.entries[0].map(|(_, decoded_access)| decoded_access)
+    }
+
+    /// Stores the decoded form of the instruction that has just been decoded, evicting the least recently used entry.
+    pub fn insert(&mut self, instruction: usize, decoded_access: DecodedAccess) {
+        self.decodes = self.decodes.wrapping_add(1);
+        self.entries.rotate_right(1);
+        self.entries[0] = Some((instruction, decoded_access));
+    }
+
+    #[cfg(feature = "insecure_debug")]
+    pub fn decodes(&self) -> usize {
+        self.decodes
+    }
+}
@@ -0,0 +1,109 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::confidential_hart::read_access_width;
+use crate::core::memory_tracker::SharedPage;
+use crate::core::mmu::PageSize;
+use crate::core::transformations::{
+    AccessFaultRequest, ConfidentialVmVirtualAddress, MmioLoadRequest, MmioStoreRequest,
+};
+use alloc::vec::Vec;
+
+/// MemoryAudit records how a confidential VM exposes its memory to the hypervisor: every page it shares or converts
+/// back to private, every MMIO access forwarded to the hypervisor, every byte written to the console, and every access
+/// rejected with an access fault. The hypervisor reads the records to diagnose why the confidential VM accessed its
+/// memory. Records contain only metadata, never the content of confidential pages. The audit is compiled only with the
+/// `memory_audit` feature.
+pub struct MemoryAudit {
+    records: [AuditRecord; Self::CAPACITY],
+    // number of records ever recorded. When it exceeds the capacity, the oldest records have been overwritten.
+    recorded: usize,
+}
+
+impl MemoryAudit {
+    pub const CAPACITY: usize = 256;
+
+    pub fn new() -> Self {
+        Self { records: [AuditRecord::EMPTY; Self::CAPACITY], recorded: 0 }
+    }
+
+    /// Appends the record overwriting the oldest one when the buffer is full.
+    pub fn record(&mut self, record: AuditRecord) {
+        self.records[self.recorded % Self::CAPACITY] = record;
+        self.recorded += 1;
+    }
+
+    /// Returns the retained records ordered from the oldest one.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        let retained = core::cmp::min(self.recorded, Self::CAPACITY);
+        (self.recorded - retained..self.recorded).map(|i| self.records[i % Self::CAPACITY]).collect()
+    }
+}
+
+#[repr(usize)]
+#[derive(Clone, Copy)]
+pub enum AuditedAccess {
+    Share = 1,
+    Unshare = 2,
+    MmioLoad = 3,
+    MmioStore = 4,
+    AccessFault = 5,
+    ConsoleWrite = 6,
+}
+
+/// A single access of the confidential VM to the hypervisor's memory. The address is the confidential VM's physical
+/// address.
+#[derive(Clone, Copy)]
+pub struct AuditRecord {
+    access: AuditedAccess,
+    address: usize,
+    size: usize,
+}
+
+impl AuditRecord {
+    /// Number of bytes the record occupies in the hypervisor's buffer: the access, the address, and the size as 64-bit
+    /// values.
+    pub const SIZE: usize = 3 * core::mem::size_of::<usize>();
+    const EMPTY: Self = Self { access: AuditedAccess::Share, address: 0, size: 0 };
+
+    pub fn share(shared_page: &SharedPage) -> Self {
+        let address = shared_page.confidential_vm_virtual_address().usize();
+        Self { access: AuditedAccess::Share, address, size: shared_page.page_size().in_bytes() }
+    }
+
+    pub fn unshare(address: ConfidentialVmVirtualAddress, number_of_pages: usize) -> Self {
+        let size = number_of_pages.saturating_mul(PageSize::Size4KiB.in_bytes());
+        Self { access: AuditedAccess::Unshare, address: address.usize(), size }
+    }
+
+    pub fn mmio_load(request: &MmioLoadRequest) -> Self {
+        Self::mmio(AuditedAccess::MmioLoad, request.stval(), request.htval(), request.instruction())
+    }
+
+    pub fn mmio_store(request: &MmioStoreRequest) -> Self {
+        Self::mmio(AuditedAccess::MmioStore, request.stval(), request.htval(), request.instruction())
+    }
+
+    /// The address of an access fault is the confidential VM's virtual address because the hardware does not report
+    /// the translated address. The size is not recorded.
+    pub fn access_fault(request: &AccessFaultRequest) -> Self {
+        Self { access: AuditedAccess::AccessFault, address: request.address(), size: 0 }
+    }
+
+    /// A byte written to the console of the security monitor. The content of the byte is not recorded.
+    pub fn console_write() -> Self {
+        Self { access: AuditedAccess::ConsoleWrite, address: 0, size: 1 }
+    }
+
+    /// htval holds the faulting confidential VM's physical address shifted right by 2 bits. The lowest bits are the
+    /// same as in the virtual address stored in stval. An access that cannot be decoded is recorded with size 0.
+    fn mmio(access: AuditedAccess, stval: usize, htval: usize, instruction: usize) -> Self {
+        let address = (htval << 2) | (stval & 0b11);
+        let size = read_access_width(instruction).map(|(access_width, _)| access_width).unwrap_or(0);
+        Self { access, address, size }
+    }
+
+    pub fn values(&self) -> [usize; 3] {
+        [self.access as usize, self.address, self.size]
+    }
+}
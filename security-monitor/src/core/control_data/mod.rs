@@ -2,15 +2,57 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::core::hart::{GpRegister, HartState};
+#[cfg(feature = "init_self_test")]
+pub use confidential_hart::self_test as confidential_hart_self_test;
 pub use confidential_hart::ConfidentialHart;
 pub use confidential_vm::{ConfidentialVm, ConfidentialVmId};
+pub use event_log::EventLog;
+pub use fault_queue::{FaultCode, FaultQueue, FaultRecord};
 pub use hardware_hart::HardwareHart;
+pub use instruction_cache::InstructionCache;
+#[cfg(feature = "memory_audit")]
+pub use memory_audit::{AuditRecord, MemoryAudit};
+pub use monotonic_counters::MonotonicCounters;
+pub use pending_requests::PendingRequests;
+#[cfg(feature = "init_self_test")]
+pub use progress_monitor::self_test as progress_monitor_self_test;
+pub use progress_monitor::ProgressMonitor;
+#[cfg(feature = "init_self_test")]
+pub use sbi_policy::self_test as sbi_policy_self_test;
+pub use sbi_policy::SbiPolicy;
+pub use secure_timers::SecureTimers;
+pub use shared_info::SharedInfo;
+#[cfg(feature = "init_self_test")]
+pub use shared_page_revocations::self_test as shared_page_revocations_self_test;
+pub use shared_page_revocations::SharedPageRevocations;
+pub use sharing_policy::SharingPolicy;
 pub use storage::{ControlData, CONTROL_DATA};
+#[cfg(feature = "init_self_test")]
+pub use virtual_counters::self_test as virtual_counters_self_test;
+pub use virtual_counters::VirtualCounters;
+pub use virtual_interrupt_file::VirtualInterruptFile;
+pub use virtual_time::{VirtualTime, TIMEBASE_FREQUENCY};
 
 mod confidential_hart;
 mod confidential_vm;
+mod event_log;
+mod fault_queue;
 mod hardware_hart;
+mod instruction_cache;
+#[cfg(feature = "memory_audit")]
+mod memory_audit;
+mod monotonic_counters;
+mod pending_requests;
+mod progress_monitor;
+mod sbi_policy;
+mod secure_timers;
+mod shared_info;
+mod shared_page_revocations;
+mod sharing_policy;
 mod storage;
+mod virtual_counters;
+mod virtual_interrupt_file;
+mod virtual_time;
 
 const fn hart_gpr_offset(index: GpRegister) -> usize {
     memoffset::offset_of!(HardwareHart, non_confidential_hart_state)
@@ -124,6 +166,11 @@ pub const HART_HGATP_OFFSET: usize = hart_csr_offset!(hgatp);
 pub const HART_HEDELEG_OFFSET: usize = hart_csr_offset!(hedeleg);
 pub const HART_HIDELEG_OFFSET: usize = hart_csr_offset!(hideleg);
 pub const HART_HTINST_OFFSET: usize = hart_csr_offset!(htinst);
+pub const HART_HCOUNTEREN_OFFSET: usize = hart_csr_offset!(hcounteren);
+pub const HART_HIE_OFFSET: usize = hart_csr_offset!(hie);
+pub const HART_HIP_OFFSET: usize = hart_csr_offset!(hip);
+pub const HART_HGEIE_OFFSET: usize = hart_csr_offset!(hgeie);
+pub const HART_HENVCFG_OFFSET: usize = hart_csr_offset!(henvcfg);
 pub const HART_MEPC_OFFSET: usize = hart_csr_offset!(mepc);
 pub const HART_MSTATUS_OFFSET: usize = hart_csr_offset!(mstatus);
 pub const HART_MIDELEG_OFFSET: usize = hart_csr_offset!(mideleg);
@@ -141,3 +188,8 @@ pub const HART_SSCRATCH_OFFSET: usize = hart_csr_offset!(sscratch);
 pub const HART_SCOUNTEREN_OFFSET: usize = hart_csr_offset!(scounteren);
 
 pub const HART_STACK_ADDRESS_OFFSET: usize = hart_element_offset!(stack_address);
+
+// The context switch assembly uses the above offsets, computed for the HardwareHart, to also save and restore the state
+// of the ConfidentialHart. This is correct only if both structures start with the hart state.
+const _: () = assert!(memoffset::offset_of!(HardwareHart, non_confidential_hart_state) == 0);
+const _: () = assert!(ConfidentialHart::HART_STATE_OFFSET == 0);
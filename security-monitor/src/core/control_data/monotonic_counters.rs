@@ -0,0 +1,79 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::error::{Error, Result};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Keeps the values of the monotonic counters. A storage backed by the platform's non-volatile memory preserves the
+/// values across reboots, so the confidential VM can detect the rollback of its persistent state. The values are kept
+/// per identity, the same one that derives the confidential VM's sealing key, so only a confidential VM with the same
+/// measurements finds the counters protecting the data it sealed.
+pub trait CounterStorage {
+    /// Returns true if the values survive the destruction of the confidential VM and a reboot of the platform.
+    fn is_persistent(&self) -> bool;
+
+    fn load(&self, identity: &[u8], index: usize) -> u64;
+
+    fn store(&mut self, identity: &[u8], index: usize, value: u64) -> Result<()>;
+}
+
+/// Keeps the values in the confidential memory of the confidential VM. They survive as long as the confidential VM
+/// exists, so they start from zero when the hypervisor destroys and recreates the confidential VM, also within a boot.
+pub struct VolatileCounterStorage {
+    values: [u64; MonotonicCounters::NUMBER_OF_COUNTERS],
+}
+
+impl VolatileCounterStorage {
+    pub fn new() -> Self {
+        Self { values: [0; MonotonicCounters::NUMBER_OF_COUNTERS] }
+    }
+}
+
+impl CounterStorage for VolatileCounterStorage {
+    fn is_persistent(&self) -> bool {
+        false
+    }
+
+    fn load(&self, _identity: &[u8], index: usize) -> u64 {
+        self.values[index]
+    }
+
+    fn store(&mut self, _identity: &[u8], index: usize, value: u64) -> Result<()> {
+        self.values[index] = value;
+        Ok(())
+    }
+}
+
+/// MonotonicCounters are counters of a confidential VM that never decrease. Only the confidential VM can increment
+/// them and the hypervisor has no way to reset them, so the confidential VM can use them for anti-rollback protection.
+pub struct MonotonicCounters {
+    // the sealing identity of the confidential VM, which selects the counters in the storage.
+    identity: Vec<u8>,
+    storage: Box<dyn CounterStorage + Send + Sync>,
+}
+
+impl MonotonicCounters {
+    pub const NUMBER_OF_COUNTERS: usize = 8;
+
+    pub fn new(identity: &[u8]) -> Self {
+        Self { identity: identity.to_vec(), storage: Box::new(VolatileCounterStorage::new()) }
+    }
+
+    pub fn read(&self, index: usize) -> Result<u64> {
+        assure!(index < Self::NUMBER_OF_COUNTERS, Error::InvalidCounterIndex(index))?;
+        Ok(self.storage.load(&self.identity, index))
+    }
+
+    /// Increments the counter and returns its new value. The counter saturates instead of wrapping around, so an
+    /// increment of the counter that reached the maximum value fails.
+    pub fn increment(&mut self, index: usize) -> Result<u64> {
+        let value = self.read(index)?.checked_add(1).ok_or(Error::CounterOverflow())?;
+        self.storage.store(&self.identity, index, value)?;
+        Ok(value)
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.storage.is_persistent()
+    }
+}
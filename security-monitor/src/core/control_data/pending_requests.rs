@@ -0,0 +1,55 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::PendingRequest;
+use crate::error::{Error, Result};
+
+/// PendingRequests are the requests of a confidential hart that await the hypervisor's response, ordered from the
+/// oldest one. A handler can enqueue a follow-up request that the security monitor handles after the current one
+/// completes, so a multi-step transformation does not lose its state. The queue has a fixed capacity and never
+/// allocates.
+pub struct PendingRequests {
+    requests: [Option<PendingRequest>; Self::CAPACITY],
+    // index of the oldest request.
+    head: usize,
+    length: usize,
+}
+
+impl PendingRequests {
+    pub const CAPACITY: usize = 4;
+    const EMPTY: Option<PendingRequest> = None;
+
+    pub fn new() -> Self {
+        Self { requests: [Self::EMPTY; Self::CAPACITY], head: 0, length: 0 }
+    }
+
+    /// Appends the request after all pending requests. Fails if the queue is full.
+    pub fn push(&mut self, request: PendingRequest) -> Result<()> {
+        assure!(self.length < Self::CAPACITY, Error::PendingRequestQueueFull())?;
+        self.requests[(self.head + self.length) % Self::CAPACITY] = Some(request);
+        self.length += 1;
+        Ok(())
+    }
+
+    /// Removes and returns the oldest pending request.
+    pub fn take_next(&mut self) -> Option<PendingRequest> {
+        let request = self.requests[self.head].take()?;
+        self.head = (self.head + 1) % Self::CAPACITY;
+        self.length -= 1;
+        Some(request)
+    }
+
+    /// Returns the oldest pending request without removing it.
+    pub fn next(&self) -> Option<&PendingRequest> {
+        self.requests[self.head].as_ref()
+    }
+
+    /// Returns true if any pending request satisfies the predicate.
+    pub fn contains<F: Fn(&PendingRequest) -> bool>(&self, predicate: F) -> bool {
+        self.requests.iter().flatten().any(predicate)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
@@ -0,0 +1,45 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// ProgressMonitor detects a confidential hart that keeps trapping at the same instruction for the same reason, e.g.,
+/// because the hypervisor never backs the page the instruction accesses or the confidential VM never handles the fault.
+/// Such a trap storm occupies the physical hart without making progress. A trap is identical to the previous one only
+/// if the security monitor did not move the confidential hart to another instruction in between.
+pub struct ProgressMonitor {
+    last_trap: Option<(usize, usize)>,
+    identical_traps: usize,
+    max_identical_traps: usize,
+}
+
+impl ProgressMonitor {
+    pub fn new(max_identical_traps: usize) -> Self {
+        Self { last_trap: None, identical_traps: 0, max_identical_traps }
+    }
+
+    /// Records the trap taken at the given address for the given cause. Returns true if the confidential hart took the
+    /// same trap more than the allowed number of times in a row. Then the counting starts anew, so the confidential
+    /// hart is reported again if it still does not make progress.
+    pub fn record_trap(&mut self, address: usize, cause: usize) -> bool {
+        match self.last_trap {
+            Some(last_trap) if last_trap == (address, cause) => self.identical_traps += 1,
+            _ => {
+                self.last_trap = Some((address, cause));
+                self.identical_traps = 0;
+            }
+        }
+        if self.identical_traps < self.max_identical_traps {
+            return false;
+        }
+        self.record_progress();
+        true
+    }
+
+    /// Records that the confidential hart moved to another instruction.
+    pub fn record_progress(&mut self) {
+        self.last_trap = None;
+        self.identical_traps = 0;
+    }
+}
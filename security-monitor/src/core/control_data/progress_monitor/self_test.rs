@@ -0,0 +1,30 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::error::{Error, Result};
+
+/// Repeats the same trap and checks that it is reported only once the threshold is exceeded, then again after the
+/// same number of traps. A trap at another address or for another cause and recorded progress restart the count.
+pub fn run() -> Result<()> {
+    const THRESHOLD: usize = 3;
+    let mut progress_monitor = ProgressMonitor::new(THRESHOLD);
+    let mut repeat = |address: usize, cause: usize, times: usize| {
+        (0..times).map(|_| progress_monitor.record_trap(address, cause)).filter(|is_reported| *is_reported).count()
+    };
+    // the first trap starts the count, so the trap is reported on its repetition number THRESHOLD.
+    let is_reported_after_threshold = repeat(0x1000, 2, THRESHOLD) == 0 && repeat(0x1000, 2, 1) == 1;
+    let is_reported_again = repeat(0x1000, 2, THRESHOLD) == 0 && repeat(0x1000, 2, 1) == 1;
+    let is_restarted_by_address = repeat(0x1000, 2, THRESHOLD) == 0 && repeat(0x1004, 2, THRESHOLD) == 0;
+    let is_restarted_by_cause = repeat(0x1004, 5, THRESHOLD) == 0;
+    progress_monitor.record_progress();
+    let is_restarted_by_progress = !progress_monitor.record_trap(0x1004, 5);
+    assure!(
+        is_reported_after_threshold
+            && is_reported_again
+            && is_restarted_by_address
+            && is_restarted_by_cause
+            && is_restarted_by_progress,
+        Error::SelfTestFailed("progress monitor")
+    )
+}
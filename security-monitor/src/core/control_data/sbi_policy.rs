@@ -0,0 +1,58 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::confidential_vm::Measurement;
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// SBI extensions whose calls the security monitor forwards to the hypervisor on behalf of the confidential VM. The
+/// policy is declared when the confidential VM is created and never changes, so a verifier learns from the measurement
+/// which services of the hypervisor the confidential VM can use. Calls to the ACE and base extensions are handled by
+/// the security monitor, so the policy never restricts them.
+#[derive(Clone, Copy)]
+pub struct SbiPolicy {
+    // bit i allows the i-th extension of `EXTENSIONS`. All extensions are allowed if there is no mask.
+    allowed_extensions: Option<usize>,
+}
+
+impl SbiPolicy {
+    /// Standard extensions that a restricted policy can allow: TIME, IPI, RFENCE, HSM, SRST, PMU, DBCN, SUSP, and CPPC.
+    const EXTENSIONS: [usize; 9] =
+        [0x54494D45, 0x735049, 0x52464E43, 0x48534D, 0x53525354, 0x504D55, 0x4442434E, 0x53555350, 0x43505043];
+    pub const NUMBER_OF_EXTENSIONS: usize = Self::EXTENSIONS.len();
+
+    pub fn unrestricted() -> Self {
+        Self { allowed_extensions: None }
+    }
+
+    /// Returns the policy that allows only the extensions selected by the mask. Vendor and experimental extensions are
+    /// never allowed by a restricted policy.
+    pub fn restricted(mask: usize) -> Self {
+        Self { allowed_extensions: Some(mask & ((1 << Self::NUMBER_OF_EXTENSIONS) - 1)) }
+    }
+
+    pub fn allows(&self, extension_id: usize) -> bool {
+        match self.allowed_extensions {
+            None => true,
+            Some(mask) => {
+                Self::EXTENSIONS.iter().position(|id| *id == extension_id).is_some_and(|i| mask & (1 << i) != 0)
+            }
+        }
+    }
+
+    /// Returns true if the confidential VM can use the standard extension, i.e., the security monitor knows the
+    /// extension and the policy allows forwarding its calls to the hypervisor.
+    pub fn exposes(&self, extension_id: usize) -> bool {
+        Self::EXTENSIONS.contains(&extension_id) && self.allows(extension_id)
+    }
+
+    /// Extends the measurement with the mask of allowed extensions encoded as a little-endian 64-bit value. The
+    /// unrestricted policy does not extend the measurement, so the measurements of confidential VMs that do not declare
+    /// a policy do not change.
+    pub fn measure(&self, measurement: &mut Measurement) {
+        if let Some(mask) = self.allowed_extensions {
+            measurement.extend(&[&(mask as u64).to_le_bytes()]);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::core::transformations::SbiBaseRequest;
+use crate::error::{Error, Result};
+
+/// Checks that a restricted policy forwards only the calls of the allowed extensions.
+pub fn run() -> Result<()> {
+    const DBCN_EXTID: usize = 0x4442434E;
+    const HSM_EXTID: usize = 0x48534D;
+    const VENDOR_EXTID: usize = 0x09000000;
+    let console_only = SbiPolicy::restricted(1 << 6);
+    let is_restricted =
+        console_only.allows(DBCN_EXTID) && !console_only.allows(HSM_EXTID) && !console_only.allows(VENDOR_EXTID);
+    let is_unrestricted = SbiPolicy::unrestricted().allows(HSM_EXTID) && SbiPolicy::unrestricted().allows(VENDOR_EXTID);
+    assure!(is_restricted && is_unrestricted, Error::SelfTestFailed("sbi policy"))?;
+    // the confidential VM discovers only the extensions that the policy allows, including the legacy ones.
+    const LEGACY_SET_TIMER_EXTID: usize = 0x00;
+    const LEGACY_CONSOLE_PUTCHAR_EXTID: usize = 0x01;
+    let probe = |extension_id| {
+        SbiBaseRequest::new(SbiBaseRequest::PROBE_EXTENSION_FID, extension_id, console_only).result().a1() == 1
+    };
+    let is_probed = probe(SbiBaseRequest::EXTID)
+        && probe(crate::ACE_EXT_ID)
+        && probe(DBCN_EXTID)
+        && !probe(HSM_EXTID)
+        && !probe(VENDOR_EXTID)
+        && probe(LEGACY_CONSOLE_PUTCHAR_EXTID)
+        && !probe(LEGACY_SET_TIMER_EXTID);
+    assure!(is_probed, Error::SelfTestFailed("sbi probe"))
+}
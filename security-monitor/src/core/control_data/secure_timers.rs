@@ -0,0 +1,66 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::error::{Error, Result};
+
+/// SecureTimers are independent timers of a confidential hart that the security monitor maintains on behalf of the
+/// confidential VM. They are multiplexed onto the single hardware timer of the physical hart by programming the
+/// nearest deadline while the confidential hart executes. An expired timer sets its flag, which only the owning
+/// confidential hart can read, and raises the VS-level software interrupt.
+pub struct SecureTimers {
+    // deadlines in the time exposed to the confidential VM, `DISARMED` denotes a timer that never fires.
+    deadlines: [usize; Self::MAX_TIMERS],
+    // bit i is set when the i-th timer expired and the confidential hart has not yet read the flags.
+    expired: usize,
+}
+
+impl SecureTimers {
+    pub const MAX_TIMERS: usize = 4;
+    pub const DISARMED: usize = usize::MAX;
+    // VS-level software interrupt raised when any timer expires.
+    const INTERRUPT: usize = 1 << 2;
+
+    pub fn new() -> Self {
+        Self { deadlines: [Self::DISARMED; Self::MAX_TIMERS], expired: 0 }
+    }
+
+    /// Arms the timer with the given deadline replacing the previous one. `DISARMED` cancels the timer.
+    pub fn set(&mut self, index: usize, deadline: usize) -> Result<()> {
+        let timer = self.deadlines.get_mut(index).ok_or(Error::InvalidSecureTimer(index))?;
+        *timer = deadline;
+        Ok(())
+    }
+
+    /// Returns the nearest deadline of all armed timers.
+    pub fn nearest_deadline(&self) -> Option<usize> {
+        self.deadlines.iter().copied().filter(|deadline| *deadline != Self::DISARMED).min()
+    }
+
+    /// Disarms all timers whose deadline passed and sets their flags. Returns true if any timer expired.
+    pub fn expire(&mut self, now: usize) -> bool {
+        let previously_expired = self.expired;
+        for (index, deadline) in self.deadlines.iter_mut().enumerate().filter(|(_, deadline)| **deadline <= now) {
+            *deadline = Self::DISARMED;
+            self.expired |= 1 << index;
+        }
+        self.expired != previously_expired
+    }
+
+    /// Returns the flags of expired timers without clearing them.
+    pub fn expired(&self) -> usize {
+        self.expired
+    }
+
+    /// Returns the flags of expired timers and clears them.
+    pub fn take_expired(&mut self) -> usize {
+        core::mem::take(&mut self.expired)
+    }
+
+    /// Returns the interrupt that is pending while any flag of an expired timer is set, encoded like hvip.
+    pub fn pending_interrupt(&self) -> usize {
+        match self.expired {
+            0 => 0,
+            _ => Self::INTERRUPT,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::{MeasurementAlgorithm, SelectedMeasurementAlgorithm};
+use crate::core::control_data::ConfidentialVm;
+use crate::core::memory_tracker::{Allocated, Page};
+use crate::core::mmu::PageSize;
+
+/// Layout of the page owned by the security monitor and mapped read-only into every confidential VM at
+/// `RootPageTable::MONITOR_PAGE_ADDRESS`. The confidential VM reads it without making an SBI call. All values are
+/// 64-bit. The page starts with the magic value, the confidential VM's id, the number of confidential harts, the
+/// measurement of the sharing policy in a 64-byte slot padded with zeros, the version of this layout, the TCG
+/// identifier of the algorithm that produced the measurements, and the lowest address of a shared page revoked by the
+/// hypervisor that the confidential VM has not yet acknowledged, with the lowest bit set, or zero if there is none. It
+/// is followed by a record for every confidential hart, indexed by the confidential hart id, consisting of the
+/// confidential hart id, the flags of expired secure timers, and the VS-level interrupts pending in the confidential
+/// hart encoded like hvip. The security monitor updates the record whenever it resumes the confidential hart.
+#[derive(Clone, Copy)]
+pub struct SharedInfo {
+    // start address of the page in the confidential memory.
+    address: usize,
+}
+
+impl SharedInfo {
+    // the page starts with this value, so the confidential VM can recognize it.
+    const MAGIC: u64 = 0x4143_452d_4d4f_4e49; // "ACE-MONI"
+    const VERSION: u64 = 3;
+    const VALUE_SIZE: usize = core::mem::size_of::<u64>();
+    const VM_ID_OFFSET: usize = Self::VALUE_SIZE;
+    const NUMBER_OF_HARTS_OFFSET: usize = 2 * Self::VALUE_SIZE;
+    const MEASUREMENT_OFFSET: usize = 3 * Self::VALUE_SIZE;
+    // the slot fits the output of every supported measurement algorithm.
+    const MEASUREMENT_SIZE: usize = 64;
+    const VERSION_OFFSET: usize = Self::MEASUREMENT_OFFSET + Self::MEASUREMENT_SIZE;
+    const MEASUREMENT_ALGORITHM_OFFSET: usize = Self::VERSION_OFFSET + Self::VALUE_SIZE;
+    const PENDING_REVOCATION_OFFSET: usize = Self::MEASUREMENT_ALGORITHM_OFFSET + Self::VALUE_SIZE;
+    const HART_RECORDS_OFFSET: usize = Self::PENDING_REVOCATION_OFFSET + Self::VALUE_SIZE;
+    const HART_RECORD_SIZE: usize = 3 * Self::VALUE_SIZE;
+
+    /// Writes the information that does not change during the lifetime of the confidential VM to the zeroed page.
+    pub fn new(
+        page: &Page<Allocated>, confidential_vm_id: usize, number_of_harts: usize, sharing_policy_measurement: &[u8],
+    ) -> Self {
+        page.write::<u64>(0, Self::MAGIC);
+        page.write::<u64>(Self::VM_ID_OFFSET, confidential_vm_id as u64);
+        page.write::<u64>(Self::NUMBER_OF_HARTS_OFFSET, number_of_harts as u64);
+        sharing_policy_measurement
+            .iter()
+            .take(Self::MEASUREMENT_SIZE)
+            .enumerate()
+            .for_each(|(i, byte)| page.write::<u8>(Self::MEASUREMENT_OFFSET + i, *byte));
+        page.write::<u64>(Self::VERSION_OFFSET, Self::VERSION);
+        page.write::<u64>(Self::MEASUREMENT_ALGORITHM_OFFSET, SelectedMeasurementAlgorithm::TAG);
+        let shared_info = Self { address: page.address().usize() };
+        (0..number_of_harts).for_each(|confidential_hart_id| shared_info.write_hart_record(confidential_hart_id, 0, 0));
+        shared_info
+    }
+
+    /// Updates the record of the given confidential hart. Records of confidential harts that the confidential VM does
+    /// not have are never written.
+    pub fn write_hart_record(
+        &self, confidential_hart_id: usize, expired_secure_timers: usize, pending_interrupts: usize,
+    ) {
+        if confidential_hart_id < ConfidentialVm::MAX_HARTS_PER_VM {
+            let offset = Self::HART_RECORDS_OFFSET + confidential_hart_id * Self::HART_RECORD_SIZE;
+            self.write(offset, confidential_hart_id as u64);
+            self.write(offset + Self::VALUE_SIZE, expired_secure_timers as u64);
+            self.write(offset + 2 * Self::VALUE_SIZE, pending_interrupts as u64);
+        }
+    }
+
+    /// Publishes the page whose revocation the confidential VM should acknowledge. Page addresses are aligned, so the
+    /// lowest bit distinguishes the page at address zero from no pending revocation.
+    pub fn write_pending_revocation(&self, page_address: Option<usize>) {
+        self.write(Self::PENDING_REVOCATION_OFFSET, page_address.map_or(0, |page_address| page_address | 1) as u64);
+    }
+
+    fn write(&self, offset: usize, value: u64) {
+        // Safety: the page is owned by the confidential VM's page table and released only when the confidential VM is
+        // destroyed together with its confidential harts. All offsets fit in the page, see the assertion below.
+        unsafe { ((self.address + offset) as *mut u64).write_volatile(value) };
+    }
+}
+
+// The measurement slot must fit the output of the selected measurement algorithm.
+const _: () = assert!(crate::core::attestation::MEASUREMENT_SIZE <= SharedInfo::MEASUREMENT_SIZE);
+// The records of all confidential harts must fit in the page.
+const _: () = assert!(
+    SharedInfo::HART_RECORDS_OFFSET + ConfidentialVm::MAX_HARTS_PER_VM * SharedInfo::HART_RECORD_SIZE
+        <= PageSize::Size4KiB as usize
+);
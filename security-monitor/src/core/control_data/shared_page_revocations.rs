@@ -0,0 +1,88 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::error::{Error, Result};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// SharedPageRevocations tracks shared pages that the hypervisor asked to take back from the confidential VM. The
+/// confidential VM learns about a revocation from the page owned by the security monitor and acknowledges it once it
+/// stopped using the page. The page is unmapped only after the acknowledgement or after the timeout, so a confidential
+/// VM that ignores revocations cannot prevent the hypervisor from reclaiming its memory. Confidential harts running
+/// when the page is unmapped might still use cached translations of it, so the hypervisor can reclaim the page only
+/// after all of them returned to the hypervisor. The security monitor flushes the second-level TLB before resuming
+/// them.
+pub struct SharedPageRevocations {
+    // deadlines and acknowledgements of revocations indexed by the start addresses of the revoked 4KiB pages.
+    pending: BTreeMap<usize, (usize, bool)>,
+    // ids of confidential harts running when the page was unmapped and the numbers of their returns to the hypervisor
+    // at that time, indexed by the start addresses of the unmapped 4KiB pages.
+    unmapped: BTreeMap<usize, Vec<(usize, usize)>>,
+    // number of timer ticks the confidential VM has to acknowledge the revocation.
+    timeout: usize,
+}
+
+impl SharedPageRevocations {
+    // number of milliseconds the confidential VM has to acknowledge the revocation.
+    const TIMEOUT_MS: usize = 1000;
+
+    /// Creates the revocations of a confidential VM whose `time` counter runs at the given frequency in Hz.
+    pub fn new(timebase_frequency: usize) -> Self {
+        let timeout = timebase_frequency.saturating_mul(Self::TIMEOUT_MS) / 1000;
+        Self { pending: BTreeMap::new(), unmapped: BTreeMap::new(), timeout }
+    }
+
+    /// Starts the revocation of the given page unless it is already pending. Returns true if the page can be unmapped
+    /// because the confidential VM acknowledged the revocation or the timeout passed.
+    pub fn request(&mut self, page_address: usize, now: usize) -> bool {
+        let (deadline, is_acknowledged) =
+            *self.pending.entry(page_address).or_insert((now.saturating_add(self.timeout), false));
+        is_acknowledged || now >= deadline
+    }
+
+    /// Returns true if the page is revoked and the confidential VM has not yet acknowledged it.
+    pub fn is_pending_acknowledgement(&self, page_address: usize) -> bool {
+        self.pending.get(&page_address).is_some_and(|(_, is_acknowledged)| !is_acknowledged)
+    }
+
+    /// Records that the confidential VM no longer uses the page. Fails if the hypervisor did not revoke it.
+    pub fn acknowledge(&mut self, page_address: usize) -> Result<()> {
+        let (_, is_acknowledged) = self.pending.get_mut(&page_address).ok_or(Error::NoPendingRevocation())?;
+        *is_acknowledged = true;
+        Ok(())
+    }
+
+    /// Records that the page was unmapped while the given confidential harts were running. Every confidential hart is
+    /// given by its id and the number of times it returned to the hypervisor so far.
+    pub fn unmap(&mut self, page_address: usize, running_harts: Vec<(usize, usize)>) {
+        self.pending.remove(&page_address);
+        if !running_harts.is_empty() {
+            self.unmapped.insert(page_address, running_harts);
+        }
+    }
+
+    /// Returns None if the page is not waiting for confidential harts that were running when it was unmapped.
+    /// Otherwise, returns true and forgets the page if all these confidential harts returned to the hypervisor
+    /// since, according to the given numbers of returns indexed by the confidential hart id.
+    pub fn take_unmapped(&mut self, page_address: usize, exits: &[usize]) -> Option<bool> {
+        let running_harts = self.unmapped.get(&page_address)?;
+        let have_exited = running_harts.iter().all(|(id, exits_at_unmap)| exits[*id] != *exits_at_unmap);
+        if have_exited {
+            self.unmapped.remove(&page_address);
+        }
+        Some(have_exited)
+    }
+
+    /// Forgets revocations of pages starting in the given range, e.g., because the pages are no longer shared.
+    pub fn remove(&mut self, start_address: usize, end_address: usize) {
+        self.pending.retain(|page_address, _| *page_address < start_address || *page_address >= end_address);
+    }
+
+    /// Returns the lowest address of a revoked page that the confidential VM has not yet acknowledged.
+    pub fn first_unacknowledged(&self) -> Option<usize> {
+        self.pending.iter().find(|(_, (_, is_acknowledged))| !is_acknowledged).map(|(page_address, _)| *page_address)
+    }
+}
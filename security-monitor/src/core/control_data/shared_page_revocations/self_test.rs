@@ -0,0 +1,42 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+/// Revokes a page that is acknowledged and a page whose timeout passes, and checks that both are reclaimed only
+/// after the confidential hart running when they were unmapped returned to the hypervisor.
+pub fn run() -> Result<()> {
+    const ACKNOWLEDGED: usize = 0x1000;
+    const IGNORED: usize = 0x2000;
+    const TIMEBASE_FREQUENCY: usize = 1_000_000;
+    let mut revocations = SharedPageRevocations::new(TIMEBASE_FREQUENCY);
+    let timeout = TIMEBASE_FREQUENCY * SharedPageRevocations::TIMEOUT_MS / 1000;
+    let is_requested = !revocations.request(ACKNOWLEDGED, 0) && !revocations.request(IGNORED, 0);
+    let is_published = revocations.first_unacknowledged() == Some(ACKNOWLEDGED);
+    revocations.acknowledge(ACKNOWLEDGED)?;
+    let is_acknowledged = revocations.first_unacknowledged() == Some(IGNORED)
+        && !revocations.is_pending_acknowledgement(ACKNOWLEDGED)
+        && revocations.request(ACKNOWLEDGED, 1);
+    // confidential hart 1 runs and returned to the hypervisor twice so far.
+    let mut exits = [0, 2];
+    revocations.unmap(ACKNOWLEDGED, Vec::from([(1, exits[1])]));
+    let is_waiting = revocations.take_unmapped(ACKNOWLEDGED, &exits) == Some(false);
+    assure!(
+        is_requested && is_published && is_acknowledged && is_waiting,
+        Error::SelfTestFailed("revocation ack")
+    )?;
+
+    let is_timed_out = !revocations.request(IGNORED, timeout - 1)
+        && revocations.request(IGNORED, timeout)
+        && revocations.is_pending_acknowledgement(IGNORED);
+    revocations.unmap(IGNORED, Vec::new());
+    let is_reclaimable = revocations.take_unmapped(IGNORED, &exits).is_none()
+        && revocations.first_unacknowledged().is_none()
+        && matches!(revocations.acknowledge(IGNORED), Err(Error::NoPendingRevocation()));
+    assure!(is_timed_out && is_reclaimable, Error::SelfTestFailed("revocation timeout"))?;
+
+    exits[1] += 1;
+    let is_flushed = revocations.take_unmapped(ACKNOWLEDGED, &exits) == Some(true)
+        && revocations.take_unmapped(ACKNOWLEDGED, &exits).is_none();
+    assure!(is_flushed, Error::SelfTestFailed("revocation flush"))
+}
@@ -0,0 +1,42 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::confidential_vm::Measurement;
+use crate::core::mmu::PageSize;
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use core::ops::Range;
+
+/// Restrictions on the pages a confidential VM can share with the hypervisor. The policy is declared when the
+/// confidential VM is created and never changes, so a verifier learns from the measurement which memory the
+/// confidential VM can expose to the hypervisor.
+pub struct SharingPolicy {
+    max_shared_pages: usize,
+    shareable_region: Range<usize>,
+}
+
+impl SharingPolicy {
+    pub fn new(max_shared_pages: usize, shareable_region: Range<usize>) -> Self {
+        Self { max_shared_pages, shareable_region }
+    }
+
+    pub fn max_shared_pages(&self) -> usize {
+        self.max_shared_pages
+    }
+
+    /// Returns true if the entire 4KiB page starting at the given address is within the shareable region.
+    pub fn allows(&self, address: ConfidentialVmVirtualAddress) -> bool {
+        match address.checked_add(PageSize::Size4KiB.in_bytes()) {
+            Ok(end_address) => {
+                self.shareable_region.start <= address.usize() && end_address.usize() <= self.shareable_region.end
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Extends the measurement with the boundaries of the shareable region encoded as little-endian 64-bit values.
+    pub fn measure(&self, measurement: &mut Measurement) {
+        let start = (self.shareable_region.start as u64).to_le_bytes();
+        let end = (self.shareable_region.end as u64).to_le_bytes();
+        measurement.extend(&[&start, &end]);
+    }
+}
@@ -1,10 +1,14 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::{ConfidentialHart, ConfidentialVm, ConfidentialVmId};
+use crate::core::attestation::ImageManifest;
+use crate::core::bounded_lock::{self, MAX_LOCK_ATTEMPTS};
+use crate::core::control_data::{ConfidentialHart, ConfidentialVm, ConfidentialVmId, SbiPolicy, SharingPolicy};
 use crate::core::mmu::RootPageTable;
-use crate::error::{Error, NOT_INITIALIZED_CONTROL_DATA};
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result, NOT_INITIALIZED_CONTROL_DATA};
 use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard, Once, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
@@ -15,8 +19,12 @@ use spin::{Mutex, MutexGuard, Once, RwLock, RwLockReadGuard, RwLockWriteGuard};
 /// accesses to the control data region descriptor requested from multiple physical harts.
 pub static CONTROL_DATA: Once<RwLock<ControlData>> = Once::new();
 
+/// Number of checks whether all confidential harts of a quiesced confidential VM have stopped before giving up.
+const MAX_QUIESCE_CHECKS: usize = 1 << 16;
+
 pub struct ControlData {
-    confidential_vms: BTreeMap<ConfidentialVmId, Mutex<ConfidentialVm>>,
+    // physical harts executing a confidential VM keep a handle to it, so they do not look it up on every trap.
+    confidential_vms: BTreeMap<ConfidentialVmId, Arc<Mutex<ConfidentialVm>>>,
 }
 
 impl ControlData {
@@ -28,62 +36,106 @@ impl ControlData {
     /// to it. This identifier is not secret and reflects the number of confidential VMs that have been created up to
     /// now. The maximum allowed number of confidential VMs created is limited by the size of the `usize` type.
     pub fn store_confidential_vm(
-        confidential_harts: Vec<ConfidentialHart>, root_page_table: RootPageTable,
-    ) -> Result<ConfidentialVmId, Error> {
+        confidential_harts: Vec<ConfidentialHart>, root_page_table: RootPageTable, sharing_policy: SharingPolicy,
+        sbi_policy: SbiPolicy, image_manifest: Option<&ImageManifest>,
+        fdt_address: Option<ConfidentialVmVirtualAddress>,
+    ) -> Result<ConfidentialVmId> {
         Self::try_write(|control_data| {
-            control_data
+            let id = control_data
                 .confidential_vms
                 .keys()
                 .max()
                 .map(|v| v.usize().checked_add(1))
                 .unwrap_or(Some(0))
-                .and_then(|max_id| {
-                    let id = ConfidentialVmId::new(max_id);
-                    let confidential_vm = ConfidentialVm::new(id, confidential_harts, root_page_table);
-                    control_data.confidential_vms.insert(id, Mutex::new(confidential_vm));
-                    Some(id)
-                })
-                .ok_or(Error::ReachedMaximumNumberOfCvms())
+                .map(|max_id| ConfidentialVmId::new(max_id))
+                .ok_or(Error::ReachedMaximumNumberOfCvms())?;
+            let confidential_vm = ConfidentialVm::new(
+                id,
+                confidential_harts,
+                root_page_table,
+                sharing_policy,
+                sbi_policy,
+                image_manifest,
+                fdt_address,
+            )?;
+            control_data.confidential_vms.insert(id, Arc::new(Mutex::new(confidential_vm)));
+            Ok(id)
+        })
+    }
+
+    pub fn confidential_vm(&self, id: ConfidentialVmId) -> Result<MutexGuard<'_, ConfidentialVm>> {
+        let confidential_vm = self.confidential_vms.get(&id).ok_or(Error::InvalidConfidentialVmId())?;
+        bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || confidential_vm.try_lock())
+    }
+
+    /// Returns the handle to the confidential VM that a physical hart keeps while it executes one of the confidential
+    /// VM's harts. The handle outlives the lock to the control data, so the confidential VM must not be removed while
+    /// any physical hart holds its handle.
+    pub fn confidential_vm_handle(confidential_vm_id: ConfidentialVmId) -> Result<Arc<Mutex<ConfidentialVm>>> {
+        Self::try_read(|control_data| {
+            control_data.confidential_vms.get(&confidential_vm_id).cloned().ok_or(Error::InvalidConfidentialVmId())
         })
     }
 
-    pub fn confidential_vm(&self, id: ConfidentialVmId) -> Option<MutexGuard<'_, ConfidentialVm>> {
-        self.confidential_vms.get(&id).and_then(|v| v.try_lock())
+    /// Stops all confidential harts of the confidential VM before an operation that requires a consistent state of the
+    /// entire VM, e.g., its termination. Physical harts executing the confidential VM are interrupted and give back
+    /// their confidential harts when they enter the security monitor. The confidential VM stays quiesced after this
+    /// function returns successfully. Returns `Error::QuiesceTimeout` if any confidential hart is still running after
+    /// the bounded number of checks, in which case the confidential VM can run again.
+    ///
+    /// The caller must not hold the lock to the control data because stopping harts need it to return their
+    /// confidential harts. The caller provides the function interrupting a physical hart given its hart id.
+    pub fn quiesce_confidential_vm<F>(
+        confidential_vm_id: ConfidentialVmId, mut interrupt_physical_hart: F,
+    ) -> Result<()>
+    where F: FnMut(usize) {
+        let physical_hart_ids = Self::try_confidential_vm(confidential_vm_id, |mut cvm| Ok(cvm.begin_quiesce()))?;
+        physical_hart_ids.into_iter().for_each(&mut interrupt_physical_hart);
+        for _ in 0..MAX_QUIESCE_CHECKS {
+            if !Self::try_confidential_vm(confidential_vm_id, |cvm| Ok(cvm.is_running()))? {
+                return Ok(());
+            }
+            core::hint::spin_loop();
+        }
+        Self::unquiesce_confidential_vm(confidential_vm_id)?;
+        Err(Error::QuiesceTimeout())
+    }
+
+    /// Allows the hypervisor to run confidential harts of a quiesced confidential VM again. Used when the operation
+    /// that required quiescing the confidential VM failed and the confidential VM still exists.
+    pub fn unquiesce_confidential_vm(confidential_vm_id: ConfidentialVmId) -> Result<()> {
+        Self::try_confidential_vm(confidential_vm_id, |mut cvm| Ok(cvm.end_quiesce()))
     }
 
-    pub fn remove_confidential_vm(
-        &mut self, confidential_vm_id: ConfidentialVmId,
-    ) -> Result<Mutex<ConfidentialVm>, Error> {
-        self.confidential_vms.remove(&confidential_vm_id).ok_or(Error::InvalidConfidentialVmId())
+    pub fn remove_confidential_vm(&mut self, confidential_vm_id: ConfidentialVmId) -> Result<Mutex<ConfidentialVm>> {
+        let confidential_vm = self.confidential_vms.get(&confidential_vm_id).ok_or(Error::InvalidConfidentialVmId())?;
+        // a physical hart still holding the handle might access the confidential VM after its removal.
+        assure!(Arc::strong_count(confidential_vm) == 1, Error::RunningVHart())?;
+        self.confidential_vms
+            .remove(&confidential_vm_id)
+            .and_then(|confidential_vm| Arc::try_unwrap(confidential_vm).ok())
+            .ok_or(Error::InvalidConfidentialVmId())
     }
 
-    fn try_read<F, O>(op: O) -> Result<F, Error>
-    where O: FnOnce(&RwLockReadGuard<'_, ControlData>) -> Result<F, Error> {
-        CONTROL_DATA
-            .get()
-            .expect(NOT_INITIALIZED_CONTROL_DATA)
-            .try_read()
-            .ok_or(Error::OptimisticLocking())
-            .and_then(|ref control_data| op(control_data))
+    fn try_read<F, O>(op: O) -> Result<F>
+    where O: FnOnce(&RwLockReadGuard<'_, ControlData>) -> Result<F> {
+        let lock = CONTROL_DATA.get().expect(NOT_INITIALIZED_CONTROL_DATA);
+        bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || lock.try_read()).and_then(|ref control_data| op(control_data))
     }
 
-    pub fn try_write<F, O>(op: O) -> Result<F, Error>
-    where O: FnOnce(&mut RwLockWriteGuard<'static, ControlData>) -> Result<F, Error> {
-        CONTROL_DATA
-            .get()
-            .expect(NOT_INITIALIZED_CONTROL_DATA)
-            .try_write()
-            .ok_or(Error::OptimisticLocking())
-            .and_then(|ref mut control_data| op(control_data))
+    pub fn try_write<F, O>(op: O) -> Result<F>
+    where O: FnOnce(&mut RwLockWriteGuard<'static, ControlData>) -> Result<F> {
+        let lock = CONTROL_DATA.get().expect(NOT_INITIALIZED_CONTROL_DATA);
+        bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || lock.try_write()).and_then(|ref mut control_data| op(control_data))
     }
 
-    pub fn try_confidential_vm<F, O>(confidential_vm_id: ConfidentialVmId, op: O) -> Result<F, Error>
-    where O: FnOnce(MutexGuard<'_, ConfidentialVm>) -> Result<F, Error> {
-        Self::try_read(|mr| op(mr.confidential_vm(confidential_vm_id).ok_or(Error::InvalidConfidentialVmId())?))
+    pub fn try_confidential_vm<F, O>(confidential_vm_id: ConfidentialVmId, op: O) -> Result<F>
+    where O: FnOnce(MutexGuard<'_, ConfidentialVm>) -> Result<F> {
+        Self::try_read(|mr| op(mr.confidential_vm(confidential_vm_id)?))
     }
 
-    pub fn try_confidential_vm_mut<F, O>(confidential_vm_id: ConfidentialVmId, op: O) -> Result<F, Error>
-    where O: FnOnce(MutexGuard<'_, ConfidentialVm>) -> Result<F, Error> {
-        Self::try_read(|m| op(m.confidential_vm(confidential_vm_id).ok_or(Error::InvalidConfidentialVmId())?))
+    pub fn try_confidential_vm_mut<F, O>(confidential_vm_id: ConfidentialVmId, op: O) -> Result<F>
+    where O: FnOnce(MutexGuard<'_, ConfidentialVm>) -> Result<F> {
+        Self::try_read(|m| op(m.confidential_vm(confidential_vm_id)?))
     }
 }
@@ -0,0 +1,114 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::VirtualTime;
+use alloc::sync::Arc;
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// VirtualCounters virtualize the `cycle` and `instret` counters of the confidential hart. The virtual counters advance
+/// only when the confidential hart executes, so the confidential VM cannot learn for how long the hypervisor, other
+/// VMs, or the security monitor executed on the physical hart. This prevents timing side and covert channels. The
+/// `time` counter follows the physical hart's `time` counter shifted by an offset that keeps it consistent across the
+/// confidential harts of the confidential VM.
+pub struct VirtualCounters {
+    cycle_offset: usize,
+    instret_offset: usize,
+    // values of the hardware counters at the moment when the confidential hart stopped executing.
+    suspended_at: Option<(usize, usize)>,
+    // the difference between the time exposed to the confidential VM and the physical hart's `time` counter.
+    time_offset: usize,
+    // the time base shared with other confidential harts of the confidential VM.
+    virtual_time: Option<Arc<VirtualTime>>,
+}
+
+impl VirtualCounters {
+    /// Creates virtual counters that start counting from zero once the confidential hart executes for the first time.
+    pub fn new() -> Self {
+        let (cycle, instret) = Self::read_hardware_counters();
+        Self {
+            cycle_offset: cycle,
+            instret_offset: instret,
+            suspended_at: Some((cycle, instret)),
+            time_offset: 0,
+            virtual_time: None,
+        }
+    }
+
+    pub fn set_virtual_time(&mut self, virtual_time: Arc<VirtualTime>) {
+        self.virtual_time = Some(virtual_time);
+    }
+
+    /// Recomputes the offset of the `time` counter, so the confidential hart continues from the latest time observed by
+    /// the confidential VM. Must be called whenever the confidential hart is scheduled on a physical hart, whose `time`
+    /// counter might be skewed with respect to the one on which the confidential hart executed before.
+    pub fn migrate(&mut self) {
+        self.migrate_at(riscv::register::time::read());
+    }
+
+    fn migrate_at(&mut self, hardware_time: usize) {
+        if let Some(virtual_time) = &self.virtual_time {
+            self.time_offset = virtual_time.offset(hardware_time);
+        }
+    }
+
+    /// Stops the virtual counters. Must be called when the confidential hart stops executing.
+    pub fn suspend(&mut self) {
+        if self.suspended_at.is_none() {
+            self.suspended_at = Some(Self::read_hardware_counters());
+        }
+    }
+
+    /// Restarts the virtual counters. The time the confidential hart did not execute is not accounted.
+    pub fn resume(&mut self) {
+        if let Some((cycle, instret)) = self.suspended_at.take() {
+            let (current_cycle, current_instret) = Self::read_hardware_counters();
+            self.cycle_offset = self.cycle_offset.wrapping_add(current_cycle.wrapping_sub(cycle));
+            self.instret_offset = self.instret_offset.wrapping_add(current_instret.wrapping_sub(instret));
+        }
+    }
+
+    pub fn cycle(&self) -> usize {
+        let (cycle, _) = self.suspended_at.unwrap_or_else(|| Self::read_hardware_counters());
+        cycle.wrapping_sub(self.cycle_offset)
+    }
+
+    pub fn instret(&self) -> usize {
+        let (_, instret) = self.suspended_at.unwrap_or_else(|| Self::read_hardware_counters());
+        instret.wrapping_sub(self.instret_offset)
+    }
+
+    /// The `time` counter represents the wall-clock time, which is already shared with the hypervisor that programs
+    /// timers for the confidential VM. We do not stop it when the confidential hart does not execute, but we never
+    /// expose a time lower than the one already observed by any confidential hart of the confidential VM.
+    pub fn time(&self) -> usize {
+        self.time_at(riscv::register::time::read())
+    }
+
+    fn time_at(&self, hardware_time: usize) -> usize {
+        let time = hardware_time.wrapping_add(self.time_offset);
+        self.virtual_time.as_ref().map_or(time, |virtual_time| virtual_time.observe(time))
+    }
+
+    /// Converts the time exposed to the confidential VM to the value of the physical hart's `time` counter, e.g., to
+    /// program the hardware timer with the deadline of a secure timer. Another confidential hart might have already
+    /// exposed a time ahead of this hart's `time` counter shifted by the offset, so the conversion uses the current
+    /// difference between the exposed time and the physical hart's `time` counter. Thus, the hardware timer fires once
+    /// the time exposed to the confidential VM reaches the given time.
+    pub fn hardware_time(&self, time: usize) -> usize {
+        self.hardware_time_at(time, riscv::register::time::read())
+    }
+
+    fn hardware_time_at(&self, time: usize, hardware_time: usize) -> usize {
+        let offset = self
+            .virtual_time
+            .as_ref()
+            .map_or(self.time_offset, |virtual_time| virtual_time.offset(hardware_time).max(self.time_offset));
+        time.saturating_sub(offset)
+    }
+
+    fn read_hardware_counters() -> (usize, usize) {
+        (riscv::register::mcycle::read(), riscv::register::minstret::read())
+    }
+}
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::error::{Error, Result};
+
+/// Runs two confidential harts of the same confidential VM on physical harts whose `time` counters are skewed and
+/// checks that the exposed time never goes backwards and that timer deadlines are converted to the physical harts.
+pub fn run() -> Result<()> {
+    // the `time` counter of the second physical hart lags 600 ticks behind the first one.
+    const SKEW: usize = 600;
+    let virtual_time = Arc::new(VirtualTime::new());
+    let mut first = VirtualCounters::new();
+    first.set_virtual_time(virtual_time.clone());
+    first.migrate_at(1000);
+    let mut second = VirtualCounters::new();
+    second.set_virtual_time(virtual_time);
+    second.migrate_at(1000 - SKEW);
+    let is_consistent =
+        first.time_at(1000) == 1000 && second.time_at(1000 - SKEW) == 1000 && second.time_at(1050 - SKEW) == 1050;
+    let are_deadlines_converted =
+        first.hardware_time_at(1100, 1050) == 1100 && second.hardware_time_at(1100, 1050 - SKEW) == 1100 - SKEW;
+    // the hypervisor schedules the first confidential hart on the second physical hart.
+    first.migrate_at(1060 - SKEW);
+    let is_monotonic = first.time_at(1060 - SKEW) >= 1050;
+    let deadline = first.hardware_time_at(1100, 1060 - SKEW);
+    let is_migrated_deadline_converted = first.time_at(deadline) == 1100;
+    assure!(
+        is_consistent && are_deadlines_converted && is_monotonic && is_migrated_deadline_converted,
+        Error::SelfTestFailed("virtual counters")
+    )
+}
@@ -0,0 +1,64 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// VirtualInterruptFile emulates the interrupt file of the AIA's IMSIC for a confidential hart. The security monitor
+/// maintains one file per confidential hart, so the external interrupts pending for one confidential VM are never
+/// visible to, nor claimable by, another VM. As in IMSIC, a lower interrupt identity denotes a higher priority.
+pub struct VirtualInterruptFile {
+    // bit i is set when the external interrupt with identity i is pending, identity 0 is not a valid interrupt.
+    pending: usize,
+}
+
+impl VirtualInterruptFile {
+    pub const MAX_IDENTITY: usize = usize::BITS as usize - 1;
+    // the major interrupt number of the supervisor external interrupt reported in stopi.
+    const EXTERNAL_INTERRUPT: usize = 9;
+    const IDENTITY_SHIFT: usize = 16;
+    const MAX_STOPI_PRIORITY: usize = 0xff;
+
+    pub fn new() -> Self {
+        Self { pending: 0 }
+    }
+
+    /// Marks the external interrupt pending. Identities that the file does not implement are ignored.
+    pub fn set_pending(&mut self, identity: usize) {
+        if (1..=Self::MAX_IDENTITY).contains(&identity) {
+            self.pending |= 1 << identity;
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending != 0
+    }
+
+    /// Returns the value of stopei: the identity of the highest-priority pending interrupt, which is also its
+    /// priority, in both the identity and the priority fields. Zero when no interrupt is pending.
+    pub fn stopei(&self) -> usize {
+        self.top().map_or(0, |identity| (identity << Self::IDENTITY_SHIFT) | identity)
+    }
+
+    /// Returns the value of stopi: the major interrupt number of the supervisor external interrupt and the priority of
+    /// the highest-priority pending interrupt. Zero when no interrupt is pending.
+    pub fn stopi(&self) -> usize {
+        self.top().map_or(0, |identity| {
+            (Self::EXTERNAL_INTERRUPT << Self::IDENTITY_SHIFT) | identity.min(Self::MAX_STOPI_PRIORITY)
+        })
+    }
+
+    /// Claims the highest-priority pending interrupt and returns the value of stopei from before the claim.
+    pub fn claim(&mut self) -> usize {
+        let stopei = self.stopei();
+        if let Some(identity) = self.top() {
+            self.pending &= !(1 << identity);
+        }
+        stopei
+    }
+
+    fn top(&self) -> Option<usize> {
+        match self.pending {
+            0 => None,
+            pending => Some(pending.trailing_zeros() as usize),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::Once;
+
+/// Frequency of the `time` counter in Hz, read from the device tree during the initialization of the security monitor.
+/// Durations that the security monitor measures in timer ticks, e.g., timeouts given to confidential VMs, are derived
+/// from it, so they do not depend on the platform.
+pub static TIMEBASE_FREQUENCY: Once<usize> = Once::new();
+
+/// VirtualTime is the time base shared by all confidential harts of a confidential VM. The `time` counters of physical
+/// harts might be skewed, so a confidential hart scheduled on another physical hart could observe time going backwards
+/// or lagging behind its sibling confidential harts. VirtualTime records the latest time observed by any confidential
+/// hart of the confidential VM. Confidential harts never expose a lower time and use it to recompute their offset to
+/// the physical hart's `time` counter whenever the hypervisor schedules them.
+pub struct VirtualTime {
+    latest: AtomicUsize,
+}
+
+impl VirtualTime {
+    pub fn new() -> Self {
+        Self { latest: AtomicUsize::new(0) }
+    }
+
+    /// Returns the offset that the confidential hart adds to the given value of the physical hart's `time` counter, so
+    /// it continues from the latest time observed by the confidential VM.
+    pub fn offset(&self, hardware_time: usize) -> usize {
+        self.latest.load(Ordering::SeqCst).saturating_sub(hardware_time)
+    }
+
+    /// Records the time read by a confidential hart and returns the time exposed to the confidential VM, which is not
+    /// lower than any time exposed to the confidential VM before.
+    pub fn observe(&self, time: usize) -> usize {
+        self.latest.fetch_max(time, Ordering::SeqCst).max(time)
+    }
+}
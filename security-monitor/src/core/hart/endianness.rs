@@ -0,0 +1,83 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::hart::HartState;
+
+/// Byte order of the data accesses of a confidential hart. The hypervisor always emulates MMIO using the little-endian
+/// representation of the value, so the security monitor swaps bytes of values exchanged with a big-endian hart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    const MSTATUS_MPP_SHIFT: usize = 11;
+    const MSTATUS_MPP_MASK: usize = 0b11;
+    const HSTATUS_VSBE: usize = 1 << 5;
+    const VSSTATUS_UBE: usize = 1 << 6;
+
+    /// Returns the byte order of the access that trapped in the security monitor. The confidential VM's kernel runs in
+    /// VS-mode, so its byte order is set by hstatus.VSBE and not by mstatus.SBE that applies to the HS-mode.
+    /// Applications running in VU-mode use the byte order set by vsstatus.UBE.
+    pub fn of_trapped_access(hart_state: &HartState) -> Self {
+        let is_user_mode = (hart_state.mstatus >> Self::MSTATUS_MPP_SHIFT) & Self::MSTATUS_MPP_MASK == 0;
+        let is_big_endian = match is_user_mode {
+            true => hart_state.vsstatus & Self::VSSTATUS_UBE != 0,
+            false => hart_state.hstatus & Self::HSTATUS_VSBE != 0,
+        };
+        match is_big_endian {
+            true => Self::Big,
+            false => Self::Little,
+        }
+    }
+
+    /// Converts the value of a register stored by an access of the given width into the value the hypervisor expects.
+    pub const fn to_canonical(&self, value: usize, access_width: usize) -> usize {
+        match self {
+            Self::Little => value,
+            Self::Big => swap_bytes(value, access_width),
+        }
+    }
+
+    /// Converts the value loaded by the hypervisor into the value of the register as seen by the confidential hart.
+    /// Only the lowest `access_width` bytes come from the hypervisor, so it cannot set bits the load does not define.
+    pub const fn from_canonical(&self, value: usize, access_width: usize, is_signed: bool) -> usize {
+        match self {
+            Self::Little if is_signed => sign_extend(value, access_width),
+            Self::Little => zero_extend(value, access_width),
+            Self::Big if is_signed => sign_extend(swap_bytes(value, access_width), access_width),
+            Self::Big => swap_bytes(value, access_width),
+        }
+    }
+}
+
+/// Reverses the order of the lowest `access_width` bytes of the value. The remaining bytes are cleared.
+const fn swap_bytes(value: usize, access_width: usize) -> usize {
+    let shift = 8 * (core::mem::size_of::<usize>() - access_width);
+    (value << shift).swap_bytes()
+}
+
+const fn zero_extend(value: usize, access_width: usize) -> usize {
+    let shift = 8 * (core::mem::size_of::<usize>() - access_width);
+    (value << shift) >> shift
+}
+
+const fn sign_extend(value: usize, access_width: usize) -> usize {
+    let shift = 8 * (core::mem::size_of::<usize>() - access_width);
+    (((value << shift) as isize) >> shift) as usize
+}
+
+// Values forwarded to the hypervisor by stores in both byte orders.
+const _: () = assert!(Endianness::Little.to_canonical(0x1122_3344, 4) == 0x1122_3344);
+const _: () = assert!(Endianness::Big.to_canonical(0x1122_3344, 4) == 0x4433_2211);
+const _: () = assert!(Endianness::Big.to_canonical(0xaabb_1122, 2) == 0x2211);
+const _: () = assert!(Endianness::Big.to_canonical(0x0102_0304_0506_0708, 8) == 0x0807_0605_0403_0201);
+// Values returned to the confidential hart by loads in both byte orders.
+const _: () = assert!(Endianness::Little.from_canonical(0xffff_ffff_ffff_8000, 2, true) == 0xffff_ffff_ffff_8000);
+const _: () = assert!(Endianness::Big.from_canonical(0x0080, 2, true) == 0xffff_ffff_ffff_8000);
+const _: () = assert!(Endianness::Big.from_canonical(0x0080, 2, false) == 0x8000);
+// Bytes beyond the access width returned by the hypervisor are discarded.
+const _: () = assert!(Endianness::Little.from_canonical(0xaabb_ccdd_1122_3344, 1, false) == 0x44);
+const _: () = assert!(Endianness::Little.from_canonical(0x1234_0080, 1, true) == 0xffff_ffff_ffff_ff80);
+const _: () = assert!(Endianness::Little.from_canonical(0xaabb_ccdd_1122_3344, 8, false) == 0xaabb_ccdd_1122_3344);
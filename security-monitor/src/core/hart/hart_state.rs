@@ -32,6 +32,11 @@ pub struct HartState {
     pub hideleg: usize,
     pub htinst: usize,
     pub htval: usize,
+    pub hcounteren: usize,
+    pub hie: usize,
+    pub hip: usize,
+    pub hgeie: usize,
+    pub henvcfg: usize,
     // S-mode
     pub sstatus: usize,
     // hstatus needed to control the virtualization bit
@@ -87,6 +92,11 @@ impl HartState {
             hideleg: existing.hideleg,
             htinst: existing.htinst,
             htval: existing.htval,
+            hcounteren: existing.hcounteren,
+            hie: existing.hie,
+            hip: existing.hip,
+            hgeie: existing.hgeie,
+            henvcfg: existing.henvcfg,
             hvip: existing.hvip,
             hgatp: existing.hgatp,
             // VS-mode
@@ -114,6 +124,11 @@ impl HartState {
             hideleg: 0,
             htinst: 0,
             htval: 0,
+            hcounteren: 0,
+            hie: 0,
+            hip: 0,
+            hgeie: 0,
+            henvcfg: 0,
             sepc: 0,
             scounteren: 0,
             vsstatus: 0,
@@ -148,12 +163,21 @@ impl HartState {
 }
 
 impl HartState {
+    /// Returns the value of the register. The zero register (x0) always reads as zero, regardless of the value stored
+    /// in its slot of the register file.
     pub fn gpr(&self, register: GpRegister) -> usize {
-        self.gprs.get(register)
+        match register {
+            GpRegister::zero => 0,
+            _ => self.gprs.get(register),
+        }
     }
 
+    /// Writes the value to the register. Writes to the zero register (x0) are discarded, like in hardware, e.g., when
+    /// the confidential VM loads a value into x0 to only trigger the side effects of the load.
     pub fn set_gpr(&mut self, register: GpRegister, value: usize) {
-        self.gprs.set(register, value)
+        if register != GpRegister::zero {
+            self.gprs.set(register, value)
+        }
     }
 
     pub fn trap_reason(&self) -> TrapReason {
@@ -198,6 +222,11 @@ impl core::fmt::Debug for HartState {
         write!(f, "vstval: {:08x}, ", self.vstval)?;
         write!(f, "hvip: {:08x}, ", self.hvip)?;
         write!(f, "\n")?;
+        write!(f, "hie: {:08x}, ", self.hie)?;
+        write!(f, "hip: {:08x}, ", self.hip)?;
+        write!(f, "hgeie: {:08x}, ", self.hgeie)?;
+        write!(f, "henvcfg: {:08x}, ", self.henvcfg)?;
+        write!(f, "\n")?;
         write!(f, "vsatp: {:08x}, ", self.vsatp)?;
         write!(f, "fcsr: {:08x}, ", self.fcsr)?;
         write!(f, "mideleg: {:08x}, ", self.mideleg)?;
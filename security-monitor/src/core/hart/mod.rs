@@ -2,10 +2,14 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 pub use crate::core::control_data::HardwareHart;
+pub use endianness::Endianness;
 pub use fp_registers::FpRegisters;
 pub use gp_registers::{GpRegister, GpRegisters};
 pub use hart_state::HartState;
+pub use per_hart::{PerHart, MAX_NUMBER_OF_HARTS};
 
+mod endianness;
 mod fp_registers;
 mod gp_registers;
 mod hart_state;
+mod per_hart;
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::error::{Error, Result};
+use alloc::boxed::Box;
+
+/// Maximum number of physical harts supported by the security monitor.
+pub const MAX_NUMBER_OF_HARTS: usize = 64;
+
+/// Stores one value per physical hart. The values are allocated once, when the security monitor initializes, and never
+/// move afterwards, so the assembly code can keep their addresses, e.g., in the mscratch register. Accesses with a hart
+/// id outside the range of initialized harts return an error instead of reading unrelated memory.
+pub struct PerHart<T> {
+    entries: Box<[T]>,
+}
+
+impl<T> PerHart<T> {
+    pub fn new<F>(number_of_harts: usize, init: F) -> Result<Self>
+    where F: FnMut(usize) -> Result<T> {
+        assure!(number_of_harts <= MAX_NUMBER_OF_HARTS, Error::InvalidHartId())?;
+        let entries = (0..number_of_harts).map(init).collect::<Result<Box<[T]>>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, hart_id: usize) -> Result<&T> {
+        self.entries.get(hart_id).ok_or(Error::InvalidHartId())
+    }
+
+    pub fn get_mut(&mut self, hart_id: usize) -> Result<&mut T> {
+        self.entries.get_mut(hart_id).ok_or(Error::InvalidHartId())
+    }
+}
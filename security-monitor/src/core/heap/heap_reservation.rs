@@ -0,0 +1,91 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::build_config::parse_number;
+use crate::core::mmu::PageSize;
+use crate::error::{Error, Result};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+// part of the heap that can be reserved for confidential VMs. It is set when the heap is initialized.
+static RESERVABLE_HEAP: AtomicUsize = AtomicUsize::new(0);
+// sum of the sizes of all existing reservations.
+static RESERVED_HEAP: AtomicUsize = AtomicUsize::new(0);
+
+/// Part of the security monitor's heap reserved for the control data of a single confidential VM when it is created.
+/// The control data of all confidential VMs is allocated on the same heap, but it is charged to the reservation before
+/// it is allocated, so a confidential VM that exhausts its reservation fails its own request instead of starving other
+/// confidential VMs. The heap is partitioned only if the security monitor is built with the ACE_VM_HEAP_PAGES
+/// environment variable set to the number of 4KiB pages reserved for every confidential VM. Otherwise, reservations
+/// are unbounded, which the security monitor reports when it initializes the heap.
+///
+/// The page tables, which grow with the memory the confidential VM maps, the event log, and the set of pinned pages
+/// are charged to the reservation. The other control data is excluded because fixed limits bound it: the fault queue
+/// (`FaultQueue::CAPACITY`) and the shared page revocations, of which there is at most one per shared page.
+/// The copy of the device tree measured at the confidential VM creation (at most 1MiB) is released before the creation
+/// call returns.
+pub struct HeapReservation {
+    // None if the heap is not partitioned.
+    size: Option<usize>,
+    // amount of the reservation in bytes charged with the control data that has not been released yet.
+    used: usize,
+}
+
+impl HeapReservation {
+    const SIZE: Option<usize> = match option_env!("ACE_VM_HEAP_PAGES") {
+        Some(value) => Some(parse_number(value, 10) * PageSize::Size4KiB as usize),
+        None => None,
+    };
+
+    /// Reserves the part of the heap for a new confidential VM. Fails if the reservations together would exceed the
+    /// reservable part of the heap.
+    pub fn reserve() -> Result<Self> {
+        Self::reserve_size(Self::SIZE)
+    }
+
+    fn reserve_size(size: Option<usize>) -> Result<Self> {
+        if let Some(size) = size {
+            let reservable_heap = RESERVABLE_HEAP.load(Ordering::SeqCst);
+            RESERVED_HEAP
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |reserved_heap| {
+                    reserved_heap.checked_add(size).filter(|reserved_heap| *reserved_heap <= reservable_heap)
+                })
+                .map_err(|_| Error::OutOfHeap())?;
+        }
+        Ok(Self { size, used: 0 })
+    }
+
+    /// Accounts the given number of bytes of the control data to the reservation. Fails without changing the
+    /// reservation if the reservation is too small.
+    pub fn charge(&mut self, size: usize) -> Result<()> {
+        let used = self.used.checked_add(size).ok_or(Error::HeapReservationExhausted())?;
+        assure!(self.size.is_none_or(|limit| used <= limit), Error::HeapReservationExhausted())?;
+        self.used = used;
+        Ok(())
+    }
+
+    /// Returns the given number of bytes of the released control data to the reservation.
+    pub fn refund(&mut self, size: usize) {
+        self.used = self.used.saturating_sub(size);
+    }
+}
+
+impl Drop for HeapReservation {
+    fn drop(&mut self) {
+        if let Some(size) = self.size {
+            RESERVED_HEAP.fetch_sub(size, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Sets the part of the heap that confidential VMs can reserve. The rest is left for the security monitor's own data,
+/// like the memory tracker, and for buffers allocated only for the duration of a single call.
+pub(super) fn init(heap_size: usize) {
+    RESERVABLE_HEAP.store(heap_size / 2, Ordering::SeqCst);
+    match HeapReservation::SIZE {
+        Some(size) => debug!("Heap reservation of every confidential VM: {} bytes", size),
+        None => debug!("Heap reservations are unbounded, ACE_VM_HEAP_PAGES was not set at build time"),
+    }
+}
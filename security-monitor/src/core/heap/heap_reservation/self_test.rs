@@ -0,0 +1,27 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+/// Charges a bounded reservation up to its limit and checks that exceeding the limit changes nothing, that refunds
+/// make the reservation usable again, and that the reservation returns its part of the heap when dropped. Also
+/// checks that reservations exceeding the reservable heap are rejected and that build-time numbers are parsed.
+pub fn run() -> Result<()> {
+    let page_size = PageSize::Size4KiB.in_bytes();
+    let reserved_heap = RESERVED_HEAP.load(Ordering::SeqCst);
+    let mut reservation = HeapReservation::reserve_size(Some(2 * page_size))?;
+    let is_reserved = RESERVED_HEAP.load(Ordering::SeqCst) == reserved_heap + 2 * page_size;
+    reservation.charge(2 * page_size)?;
+    let is_exhausted =
+        matches!(reservation.charge(1), Err(Error::HeapReservationExhausted())) && reservation.used == 2 * page_size;
+    reservation.refund(page_size);
+    let is_refunded = reservation.charge(page_size).is_ok();
+    drop(reservation);
+    let is_returned = RESERVED_HEAP.load(Ordering::SeqCst) == reserved_heap;
+    let is_unbounded =
+        HeapReservation::reserve_size(None).and_then(|mut reservation| reservation.charge(usize::MAX)).is_ok();
+    let is_limited = matches!(HeapReservation::reserve_size(Some(usize::MAX)), Err(Error::OutOfHeap()));
+    assure!(is_reserved && is_exhausted && is_refunded && is_returned, Error::SelfTestFailed("heap reservation"))?;
+    let is_parsed = parse_number("4096", 10) == 4096 && parse_number("0x510000", 16) == 0x510000;
+    assure!(is_unbounded && is_limited && is_parsed, Error::SelfTestFailed("heap reservation limits"))
+}
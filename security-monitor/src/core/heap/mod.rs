@@ -2,8 +2,12 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use allocator::MemoryAllocator;
+#[cfg(feature = "init_self_test")]
+pub use heap_reservation::self_test as heap_reservation_self_test;
+pub use heap_reservation::HeapReservation;
 
 mod allocator;
+mod heap_reservation;
 
 // This object allocates memory on the security monitor's heap.
 #[global_allocator]
@@ -14,4 +18,5 @@ pub(super) fn init_heap(start_address: usize, heap_size: usize) {
     unsafe {
         HEAP_ALLOCATOR.lock().add_free_region(start_address, heap_size);
     }
+    heap_reservation::init(heap_size);
 }
@@ -1,12 +1,19 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::{ControlData, HardwareHart, CONTROL_DATA};
-use crate::core::memory_tracker::{MemoryTracker, Page, UnAllocated, CONFIDENTIAL_MEMORY_RANGE, MEMORY_TRACKER};
+use crate::core::attestation::{
+    Ed25519AttestationKey, SealingKey, Zeroizing, ATTESTATION_KEY, ENTROPY_SOURCE, SEALING_KEY,
+};
+use crate::core::control_data::{ControlData, HardwareHart, CONTROL_DATA, TIMEBASE_FREQUENCY};
+use crate::core::hart::PerHart;
+use crate::core::memory_tracker::{
+    MemoryTracker, Page, UnAllocated, CONFIDENTIAL_MEMORY_RANGE, MEMORY_TRACKER, MONITOR_IMAGE_RANGE,
+    NON_CONFIDENTIAL_MEMORY_RANGE,
+};
 use crate::core::mmu::PageSize;
-use crate::error::{Error, InitializationErrorType, NOT_INITIALIZED_HART, NOT_INITIALIZED_HARTS};
-use alloc::vec::Vec;
+use crate::error::{Error, InitializationErrorType, Result, NOT_INITIALIZED_HART, NOT_INITIALIZED_HARTS};
 use core::ffi::c_void;
+use core::ops::Range;
 use spin::{Mutex, Once, RwLock};
 
 extern "C" {
@@ -20,7 +27,10 @@ extern "C" {
 // structure.
 // Initialization procedure must guarantee that the mscratch register contains
 // the address of the memry region storing the corresponding hart state.
-static HARTS_STATES: Once<Mutex<Vec<HardwareHart>>> = Once::new();
+#[cfg(feature = "init_self_test")]
+mod self_test;
+
+static HARTS_STATES: Once<Mutex<PerHart<HardwareHart>>> = Once::new();
 
 /// This is the entry point to the security monitor. It is called
 /// by the OpenSBI during the boot process. After return, the control
@@ -41,13 +51,24 @@ extern "C" fn init_security_monitor(fdt: *const c_void) {
         }
     };
 
-    let (base_address, end_address) = match read_memory_region(fdt) {
+    let (non_confidential_memory_base_address, base_address, end_address) = match read_memory_region(fdt) {
         Ok(v) => v,
         Err(error) => {
             debug!("Failed while parsing FDT: {:?}", error);
             return;
         }
     };
+    NON_CONFIDENTIAL_MEMORY_RANGE.call_once(|| non_confidential_memory_base_address..base_address);
+    MONITOR_IMAGE_RANGE.call_once(read_monitor_image);
+
+    let timebase_frequency = match read_timebase_frequency(fdt) {
+        Ok(v) => v,
+        Err(error) => {
+            debug!("Failed while parsing FDT for the timebase frequency: {:?}", error);
+            return;
+        }
+    };
+    TIMEBASE_FREQUENCY.call_once(|| timebase_frequency);
 
     // Isolate confidential memory using PMP and IOPMP
     configure_pmps(base_address, end_address);
@@ -59,6 +80,11 @@ extern "C" fn init_security_monitor(fdt: *const c_void) {
         return;
     }
 
+    #[cfg(feature = "init_self_test")]
+    if let Err(error) = self_test::run() {
+        panic!("Security monitor self-test failed: {:?}", error);
+    }
+
     if let Err(error) = set_delegation() {
         debug!("Could not change the interrupt/exception delegation: {:?}", error);
         return;
@@ -66,9 +92,41 @@ extern "C" fn init_security_monitor(fdt: *const c_void) {
 
     // if we reached this line, then the security monitor has been correctly
     // initialized. This means that we can safely generate attestation keys
+    let has_entropy_source = read_entropy_source(fdt).unwrap_or_else(|error| {
+        debug!("Failed while parsing FDT for the entropy source: {:?}", error);
+        false
+    });
+    ENTROPY_SOURCE.call_once(|| has_entropy_source);
+
+    if let Err(error) = init_attestation_key() {
+        debug!("Could not generate the attestation key: {:?}", error);
+        return;
+    }
+
+    if let Err(error) = init_sealing_key(fdt) {
+        debug!("Could not create the sealing key: {:?}", error);
+        return;
+    }
+}
+
+fn init_attestation_key() -> Result<()> {
+    let attestation_key = Ed25519AttestationKey::generate()?;
+    ATTESTATION_KEY.call_once(|| attestation_key);
+    Ok(())
 }
 
-fn read_number_of_cpus(_fdt: *const c_void) -> Result<usize, Error> {
+fn init_sealing_key(fdt: *const c_void) -> Result<()> {
+    match read_sealing_secret(fdt)? {
+        Some(secret) => {
+            let sealing_key = SealingKey::new(secret)?;
+            SEALING_KEY.call_once(|| sealing_key);
+        }
+        None => debug!("The boot loader did not provide the sealing secret"),
+    }
+    Ok(())
+}
+
+fn read_number_of_cpus(_fdt: *const c_void) -> Result<usize> {
     debug!("Number of harts: {}", 0);
     // debug!("RISC-V ISA: {}", "");
     // debug!("MMU system: {}", "");
@@ -76,7 +134,73 @@ fn read_number_of_cpus(_fdt: *const c_void) -> Result<usize, Error> {
     Ok(8)
 }
 
-fn read_memory_region(fdt: *const c_void) -> Result<(usize, usize), Error> {
+/// Returns true if the device tree declares the Zkr extension in the ISA string or in the list of ISA extensions. Keys
+/// are generated on the boot hart, so it is enough that one hart implements Zkr on the homogeneous platforms supported
+/// by the security monitor.
+fn read_entropy_source(fdt: *const c_void) -> Result<bool> {
+    use fdt_rs::base::DevTree;
+    use fdt_rs::prelude::{FallibleIterator, PropReader};
+
+    // Safety: This unsafe is fine because we trust that the boot loader gave us a
+    // correct address of a flatten device tree.
+    let blob = unsafe { DevTree::from_raw_pointer(fdt as *const u8)? };
+    let has_zkr = blob.props().any(|p| {
+        let is_isa = matches!(p.name()?, "riscv,isa" | "riscv,isa-extensions");
+        Ok(is_isa && p.iter_str().any(|extensions| Ok(extensions.split('_').any(|extension| extension == "zkr")))?)
+    })?;
+    debug!("Zkr entropy source: {}", has_zkr);
+    Ok(has_zkr)
+}
+
+/// Returns the frequency of the `time` counter from the `timebase-frequency` property of the device tree. The property
+/// is defined in the `cpus` node, or in the nodes of individual harts, which run at the same frequency on the
+/// homogeneous platforms supported by the security monitor.
+fn read_timebase_frequency(fdt: *const c_void) -> Result<usize> {
+    use fdt_rs::base::DevTree;
+    use fdt_rs::prelude::{FallibleIterator, PropReader};
+
+    // Safety: This unsafe is fine because we trust that the boot loader gave us a
+    // correct address of a flatten device tree.
+    let blob = unsafe { DevTree::from_raw_pointer(fdt as *const u8)? };
+    let property = blob
+        .props()
+        .find(|p| Ok(p.name()? == "timebase-frequency"))?
+        .ok_or_else(|| Error::InitializationError(InitializationErrorType::FdtTimebaseFrequency))?;
+    let timebase_frequency = property.u32(0)? as usize;
+    assure_not!(timebase_frequency == 0, Error::InitializationError(InitializationErrorType::FdtTimebaseFrequency))?;
+    debug!("Timebase frequency: {}Hz", timebase_frequency);
+    Ok(timebase_frequency)
+}
+
+/// Returns the root secret of the sealing keys from the `ace,sealing-secret` property of the device tree. The boot
+/// loader derives this secret from a platform secret and the measurement of the security monitor, so it is the same on
+/// every boot of the same security monitor. The property is zeroed after it has been read, because the hypervisor later
+/// receives the same device tree. Returns None if the device tree does not contain the property.
+fn read_sealing_secret(fdt: *const c_void) -> Result<Option<Zeroizing<{ SealingKey::SECRET_SIZE }>>> {
+    use fdt_rs::base::DevTree;
+    use fdt_rs::prelude::{FallibleIterator, PropReader};
+
+    // Safety: This unsafe is fine because we trust that the boot loader gave us a
+    // correct address of a flatten device tree.
+    let blob = unsafe { DevTree::from_raw_pointer(fdt as *const u8)? };
+    let value = match blob.props().find(|p| Ok(p.name()? == "ace,sealing-secret"))? {
+        Some(property) => property.propbuf(),
+        None => return Ok(None),
+    };
+    let is_valid = value.len() == SealingKey::SECRET_SIZE;
+    assure!(is_valid, Error::InitializationError(InitializationErrorType::FdtSealingSecret))?;
+    let mut secret = Zeroizing::<{ SealingKey::SECRET_SIZE }>::zeroed();
+    secret.copy_from_slice(value);
+    let value_address = (fdt as *mut u8).wrapping_add(value.as_ptr() as usize - fdt as usize);
+    // Safety: the device tree is located in the writable non-confidential memory that the firmware also modifies, and
+    // the property value is within the device tree.
+    (0..SealingKey::SECRET_SIZE).for_each(|i| unsafe { value_address.add(i).write_volatile(0) });
+    Ok(Some(secret))
+}
+
+/// Returns the start address of the non-confidential memory and the boundaries of the confidential memory, which
+/// directly follows the non-confidential memory.
+fn read_memory_region(fdt: *const c_void) -> Result<(usize, usize, usize)> {
     use fdt_rs::base::DevTree;
     use fdt_rs::prelude::{FallibleIterator, PropReader};
 
@@ -101,6 +225,8 @@ fn read_memory_region(fdt: *const c_void) -> Result<(usize, usize), Error> {
 
     // assume here that the memory has been already split in two chunks during early
     // execution of the OpenSBI code
+    let non_confidential_memory_base_address: usize =
+        base.try_into().map_err(|_| Error::InitializationError(InitializationErrorType::FdtMemoryCasting))?;
     let confidential_memory_base_address: usize =
         (base + size).try_into().map_err(|_| Error::InitializationError(InitializationErrorType::FdtMemoryCasting))?;
     let confidential_memory_size: usize =
@@ -116,7 +242,22 @@ fn read_memory_region(fdt: *const c_void) -> Result<(usize, usize), Error> {
         return Err(Error::InitializationError(InitializationErrorType::InvalidMemoryBoundaries));
     }
 
-    Ok((confidential_memory_base_address, confidential_memory_end_address))
+    Ok((
+        non_confidential_memory_base_address,
+        confidential_memory_base_address,
+        confidential_memory_end_address,
+    ))
+}
+
+/// Returns the memory region of the firmware image linked with OpenSBI, which contains the security monitor.
+fn read_monitor_image() -> Range<usize> {
+    // Safety: OpenSBI calls the initialization with mscratch pointing to its scratch structure of this hart, which
+    // describes the firmware image. The security monitor stores the address of its hart state in mscratch only later.
+    let scratch = unsafe { &*(riscv::register::mscratch::read() as *const opensbi_sys::sbi_scratch) };
+    let start = scratch.fw_start as usize;
+    let end = start.saturating_add(scratch.fw_size as usize);
+    debug!("Security monitor image {:x}-{:x}", start, end);
+    start..end
 }
 
 fn configure_iopmps() {
@@ -126,7 +267,7 @@ fn configure_iopmps() {
 /// This function is called only once during the initialization of the security
 /// monitor during the boot process. This function initializes secure monitor's
 /// memory management like allocators.
-fn init_confidential_memory(mut start_address: usize, end_address: usize, number_of_harts: usize) -> Result<(), Error> {
+fn init_confidential_memory(mut start_address: usize, end_address: usize, number_of_harts: usize) -> Result<()> {
     // align to 4KiB.
     // TODO: to what page size should we align to???
     let mut start_address_aligned =
@@ -140,7 +281,9 @@ fn init_confidential_memory(mut start_address: usize, end_address: usize, number
     // calculate if we have enough memory in the system
     let memory_size = end_address - start_address;
     let available_pages = memory_size / PageSize::Size4KiB.in_bytes();
-    let minimum_memory_tracker_size = available_pages * core::mem::size_of::<Page<UnAllocated>>();
+    // the memory tracker stores a page and the id of the reservation that owns it for every 4KiB page.
+    let minimum_memory_tracker_size =
+        available_pages * (core::mem::size_of::<Page<UnAllocated>>() + core::mem::size_of::<usize>());
     let minimum_pages = 4 * 1024 + (minimum_memory_tracker_size / PageSize::Size4KiB.in_bytes());
     if available_pages < minimum_pages {
         return Err(Error::InitializationError(InitializationErrorType::NotEnoughMemory));
@@ -156,13 +299,12 @@ fn init_confidential_memory(mut start_address: usize, end_address: usize, number
     let tracker_memory_start = start_address + heap_size;
     let tracker_memory_size = PageSize::Size4KiB.in_bytes() * (available_pages - heap_pages);
     debug!("Memory tracker {:x}-{:x}", tracker_memory_start, tracker_memory_start + tracker_memory_size);
-    let memory_tracker = MemoryTracker::new(tracker_memory_start, tracker_memory_size)?;
+    let memory_tracker = MemoryTracker::new(&[tracker_memory_start..tracker_memory_start + tracker_memory_size])?;
     MEMORY_TRACKER.call_once(|| RwLock::new(memory_tracker));
     CONFIDENTIAL_MEMORY_RANGE.call_once(|| start_address..end_address);
 
     // we need to allocate stack for the dumped state of each physical HART.
-    let mut physical_harts_states = Vec::with_capacity(number_of_harts);
-    for hart_id in 0..number_of_harts {
+    let physical_harts_states = PerHart::new(number_of_harts, |hart_id| {
         let stack = MemoryTracker::acquire_continous_pages(1, PageSize::Size2MiB)?.remove(0);
         debug!(
             "Init area for HART[{}], stack {:x}-{:x}",
@@ -170,8 +312,8 @@ fn init_confidential_memory(mut start_address: usize, end_address: usize, number
             stack.address().usize(),
             stack.end_address().usize()
         );
-        physical_harts_states.insert(hart_id, HardwareHart::init(hart_id, stack));
-    }
+        Ok(HardwareHart::init(hart_id, stack))
+    })?;
     CONTROL_DATA.call_once(|| RwLock::new(ControlData::new()));
     HARTS_STATES.call_once(|| Mutex::new(physical_harts_states));
 
@@ -216,7 +358,7 @@ fn configure_pmps(confidential_memory_base_address: usize, confidential_memory_e
     crate::debug::__print_pmp_configuration();
 }
 
-fn set_delegation() -> Result<(), Error> {
+fn set_delegation() -> Result<()> {
     // let the hypervisor handle all traps except for two exceptions
     // that carry potentially SM-calls. These exceptions will be trapped in the
     // security monitor. The security monitor trap handler will delegate these
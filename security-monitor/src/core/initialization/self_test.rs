@@ -0,0 +1,107 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::{image_manifest_self_test, measurement_algorithm_self_test};
+use crate::core::control_data::{
+    confidential_hart_self_test, progress_monitor_self_test, sbi_policy_self_test, shared_page_revocations_self_test,
+    virtual_counters_self_test,
+};
+use crate::core::heap::heap_reservation_self_test;
+use crate::core::memory_tracker::memory_tracker_self_test;
+use crate::core::mmu::page_table_self_test;
+use crate::debug::console_write;
+use crate::error::{Error, Result};
+use sha2::{Digest, Sha256};
+
+/// Runs sanity checks of the security monitor on the platform it boots on. The offsets of registers used by the
+/// context switch are not checked here, because const assertions already break the build if they are wrong. The result
+/// of every check is written to the console, also in builds without the verbose feature.
+pub fn run() -> Result<()> {
+    console_write("#SecurityMonitor: Running the self-test\r\n");
+    let checks: [(&str, fn() -> Result<()>); 4] = [
+        ("page table", page_table_self_test::build_walk_teardown),
+        ("sha256", sha256),
+        ("instruction decoder", instruction_decoder),
+        ("fault injection", fault_injection),
+    ];
+    // the checks of individual modules are kept next to the code they check.
+    let module_checks: [(&str, fn() -> Result<()>); 14] = [
+        ("memory tracker", memory_tracker_self_test::run),
+        ("heap reservation", heap_reservation_self_test::run),
+        ("page table usage", page_table_self_test::run),
+        ("confidential hart", confidential_hart_self_test::run),
+        ("virtual counters", virtual_counters_self_test::run),
+        ("progress monitor", progress_monitor_self_test::run),
+        ("sbi policy", sbi_policy_self_test::run),
+        ("hypercall authorization", crate::confidential_flow::hypercall_self_test::run),
+        ("shared page revocations", shared_page_revocations_self_test::run),
+        ("console sink", crate::debug::self_test::run),
+        ("image manifest", image_manifest_self_test::run),
+        ("measurement algorithms", measurement_algorithm_self_test::run),
+        ("panic policy", crate::core::panic::self_test::run),
+        ("nested trap", crate::core::nested_trap::self_test::run),
+    ];
+    checks.iter().chain(module_checks.iter()).try_for_each(|(name, check)| {
+        let result = check();
+        let status = match result {
+            Ok(_) => "passed",
+            Err(_) => "failed",
+        };
+        console_write("#SecurityMonitor: Self-test ");
+        console_write(name);
+        console_write(": ");
+        console_write(status);
+        console_write("\r\n");
+        result
+    })
+}
+
+/// The test vectors from FIPS 180-2.
+const SHA256_VECTORS: [(&[u8], [u8; 32]); 2] = [
+    (
+        b"abc",
+        [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23, 0xb0, 0x03,
+            0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+        ],
+    ),
+    (
+        b"",
+        [
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9, 0x24, 0x27, 0xae,
+            0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+        ],
+    ),
+];
+
+/// Hashes the test vectors from FIPS 180-2.
+fn sha256() -> Result<()> {
+    check_sha256(&SHA256_VECTORS)
+}
+
+fn check_sha256(vectors: &[(&[u8], [u8; 32])]) -> Result<()> {
+    vectors.iter().try_for_each(|(input, digest)| {
+        assure!(Sha256::digest(input)[..] == digest[..], Error::SelfTestFailed("sha256 test vector"))
+    })
+}
+
+/// Flips a bit of an expected digest and checks that the self-test reports it, so a check that cannot fail does not
+/// go unnoticed.
+fn fault_injection() -> Result<()> {
+    let (input, mut digest) = SHA256_VECTORS[0];
+    digest[0] ^= 1;
+    assure!(check_sha256(&[(input, digest)]).is_err(), Error::SelfTestFailed("fault injection"))
+}
+
+/// Decodes the encodings of the load and store instructions the security monitor emulates for MMIO accesses.
+fn instruction_decoder() -> Result<()> {
+    use riscv_decode::Instruction::{Lw, Sd};
+    // lw a0, 0(a1) and sd a0, 8(a1)
+    let is_load = matches!(riscv_decode::decode(0x0005a503), Ok(Lw(i)) if i.rd() == 10 && i.rs1() == 11);
+    let is_store = matches!(riscv_decode::decode(0x00a5b423), Ok(Sd(s)) if s.rs1() == 11 && s.rs2() == 10);
+    // c.lw a0, 0(a1) is a compressed instruction
+    let lengths = riscv_decode::instruction_length(0x4188) == 2 && riscv_decode::instruction_length(0xa503) == 4;
+    // the all-zero encoding is defined to be illegal, so decoding it fails with the invalid instruction error.
+    let is_rejected = matches!(riscv_decode::decode(0).map_err(Error::from), Err(Error::InvalidRiscvInstruction(_)));
+    assure!(is_load && is_store && lengths && is_rejected, Error::SelfTestFailed("instruction decoder"))
+}
@@ -1,7 +1,8 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::error::Error;
+use crate::core::mmu::PageSize;
+use crate::error::{Error, Result};
 use core::ops::Range;
 use spin::Once;
 
@@ -9,6 +10,12 @@ use spin::Once;
 /// function) and never change later -- this is guaranteed by Once<>. They
 /// describe the memroy region containing the confidential memory and OpenSBI.
 pub static CONFIDENTIAL_MEMORY_RANGE: Once<Range<usize>> = Once::new();
+/// The memory described by the device tree that is not confidential. It is shared by the hypervisor, its VMs, and
+/// OpenSBI.
+pub static NON_CONFIDENTIAL_MEMORY_RANGE: Once<Range<usize>> = Once::new();
+/// The firmware image linked with OpenSBI, which contains the code, data, and stacks of the security monitor. It is
+/// located in the non-confidential memory, so addresses in this range are valid non-confidential memory addresses.
+pub static MONITOR_IMAGE_RANGE: Once<Range<usize>> = Once::new();
 
 #[repr(transparent)]
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -18,6 +25,14 @@ impl ConfidentialMemoryAddress {
     pub fn usize(&self) -> usize {
         self.0
     }
+
+    /// Returns the address moved by the given offset. Fails if the computation overflows or the resulting address is
+    /// outside the confidential memory.
+    pub fn checked_add(&self, offset: usize) -> Result<Self> {
+        let address = self.0.checked_add(offset).ok_or(Error::AddressOverflow())?;
+        assure!(confidential_memory_range().contains(&address), Error::MemoryAccessAuthorization())?;
+        Ok(Self(address))
+    }
 }
 
 #[repr(transparent)]
@@ -25,15 +40,40 @@ impl ConfidentialMemoryAddress {
 pub struct NonConfidentialMemoryAddress(usize);
 
 impl NonConfidentialMemoryAddress {
-    pub fn new(address: usize) -> Result<Self, Error> {
-        use crate::error::NOT_INITIALIZED_CONFIDENTIAL_MEMORY;
-        match CONFIDENTIAL_MEMORY_RANGE.get().expect(NOT_INITIALIZED_CONFIDENTIAL_MEMORY).contains(&address) {
+    pub fn new(address: usize) -> Result<Self> {
+        match confidential_memory_range().contains(&address) {
             true => Err(Error::MemoryAccessAuthorization()),
             false => Ok(Self(address)),
         }
     }
 
+    /// Returns the start address of a page located in the non-confidential memory. Fails if the end of the page does
+    /// not fit in the address space or if any byte of the page is in the confidential memory.
+    pub fn new_page(address: usize, page_size: PageSize) -> Result<Self> {
+        Self::new_region(address, page_size.in_bytes())
+    }
+
+    /// Returns the start address of a memory region of the given size located in the non-confidential memory.
+    pub fn new_region(address: usize, size: usize) -> Result<Self> {
+        let end_address = address.checked_add(size).ok_or(Error::AddressOverflow())?;
+        let confidential_memory = confidential_memory_range();
+        let overlaps = address < confidential_memory.end && confidential_memory.start < end_address;
+        assure_not!(overlaps, Error::MemoryAccessAuthorization())?;
+        Ok(Self(address))
+    }
+
+    /// Returns the address moved by the given offset. Fails if the computation overflows or the resulting address is
+    /// in the confidential memory.
+    pub fn checked_add(&self, offset: usize) -> Result<Self> {
+        Self::new(self.0.checked_add(offset).ok_or(Error::AddressOverflow())?)
+    }
+
     pub fn usize(&self) -> usize {
         self.0
     }
 }
+
+fn confidential_memory_range() -> &'static Range<usize> {
+    use crate::error::NOT_INITIALIZED_CONFIDENTIAL_MEMORY;
+    CONFIDENTIAL_MEMORY_RANGE.get().expect(NOT_INITIALIZED_CONFIDENTIAL_MEMORY)
+}
@@ -2,27 +2,387 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use super::page::{Page, UnAllocated};
-use crate::core::memory_tracker::ConfidentialMemoryAddress;
+use crate::core::bounded_lock::{self, MAX_LOCK_ATTEMPTS};
+use crate::core::heap::HeapReservation;
+use crate::core::memory_tracker::{
+    ConfidentialMemoryAddress, CONFIDENTIAL_MEMORY_RANGE, MONITOR_IMAGE_RANGE, NON_CONFIDENTIAL_MEMORY_RANGE,
+};
 use crate::core::mmu::PageSize;
-use crate::error::Error;
+use crate::error::{Error, InitializationErrorType, Result};
 use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::Range;
-use spin::{Once, RwLock, RwLockWriteGuard};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::{Once, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
 
 /// A static global structure containing unallocated pages. Once<> guarantees
 /// that it the memory tracker can only be initialized once.
 pub static MEMORY_TRACKER: Once<RwLock<MemoryTracker>> = Once::new();
 
+/// Number of released pages that the memory tracker rejected because they were outside the tracked memory or already
+/// free. Any value other than zero indicates a bug in the security monitor.
+static REJECTED_RELEASES: AtomicUsize = AtomicUsize::new(0);
+
+/// Start addresses of released pages in the order of their release, recorded only while a self-test observes releases.
+#[cfg(feature = "init_self_test")]
+static RELEASE_LOG: spin::Mutex<Option<Vec<usize>>> = spin::Mutex::new(None);
+
+/// Largest number of 4KiB pages that a confidential VM can reserve at its creation. The hypervisor approves the limit,
+/// so a confidential VM cannot exhaust the confidential memory with its reservation.
+static RESERVATION_LIMIT: AtomicUsize = AtomicUsize::new(MemoryTracker::DEFAULT_RESERVATION_LIMIT);
+
 pub struct MemoryTracker {
+    // disjoint regions of the confidential memory sorted by their start addresses.
+    regions: Vec<TrackedRegion>,
+    // amount of free memory in bytes promised to confidential VMs and not available to other allocations.
+    reserved_memory: usize,
+    // id assigned to the next reservation.
+    next_reservation_id: usize,
+    // amount of allocated memory in bytes acquired through every reservation that still holds allocated pages.
+    usage: BTreeMap<usize, usize>,
+}
+
+/// Consistent view of the confidential memory taken while holding the memory tracker's lock, so it never mixes values
+/// from before and after an allocation. All sizes are in 4KiB pages. Every confidential VM allocates through its own
+/// reservation, so the usage of a reservation is the confidential memory allocated for a single confidential VM.
+#[derive(Debug)]
+pub struct TrackerSnapshot {
+    pub total_pages: usize,
+    pub free_pages: usize,
+    // the largest number of physically contiguous free pages.
+    pub largest_free_run: usize,
+    pub reserved_pages: usize,
+    // pairs of the reservation id and the number of allocated pages acquired through the reservation.
+    pub usage: Vec<(usize, usize)>,
+}
+
+/// Class of a physical address, so that decisions about what may be read, written, or emulated at an address are made
+/// in one place. Unlike `mmu::MemoryClass`, it describes the host's physical address space, not the pages of a
+/// confidential VM.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PhysicalMemoryClass {
+    /// Memory managed by the memory tracker and allocated to confidential VMs or the security monitor.
+    Confidential,
+    /// Memory shared by the hypervisor, its VMs, and OpenSBI.
+    NonConfidential,
+    /// Memory of the security monitor: the confidential memory not managed by the memory tracker, like the security
+    /// monitor's heap, and the monitor's image, which is located in the non-confidential memory.
+    MonitorOwned,
+    /// Physical addresses outside the memory described by the device tree, which belong to devices.
+    Mmio,
+    /// Addresses beyond the physical address space or classified before the memory was initialized.
+    Unknown,
+}
+
+/// Amount of the confidential memory reserved for a single confidential VM when it is created. Allocations made through
+/// the reservation can use the reserved memory, so they do not fail when other confidential VMs exhaust the
+/// confidential memory. The reservation guarantees the amount of free memory, not its contiguity. Pages allocated
+/// through the reservation are returned to the memory tracker, not to the reservation, when released, except for pages
+/// released on the error path of an allocation. The unused part of the reservation is returned to the memory tracker
+/// when the reservation is dropped. The reservation also bounds the part of the security monitor's heap used for the
+/// confidential VM's control data.
+pub struct MemoryReservation {
+    id: usize,
+    remaining_memory: usize,
+    // memory acquired from the memory that is not reserved, because the remaining reservation was too small, and not
+    // released yet.
+    unreserved_memory: usize,
+    heap: HeapReservation,
+}
+
+/// Free pages of a single physically contiguous region of the confidential memory. Pages are never moved between
+/// regions, so a continuous allocation never spans two regions.
+struct TrackedRegion {
     map: BTreeMap<PageSize, Vec<Page<UnAllocated>>>,
+    // start addresses of free pages, used to detect pages released twice.
+    free_pages: BTreeMap<usize, PageSize>,
+    tracked_memory: Range<usize>,
+    // sum of the sizes of free pages in bytes.
+    free_memory: usize,
+    // ids of reservations through which the allocated pages were acquired, indexed by the 4KiB page number within the
+    // region. It is allocated with the region, so allocations and releases never allocate heap memory.
+    owners: Vec<usize>,
 }
 
 impl<'a> MemoryTracker {
-    pub fn new(base_address: usize, memory_size: usize) -> Result<Self, Error> {
+    /// The reservation limit in effect until the hypervisor approves another one: 4096 pages, i.e., 16MiB.
+    pub const DEFAULT_RESERVATION_LIMIT: usize = 4096;
+
+    /// Creates the memory tracker managing the given regions of the confidential memory. Fails if the regions overlap.
+    pub fn new(regions: &[Range<usize>]) -> Result<Self> {
+        let mut regions: Vec<_> = regions
+            .iter()
+            .filter(|region| !region.is_empty())
+            .map(|region| TrackedRegion::new(region.start, region.end - region.start))
+            .collect();
+        regions.sort_by_key(|region| region.tracked_memory.start);
+        let overlaps = regions.windows(2).any(|pair| pair[0].tracked_memory.end > pair[1].tracked_memory.start);
+        assure_not!(overlaps, Error::InitializationError(InitializationErrorType::InvalidMemoryBoundaries))?;
+        Ok(Self { regions, reserved_memory: 0, next_reservation_id: 0, usage: BTreeMap::new() })
+    }
+
+    /// Returns physically contiguous pages from the first region that has enough free memory. Fails if no single region
+    /// can satisfy the request, even if the regions together have enough free pages.
+    /// The memory reserved for confidential VMs is not available to this allocation.
+    pub fn acquire_continous_pages(number_of_pages: usize, page_size: PageSize) -> Result<Vec<Page<UnAllocated>>> {
+        Self::acquire_unreserved(number_of_pages, page_size, None)
+    }
+
+    /// Allocates from the memory that is not reserved and accounts the pages to the given reservation, if any.
+    fn acquire_unreserved(
+        number_of_pages: usize, page_size: PageSize, owner: Option<usize>,
+    ) -> Result<Vec<Page<UnAllocated>>> {
+        let size = number_of_pages.checked_mul(page_size.in_bytes()).ok_or(Error::OutOfMemory())?;
+        let pages = try_write(|tracker| {
+            assure!(tracker.unreserved_memory() >= size, Error::OutOfMemory())?;
+            Ok(tracker.acquire(number_of_pages, page_size, owner))
+        })?;
+        assure_not!(pages.is_empty(), Error::OutOfMemory())?;
+        Ok(pages)
+    }
+
+    /// Reserves the given number of 4KiB pages of the free memory. Fails if the reservation exceeds the limit approved
+    /// by the hypervisor or if the memory that is neither allocated nor reserved is smaller than the reservation.
+    pub fn reserve(number_of_pages: usize) -> Result<MemoryReservation> {
+        let limit = RESERVATION_LIMIT.load(Ordering::SeqCst);
+        assure!(number_of_pages <= limit, Error::ReservationLimitExceeded(number_of_pages, limit))?;
+        let size = number_of_pages.checked_mul(PageSize::Size4KiB.in_bytes()).ok_or(Error::OutOfMemory())?;
+        let heap = HeapReservation::reserve()?;
+        try_write(|tracker| {
+            assure!(tracker.unreserved_memory() >= size, Error::OutOfMemory())?;
+            tracker.reserved_memory += size;
+            let id = tracker.next_reservation_id;
+            tracker.next_reservation_id = tracker.next_reservation_id.wrapping_add(1);
+            Ok(MemoryReservation { id, remaining_memory: size, unreserved_memory: 0, heap })
+        })
+    }
+
+    /// Sets the largest number of 4KiB pages that a confidential VM created later can reserve.
+    pub fn set_reservation_limit(number_of_pages: usize) {
+        RESERVATION_LIMIT.store(number_of_pages, Ordering::SeqCst);
+    }
+
+    /// Returns the state of the confidential memory read in a single critical section. The cost is linear in the
+    /// number of free pages and reservations, so the snapshot can be taken periodically.
+    pub fn snapshot() -> Result<TrackerSnapshot> {
+        try_read(|tracker| Ok(tracker.take_snapshot()))
+    }
+
+    /// Classifies the physical address using the regions managed by the memory tracker and the memory boundaries read
+    /// from the device tree.
+    pub fn classify(physical_address: usize) -> PhysicalMemoryClass {
+        // RISC-V physical addresses have at most 56 bits.
+        const PHYSICAL_ADDRESS_SPACE_END: usize = 1 << 56;
+        let (confidential_memory, non_confidential_memory) =
+            match (CONFIDENTIAL_MEMORY_RANGE.get(), NON_CONFIDENTIAL_MEMORY_RANGE.get()) {
+                (Some(confidential_memory), Some(non_confidential_memory)) => {
+                    (confidential_memory, non_confidential_memory)
+                }
+                _ => return PhysicalMemoryClass::Unknown,
+            };
+        // the image of the security monitor is located in the non-confidential memory, so it is checked first.
+        if MONITOR_IMAGE_RANGE.get().is_some_and(|image| image.contains(&physical_address)) {
+            return PhysicalMemoryClass::MonitorOwned;
+        }
+        if non_confidential_memory.contains(&physical_address) {
+            return PhysicalMemoryClass::NonConfidential;
+        }
+        if !confidential_memory.contains(&physical_address) {
+            return match physical_address < PHYSICAL_ADDRESS_SPACE_END {
+                true => PhysicalMemoryClass::Mmio,
+                false => PhysicalMemoryClass::Unknown,
+            };
+        }
+        let is_tracked = try_read(|tracker| {
+            Ok(tracker.regions.iter().any(|region| region.tracked_memory.contains(&physical_address)))
+        });
+        match is_tracked {
+            Ok(true) => PhysicalMemoryClass::Confidential,
+            Ok(false) => PhysicalMemoryClass::MonitorOwned,
+            Err(_) => PhysicalMemoryClass::Unknown,
+        }
+    }
+
+    /// Returns true if any byte of the memory region belongs to the security monitor. The image of the security monitor
+    /// is contiguous, so the region overlaps it if the region starts inside the image or the image starts inside the
+    /// region.
+    pub fn is_monitor_owned(address: usize, size: usize) -> bool {
+        let starts_before_image =
+            MONITOR_IMAGE_RANGE.get().is_some_and(|image| address < image.start && image.start - address < size);
+        starts_before_image || Self::classify(address) == PhysicalMemoryClass::MonitorOwned
+    }
+
+    /// Fails unless the memory region belongs to the hypervisor, so the security monitor never writes data requested by
+    /// the hypervisor into its own image, which is located in the non-confidential memory.
+    pub fn assure_hypervisor_memory(address: usize, size: usize) -> Result<()> {
+        let is_non_confidential = Self::classify(address) == PhysicalMemoryClass::NonConfidential;
+        assure!(is_non_confidential && !Self::is_monitor_owned(address, size), Error::BufferInMonitorMemory())
+    }
+
+    /// Returns the pages to the memory tracker. Pages outside the tracked memory or already free are not returned,
+    /// because they would corrupt the free lists. They are counted instead, so that the bug that released them is
+    /// observable.
+    pub fn release_pages(pages: Vec<Page<UnAllocated>>) {
+        let _ = try_write(|tracker| Ok(tracker.release_all(pages)))
+            .inspect_err(|_| debug!("Memory leak: failed to store released pages in the memory tracker"));
+    }
+
+    pub fn release_page(page: Page<UnAllocated>) {
+        Self::release_pages(vec![page])
+    }
+
+    /// Returns the pages to the regions they were allocated from and counts the rejected ones. Returns the amount of
+    /// the released memory in bytes.
+    fn release_all(&mut self, pages: Vec<Page<UnAllocated>>) -> usize {
+        pages
+            .into_iter()
+            .map(|page| {
+                let size = page.size().in_bytes();
+                #[cfg(feature = "init_self_test")]
+                if let Some(release_log) = RELEASE_LOG.lock().as_mut() {
+                    release_log.push(page.address().usize());
+                }
+                match self.release(page) {
+                    Ok(()) => size,
+                    Err(error) => {
+                        let rejected_releases = REJECTED_RELEASES.fetch_add(1, Ordering::SeqCst) + 1;
+                        debug!("Memory tracker rejected a released page: {:?}, rejected {}", error, rejected_releases);
+                        0
+                    }
+                }
+            })
+            .sum()
+    }
+
+    /// Returns the page to the region it was allocated from. Fails if the page is outside the tracked regions.
+    fn release(&mut self, page: Page<UnAllocated>) -> Result<()> {
+        let start = page.address().usize();
+        let end = page.end_address().usize();
+        let size = page.size().in_bytes();
+        let owner = self
+            .regions
+            .iter_mut()
+            .find(|region| region.tracked_memory.start <= start && end <= region.tracked_memory.end)
+            .ok_or(Error::InvalidPageRelease())?
+            .release(page)?;
+        if let Some(owner) = owner {
+            let usage = self.usage.get(&owner).map_or(0, |usage| usage.saturating_sub(size));
+            match usage {
+                0 => self.usage.remove(&owner),
+                _ => self.usage.insert(owner, usage),
+            };
+        }
+        Ok(())
+    }
+
+    fn acquire(&mut self, number_of_pages: usize, page_size: PageSize, owner: Option<usize>) -> Vec<Page<UnAllocated>> {
+        let pages = self
+            .regions
+            .iter_mut()
+            .map(|region| region.acquire(number_of_pages, page_size, owner))
+            .find(|pages| !pages.is_empty())
+            .unwrap_or(vec![]);
+        if let Some(owner) = owner.filter(|_| !pages.is_empty()) {
+            *self.usage.entry(owner).or_insert(0) += pages.len() * page_size.in_bytes();
+        }
+        pages
+    }
+
+    fn take_snapshot(&self) -> TrackerSnapshot {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        let total_memory: usize = self.regions.iter().map(|region| region.tracked_memory.len()).sum();
+        let free_memory: usize = self.regions.iter().map(|region| region.free_memory).sum();
+        let largest_free_run = self.regions.iter().map(|region| region.largest_free_run()).max().unwrap_or(0);
+        TrackerSnapshot {
+            total_pages: total_memory / page_size,
+            free_pages: free_memory / page_size,
+            largest_free_run: largest_free_run / page_size,
+            reserved_pages: self.reserved_memory / page_size,
+            usage: self.usage.iter().map(|(id, memory)| (*id, memory / page_size)).collect(),
+        }
+    }
+
+    fn unreserved_memory(&self) -> usize {
+        let free_memory: usize = self.regions.iter().map(|region| region.free_memory).sum();
+        free_memory.saturating_sub(self.reserved_memory)
+    }
+}
+
+impl MemoryReservation {
+    /// Returns physically contiguous pages allocated from the reserved memory. When the remaining reservation is too
+    /// small, the pages are allocated from the memory that is not reserved.
+    pub fn acquire_continous_pages(
+        &mut self, number_of_pages: usize, page_size: PageSize,
+    ) -> Result<Vec<Page<UnAllocated>>> {
+        let size = number_of_pages.checked_mul(page_size.in_bytes()).ok_or(Error::OutOfMemory())?;
+        if size > self.remaining_memory {
+            let pages = MemoryTracker::acquire_unreserved(number_of_pages, page_size, Some(self.id))?;
+            self.unreserved_memory += size;
+            return Ok(pages);
+        }
+        let pages = try_write(|tracker| {
+            let pages = tracker.acquire(number_of_pages, page_size, Some(self.id));
+            if !pages.is_empty() {
+                tracker.reserved_memory -= size;
+            }
+            Ok(pages)
+        })?;
+        assure_not!(pages.is_empty(), Error::OutOfMemory())?;
+        self.remaining_memory -= size;
+        Ok(pages)
+    }
+
+    /// Returns the pages to the memory tracker and their memory to the reservation, so that an allocation undone on an
+    /// error path does not consume the reservation. Memory acquired beyond the reservation is released first and
+    /// returned to the memory that is not reserved, so the reservation never grows beyond the memory it was created
+    /// with.
+    pub fn release_pages(&mut self, pages: Vec<Page<UnAllocated>>) {
+        let _ = try_write(|tracker| {
+            let released_memory = tracker.release_all(pages);
+            let unreserved_memory = core::cmp::min(released_memory, self.unreserved_memory);
+            self.unreserved_memory -= unreserved_memory;
+            tracker.reserved_memory += released_memory - unreserved_memory;
+            self.remaining_memory += released_memory - unreserved_memory;
+            Ok(())
+        })
+        .inspect_err(|_| debug!("Memory leak: failed to store released pages in the memory tracker"));
+    }
+
+    /// Accounts the given number of bytes of the confidential VM's control data to the heap reservation.
+    pub fn charge_heap(&mut self, size: usize) -> Result<()> {
+        self.heap.charge(size)
+    }
+
+    /// Returns the given number of bytes of the released control data to the heap reservation.
+    pub fn refund_heap(&mut self, size: usize) {
+        self.heap.refund(size)
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        if self.remaining_memory > 0 {
+            let remaining_memory = self.remaining_memory;
+            let _ = try_write(|tracker| {
+                tracker.reserved_memory -= remaining_memory;
+                Ok(())
+            })
+            .inspect_err(|_| debug!("Memory leak: failed to return the unused reservation to the memory tracker"));
+        }
+    }
+}
+
+impl TrackedRegion {
+    const NO_OWNER: usize = usize::MAX;
+
+    fn new(base_address: usize, memory_size: usize) -> Self {
         // TODO: ensure base_address is aligned
         let mut map = BTreeMap::new();
+        let mut free_pages = BTreeMap::new();
         let mut address = base_address;
 
         for page_size in &[PageSize::Size1GiB, PageSize::Size2MiB, PageSize::Size4KiB] {
@@ -31,6 +391,7 @@ impl<'a> MemoryTracker {
             let new_pages = (0..number_of_new_pages)
                 .map(|i| {
                     let start_address = ConfidentialMemoryAddress(address + i * page_size.in_bytes());
+                    free_pages.insert(start_address.usize(), page_size.clone());
                     Page::<UnAllocated>::init(start_address, page_size.clone())
                 })
                 .collect();
@@ -38,34 +399,61 @@ impl<'a> MemoryTracker {
             map.insert(page_size.clone(), new_pages);
         }
 
-        Ok(Self { map })
+        let owners = vec![Self::NO_OWNER; (address - base_address) / PageSize::Size4KiB.in_bytes()];
+        Self { map, free_pages, tracked_memory: base_address..address, free_memory: address - base_address, owners }
     }
 
-    pub fn acquire_continous_pages(
-        number_of_pages: usize, page_size: PageSize,
-    ) -> Result<Vec<Page<UnAllocated>>, Error> {
-        let pages = try_write(|tracker| Ok(tracker.acquire(number_of_pages, page_size)))?;
-        assure_not!(pages.is_empty(), Error::OutOfMemory())?;
-        Ok(pages)
+    /// Returns the page to the free list of its size and the id of the reservation through which it was acquired, if
+    /// any. Fails if the page overlaps with a free page.
+    fn release(&mut self, page: Page<UnAllocated>) -> Result<Option<usize>> {
+        let start = page.address().usize();
+        let end = page.end_address().usize();
+        let overlaps_preceding = self
+            .free_pages
+            .range(..=start)
+            .next_back()
+            .is_some_and(|(address, page_size)| address + page_size.in_bytes() > start);
+        let overlaps_following = self.free_pages.range(start..end).next().is_some();
+        assure_not!(overlaps_preceding || overlaps_following, Error::InvalidPageRelease())?;
+        let pages = self.map.get_mut(&page.size()).ok_or(Error::InvalidPageRelease())?;
+        self.free_pages.insert(start, page.size().clone());
+        self.free_memory += page.size().in_bytes();
+        pages.push(page);
+        let owner_index = self.owner_index(start);
+        let owner = core::mem::replace(&mut self.owners[owner_index], Self::NO_OWNER);
+        Ok(Some(owner).filter(|owner| *owner != Self::NO_OWNER))
     }
 
-    pub fn release_pages(pages: Vec<Page<UnAllocated>>) {
-        let _ = try_write(|tracker| {
-            Ok(pages.into_iter().for_each(|page| {
-                tracker.map.get_mut(&page.size()).and_then(|v| Some(v.push(page)));
-            }))
-        })
-        .inspect_err(|_| debug!("Memory leak: failed to store released pages in the memory tracker"));
+    fn acquire(&mut self, number_of_pages: usize, page_size: PageSize, owner: Option<usize>) -> Vec<Page<UnAllocated>> {
+        let pages: Vec<Page<UnAllocated>> = self
+            .find_allocation(number_of_pages, page_size)
+            .and_then(|range| self.map.get_mut(&page_size).and_then(|pages| Some(pages.drain(range).collect())))
+            .unwrap_or(vec![]);
+        pages.iter().for_each(|page| {
+            self.free_pages.remove(&page.address().usize());
+            self.free_memory -= page.size().in_bytes();
+            let owner_index = self.owner_index(page.address().usize());
+            self.owners[owner_index] = owner.unwrap_or(Self::NO_OWNER);
+        });
+        pages
     }
 
-    pub fn release_page(page: Page<UnAllocated>) {
-        Self::release_pages(vec![page])
+    fn owner_index(&self, address: usize) -> usize {
+        (address - self.tracked_memory.start) / PageSize::Size4KiB.in_bytes()
     }
 
-    fn acquire(&mut self, number_of_pages: usize, page_size: PageSize) -> Vec<Page<UnAllocated>> {
-        self.find_allocation(number_of_pages, page_size)
-            .and_then(|range| self.map.get_mut(&page_size).and_then(|pages| Some(pages.drain(range).collect())))
-            .unwrap_or(vec![])
+    /// Returns the size in bytes of the largest physically contiguous range of free pages of any size.
+    fn largest_free_run(&self) -> usize {
+        let mut largest_run = 0;
+        let mut current_run = 0..0;
+        self.free_pages.iter().for_each(|(address, page_size)| {
+            if *address != current_run.end {
+                current_run = *address..*address;
+            }
+            current_run.end += page_size.in_bytes();
+            largest_run = largest_run.max(current_run.len());
+        });
+        largest_run
     }
 
     // this function will divide larger pages when it failes to find allocation within free pages of the requested size.
@@ -98,7 +486,11 @@ impl<'a> MemoryTracker {
         if let Some(to) = from.smaller() {
             if let Some(page) = self.map.get_mut(&from).and_then(|pages| pages.pop()) {
                 if let Some(ref mut pages) = self.map.get_mut(&to) {
-                    pages.append(&mut page.divide());
+                    let mut smaller_pages = page.divide();
+                    smaller_pages.iter().for_each(|smaller_page| {
+                        self.free_pages.insert(smaller_page.address().usize(), to);
+                    });
+                    pages.append(&mut smaller_pages);
                     return true;
                 }
             }
@@ -130,13 +522,16 @@ impl<'a> MemoryTracker {
     }
 }
 
-fn try_write<F, O>(op: O) -> Result<F, Error>
-where O: FnOnce(&mut RwLockWriteGuard<'static, MemoryTracker>) -> Result<F, Error> {
+fn try_read<F, O>(op: O) -> Result<F>
+where O: FnOnce(&RwLockReadGuard<'static, MemoryTracker>) -> Result<F> {
+    use crate::error::NOT_INITIALIZED_MEMORY_TRACKER;
+    let lock = MEMORY_TRACKER.get().expect(NOT_INITIALIZED_MEMORY_TRACKER);
+    bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || lock.try_read()).and_then(|ref memory_tracker| op(memory_tracker))
+}
+
+fn try_write<F, O>(op: O) -> Result<F>
+where O: FnOnce(&mut RwLockWriteGuard<'static, MemoryTracker>) -> Result<F> {
     use crate::error::NOT_INITIALIZED_MEMORY_TRACKER;
-    MEMORY_TRACKER
-        .get()
-        .expect(NOT_INITIALIZED_MEMORY_TRACKER)
-        .try_write()
-        .ok_or(Error::OptimisticLocking())
-        .and_then(|ref mut memory_tracker| op(memory_tracker))
+    let lock = MEMORY_TRACKER.get().expect(NOT_INITIALIZED_MEMORY_TRACKER);
+    bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || lock.try_write()).and_then(|ref mut memory_tracker| op(memory_tracker))
 }
@@ -0,0 +1,68 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+
+/// Executes the operation and returns the start addresses of all pages it released, including the rejected ones,
+/// in the order of their release.
+pub fn record_releases<O: FnOnce()>(op: O) -> Vec<usize> {
+    *RELEASE_LOG.lock() = Some(Vec::new());
+    op();
+    RELEASE_LOG.lock().take().unwrap_or_default()
+}
+
+/// Allocates pages through a reservation from a tracker of memory that is never accessed and checks that the
+/// snapshot accounts them to the reservation until they are released. Then classifies an address of every class of
+/// the physical address space of this platform.
+pub fn run() -> Result<()> {
+    const OWNER: usize = 7;
+    let mut tracker = MemoryTracker::new(&[0x1000_0000..0x1001_0000])?;
+    let total_pages = tracker.take_snapshot().total_pages;
+    let pages = tracker.acquire(3, PageSize::Size4KiB, Some(OWNER));
+    let snapshot = tracker.take_snapshot();
+    let is_accounted = total_pages == 16
+        && snapshot.free_pages == total_pages - pages.len()
+        && snapshot.largest_free_run == total_pages - pages.len()
+        && snapshot.usage == [(OWNER, pages.len())];
+    assure!(is_accounted, Error::SelfTestFailed("memory tracker snapshot"))?;
+    tracker.release_all(pages);
+    let snapshot = tracker.take_snapshot();
+    let is_released = snapshot.free_pages == total_pages && snapshot.usage.is_empty();
+    assure!(is_released, Error::SelfTestFailed("memory tracker release"))?;
+
+    // pages acquired beyond the reservation are released to the memory that is not reserved, so undoing an
+    // allocation never increases the reservation.
+    let page_size = PageSize::Size4KiB.in_bytes();
+    let reserved_memory = try_read(|tracker| Ok(tracker.reserved_memory))?;
+    let mut reservation = MemoryTracker::reserve(1)?;
+    let mut pages = reservation.acquire_continous_pages(1, PageSize::Size4KiB)?;
+    pages.append(&mut reservation.acquire_continous_pages(1, PageSize::Size4KiB)?);
+    let is_exhausted = reservation.remaining_memory == 0 && reservation.unreserved_memory == page_size;
+    reservation.release_pages(pages);
+    let is_restored = reservation.remaining_memory == page_size
+        && reservation.unreserved_memory == 0
+        && try_read(|tracker| Ok(tracker.reserved_memory))? == reserved_memory + page_size;
+    drop(reservation);
+    let is_returned = try_read(|tracker| Ok(tracker.reserved_memory))? == reserved_memory;
+    assure!(is_exhausted && is_restored && is_returned, Error::SelfTestFailed("memory reservation release"))?;
+
+    // the heap of the security monitor is at the start of the confidential memory, the memory tracker manages the
+    // rest of it.
+    let confidential_memory = CONFIDENTIAL_MEMORY_RANGE.get().ok_or(Error::SelfTestFailed("memory classes"))?;
+    let non_confidential_memory = NON_CONFIDENTIAL_MEMORY_RANGE.get().ok_or(Error::SelfTestFailed("memory classes"))?;
+    let image = MONITOR_IMAGE_RANGE.get().ok_or(Error::SelfTestFailed("memory classes"))?;
+    let tracked_address = try_read(|tracker| Ok(tracker.regions[0].tracked_memory.start))?;
+    let hypervisor_address =
+        if image.contains(&non_confidential_memory.start) { image.end } else { non_confidential_memory.start };
+    let is_classified = MemoryTracker::classify(tracked_address) == PhysicalMemoryClass::Confidential
+        && MemoryTracker::classify(confidential_memory.start) == PhysicalMemoryClass::MonitorOwned
+        && MemoryTracker::classify(image.start) == PhysicalMemoryClass::MonitorOwned
+        && MemoryTracker::classify(hypervisor_address) == PhysicalMemoryClass::NonConfidential
+        && MemoryTracker::classify(confidential_memory.end.max(non_confidential_memory.end))
+            == PhysicalMemoryClass::Mmio
+        && MemoryTracker::classify(usize::MAX) == PhysicalMemoryClass::Unknown;
+    let is_buffer_checked = MemoryTracker::assure_hypervisor_memory(hypervisor_address, 8).is_ok()
+        && MemoryTracker::assure_hypervisor_memory(image.start, 8).is_err()
+        && MemoryTracker::assure_hypervisor_memory(tracked_address, 8).is_err();
+    assure!(is_classified && is_buffer_checked, Error::SelfTestFailed("memory classes"))
+}
@@ -1,12 +1,19 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-pub use memory_address::{ConfidentialMemoryAddress, NonConfidentialMemoryAddress, CONFIDENTIAL_MEMORY_RANGE};
-pub use memory_tracker::{MemoryTracker, MEMORY_TRACKER};
+pub use memory_address::{
+    ConfidentialMemoryAddress, NonConfidentialMemoryAddress, CONFIDENTIAL_MEMORY_RANGE, MONITOR_IMAGE_RANGE,
+    NON_CONFIDENTIAL_MEMORY_RANGE,
+};
+#[cfg(feature = "init_self_test")]
+pub use memory_tracker::self_test as memory_tracker_self_test;
+pub use memory_tracker::{MemoryReservation, MemoryTracker, PhysicalMemoryClass, TrackerSnapshot, MEMORY_TRACKER};
 pub use page::{Allocated, Page, PageState, UnAllocated};
+pub use scrub::scrub_page;
 pub use shared_page::SharedPage;
 
 mod memory_address;
 mod memory_tracker;
 mod page;
+mod scrub;
 mod shared_page;
@@ -1,9 +1,9 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::memory_tracker::{ConfidentialMemoryAddress, NonConfidentialMemoryAddress};
+use crate::core::memory_tracker::{scrub_page, ConfidentialMemoryAddress, NonConfidentialMemoryAddress};
 use crate::core::mmu::PageSize;
-use crate::error::Error;
+use crate::error::Result;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
 use core::ops::Range;
@@ -37,9 +37,7 @@ impl Page<UnAllocated> {
 
     /// Moves a page to the Allocated state after filling its content with the
     /// content of a page located in the non-confidential memory.
-    pub fn copy_from_non_confidential_memory(
-        self, address: NonConfidentialMemoryAddress,
-    ) -> Result<Page<Allocated>, Error> {
+    pub fn copy_from_non_confidential_memory(self, address: NonConfidentialMemoryAddress) -> Result<Page<Allocated>> {
         // The below copy is secure because we checked that any address in the
         // address range belongs to the confidential memory (no overlapping).
         self.offsets().for_each(|offset| {
@@ -80,6 +78,10 @@ impl<T: PageState> Page<T> {
         self.address
     }
 
+    pub fn address_at_offset(&self, offset: usize) -> Option<ConfidentialMemoryAddress> {
+        (offset < self.size.in_bytes()).then(|| ConfidentialMemoryAddress(self.address.usize() + offset))
+    }
+
     pub fn size(&self) -> &PageSize {
         &self.size
     }
@@ -103,7 +105,6 @@ impl<T: PageState> Page<T> {
     }
 
     fn clear(&self) {
-        // TODO: performance optimisation. Write word/double word instead of a byte
-        self.offsets().for_each(|offset| self.write::<u8>(offset, 0));
+        scrub_page(self.address, self.size);
     }
 }
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::memory_tracker::ConfidentialMemoryAddress;
+use crate::core::mmu::PageSize;
+
+// size of the cache block zeroed by a single cbo.zero instruction. It must match the riscv,cboz-block-size property
+// of the platform's device tree.
+#[cfg(feature = "zicboz")]
+const CACHE_BLOCK_SIZE: usize = 64;
+
+/// Writes zeros to the entire page starting at the given address. The address must be aligned to the page size. The
+/// writes are volatile, so the compiler never removes them even though the page is not read afterwards. With the
+/// `zicboz` feature, the page is cleared one cache block at a time with the cbo.zero instruction.
+pub fn scrub_page(address: ConfidentialMemoryAddress, page_size: PageSize) {
+    let page = address.usize()..address.usize() + page_size.in_bytes();
+    #[cfg(feature = "zicboz")]
+    page.step_by(CACHE_BLOCK_SIZE).for_each(|block_address| {
+        // Safety: the cache block is in the page owned by the caller. cbo.zero is encoded with .insn, so the assembler
+        // does not have to support the Zicboz extension.
+        unsafe { core::arch::asm!(".insn i 0x0f, 0x2, x0, {0}, 0x4", in(reg) block_address) };
+    });
+    #[cfg(not(feature = "zicboz"))]
+    page.step_by(core::mem::size_of::<usize>()).for_each(|word_address| {
+        // Safety: the word is in the page owned by the caller and aligned because the page is aligned.
+        unsafe { (word_address as *mut usize).write_volatile(0) };
+    });
+}
+
+// Pages of every size consist of whole cache blocks.
+#[cfg(feature = "zicboz")]
+const _: () = assert!(PageSize::Size4KiB as usize % CACHE_BLOCK_SIZE == 0);
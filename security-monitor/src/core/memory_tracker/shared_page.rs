@@ -4,26 +4,26 @@
 use crate::core::memory_tracker::NonConfidentialMemoryAddress;
 use crate::core::mmu::PageSize;
 use crate::core::transformations::{ConfidentialVmVirtualAddress, SharePageRequest};
-use crate::error::Error;
+use crate::error::Result;
 
 #[derive(Debug)]
 pub struct SharedPage {
     hypervisor_address: NonConfidentialMemoryAddress,
     confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
     page_size: PageSize,
+    is_read_only: bool,
 }
 
 impl SharedPage {
-    pub fn new(hypervisor_address: usize, request: SharePageRequest) -> Result<Self, Error> {
+    /// Creates the page that the hypervisor exported at the given address. The hypervisor decides whether the
+    /// confidential VM can write to it, so a page the hypervisor exported read-only is never mapped writable.
+    pub fn new(hypervisor_address: usize, is_read_only: bool, request: SharePageRequest) -> Result<Self> {
         let page_size = request.page_size();
-        let hypervisor_end_address = hypervisor_address + page_size.in_bytes() - 1;
-
-        let hypervisor_address = NonConfidentialMemoryAddress::new(hypervisor_address)?;
-        NonConfidentialMemoryAddress::new(hypervisor_end_address)?;
+        let hypervisor_address = NonConfidentialMemoryAddress::new_page(hypervisor_address, page_size)?;
 
         let confidential_vm_virtual_address = request.confidential_vm_virtual_address();
 
-        Ok(Self { hypervisor_address, confidential_vm_virtual_address, page_size })
+        Ok(Self { hypervisor_address, confidential_vm_virtual_address, page_size, is_read_only })
     }
 
     pub fn hypervisor_address(&self) -> NonConfidentialMemoryAddress {
@@ -37,4 +37,8 @@ impl SharedPage {
     pub fn page_size(&self) -> PageSize {
         self.page_size
     }
+
+    pub fn is_read_only(&self) -> bool {
+        self.is_read_only
+    }
 }
@@ -0,0 +1,88 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::mmu::PageSize;
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
+
+/// Translation mode of the confidential VM's first-stage page table configured in the vsatp register.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum FirstStageMode {
+    Bare,
+    Sv39,
+    Sv48,
+    Sv57,
+}
+
+impl FirstStageMode {
+    const VSATP_MODE_SHIFT: usize = 60;
+
+    fn from_vsatp(vsatp: usize) -> Result<Self> {
+        match vsatp >> Self::VSATP_MODE_SHIFT {
+            0 => Ok(Self::Bare),
+            8 => Ok(Self::Sv39),
+            9 => Ok(Self::Sv48),
+            10 => Ok(Self::Sv57),
+            _ => Err(Error::GuestPageFault(vsatp)),
+        }
+    }
+
+    fn levels(&self) -> usize {
+        match self {
+            Self::Bare => 0,
+            Self::Sv39 => 3,
+            Self::Sv48 => 4,
+            Self::Sv57 => 5,
+        }
+    }
+}
+
+const VPN_BITS: usize = 9;
+const PAGE_OFFSET_BITS: usize = 12;
+const VSATP_PPN_MASK: usize = (1 << 44) - 1;
+const PTE_VALID: usize = 1 << 0;
+const PTE_READ: usize = 1 << 1;
+const PTE_WRITE: usize = 1 << 2;
+const PTE_EXECUTE: usize = 1 << 3;
+const PTE_PPN_SHIFT: usize = 10;
+const PTE_PPN_MASK: usize = (1 << 44) - 1;
+
+/// Walks the confidential VM's first-stage page table described by vsatp and returns the confidential VM's physical
+/// address to which the guest virtual address translates. Page table entries are read with the given function, which
+/// translates the confidential VM's physical address of every entry with the second-stage page table, so the walk
+/// never reads memory the confidential VM does not own. Only the translation is checked, the permissions of the leaf
+/// are left to the caller. Fails with `GuestPageFault` if the first-stage page table does not map the address.
+pub(super) fn translate<F>(vsatp: usize, address: usize, read_entry: F) -> Result<ConfidentialVmVirtualAddress>
+where F: Fn(ConfidentialVmVirtualAddress) -> Result<usize> {
+    // the mode is decoded on every walk because the confidential VM changes vsatp without asking the security monitor.
+    let mode = FirstStageMode::from_vsatp(vsatp)?;
+    if mode == FirstStageMode::Bare {
+        // without the first-stage translation, the guest virtual address is the confidential VM's physical address.
+        return Ok(ConfidentialVmVirtualAddress::new(address));
+    }
+    // the bits of the virtual address above the translated ones must equal the most significant translated bit.
+    let address_bits = PAGE_OFFSET_BITS + mode.levels() * VPN_BITS;
+    let upper_bits = ((address as isize) >> (address_bits - 1)) as usize;
+    assure!(upper_bits == 0 || upper_bits == usize::MAX, Error::GuestPageFault(address))?;
+
+    let mut table_address = (vsatp & VSATP_PPN_MASK) << PAGE_OFFSET_BITS;
+    for level in (0..mode.levels()).rev() {
+        let vpn = (address >> (PAGE_OFFSET_BITS + level * VPN_BITS)) & ((1 << VPN_BITS) - 1);
+        let entry_address = table_address.checked_add(vpn * core::mem::size_of::<usize>());
+        let entry = read_entry(ConfidentialVmVirtualAddress::new(entry_address.ok_or(Error::GuestPageFault(address))?))
+            .map_err(|_| Error::GuestPageFault(address))?;
+        let is_valid = entry & PTE_VALID != 0 && !(entry & PTE_READ == 0 && entry & PTE_WRITE != 0);
+        assure!(is_valid, Error::GuestPageFault(address))?;
+        let page_address = ((entry >> PTE_PPN_SHIFT) & PTE_PPN_MASK) << PAGE_OFFSET_BITS;
+        if entry & (PTE_READ | PTE_EXECUTE) == 0 {
+            // a pointer to the page table of the next level
+            table_address = page_address;
+            continue;
+        }
+        // a leaf at a higher level maps a superpage, which must be aligned to its size.
+        let offset_mask = (PageSize::Size4KiB.in_bytes() << (level * VPN_BITS)) - 1;
+        assure!(page_address & offset_mask == 0, Error::GuestPageFault(address))?;
+        return Ok(ConfidentialVmVirtualAddress::new(page_address | (address & offset_mask)));
+    }
+    Err(Error::GuestPageFault(address))
+}
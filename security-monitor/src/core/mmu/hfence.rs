@@ -0,0 +1,16 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// Removes the cached second-stage translations of the confidential VM's physical address for the given VMID on the
+/// executing hart. The instruction takes the guest physical address shifted right by 2 bits.
+pub fn hfence_gvma(vmid: usize, address: usize) {
+    // Safety: the fence only invalidates cached translations, which the hardware reloads from the page tables.
+    unsafe { core::arch::asm!("hfence.gvma {0}, {1}", in(reg) address >> 2, in(reg) vmid) };
+}
+
+/// Removes all cached second-stage translations of the given VMID on the executing hart.
+pub fn hfence_gvma_vmid(vmid: usize) {
+    // Safety: the fence only invalidates cached translations, which the hardware reloads from the page tables.
+    unsafe { core::arch::asm!("hfence.gvma zero, {0}", in(reg) vmid) };
+}
@@ -1,10 +1,15 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+pub use hfence::{hfence_gvma, hfence_gvma_vmid};
 pub use page_size::PageSize;
-pub use page_table::RootPageTable;
+#[cfg(feature = "init_self_test")]
+pub use page_table::self_test as page_table_self_test;
+pub use page_table::{MemoryClass, PageStatus, PageTableCopyMode, RootPageTable};
 pub use paging_system::PagingSystem;
 
+mod first_stage;
+mod hfence;
 mod page_size;
 mod page_table;
 mod page_table_entry;
@@ -1,32 +1,455 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::memory_tracker::{ConfidentialMemoryAddress, MemoryTracker, NonConfidentialMemoryAddress, SharedPage};
+use crate::core::attestation::{digest_non_confidential_range, digest_range, ImageManifest, PageDigest};
+use crate::core::memory_tracker::{
+    Allocated, ConfidentialMemoryAddress, MemoryReservation, MemoryTracker, NonConfidentialMemoryAddress, Page,
+    SharedPage, UnAllocated,
+};
 use crate::core::mmu::page_table_entry::{
     PageTableAddress, PageTableBits, PageTableConfiguration, PageTableEntry, PageTablePermission,
 };
 use crate::core::mmu::page_table_memory::PageTableMemory;
 use crate::core::mmu::paging_system::PageTableLevel;
-use crate::core::mmu::PagingSystem;
-use crate::error::Error;
+use crate::core::mmu::{first_stage, PageSize, PagingSystem};
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use core::ops::Range;
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// Determines when the content of the confidential VM's pages is copied from the hypervisor's memory to the
+/// confidential memory. The page table structure is always copied when the confidential VM is created.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PageTableCopyMode {
+    /// All pages are copied when the confidential VM is created.
+    Eager,
+    /// A page is copied when the confidential VM accesses it for the first time. This makes the creation faster at the
+    /// cost of a trap on the first access to every page.
+    Deferred,
+}
+
+/// Describes what backs the pages of a confidential VM's buffer that the security monitor accesses on behalf of the
+/// confidential VM.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MemoryClass {
+    /// Pages owned by the confidential VM and stored in the confidential memory. Secrets must only be written there.
+    Confidential,
+    /// Pages shared with the hypervisor.
+    Shared,
+}
+
+/// Classification of a page of the confidential VM's physical address space reported to the confidential VM, so it can
+/// check that a buffer is shared before passing it to a device. The values are part of the ABI.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PageStatus {
+    /// No page is mapped at the address.
+    Unmapped = 0,
+    /// The page is not accessible to the hypervisor.
+    Private = 1,
+    /// The page is shared with the hypervisor.
+    Shared = 2,
+}
+
+impl MemoryClass {
+    fn includes(&self, entry: &PageTableEntry) -> bool {
+        match self {
+            MemoryClass::Confidential => entry.is_leaf(),
+            MemoryClass::Shared => entry.is_shared(),
+        }
+    }
+}
+
+/// Integer types the security monitor reads from or writes to the confidential VM's memory. Every bit pattern is a
+/// valid value of these types, so a value read from memory controlled by the confidential VM is always well-formed.
+pub trait GuestInteger: Copy {}
+
+impl GuestInteger for u8 {}
+impl GuestInteger for u16 {}
+impl GuestInteger for u32 {}
+impl GuestInteger for u64 {}
+impl GuestInteger for usize {}
 
 pub struct RootPageTable {
     paging_system: PagingSystem,
     page_table: PageTable,
+    // memory reserved for the confidential VM at its creation. Page tables and pages allocated after the creation are
+    // allocated through it.
+    reservation: MemoryReservation,
 }
 
 impl RootPageTable {
+    /// The confidential VM's physical address at which every confidential VM finds the page owned by the security
+    /// monitor.
+    pub const MONITOR_PAGE_ADDRESS: usize = 0xffff_ffff_f000;
+
     pub fn copy_from_non_confidential_memory(
-        address: NonConfidentialMemoryAddress, paging_system: PagingSystem,
-    ) -> Result<Self, Error> {
-        let page_table = PageTable::copy_from_non_confidential_memory(address, paging_system, paging_system.levels())?;
-        Ok(Self { paging_system, page_table })
+        address: NonConfidentialMemoryAddress, paging_system: PagingSystem, copy_mode: PageTableCopyMode,
+        mut reservation: MemoryReservation,
+    ) -> Result<Self> {
+        let level = paging_system.levels();
+        let page_table = PageTable::copy_from_non_confidential_memory(
+            address,
+            paging_system,
+            level,
+            copy_mode,
+            &mut reservation,
+            &mut BTreeMap::new(),
+        )?;
+        Ok(Self { paging_system, page_table, reservation })
+    }
+
+    /// Maps the hypervisor's page into the confidential VM. Fails if the page is backed by the image of the security
+    /// monitor, which is a valid non-confidential memory address but would let the confidential VM read or corrupt the
+    /// security monitor.
+    pub fn map_shared_page(&mut self, shared_page: &SharedPage) -> Result<()> {
+        let hypervisor_address = shared_page.hypervisor_address().usize();
+        let is_monitor_owned = MemoryTracker::is_monitor_owned(hypervisor_address, shared_page.page_size().in_bytes());
+        assure_not!(is_monitor_owned, Error::ShareIntoMonitorMemory())?;
+        self.page_table.map_shared_page(self.paging_system, &mut self.reservation, shared_page)
+    }
+
+    /// Accounts the given number of bytes of the confidential VM's control data, other than its page tables, to the
+    /// heap reservation.
+    pub fn charge_heap(&mut self, size: usize) -> Result<()> {
+        self.reservation.charge_heap(size)
+    }
+
+    /// Returns the given number of bytes of the released control data to the heap reservation.
+    pub fn refund_heap(&mut self, size: usize) {
+        self.reservation.refund_heap(size)
+    }
+
+    /// Maps the page owned by the security monitor at `MONITOR_PAGE_ADDRESS`. The confidential VM can only read this
+    /// page and later requests to share a page at this address are rejected. Returns an error if the hypervisor
+    /// already mapped memory at this address.
+    pub fn map_monitor_page(&mut self, page: Page<Allocated>) -> Result<()> {
+        let address = ConfidentialVmVirtualAddress::new(Self::MONITOR_PAGE_ADDRESS);
+        self.page_table.map_monitor_page(self.paging_system, &mut self.reservation, address, page)
+    }
+
+    /// Returns true if the address belongs to the page reserved for the security monitor.
+    pub fn is_reserved(address: ConfidentialVmVirtualAddress) -> bool {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        address.usize() / page_size == Self::MONITOR_PAGE_ADDRESS / page_size
+    }
+
+    /// Returns true if the address belongs to a page whose content has not yet been copied to the confidential memory.
+    pub fn is_deferred(&self, address: ConfidentialVmVirtualAddress) -> bool {
+        self.page_table.entry(self.paging_system, address).is_some_and(|entry| entry.is_deferred())
+    }
+
+    /// Returns true if the address belongs to a page copied to the confidential memory that the confidential VM has not
+    /// accepted yet.
+    pub fn is_imported(&self, address: ConfidentialVmVirtualAddress) -> bool {
+        self.page_table.entry(self.paging_system, address).is_some_and(|entry| entry.is_imported())
+    }
+
+    /// Returns true if the address belongs to a page owned by the confidential VM and stored in confidential memory.
+    pub fn is_confidential(&self, address: ConfidentialVmVirtualAddress) -> bool {
+        self.page_table.entry(self.paging_system, address).is_some_and(|entry| entry.is_leaf())
+    }
+
+    /// Copies data between two buffers of the confidential VM shared with the hypervisor directly in the hypervisor's
+    /// memory backing them. Buffers overlapping in contiguous hypervisor's memory are copied as if the data was first
+    /// copied to a temporary buffer. The confidential VM can map the hypervisor's pages in any order, so the copy is
+    /// split into chunks that do not cross a page boundary of either buffer and the direction of every chunk is chosen
+    /// from the hypervisor's addresses of its source and destination. All pages of both buffers are translated before
+    /// any byte is written, so either the entire data is copied or nothing. Returns the number of copied bytes.
+    pub fn copy_within_shared_memory(
+        &self, destination: ConfidentialVmVirtualAddress, source: ConfidentialVmVirtualAddress, size: usize,
+    ) -> Result<usize> {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        let source_pages = self.shared_pages(source, size, false)?;
+        let destination_pages = self.shared_pages(destination, size, true)?;
+        let hypervisor_address = |pages: &[usize], address: ConfidentialVmVirtualAddress, offset: usize| {
+            let page_offset = address.usize() % page_size + offset;
+            pages[page_offset / page_size] + page_offset % page_size
+        };
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < size {
+            let source_left = page_size - (source.usize() + offset) % page_size;
+            let destination_left = page_size - (destination.usize() + offset) % page_size;
+            let length = source_left.min(destination_left).min(size - offset);
+            chunks.push((
+                hypervisor_address(&source_pages, source, offset),
+                hypervisor_address(&destination_pages, destination, offset),
+                length,
+            ));
+            offset += length;
+        }
+        let copy_chunk = |&(from, to, length): &(usize, usize, usize)| {
+            let copy_byte = |i: usize| {
+                // Safety: both addresses were translated using this page table to pages in the non-confidential memory
+                // shared by the confidential VM and the destination page is writable.
+                unsafe { ((to + i) as *mut u8).write_volatile(((from + i) as *const u8).read_volatile()) };
+            };
+            // copying backwards when the destination follows the source does not overwrite bytes that are not copied
+            // yet.
+            match to > from {
+                true => (0..length).rev().for_each(copy_byte),
+                false => (0..length).for_each(copy_byte),
+            }
+        };
+        // the chunks are visited in the same direction as the bytes of the first chunk, which keeps buffers overlapping
+        // in the hypervisor's memory intact when the hypervisor backs them with contiguous memory.
+        match chunks.first().is_some_and(|&(from, to, _)| to > from) {
+            true => chunks.iter().rev().for_each(copy_chunk),
+            false => chunks.iter().for_each(copy_chunk),
+        }
+        Ok(size)
+    }
+
+    /// Returns the addresses in the hypervisor's memory of all 4KiB pages overlapping the buffer. Fails if any of them
+    /// is not shared or, when the buffer is written, shared read-only.
+    fn shared_pages(&self, address: ConfidentialVmVirtualAddress, size: usize, is_write: bool) -> Result<Vec<usize>> {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        let end_address = address.checked_add(size)?;
+        (address.usize() / page_size * page_size..end_address.usize())
+            .step_by(page_size)
+            .map(|page_address| {
+                match self.page_table.entry(self.paging_system, ConfidentialVmVirtualAddress::new(page_address)) {
+                    Some(PageTableEntry::Shared(hypervisor_address, _, permission))
+                        if !is_write || permission.can_write() =>
+                    {
+                        Ok(hypervisor_address.usize())
+                    }
+                    _ => Err(Error::MemoryAccessAuthorization()),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns true if the address belongs to a page shared with the hypervisor.
+    pub fn is_shared(&self, address: ConfidentialVmVirtualAddress) -> bool {
+        self.page_table.entry(self.paging_system, address).is_some_and(|entry| entry.is_shared())
+    }
+
+    /// Classifies the page containing the given address. Pages whose content has not yet been copied to the
+    /// confidential memory or accepted and the page owned by the security monitor are private because the
+    /// hypervisor cannot change them.
+    pub fn page_status(&self, address: ConfidentialVmVirtualAddress) -> PageStatus {
+        match self.translate(address) {
+            Ok(_) => PageStatus::Private,
+            Err(_) if self.is_shared(address) => PageStatus::Shared,
+            Err(_) if self.is_deferred(address) || self.is_imported(address) || Self::is_reserved(address) => {
+                PageStatus::Private
+            }
+            Err(_) => PageStatus::Unmapped,
+        }
+    }
+
+    /// Replaces the shared 4KiB pages starting at the given address with zeroed pages allocated in the confidential
+    /// memory. Either all pages are converted or none. The content of the shared pages is never copied. The hardware
+    /// stops using the old mappings because the second-level TLB is flushed before the confidential VM resumes.
+    pub fn convert_to_private(&mut self, address: ConfidentialVmVirtualAddress, number_of_pages: usize) -> Result<()> {
+        let page_size = PageSize::Size4KiB;
+        let size = number_of_pages.checked_mul(page_size.in_bytes()).ok_or(Error::AddressOverflow())?;
+        self.validate_range(address, size, MemoryClass::Shared)?;
+        // the range is validated before any page is allocated, so the allocation is bounded by the shared pages.
+        let pages = self.reservation.acquire_continous_pages(number_of_pages, page_size)?;
+        pages.into_iter().enumerate().try_for_each(|(i, page)| {
+            let page_address = address.checked_add(i * page_size.in_bytes())?;
+            self.page_table.map_private_page(self.paging_system, page_address, page.zeroize())
+        })
+    }
+
+    /// Removes the mapping of the shared page containing the given address and releases page tables left empty. The
+    /// hypervisor's page is not accessed. The caller must flush the cached translations of the address.
+    pub fn unmap_shared_page(&mut self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        self.page_table.unmap_shared_page(self.paging_system, address)?;
+        self.page_table.release_empty_page_tables(self.paging_system, &mut self.reservation, address);
+        Ok(())
     }
 
-    pub fn map_shared_page(&mut self, shared_page: &SharedPage) -> Result<(), Error> {
-        self.page_table.map_shared_page(self.paging_system, shared_page)
+    /// Moves the confidential 4KiB page mapped at the old address to the new address without copying its content.
+    /// Fails if the old address is not mapped to a 4KiB page owned by the confidential VM or if the new address is
+    /// already mapped. Page tables created for the new address are kept even if the move fails, and page tables left
+    /// empty at the old address are released. The caller must flush the cached translations of both addresses.
+    pub fn remap_confidential_page(
+        &mut self, old_address: ConfidentialVmVirtualAddress, new_address: ConfidentialVmVirtualAddress,
+    ) -> Result<()> {
+        let is_small_leaf = match self.page_table.entry(self.paging_system, old_address) {
+            Some(PageTableEntry::Leaf(page, _, _)) => page.size() == &PageSize::Size4KiB,
+            _ => false,
+        };
+        assure!(is_small_leaf, Error::MemoryAccessAuthorization())?;
+        assure_not!(Self::is_reserved(new_address), Error::ReservedAddress())?;
+        // the new slot is prepared before the page is detached, so the page is never left without a mapping.
+        self.page_table.prepare_leaf_slot(self.paging_system, &mut self.reservation, new_address)?;
+        let entry = self.page_table.take_leaf(self.paging_system, old_address)?;
+        self.page_table.set_leaf(self.paging_system, new_address, entry)?;
+        self.page_table.release_empty_page_tables(self.paging_system, &mut self.reservation, old_address);
+        Ok(())
+    }
+
+    /// Copies the content of the page containing the given address from the hypervisor's memory to the confidential
+    /// memory and maps it into the confidential VM's address space. Returns the imported page.
+    pub fn import_page(&mut self, address: ConfidentialVmVirtualAddress) -> Result<&Page<Allocated>> {
+        self.page_table.import_page(self.paging_system, &mut self.reservation, address)
+    }
+
+    /// Maps the imported page containing the given address, so the confidential VM can access it. Fails with
+    /// `PageNotPendingAccept` if the page was not imported or has already been accepted.
+    pub fn accept_page(&mut self, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        self.page_table.accept_page(self.paging_system, address)
+    }
+
+    /// Translates the confidential VM's physical address into the address in the confidential memory. Only addresses
+    /// mapped to confidential pages are translated, an error is returned for shared or not mapped addresses.
+    pub fn translate(&self, address: ConfidentialVmVirtualAddress) -> Result<ConfidentialMemoryAddress> {
+        self.page_table.translate(self.paging_system, address)
+    }
+
+    /// Translates the guest virtual address with the confidential VM's first-stage page table configured in vsatp and
+    /// then with this second-stage page table, returning the address in the confidential memory. Unlike reading with
+    /// MPRV, the walk is done in software, so it works for any vsatp and does not trap in the security monitor. All
+    /// first-stage page tables must be in pages owned by the confidential VM.
+    pub fn two_stage_translate(&self, vsatp: usize, address: usize) -> Result<ConfidentialMemoryAddress> {
+        let guest_physical_address =
+            first_stage::translate(vsatp, address, |entry_address| self.read_guest::<usize>(entry_address))?;
+        self.translate(guest_physical_address)
+    }
+
+    /// Returns an error if the instruction at the guest virtual address is not entirely located in pages owned by the
+    /// confidential VM. The security monitor reads the instruction using the confidential VM's address translation, so
+    /// an instruction in a shared page could be modified by the hypervisor before or while the security monitor decodes
+    /// it.
+    pub fn assure_confidential_instruction(&self, vsatp: usize, address: usize) -> Result<()> {
+        let first_half = self.two_stage_translate(vsatp, address).map_err(|_| Error::ExecutingFromSharedMemory())?;
+        // Safety: the address was translated to the page owned by the confidential VM and instructions are aligned to 2
+        // bytes, so the read is within this page. The hypervisor cannot modify the value.
+        let first_half = unsafe { (first_half.usize() as *const u16).read_volatile() };
+        // the rest of a longer instruction might be in the next page.
+        if riscv_decode::instruction_length(first_half) > core::mem::size_of::<u16>() {
+            let second_half = address.wrapping_add(core::mem::size_of::<u16>());
+            self.two_stage_translate(vsatp, second_half).map_err(|_| Error::ExecutingFromSharedMemory())?;
+        }
+        Ok(())
+    }
+
+    /// Reads the value stored at the given confidential VM's physical address. The address must be aligned to the size
+    /// of the value, so the value never crosses the page boundary. Fails with `GuestAccessFault` if the address is not
+    /// aligned or not in a readable page owned by the confidential VM.
+    pub fn read_guest<S: GuestInteger>(&self, address: ConfidentialVmVirtualAddress) -> Result<S> {
+        let pointer = self.guest_pointer::<S>(address, false)?;
+        // Safety: the address was translated using this page table and the value does not cross the page boundary, so
+        // the read is within the page owned by the confidential VM.
+        Ok(unsafe { (pointer as *const S).read_volatile() })
+    }
+
+    /// Writes the value at the given confidential VM's physical address. Fails with `GuestAccessFault` if the address
+    /// is not aligned to the size of the value or not in a writable page owned by the confidential VM.
+    pub fn write_guest<S: GuestInteger>(&self, address: ConfidentialVmVirtualAddress, value: S) -> Result<()> {
+        let pointer = self.guest_pointer::<S>(address, true)?;
+        // Safety: the address was translated using this page table and the value does not cross the page boundary, so
+        // the write is within the page owned by the confidential VM.
+        unsafe { pointer.write_volatile(value) };
+        Ok(())
+    }
+
+    fn guest_pointer<S: GuestInteger>(&self, address: ConfidentialVmVirtualAddress, is_write: bool) -> Result<*mut S> {
+        let size = core::mem::size_of::<S>();
+        let is_aligned = size > 0 && PageSize::Size4KiB.in_bytes() % size == 0 && address.usize() % size == 0;
+        assure!(is_aligned, Error::GuestAccessFault())?;
+        let is_permitted = match self.page_table.entry(self.paging_system, address) {
+            Some(PageTableEntry::Leaf(_, _, permission)) => {
+                permission.can_read() && (!is_write || permission.can_write())
+            }
+            _ => false,
+        };
+        assure!(is_permitted, Error::GuestAccessFault())?;
+        let confidential_memory_address = self.translate(address).map_err(|_| Error::GuestAccessFault())?;
+        Ok(confidential_memory_address.usize() as *mut S)
+    }
+
+    /// Returns an error if any 4KiB page overlapping the buffer of the given size is not of the required memory class.
+    /// Handlers validate all buffers of a call before accessing any of them, so a call either completes or has no
+    /// effect.
+    pub fn validate_range(
+        &self, address: ConfidentialVmVirtualAddress, size: usize, memory_class: MemoryClass,
+    ) -> Result<()> {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        let end_address = address.checked_add(size)?;
+        (address.usize() / page_size * page_size..end_address.usize()).step_by(page_size).try_for_each(|page_address| {
+            let entry = self.page_table.entry(self.paging_system, ConfidentialVmVirtualAddress::new(page_address));
+            assure!(entry.is_some_and(|entry| memory_class.includes(entry)), Error::MemoryAccessAuthorization())
+        })
+    }
+
+    /// Copies data into the confidential VM's memory starting at the given confidential VM's physical address. The
+    /// entire buffer is validated and all addresses are translated before any byte is written, so either the entire
+    /// data is copied or nothing.
+    pub fn copy_to_confidential_vm(&self, address: ConfidentialVmVirtualAddress, data: &[u8]) -> Result<()> {
+        self.validate_range(address, data.len(), MemoryClass::Confidential)?;
+        let page_size = PageSize::Size4KiB.in_bytes();
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let chunk_address = address.checked_add(offset)?;
+            let confidential_memory_address = self.translate(chunk_address)?;
+            let chunk_size = core::cmp::min(data.len() - offset, page_size - chunk_address.usize() % page_size);
+            chunks.push((confidential_memory_address, offset..offset + chunk_size));
+            offset += chunk_size;
+        }
+        chunks.into_iter().for_each(|(confidential_memory_address, range)| {
+            data[range].iter().enumerate().for_each(|(i, byte)| {
+                // Safety: the address was translated using this page table and the chunk does not cross the boundary
+                // of the 4KiB page, so the write is within the page owned by the confidential VM.
+                unsafe { ((confidential_memory_address.usize() + i) as *mut u8).write_volatile(*byte) };
+            })
+        });
+        Ok(())
+    }
+
+    /// Copies data from the confidential VM's memory starting at the given confidential VM's physical address. Fails if
+    /// any part of the data is not in a page owned by the confidential VM, which is checked before any byte is read.
+    pub fn copy_from_confidential_vm(&self, address: ConfidentialVmVirtualAddress, size: usize) -> Result<Vec<u8>> {
+        self.validate_range(address, size, MemoryClass::Confidential)?;
+        let page_size = PageSize::Size4KiB.in_bytes();
+        let mut data = Vec::with_capacity(size);
+        while data.len() < size {
+            let chunk_address = address.checked_add(data.len())?;
+            let confidential_memory_address = self.translate(chunk_address)?;
+            let chunk_size = core::cmp::min(size - data.len(), page_size - chunk_address.usize() % page_size);
+            // Safety: the address was translated using this page table and the chunk does not cross the boundary of
+            // the 4KiB page, so the read is within the page owned by the confidential VM.
+            (0..chunk_size).for_each(|i| {
+                data.push(unsafe { ((confidential_memory_address.usize() + i) as *const u8).read_volatile() })
+            });
+        }
+        Ok(data)
+    }
+
+    /// Returns an error if any page of the confidential VM's image does not match the image manifest or if any page
+    /// listed in the manifest is not mapped. Pages imported on the first access cannot be verified at the confidential
+    /// VM creation, so their presence fails the verification.
+    pub fn verify_image(&self, image_manifest: &ImageManifest) -> Result<()> {
+        self.page_table.verify_image(self.paging_system, 0, image_manifest)?;
+        image_manifest.entries().try_for_each(|(address, _)| {
+            let entry = self.page_table.entry(self.paging_system, ConfidentialVmVirtualAddress::new(*address));
+            assure!(entry.is_some_and(|entry| entry.is_leaf()), Error::ManifestVerificationFailed())
+        })
+    }
+
+    /// Returns ranges of the confidential VM's physical address space that are backed by confidential memory, including
+    /// pages that have not yet been imported. Contiguous ranges are merged.
+    /// Calls the given function with the confidential VM's physical address and the digest of every page of the
+    /// confidential VM's memory in the order of the addresses. Deferred pages are reported with the digest computed
+    /// when the page table was copied, so the result depends neither on the copy mode nor on the pages accessed so far.
+    pub fn measure_pages<F: FnMut(usize, &PageDigest)>(&self, mut measure: F) -> Result<()> {
+        self.page_table.measure_pages(self.paging_system, 0, &mut measure)
+    }
+
+    pub fn confidential_memory_regions(&self) -> Vec<Range<usize>> {
+        let mut regions = Vec::new();
+        self.page_table.confidential_memory_regions(self.paging_system, 0, &mut regions);
+        regions
     }
 
     pub fn address(&self) -> ConfidentialMemoryAddress {
@@ -47,77 +470,196 @@ pub(super) struct PageTable {
 impl PageTable {
     /// This functions copies recursively page table structure from non-confidential memory to confidential memory. It
     /// allocated a page in confidential memory for every page table. After this function executes, a valid page table
-    /// configuration is in the confidential memory.
+    /// configuration is in the confidential memory. In the deferred copy mode, the content of leaf pages is not copied.
+    /// The hypervisor's memory ranges of all page tables copied so far are tracked, so a page table referenced more
+    /// than once is rejected. This covers page tables that point to themselves or to their ancestors as well as
+    /// subtables shared by several entries, which would otherwise be copied once per reference. The control data of
+    /// every page table is charged to the reservation.
+    ///
+    /// The hypervisor can modify its page table while it is being copied. Every page table is therefore read from the
+    /// hypervisor's memory exactly once, into the confidential memory, and every entry is decoded and checked from that
+    /// copy. The entries the security monitor keeps thus always match the configuration it stores, whatever the
+    /// hypervisor writes during or after the read.
     fn copy_from_non_confidential_memory(
         address: NonConfidentialMemoryAddress, paging_system: PagingSystem, level: PageTableLevel,
-    ) -> Result<Self, Error> {
+        copy_mode: PageTableCopyMode, reservation: &mut MemoryReservation, visited: &mut BTreeMap<usize, usize>,
+    ) -> Result<Self> {
+        let start = address.usize();
+        let end = start.checked_add(paging_system.size_in_bytes(level)).ok_or(Error::AddressOverflow())?;
+        let overlaps = visited.range(..end).next_back().is_some_and(|(_, visited_end)| *visited_end > start);
+        assure_not!(overlaps, Error::AliasedPageTable())?;
+        visited.insert(start, end);
         let mut page_table_memory = PageTableMemory::copy_from_non_confidential_memory(address, paging_system, level)?;
-        let entries = page_table_memory
-            .indices()
-            .map(|index| {
-                let entry_raw = page_table_memory.entry(index).unwrap();
-                let page_table_entry = if !PageTableBits::is_valid(entry_raw) {
-                    PageTableEntry::NotValid
-                } else if PageTableBits::is_leaf(entry_raw) {
-                    let address = NonConfidentialMemoryAddress::new(PageTableAddress::decode(entry_raw))?;
-                    let page_size = paging_system.page_size(level);
-                    let page = MemoryTracker::acquire_continous_pages(1, page_size)?
-                        .remove(0)
-                        .copy_from_non_confidential_memory(address)
-                        .map_err(|_| Error::PageTableCorrupted())?;
-                    let configuration = PageTableConfiguration::decode(entry_raw);
-                    let permission = PageTablePermission::decode(entry_raw);
-                    PageTableEntry::Leaf(Box::new(page), configuration, permission)
-                } else {
-                    let lower_level = level.lower().ok_or(Error::PageTableCorrupted())?;
-                    let address = NonConfidentialMemoryAddress::new(PageTableAddress::decode(entry_raw))?;
-                    let page_table = Self::copy_from_non_confidential_memory(address, paging_system, lower_level)?;
-                    let configuration = PageTableConfiguration::decode(entry_raw);
-                    PageTableEntry::Pointer(Box::new(page_table), configuration)
-                };
-                page_table_memory.set_entry(index, &page_table_entry);
-                Ok(page_table_entry)
-            })
-            .collect::<Result<Vec<PageTableEntry>, Error>>()?;
+        reservation.charge_heap(Self::heap_size(page_table_memory.number_of_entries()))?;
+        let page_size = paging_system.page_size(level);
+        let mut leaf_pages = match copy_mode {
+            PageTableCopyMode::Eager => Self::acquire_leaf_pages(&page_table_memory, page_size, reservation)?,
+            PageTableCopyMode::Deferred => Vec::new(),
+        }
+        .into_iter();
+        let mut entries = Vec::with_capacity(page_table_memory.number_of_entries());
+        let result = page_table_memory.indices().try_for_each(|index| {
+            let entry_raw = page_table_memory.entry(index).unwrap();
+            let page_table_entry = if !PageTableBits::is_valid(entry_raw) {
+                PageTableEntry::NotValid
+            } else if PageTableBits::is_leaf(entry_raw) {
+                let address = NonConfidentialMemoryAddress::new_page(PageTableAddress::decode(entry_raw), page_size)?;
+                let configuration = PageTableConfiguration::decode(entry_raw);
+                let permission = PageTablePermission::decode(entry_raw)?;
+                match copy_mode {
+                    PageTableCopyMode::Eager => {
+                        let page = leaf_pages
+                            .next()
+                            .ok_or(Error::PageTableCorrupted())?
+                            .copy_from_non_confidential_memory(address)
+                            .map_err(|_| Error::PageTableCorrupted())?;
+                        PageTableEntry::Leaf(Box::new(page), configuration, permission)
+                    }
+                    PageTableCopyMode::Deferred => {
+                        let page_digest = digest_non_confidential_range(address, page_size.in_bytes())?;
+                        PageTableEntry::Deferred(address, Box::new(page_digest), configuration, permission)
+                    }
+                }
+            } else {
+                // leaves are allowed at every level, but there is no page table below the lowest level to point to.
+                let lower_level = level.lower().ok_or(Error::InvalidPageTableEntryLevel())?;
+                let address = NonConfidentialMemoryAddress::new_region(
+                    PageTableAddress::decode(entry_raw),
+                    paging_system.size_in_bytes(lower_level),
+                )?;
+                let page_table = Self::copy_from_non_confidential_memory(
+                    address,
+                    paging_system,
+                    lower_level,
+                    copy_mode,
+                    reservation,
+                    visited,
+                )?;
+                let configuration = PageTableConfiguration::decode(entry_raw);
+                PageTableEntry::Pointer(Box::new(page_table), configuration)
+            };
+            page_table_memory.set_entry(index, &page_table_entry)?;
+            entries.push(page_table_entry);
+            Ok(())
+        });
+        // the leaf pages were acquired for the leaves of the same copy, so every one of them must back a leaf.
+        let result = result.and_then(|_| assure!(leaf_pages.as_slice().is_empty(), Error::PageTableCorrupted()));
+        if let Err(error) = result {
+            // leaf pages acquired for this page table, copied or not, are returned to the reservation.
+            let mut pages: Vec<_> = leaf_pages.collect();
+            entries.into_iter().for_each(|entry| match entry {
+                PageTableEntry::Leaf(page, _, _) => pages.push(page.deallocate()),
+                entry => Self::release_entry(entry),
+            });
+            reservation.release_pages(pages);
+            return Err(error);
+        }
         Ok(Self { level, page_table_memory, entries })
     }
 
-    fn empty(paging_system: PagingSystem, level: PageTableLevel) -> Result<Self, Error> {
-        let page_table_memory = PageTableMemory::empty(paging_system, level)?;
-        let entries = Vec::with_capacity(page_table_memory.number_of_entries());
+    /// Returns pages of the confidential memory for all leaves of the page table, in the order of the leaves. Leaves
+    /// mapping a run of contiguous pages of the hypervisor's memory are backed by contiguous confidential pages
+    /// acquired at once. This reduces the number of allocations when creating large confidential VMs and keeps
+    /// their memory local. Leaves of a run that cannot be allocated contiguously are backed by pages acquired one
+    /// by one. The pages are acquired through the reservation and returned to it if any of them cannot be acquired.
+    fn acquire_leaf_pages(
+        page_table_memory: &PageTableMemory, page_size: PageSize, reservation: &mut MemoryReservation,
+    ) -> Result<Vec<Page<UnAllocated>>> {
+        let mut pages = Vec::new();
+        let result = Self::leaf_runs(page_table_memory, page_size).into_iter().try_for_each(|number_of_pages| {
+            match reservation.acquire_continous_pages(number_of_pages, page_size) {
+                Ok(mut run) => pages.append(&mut run),
+                Err(_) => {
+                    for _ in 0..number_of_pages {
+                        pages.append(&mut reservation.acquire_continous_pages(1, page_size)?);
+                    }
+                }
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => Ok(pages),
+            Err(error) => {
+                reservation.release_pages(pages);
+                Err(error)
+            }
+        }
+    }
+
+    /// Returns the lengths of runs of leaves mapping contiguous pages of the hypervisor's memory, in the order of the
+    /// leaves.
+    fn leaf_runs(page_table_memory: &PageTableMemory, page_size: PageSize) -> Vec<usize> {
+        let mut runs: Vec<usize> = Vec::new();
+        let mut run_end = None;
+        page_table_memory
+            .indices()
+            .filter_map(|index| page_table_memory.entry(index))
+            .filter(|entry_raw| PageTableBits::is_valid(*entry_raw) && PageTableBits::is_leaf(*entry_raw))
+            .for_each(|entry_raw| {
+                let address = PageTableAddress::decode(entry_raw);
+                match runs.last_mut() {
+                    Some(number_of_pages) if run_end == Some(address) => *number_of_pages += 1,
+                    _ => runs.push(1),
+                }
+                run_end = Some(address.wrapping_add(page_size.in_bytes()));
+            });
+        runs
+    }
+
+    /// Returns the upper bound of the heap used by a page table with the given number of entries, assuming that every
+    /// entry owns a page or the digest of a deferred page. Page tables pointed to by the entries are accounted
+    /// separately.
+    fn heap_size(number_of_entries: usize) -> usize {
+        let entry_data_size =
+            core::cmp::max(core::mem::size_of::<Page<Allocated>>(), core::mem::size_of::<PageDigest>());
+        let entry_size = core::mem::size_of::<PageTableEntry>() + entry_data_size;
+        core::mem::size_of::<PageTable>() + number_of_entries * entry_size
+    }
+
+    fn empty(paging_system: PagingSystem, level: PageTableLevel, reservation: &mut MemoryReservation) -> Result<Self> {
+        let page_table_memory = PageTableMemory::empty(paging_system, level, reservation)?;
+        reservation.charge_heap(Self::heap_size(page_table_memory.number_of_entries()))?;
+        let entries = page_table_memory.indices().map(|_| PageTableEntry::NotValid).collect();
         Ok(Self { level, page_table_memory, entries })
     }
 
     /// This function maps the confidential VM's physical address into the address of the page allocated by the
     /// hypervisor. The second-level page table is modified. If there was already a mapping, the address of a previosuly
     /// mapped page is returned. The below function works only for shared pages of size 4KiB.
-    fn map_shared_page(&mut self, paging_system: PagingSystem, shared_page: &SharedPage) -> Result<(), Error> {
+    fn map_shared_page(
+        &mut self, paging_system: PagingSystem, reservation: &mut MemoryReservation, shared_page: &SharedPage,
+    ) -> Result<()> {
         // walk from the root page table until the leaf node recreating the intermediary page tables if necessary.
         let virtual_page_number = paging_system.vpn(shared_page.confidential_vm_virtual_address(), self.level);
         let entry = self.entry_mut(virtual_page_number).ok_or_else(|| Error::PageTableConfiguration())?;
         match entry {
             PageTableEntry::Pointer(next_page_table, _) => {
-                next_page_table.map_shared_page(paging_system, shared_page)?;
+                next_page_table.map_shared_page(paging_system, reservation, shared_page)?;
             }
-            PageTableEntry::Leaf(_page, _configuration, _permission) => {
-                // The virtual address is already mapped to this physical address. Let's detach the old address and map
-                // the requested address TODO: deallocate the old page
-                let new_entry = PageTableEntry::Shared(
-                    shared_page.hypervisor_address(),
-                    PageTableConfiguration::shared_page_configuration(),
-                    PageTablePermission::shared_page_permission(),
-                );
-                self.set_entry(virtual_page_number, new_entry);
+            PageTableEntry::Leaf(_, _, _)
+            | PageTableEntry::Imported(_, _, _)
+            | PageTableEntry::Deferred(_, _, _, _) => {
+                // sharing a page owned by the confidential VM would silently expose its address to the hypervisor. A
+                // deferred page is owned by the confidential VM as well, its measured content is only not copied yet.
+                // The confidential VM must first move the page away, so sharing is always a deliberate act.
+                return Err(Error::CannotShareConfidentialLeaf());
             }
-            PageTableEntry::Shared(_address, _configuration, _permission) => {
+            PageTableEntry::Shared(_address, _configuration, permission) => {
                 // confidential VM virtual address already mapped to a physical address in non-confidential memory.
-                // Let's simply re-map to the new address.
+                // Let's re-map to the new address. The permissions can only narrow, so a page shared read-only does
+                // not become writable until the confidential VM converts it to private and shares it again.
+                let new_permission = PageTablePermission::shared_page_permission(shared_page.is_read_only());
+                assure!(new_permission.is_within(permission), Error::SharePermissionWidened())?;
                 let new_entry = PageTableEntry::Shared(
                     shared_page.hypervisor_address(),
                     PageTableConfiguration::shared_page_configuration(),
-                    PageTablePermission::shared_page_permission(),
+                    new_permission,
                 );
-                self.set_entry(virtual_page_number, new_entry);
+                self.set_entry(virtual_page_number, new_entry)?;
+            }
+            PageTableEntry::Monitor(_page, _configuration, _permission) => {
+                // the page owned by the security monitor must not be replaced by a page provided by the hypervisor.
+                return Err(Error::ReservedAddress());
             }
             PageTableEntry::NotValid => {
                 if self.level == PageTableLevel::Level1 {
@@ -125,21 +667,307 @@ impl PageTable {
                     let new_entry = PageTableEntry::Shared(
                         shared_page.hypervisor_address(),
                         PageTableConfiguration::shared_page_configuration(),
-                        PageTablePermission::shared_page_permission(),
+                        PageTablePermission::shared_page_permission(shared_page.is_read_only()),
                     );
-                    self.set_entry(virtual_page_number, new_entry);
+                    self.set_entry(virtual_page_number, new_entry)?;
                 } else {
                     // intermediary page table does not exist, let's create it
-                    let mut next_page_table = PageTable::empty(paging_system, self.level)?;
-                    next_page_table.map_shared_page(paging_system, shared_page)?;
+                    let lower_level = self.level.lower().ok_or(Error::PageTableCorrupted())?;
+                    let mut next_page_table = PageTable::empty(paging_system, lower_level, reservation)?;
+                    next_page_table.map_shared_page(paging_system, reservation, shared_page)?;
                     let new_entry = PageTableEntry::Pointer(Box::new(next_page_table), PageTableConfiguration::empty());
-                    self.set_entry(virtual_page_number, new_entry);
+                    self.set_entry(virtual_page_number, new_entry)?;
                 }
             }
         }
         Ok(())
     }
 
+    fn map_monitor_page(
+        &mut self, paging_system: PagingSystem, reservation: &mut MemoryReservation,
+        address: ConfidentialVmVirtualAddress, page: Page<Allocated>,
+    ) -> Result<()> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        let entry = self.entry_mut(virtual_page_number).ok_or_else(|| Error::PageTableConfiguration())?;
+        match entry {
+            PageTableEntry::Pointer(next_page_table, _) => {
+                next_page_table.map_monitor_page(paging_system, reservation, address, page)
+            }
+            PageTableEntry::NotValid if self.level == PageTableLevel::Level1 => {
+                let new_entry = PageTableEntry::Monitor(
+                    Box::new(page),
+                    PageTableConfiguration::monitor_page_configuration(),
+                    PageTablePermission::monitor_page_permission(),
+                );
+                self.set_entry(virtual_page_number, new_entry)?;
+                Ok(())
+            }
+            PageTableEntry::NotValid => {
+                let lower_level = self.level.lower().ok_or(Error::PageTableCorrupted())?;
+                let mut next_page_table = PageTable::empty(paging_system, lower_level, reservation)?;
+                next_page_table.map_monitor_page(paging_system, reservation, address, page)?;
+                let new_entry = PageTableEntry::Pointer(Box::new(next_page_table), PageTableConfiguration::empty());
+                self.set_entry(virtual_page_number, new_entry)?;
+                Ok(())
+            }
+            // the hypervisor mapped memory at the address reserved for the security monitor. We do not silently unmap
+            // it because the confidential VM might rely on it.
+            _ => {
+                MemoryTracker::release_page(page.deallocate());
+                Err(Error::ReservedAddress())
+            }
+        }
+    }
+
+    /// Returns the entry that maps the given address, i.e., the first entry on the walk that is not a Pointer.
+    fn entry(&self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress) -> Option<&PageTableEntry> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => next_page_table.entry(paging_system, address),
+            entry => entry,
+        }
+    }
+
+    fn map_private_page(
+        &mut self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress, page: Page<Allocated>,
+    ) -> Result<()> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entry_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => {
+                next_page_table.map_private_page(paging_system, address, page)
+            }
+            Some(PageTableEntry::Shared(_, _, _)) => {
+                let new_entry = PageTableEntry::Leaf(
+                    Box::new(page),
+                    PageTableConfiguration::private_page_configuration(),
+                    PageTablePermission::private_page_permission(),
+                );
+                self.set_entry(virtual_page_number, new_entry)?;
+                Ok(())
+            }
+            _ => {
+                MemoryTracker::release_page(page.deallocate());
+                Err(Error::MemoryAccessAuthorization())
+            }
+        }
+    }
+
+    fn unmap_shared_page(&mut self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => {
+                next_page_table.unmap_shared_page(paging_system, address)
+            }
+            Some(PageTableEntry::Shared(_, _, _)) => self.set_entry(virtual_page_number, PageTableEntry::NotValid),
+            _ => Err(Error::PageNotShared()),
+        }
+    }
+
+    /// Creates the page tables on the walk to the 4KiB entry of the given address. Fails if any entry on the walk maps
+    /// memory, i.e., the address is already mapped.
+    fn prepare_leaf_slot(
+        &mut self, paging_system: PagingSystem, reservation: &mut MemoryReservation,
+        address: ConfidentialVmVirtualAddress,
+    ) -> Result<()> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entry_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => {
+                next_page_table.prepare_leaf_slot(paging_system, reservation, address)
+            }
+            Some(PageTableEntry::NotValid) if self.level == PageTableLevel::Level1 => Ok(()),
+            Some(PageTableEntry::NotValid) => {
+                let lower_level = self.level.lower().ok_or(Error::PageTableCorrupted())?;
+                let mut next_page_table = PageTable::empty(paging_system, lower_level, reservation)?;
+                next_page_table.prepare_leaf_slot(paging_system, reservation, address)?;
+                let new_entry = PageTableEntry::Pointer(Box::new(next_page_table), PageTableConfiguration::empty());
+                self.set_entry(virtual_page_number, new_entry)?;
+                Ok(())
+            }
+            _ => Err(Error::MemoryAccessAuthorization()),
+        }
+    }
+
+    /// Detaches the leaf mapping the given address and returns it. The page owned by the leaf is not released.
+    fn take_leaf(
+        &mut self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress,
+    ) -> Result<PageTableEntry> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => next_page_table.take_leaf(paging_system, address),
+            Some(PageTableEntry::Leaf(_, _, _)) => {
+                self.page_table_memory.set_entry(virtual_page_number, &PageTableEntry::NotValid)?;
+                Ok(core::mem::replace(&mut self.entries[virtual_page_number], PageTableEntry::NotValid))
+            }
+            _ => Err(Error::MemoryAccessAuthorization()),
+        }
+    }
+
+    /// Stores the leaf in the 4KiB entry of the given address prepared with `prepare_leaf_slot`.
+    fn set_leaf(
+        &mut self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress, entry: PageTableEntry,
+    ) -> Result<()> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => {
+                next_page_table.set_leaf(paging_system, address, entry)
+            }
+            Some(PageTableEntry::NotValid) if self.level == PageTableLevel::Level1 => {
+                self.set_entry(virtual_page_number, entry)
+            }
+            _ => Err(Error::PageTableCorrupted()),
+        }
+    }
+
+    /// Releases the page tables on the walk to the given address that no longer contain valid entries and returns their
+    /// control data to the reservation. Returns true if this page table is empty.
+    fn release_empty_page_tables(
+        &mut self, paging_system: PagingSystem, reservation: &mut MemoryReservation,
+        address: ConfidentialVmVirtualAddress,
+    ) -> bool {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        if let Some(PageTableEntry::Pointer(next_page_table, _)) = self.entries.get_mut(virtual_page_number) {
+            if next_page_table.release_empty_page_tables(paging_system, reservation, address) {
+                let heap_size = Self::heap_size(next_page_table.entries.len());
+                if self.set_entry(virtual_page_number, PageTableEntry::NotValid).is_ok() {
+                    reservation.refund_heap(heap_size);
+                }
+            }
+        }
+        self.entries.iter().all(|entry| !entry.is_valid())
+    }
+
+    fn import_page(
+        &mut self, paging_system: PagingSystem, reservation: &mut MemoryReservation,
+        address: ConfidentialVmVirtualAddress,
+    ) -> Result<&Page<Allocated>> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get(virtual_page_number) {
+            Some(PageTableEntry::Pointer(_, _)) => {}
+            Some(PageTableEntry::Deferred(hypervisor_address, page_digest, configuration, permission)) => {
+                let (hypervisor_address, page_digest, configuration, permission) =
+                    (*hypervisor_address, **page_digest, *configuration, *permission);
+                let page = reservation
+                    .acquire_continous_pages(1, paging_system.page_size(self.level))?
+                    .remove(0)
+                    .copy_from_non_confidential_memory(hypervisor_address)?;
+                // the hypervisor might have changed the content after it was measured. Such a page is not mapped.
+                if digest_range(page.address(), page.size().in_bytes())? != page_digest {
+                    MemoryTracker::release_page(page.deallocate());
+                    return Err(Error::DeferredPageModified());
+                }
+                // the page stays inaccessible to the confidential VM until the confidential VM accepts it.
+                let entry = PageTableEntry::Imported(Box::new(page), configuration, permission);
+                self.set_entry(virtual_page_number, entry)?;
+            }
+            // the page has been imported before, but the confidential VM accessed it without accepting it.
+            Some(PageTableEntry::Imported(_, _, _)) => {}
+            _ => return Err(Error::MemoryAccessAuthorization()),
+        }
+        match self.entries.get_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => {
+                next_page_table.import_page(paging_system, reservation, address)
+            }
+            Some(PageTableEntry::Imported(page, _, _)) => Ok(page.as_ref()),
+            _ => Err(Error::PageTableCorrupted()),
+        }
+    }
+
+    /// Turns the imported entry mapping the given address into a leaf, so the hardware translates the address.
+    fn accept_page(&mut self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress) -> Result<()> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get_mut(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => next_page_table.accept_page(paging_system, address),
+            Some(entry @ PageTableEntry::Imported(_, _, _)) => {
+                let accepted_entry = match core::mem::replace(entry, PageTableEntry::NotValid) {
+                    PageTableEntry::Imported(page, configuration, permission) => {
+                        PageTableEntry::Leaf(page, configuration, permission)
+                    }
+                    _ => return Err(Error::PageTableCorrupted()),
+                };
+                self.page_table_memory.set_entry(virtual_page_number, &accepted_entry)?;
+                self.entries[virtual_page_number] = accepted_entry;
+                Ok(())
+            }
+            _ => Err(Error::PageNotPendingAccept()),
+        }
+    }
+
+    fn translate(
+        &self, paging_system: PagingSystem, address: ConfidentialVmVirtualAddress,
+    ) -> Result<ConfidentialMemoryAddress> {
+        let virtual_page_number = paging_system.vpn(address, self.level);
+        match self.entries.get(virtual_page_number) {
+            Some(PageTableEntry::Pointer(next_page_table, _)) => next_page_table.translate(paging_system, address),
+            Some(PageTableEntry::Leaf(page, _, _)) => page
+                .address_at_offset(address.usize() % page.size().in_bytes())
+                .ok_or(Error::MemoryAccessAuthorization()),
+            _ => Err(Error::MemoryAccessAuthorization()),
+        }
+    }
+
+    fn confidential_memory_regions(
+        &self, paging_system: PagingSystem, base_address: usize, regions: &mut Vec<Range<usize>>,
+    ) {
+        self.entries.iter().enumerate().for_each(|(virtual_page_number, entry)| {
+            let address = base_address + paging_system.vpn_address(virtual_page_number, self.level);
+            match entry {
+                PageTableEntry::Pointer(next_page_table, _) => {
+                    next_page_table.confidential_memory_regions(paging_system, address, regions)
+                }
+                PageTableEntry::Leaf(_, _, _)
+                | PageTableEntry::Deferred(_, _, _, _)
+                | PageTableEntry::Imported(_, _, _) => {
+                    let end_address = address + paging_system.page_size(self.level).in_bytes();
+                    match regions.last_mut() {
+                        Some(region) if region.end == address => region.end = end_address,
+                        _ => regions.push(Range { start: address, end: end_address }),
+                    }
+                }
+                _ => {}
+            }
+        });
+    }
+
+    fn measure_pages<F: FnMut(usize, &PageDigest)>(
+        &self, paging_system: PagingSystem, base_address: usize, measure: &mut F,
+    ) -> Result<()> {
+        self.entries.iter().enumerate().try_for_each(|(virtual_page_number, entry)| {
+            let address = base_address + paging_system.vpn_address(virtual_page_number, self.level);
+            match entry {
+                PageTableEntry::Pointer(next_page_table, _) => {
+                    next_page_table.measure_pages(paging_system, address, measure)
+                }
+                PageTableEntry::Leaf(page, _, _) | PageTableEntry::Imported(page, _, _) => {
+                    measure(address, &digest_range(page.address(), page.size().in_bytes())?);
+                    Ok(())
+                }
+                PageTableEntry::Deferred(_, page_digest, _, _) => {
+                    measure(address, page_digest);
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn verify_image(
+        &self, paging_system: PagingSystem, base_address: usize, image_manifest: &ImageManifest,
+    ) -> Result<()> {
+        self.entries.iter().enumerate().try_for_each(|(virtual_page_number, entry)| {
+            let address = base_address + paging_system.vpn_address(virtual_page_number, self.level);
+            match entry {
+                PageTableEntry::Pointer(next_page_table, _) => {
+                    next_page_table.verify_image(paging_system, address, image_manifest)
+                }
+                PageTableEntry::Leaf(page, _, _) | PageTableEntry::Imported(page, _, _) => {
+                    image_manifest.verify_page(address, page)
+                }
+                PageTableEntry::Deferred(_, _, _, _) => Err(Error::ManifestVerificationFailed()),
+                _ => Ok(()),
+            }
+        })
+    }
+
     pub(super) fn address(&self) -> ConfidentialMemoryAddress {
         self.page_table_memory.start_address()
     }
@@ -148,23 +976,43 @@ impl PageTable {
         self.entries.get_mut(index)
     }
 
-    fn set_entry(&mut self, index: usize, entry: PageTableEntry) {
-        self.page_table_memory.set_entry(index, &entry);
+    fn set_entry(&mut self, index: usize, entry: PageTableEntry) -> Result<()> {
+        assure!(index < self.entries.len(), Error::PageTableIndexOutOfBounds(index))?;
+        self.page_table_memory.set_entry(index, &entry)?;
         let entry_to_remove = core::mem::replace(&mut self.entries[index], entry);
-        if let PageTableEntry::Leaf(page, _, _) = entry_to_remove {
-            MemoryTracker::release_page(page.deallocate());
+        Self::release_entry(entry_to_remove);
+        Ok(())
+    }
+
+    /// Returns to the memory tracker all pages owned by the page table entry. Leaf, Monitor, and Imported entries own a
+    /// page of the confidential VM's address space. Pointer entries own the entire subtree, i.e., the pages storing the
+    /// configuration of the lower-level page table and all pages owned by its entries. Other entries do not own pages.
+    fn release_entry(entry: PageTableEntry) {
+        match entry {
+            PageTableEntry::Leaf(page, _, _)
+            | PageTableEntry::Monitor(page, _, _)
+            | PageTableEntry::Imported(page, _, _) => MemoryTracker::release_page(page.deallocate()),
+            PageTableEntry::Pointer(mut page_table, _) => page_table.release(),
+            PageTableEntry::Shared(_, _, _) | PageTableEntry::Deferred(_, _, _, _) | PageTableEntry::NotValid => {}
         }
     }
+
+    /// Returns all pages owned by this page table to the memory tracker in a fixed order: first the subtrees of the
+    /// next-level page tables, then the leaf pages, and last the pages storing this page table's configuration. Thus,
+    /// every page is released exactly once and a page table is released only after all its children. Releasing an
+    /// already released page table does nothing, so the following drop of the page table is a no-op.
+    fn release(&mut self) {
+        let (pointers, leaves): (Vec<_>, Vec<_>) = self.entries.drain(..).partition(|entry| entry.is_pointer());
+        pointers.into_iter().for_each(|entry| Self::release_entry(entry));
+        leaves.into_iter().for_each(|entry| Self::release_entry(entry));
+        self.page_table_memory.release();
+    }
 }
 
 impl Drop for PageTable {
     fn drop(&mut self) {
-        // We must deallocate only a page owned by the Leaf entry because there are no other PageTableEntries but Leaf
-        // that own a page.
-        self.entries.drain(..).for_each(|entry| {
-            if let PageTableEntry::Leaf(page, _, _) = entry {
-                MemoryTracker::release_page(page.deallocate());
-            }
-        });
+        // The release order must not depend on the order in which the compiler drops the fields, so all pages,
+        // including the ones storing the configuration, are released explicitly before the fields are dropped.
+        self.release();
     }
 }
@@ -0,0 +1,280 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::core::memory_tracker::memory_tracker_self_test::record_releases;
+
+/// Pages of the security monitor's image that the self-test uses as the hypervisor's page table and page, because the
+/// image is located in the non-confidential memory.
+#[repr(C, align(4096))]
+struct HostPage([usize; 512]);
+
+static HOST_PAGES: spin::Mutex<[HostPage; 3]> =
+    spin::Mutex::new([HostPage([0; 512]), HostPage([0; 512]), HostPage([0; 512])]);
+
+/// Builds a page table in the confidential memory, maps a page, translates its address, and tears the page table
+/// down. Fails if the walk does not find the page or if not all pages return to the memory tracker.
+pub fn build_walk_teardown() -> Result<()> {
+    let free_pages = MemoryTracker::snapshot()?.free_pages;
+    {
+        let paging_system = PagingSystem::Sv57x4;
+        // the root page table spans 4 pages, every lower level and the mapped page take 1 page each.
+        let mut reservation = MemoryTracker::reserve(16)?;
+        let page_table = PageTable::empty(paging_system, paging_system.levels(), &mut reservation)?;
+        let mut root_page_table = RootPageTable { paging_system, page_table, reservation };
+        let address = ConfidentialVmVirtualAddress::new(0x8000_0000);
+        let page = root_page_table.reservation.acquire_continous_pages(1, PageSize::Size4KiB)?.remove(0).zeroize();
+        let page_address = page.address().usize();
+        root_page_table.page_table.map_private_page(paging_system, address, page)?;
+        let translated = root_page_table.translate(address)?.usize();
+        assure!(translated == page_address, Error::SelfTestFailed("page table walk"))?;
+        // overwriting the Pointer entry of the root page table returns the whole subtree to the memory tracker: the
+        // 4 lower-level page tables of Sv57x4 and the mapped page.
+        let free_pages_before_overwrite = MemoryTracker::snapshot()?.free_pages;
+        let virtual_page_number = paging_system.vpn(address, root_page_table.page_table.level);
+        root_page_table.page_table.set_entry(virtual_page_number, PageTableEntry::NotValid)?;
+        let reclaimed_pages = MemoryTracker::snapshot()?.free_pages - free_pages_before_overwrite;
+        assure!(reclaimed_pages == 5, Error::SelfTestFailed("page table subtree release"))?;
+    }
+    let is_released = MemoryTracker::snapshot()?.free_pages == free_pages;
+    assure!(is_released, Error::SelfTestFailed("page table teardown"))
+}
+
+/// Accesses the guest memory through a page table and checks the bounds of its root and the mapping of shared pages.
+/// Also checks the order in which a dropped page table releases its pages, translates a guest virtual address through
+/// a first-stage page table in the confidential VM's memory, checks that instructions reaching into shared pages are
+/// not trusted, and copies a page table from the hypervisor's memory and checks that later changes to it do not
+/// affect the copy. Fails if not all pages return to the memory tracker.
+pub fn run() -> Result<()> {
+    let free_pages = MemoryTracker::snapshot()?.free_pages;
+    {
+        let paging_system = PagingSystem::Sv57x4;
+        let mut reservation = MemoryTracker::reserve(16)?;
+        let page_table = PageTable::empty(paging_system, paging_system.levels(), &mut reservation)?;
+        let mut root_page_table = RootPageTable { paging_system, page_table, reservation };
+        let address = ConfidentialVmVirtualAddress::new(0x8000_0000);
+        let page = root_page_table.reservation.acquire_continous_pages(1, PageSize::Size4KiB)?.remove(0).zeroize();
+        root_page_table.page_table.map_private_page(paging_system, address, page)?;
+        // the guest memory is accessed only through aligned integers in readable or writable pages.
+        root_page_table.write_guest::<u32>(ConfidentialVmVirtualAddress::new(0x8000_0004), 0xace0_0ace)?;
+        let is_written = root_page_table.read_guest::<u64>(address)? == 0xace0_0ace_0000_0000;
+        let is_unaligned = root_page_table.read_guest::<u16>(ConfidentialVmVirtualAddress::new(0x8000_0001)).is_err();
+        let is_unmapped = root_page_table.read_guest::<u8>(ConfidentialVmVirtualAddress::new(0x8000_1000)).is_err();
+        assure!(is_written && is_unaligned && is_unmapped, Error::SelfTestFailed("guest memory access"))?;
+        // the root page table spans multiple pages, so the last entry is stored at the end of the last page.
+        let memory = &mut root_page_table.page_table.page_table_memory;
+        let last_index = memory.number_of_entries() - 1;
+        let is_bounded = memory.entry(last_index).is_some()
+            && memory.set_entry(last_index, &PageTableEntry::NotValid).is_ok()
+            && memory.entry(last_index + 1).is_none()
+            && memory.set_entry(last_index + 1, &PageTableEntry::NotValid).is_err();
+        assure!(is_bounded, Error::SelfTestFailed("page table bounds"))?;
+        // the image of the security monitor is located in the non-confidential memory but must never be shared.
+        let image =
+            crate::core::memory_tracker::MONITOR_IMAGE_RANGE.get().ok_or(Error::SelfTestFailed("monitor image"))?;
+        let request = crate::core::transformations::SharePageRequest::new(0x9000_0000)?;
+        let shared_page = SharedPage::new(image.start, false, request)?;
+        let is_rejected = matches!(root_page_table.map_shared_page(&shared_page), Err(Error::ShareIntoMonitorMemory()));
+        assure!(is_rejected, Error::SelfTestFailed("share into monitor memory"))?;
+        // a page the hypervisor exported read-only cannot be remapped writable, but can be remapped read-only. The
+        // page is never accessed, so it is mapped without the check of the monitor memory.
+        let page_table = &mut root_page_table.page_table;
+        let reservation = &mut root_page_table.reservation;
+        let request = crate::core::transformations::SharePageRequest::new(0x9000_0000)?;
+        let read_only_page = SharedPage::new(image.start, true, request)?;
+        page_table.map_shared_page(paging_system, reservation, &read_only_page)?;
+        let request = crate::core::transformations::SharePageRequest::new(0x9000_0000)?;
+        let writable_page = SharedPage::new(image.start, false, request)?;
+        let is_widened = matches!(
+            page_table.map_shared_page(paging_system, reservation, &writable_page),
+            Err(Error::SharePermissionWidened())
+        );
+        let is_kept = page_table.map_shared_page(paging_system, reservation, &read_only_page).is_ok();
+        assure!(is_widened && is_kept, Error::SelfTestFailed("shared page permission widening"))?;
+        // a deferred page belongs to the confidential VM, so it cannot be replaced by a shared page.
+        let deferred_address = ConfidentialVmVirtualAddress::new(0xa000_0000);
+        let virtual_page_number = paging_system.vpn(deferred_address, page_table.level);
+        let hypervisor_address = NonConfidentialMemoryAddress::new_page(image.start, PageSize::Size4KiB)?;
+        let configuration = PageTableConfiguration::shared_page_configuration();
+        let permission = PageTablePermission::shared_page_permission(true);
+        let page_digest = Box::new([0; crate::core::attestation::MEASUREMENT_SIZE]);
+        let deferred_entry = PageTableEntry::Deferred(hypervisor_address, page_digest, configuration, permission);
+        page_table.set_entry(virtual_page_number, deferred_entry)?;
+        let request = crate::core::transformations::SharePageRequest::new(deferred_address.usize())?;
+        let shared_page = SharedPage::new(image.start, true, request)?;
+        let is_rejected = matches!(
+            page_table.map_shared_page(paging_system, reservation, &shared_page),
+            Err(Error::CannotShareConfidentialLeaf())
+        );
+        let is_kept = page_table.entry(paging_system, deferred_address).is_some_and(|entry| entry.is_deferred());
+        assure!(is_rejected && is_kept, Error::SelfTestFailed("share over deferred page"))?;
+    }
+    {
+        // dropping a page table releases every page exactly once: the subtrees first, then the leaf pages, and
+        // last the pages storing the page table itself.
+        let paging_system = PagingSystem::Sv57x4;
+        let mut reservation = MemoryTracker::reserve(16)?;
+        let mut page_table = PageTable::empty(paging_system, paging_system.levels(), &mut reservation)?;
+        let page = reservation.acquire_continous_pages(1, PageSize::Size4KiB)?.remove(0).zeroize();
+        let leaf_address = page.address().usize();
+        page_table.map_private_page(paging_system, ConfidentialVmVirtualAddress::new(0x8000_0000), page)?;
+        // start addresses of the page tables from the root to the page table at the lowest level.
+        let mut table_addresses = Vec::new();
+        let mut table = Some(&page_table);
+        while let Some(current_table) = table {
+            table_addresses.push(current_table.page_table_memory.start_address().usize());
+            table = current_table.entries.iter().find_map(|entry| match entry {
+                PageTableEntry::Pointer(next_table, _) => Some(next_table.as_ref()),
+                _ => None,
+            });
+        }
+        let released = record_releases(|| core::mem::drop(page_table));
+        let position = |address: usize| released.iter().position(|released_address| *released_address == address);
+        // Sv57x4 has 5 levels. Its root page table spans 4 pages, every lower level and the mapped page take 1 page
+        // each.
+        let is_released_once = released.len() == 4 + (table_addresses.len() - 1) + 1
+            && released.iter().enumerate().all(|(i, address)| !released[..i].contains(address));
+        let is_ordered = table_addresses.len() == 5
+            && table_addresses.windows(2).all(|pair| position(pair[1]) < position(pair[0]))
+            && table_addresses.last().is_some_and(|lowest_table| position(leaf_address) < position(*lowest_table))
+            && table_addresses.iter().chain([&leaf_address]).all(|address| position(*address).is_some());
+        assure!(is_released_once && is_ordered, Error::SelfTestFailed("page table release order"))?;
+    }
+    {
+        // an Sv39 first-stage page table built in the confidential VM's memory: the root page table points to a
+        // page table whose leaf maps a 2MiB superpage, of which one 4KiB page is mapped by the second stage.
+        const SV39: usize = 8 << 60;
+        let paging_system = PagingSystem::Sv57x4;
+        let mut reservation = MemoryTracker::reserve(16)?;
+        let page_table = PageTable::empty(paging_system, paging_system.levels(), &mut reservation)?;
+        let mut root_page_table = RootPageTable { paging_system, page_table, reservation };
+        let (root_table, next_table, superpage, data_page) = (0x8000_0000, 0x8000_1000, 0x8020_0000, 0x8020_3000);
+        let mut data_page_address = 0;
+        for address in [root_table, next_table, data_page] {
+            let page = root_page_table.reservation.acquire_continous_pages(1, PageSize::Size4KiB)?.remove(0).zeroize();
+            if address == data_page {
+                data_page_address = page.address().usize();
+            }
+            let address = ConfidentialVmVirtualAddress::new(address);
+            root_page_table.page_table.map_private_page(paging_system, address, page)?;
+        }
+        let pointer = PageTableBits::Valid.mask() | PageTableAddress::encode(next_table);
+        let leaf = PageTableBits::Valid.mask()
+            | PageTableBits::Read.mask()
+            | PageTableBits::Write.mask()
+            | PageTableAddress::encode(superpage);
+        // the guest virtual address 0x4020_3008 is translated by the entries with index 1 at both levels.
+        root_page_table.write_guest::<usize>(ConfidentialVmVirtualAddress::new(root_table + 8), pointer)?;
+        root_page_table.write_guest::<usize>(ConfidentialVmVirtualAddress::new(next_table + 8), leaf)?;
+        let vsatp = SV39 | (root_table >> 12);
+        let is_translated = root_page_table
+            .two_stage_translate(vsatp, 0x4020_3008)
+            .is_ok_and(|address| address.usize() == data_page_address + 8);
+        // the superpage is mapped by the first stage, but its other pages are not mapped by the second stage.
+        let is_unmapped_rejected = root_page_table.two_stage_translate(vsatp, 0x4020_4000).is_err();
+        let is_first_stage_fault =
+            matches!(root_page_table.two_stage_translate(vsatp, 0x4060_3008), Err(Error::GuestPageFault(_)));
+        let is_bare = root_page_table
+            .two_stage_translate(0, data_page + 8)
+            .is_ok_and(|address| address.usize() == data_page_address + 8);
+        assure!(
+            is_translated && is_unmapped_rejected && is_first_stage_fault && is_bare,
+            Error::SelfTestFailed("two-stage translation")
+        )?;
+        // an instruction is trusted only if all its bytes are in pages owned by the confidential VM. The shared
+        // page following the data page is never accessed, so it is backed by the image of the security
+        // monitor.
+        let image =
+            crate::core::memory_tracker::MONITOR_IMAGE_RANGE.get().ok_or(Error::SelfTestFailed("monitor image"))?;
+        let request = crate::core::transformations::SharePageRequest::new(data_page + 0x1000)?;
+        let shared_page = SharedPage::new(image.start, true, request)?;
+        let reservation = &mut root_page_table.reservation;
+        root_page_table.page_table.map_shared_page(paging_system, reservation, &shared_page)?;
+        // lw a0, 0(a1) at the start and c.lw a0, 0(a1) at the end of the data page.
+        root_page_table.write_guest::<u32>(ConfidentialVmVirtualAddress::new(data_page), 0x0005_a503)?;
+        root_page_table.write_guest::<u16>(ConfidentialVmVirtualAddress::new(data_page + 0xffe), 0x4188)?;
+        let is_confidential = root_page_table.assure_confidential_instruction(vsatp, 0x4020_3000).is_ok()
+            && root_page_table.assure_confidential_instruction(vsatp, 0x4020_3ffe).is_ok();
+        // the first half of lw a0, 0(a1) at the end of the data page, so the second half is in the shared page.
+        root_page_table.write_guest::<u16>(ConfidentialVmVirtualAddress::new(data_page + 0xffe), 0xa503)?;
+        let is_crossing_rejected = matches!(
+            root_page_table.assure_confidential_instruction(vsatp, 0x4020_3ffe),
+            Err(Error::ExecutingFromSharedMemory())
+        );
+        let is_shared_rejected = matches!(
+            root_page_table.assure_confidential_instruction(vsatp, 0x4020_4000),
+            Err(Error::ExecutingFromSharedMemory())
+        );
+        assure!(
+            is_confidential && is_crossing_rejected && is_shared_rejected,
+            Error::SelfTestFailed("instruction in shared memory")
+        )?;
+    }
+    {
+        // the hypervisor rewrites its page table after the security monitor read it. The copy keeps the entries
+        // read before, while entries inconsistent with the level or pointing to the confidential memory are
+        // rejected as they are decoded. Pages of the security monitor's image stand in for the hypervisor's memory.
+        let paging_system = PagingSystem::Sv57x4;
+        let mut host_pages = HOST_PAGES.lock();
+        let table_address = host_pages[0].0.as_ptr() as usize;
+        let page_address = host_pages[1].0.as_ptr() as usize;
+        let upper_table_address = host_pages[2].0.as_ptr() as usize;
+        let leaf_entry = |address: usize| {
+            PageTableBits::Valid.mask()
+                | PageTableBits::Read.mask()
+                | PageTableBits::Write.mask()
+                | PageTableBits::User.mask()
+                | PageTableAddress::encode(address)
+        };
+        host_pages[1].0.fill(0xace);
+        host_pages[0].0.fill(0);
+        host_pages[0].0[..2].fill(leaf_entry(page_address));
+        host_pages[2].0.fill(0);
+        host_pages[2].0[0] = PageTableBits::Valid.mask() | PageTableAddress::encode(table_address);
+        let mut reservation = MemoryTracker::reserve(8)?;
+        let mut copy = |address: usize, level: PageTableLevel| {
+            let address = NonConfidentialMemoryAddress::new(address)?;
+            let copy_mode = PageTableCopyMode::Eager;
+            PageTable::copy_from_non_confidential_memory(
+                address,
+                paging_system,
+                level,
+                copy_mode,
+                &mut reservation,
+                &mut BTreeMap::new(),
+            )
+        };
+        // a pointer is allowed above the lowest level, so the table at the lowest level is copied with its leaves.
+        let is_pointer_copied =
+            copy(upper_table_address, PageTableLevel::Level2).is_ok_and(|page_table| match &page_table.entries[0] {
+                PageTableEntry::Pointer(lower_table, _) => lower_table.entries[..2]
+                    .iter()
+                    .all(|entry| matches!(entry, PageTableEntry::Leaf(page, _, _) if page.read::<usize>(0) == 0xace)),
+                _ => false,
+            });
+        let page_table = copy(table_address, PageTableLevel::Level1)?;
+        host_pages[0].0[0] = PageTableBits::Valid.mask() | PageTableAddress::encode(table_address);
+        host_pages[0].0[1] = 0;
+        let is_kept = page_table.entries.iter().enumerate().all(|(index, entry)| {
+            let is_copied = match entry {
+                PageTableEntry::Leaf(page, _, _) => index < 2 && page.read::<usize>(0) == 0xace,
+                PageTableEntry::NotValid => index >= 2,
+                _ => false,
+            };
+            is_copied && page_table.page_table_memory.entry(index) == Some(entry.encode())
+        });
+        let confidential_address = page_table.page_table_memory.start_address().usize();
+        core::mem::drop(page_table);
+        // a pointer at the lowest level and a leaf mapping the confidential memory.
+        let is_pointer_rejected =
+            matches!(copy(table_address, PageTableLevel::Level1), Err(Error::InvalidPageTableEntryLevel()));
+        host_pages[0].0[0] = leaf_entry(confidential_address);
+        let is_confidential_rejected = copy(table_address, PageTableLevel::Level1).is_err();
+        assure!(
+            is_pointer_copied && is_kept && is_pointer_rejected && is_confidential_rejected,
+            Error::SelfTestFailed("page table copy")
+        )?;
+    }
+    let is_released = MemoryTracker::snapshot()?.free_pages == free_pages;
+    assure!(is_released, Error::SelfTestFailed("page table teardown"))
+}
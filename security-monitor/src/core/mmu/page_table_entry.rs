@@ -1,14 +1,25 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+use crate::core::attestation::PageDigest;
 use crate::core::memory_tracker::{Allocated, NonConfidentialMemoryAddress, Page};
 use crate::core::mmu::page_table::PageTable;
+use crate::error::{Error, Result};
 use alloc::boxed::Box;
 
 pub(super) enum PageTableEntry {
     Pointer(Box<PageTable>, PageTableConfiguration),
     Leaf(Box<Page<Allocated>>, PageTableConfiguration, PageTablePermission),
     Shared(NonConfidentialMemoryAddress, PageTableConfiguration, PageTablePermission),
+    // a page owned by the security monitor that the confidential VM can read but neither it nor the hypervisor can
+    // remap or unshare.
+    Monitor(Box<Page<Allocated>>, PageTableConfiguration, PageTablePermission),
+    // a leaf whose content is still in the hypervisor's memory. It is copied to the confidential memory when the
+    // confidential VM accesses it for the first time and only if its digest equals the one measured at the creation.
+    Deferred(NonConfidentialMemoryAddress, Box<PageDigest>, PageTableConfiguration, PageTablePermission),
+    // a leaf whose content has been copied to the confidential memory but that the confidential VM has not accepted
+    // yet. It becomes a Leaf when the confidential VM accepts it.
+    Imported(Box<Page<Allocated>>, PageTableConfiguration, PageTablePermission),
     NotValid,
 }
 
@@ -20,7 +31,8 @@ impl PageTableEntry {
                     | PageTableAddress::encode(page_table.address().usize())
                     | configuration.encode()
             }
-            PageTableEntry::Leaf(page, configuration, permissions) => {
+            PageTableEntry::Leaf(page, configuration, permissions)
+            | PageTableEntry::Monitor(page, configuration, permissions) => {
                 PageTableBits::Valid.mask()
                     | PageTableAddress::encode(page.address().usize())
                     | configuration.encode()
@@ -32,7 +44,73 @@ impl PageTableEntry {
                     | configuration.encode()
                     | permissions.encode()
             }
-            PageTableEntry::NotValid => 0,
+            // the hardware must not translate addresses of pages not yet imported or accepted, so that the access traps
+            // in the security monitor.
+            PageTableEntry::Deferred(_, _, _, _) | PageTableEntry::Imported(_, _, _) | PageTableEntry::NotValid => 0,
+        }
+    }
+}
+
+// Read-only inspection of installed entries, used when walking page tables.
+#[allow(unused)]
+impl PageTableEntry {
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, PageTableEntry::NotValid)
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        matches!(self, PageTableEntry::Leaf(_, _, _))
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, PageTableEntry::Pointer(_, _))
+    }
+
+    pub fn is_shared(&self) -> bool {
+        matches!(self, PageTableEntry::Shared(_, _, _))
+    }
+
+    pub fn is_deferred(&self) -> bool {
+        matches!(self, PageTableEntry::Deferred(_, _, _, _))
+    }
+
+    pub fn is_imported(&self) -> bool {
+        matches!(self, PageTableEntry::Imported(_, _, _))
+    }
+
+    pub fn is_monitor(&self) -> bool {
+        matches!(self, PageTableEntry::Monitor(_, _, _))
+    }
+
+    /// Returns the address in the hypervisor's memory that a shared entry maps to.
+    pub fn as_shared_address(&self) -> Option<NonConfidentialMemoryAddress> {
+        match self {
+            PageTableEntry::Shared(address, _, _) => Some(*address),
+            _ => None,
+        }
+    }
+
+    /// Returns the access permissions of entries that map a page. Pointers and invalid entries have no permissions.
+    pub fn permission(&self) -> Option<&PageTablePermission> {
+        match self {
+            PageTableEntry::Leaf(_, _, permission)
+            | PageTableEntry::Shared(_, _, permission)
+            | PageTableEntry::Monitor(_, _, permission)
+            | PageTableEntry::Deferred(_, _, _, permission)
+            | PageTableEntry::Imported(_, _, permission) => Some(permission),
+            PageTableEntry::Pointer(_, _) | PageTableEntry::NotValid => None,
+        }
+    }
+
+    pub fn configuration(&self) -> Option<&PageTableConfiguration> {
+        match self {
+            PageTableEntry::Pointer(_, configuration)
+            | PageTableEntry::Leaf(_, configuration, _)
+            | PageTableEntry::Shared(_, configuration, _)
+            | PageTableEntry::Monitor(_, configuration, _)
+            | PageTableEntry::Deferred(_, _, configuration, _)
+            | PageTableEntry::Imported(_, configuration, _) => Some(configuration),
+            PageTableEntry::NotValid => None,
         }
     }
 }
@@ -82,22 +160,69 @@ impl PageTableAddress {
     }
 }
 
+/// Access rights granted by a leaf entry. The 2nd-stage translation treats all accesses of the confidential VM as
+/// user-mode accesses, so leaves mapping pages of the confidential VM must be accessible to the user.
+#[derive(Copy, Clone)]
 pub(super) struct PageTablePermission {
     can_read: bool,
     can_write: bool,
     can_execute: bool,
+    is_accessible_to_user: bool,
 }
 
 impl PageTablePermission {
-    pub fn shared_page_permission() -> Self {
-        Self { can_read: true, can_write: true, can_execute: false }
+    /// Returns the permission granting the given access rights. Fails for the combination of write without read
+    /// permission, which the RISC-V privileged specification reserves.
+    pub fn new(can_read: bool, can_write: bool, can_execute: bool, is_accessible_to_user: bool) -> Result<Self> {
+        assure_not!(can_write && !can_read, Error::ReservedPagePermission())?;
+        Ok(Self { can_read, can_write, can_execute, is_accessible_to_user })
     }
 
-    pub fn decode(raw_entry: usize) -> Self {
+    pub fn shared_page_permission(is_read_only: bool) -> Self {
+        Self { can_read: true, can_write: !is_read_only, can_execute: false, is_accessible_to_user: true }
+    }
+
+    pub fn private_page_permission() -> Self {
+        Self { can_read: true, can_write: true, can_execute: true, is_accessible_to_user: true }
+    }
+
+    pub fn monitor_page_permission() -> Self {
+        Self { can_read: true, can_write: false, can_execute: false, is_accessible_to_user: true }
+    }
+
+    pub fn can_read(&self) -> bool {
+        self.can_read
+    }
+
+    pub fn can_write(&self) -> bool {
+        self.can_write
+    }
+
+    #[allow(unused)]
+    pub fn can_execute(&self) -> bool {
+        self.can_execute
+    }
+
+    #[allow(unused)]
+    pub fn is_accessible_to_user(&self) -> bool {
+        self.is_accessible_to_user
+    }
+
+    /// Returns true if this permission grants no access that the other permission does not grant.
+    pub fn is_within(&self, other: &Self) -> bool {
+        (!self.can_read || other.can_read)
+            && (!self.can_write || other.can_write)
+            && (!self.can_execute || other.can_execute)
+            && (!self.is_accessible_to_user || other.is_accessible_to_user)
+    }
+
+    /// Decodes the permission of a leaf entry. Fails if the entry uses the reserved combination of permissions.
+    pub fn decode(raw_entry: usize) -> Result<Self> {
         let can_read = PageTableBits::Read.is_set(raw_entry);
         let can_write = PageTableBits::Write.is_set(raw_entry);
         let can_execute = PageTableBits::Execute.is_set(raw_entry);
-        Self { can_read, can_write, can_execute }
+        let is_accessible_to_user = PageTableBits::User.is_set(raw_entry);
+        Self::new(can_read, can_write, can_execute, is_accessible_to_user)
     }
 
     pub fn encode(&self) -> usize {
@@ -111,12 +236,15 @@ impl PageTablePermission {
         if self.can_execute {
             encoded_value = encoded_value | PageTableBits::Execute.mask();
         }
+        if self.is_accessible_to_user {
+            encoded_value = encoded_value | PageTableBits::User.mask();
+        }
         encoded_value
     }
 }
 
+#[derive(Copy, Clone)]
 pub(super) struct PageTableConfiguration {
-    is_accessible_to_user: bool,
     was_accessed: bool,
     is_global_mapping: bool,
     is_dirty: bool,
@@ -124,26 +252,30 @@ pub(super) struct PageTableConfiguration {
 
 impl PageTableConfiguration {
     pub fn empty() -> Self {
-        Self { is_accessible_to_user: false, was_accessed: false, is_global_mapping: false, is_dirty: false }
+        Self { was_accessed: false, is_global_mapping: false, is_dirty: false }
     }
 
     pub fn shared_page_configuration() -> Self {
-        Self { is_accessible_to_user: true, was_accessed: true, is_global_mapping: false, is_dirty: true }
+        Self { was_accessed: true, is_global_mapping: false, is_dirty: true }
+    }
+
+    pub fn private_page_configuration() -> Self {
+        Self { was_accessed: true, is_global_mapping: false, is_dirty: true }
+    }
+
+    pub fn monitor_page_configuration() -> Self {
+        Self { was_accessed: true, is_global_mapping: false, is_dirty: false }
     }
 
     pub fn decode(raw_entry: usize) -> Self {
-        let is_accessible_to_user = PageTableBits::User.is_set(raw_entry);
         let was_accessed = PageTableBits::Accessed.is_set(raw_entry);
         let is_global_mapping = PageTableBits::Global.is_set(raw_entry);
         let is_dirty = PageTableBits::Dirty.is_set(raw_entry);
-        Self { is_accessible_to_user, was_accessed, is_global_mapping, is_dirty }
+        Self { was_accessed, is_global_mapping, is_dirty }
     }
 
     pub fn encode(&self) -> usize {
         let mut encoded_value = 0;
-        if self.is_accessible_to_user {
-            encoded_value = encoded_value | PageTableBits::User.mask();
-        }
         if self.was_accessed {
             encoded_value = encoded_value | PageTableBits::Accessed.mask();
         }
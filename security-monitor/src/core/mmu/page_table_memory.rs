@@ -3,12 +3,12 @@
 // SPDX-License-Identifier: Apache-2.0
 use super::PagingSystem;
 use crate::core::memory_tracker::{
-    Allocated, ConfidentialMemoryAddress, MemoryTracker, NonConfidentialMemoryAddress, Page,
+    Allocated, ConfidentialMemoryAddress, MemoryReservation, MemoryTracker, NonConfidentialMemoryAddress, Page,
 };
 use crate::core::mmu::page_table_entry::PageTableEntry;
 use crate::core::mmu::paging_system::PageTableLevel;
 use crate::core::mmu::PageSize;
-use crate::error::Error;
+use crate::error::{Error, Result};
 use alloc::vec::Vec;
 use core::ops::Range;
 
@@ -23,26 +23,32 @@ pub(super) struct PageTableMemory {
 impl PageTableMemory {
     const PAGE_SIZE: PageSize = PageSize::Size4KiB;
 
+    /// Copies the page table located in the non-confidential memory. The page table must be aligned to its size. This
+    /// matters for the root of the 2nd-stage page table, which spans multiple 4KiB pages, e.g., 16KiB in Sv57x4.
     pub(super) fn copy_from_non_confidential_memory(
         address: NonConfidentialMemoryAddress, paging_system: PagingSystem, level: PageTableLevel,
-    ) -> Result<Self, Error> {
+    ) -> Result<Self> {
+        assure!(address.usize() % paging_system.size_in_bytes(level) == 0, Error::MisalignedPageTableRoot())?;
         let number_of_pages = paging_system.configuration_pages(level);
         let pages = MemoryTracker::acquire_continous_pages(number_of_pages, Self::PAGE_SIZE)?
             .into_iter()
             .enumerate()
             .map(|(i, page)| {
-                let address = NonConfidentialMemoryAddress::new(address.usize() + i * page.size().in_bytes())?;
+                let address = address.checked_add(i * page.size().in_bytes())?;
                 page.copy_from_non_confidential_memory(address)
             })
-            .collect::<Result<Vec<Page<Allocated>>, Error>>()?;
+            .collect::<Result<Vec<Page<Allocated>>>>()?;
         let number_of_entries = paging_system.entries(level);
         let entry_size = paging_system.entry_size();
         Ok(Self { pages, number_of_entries, entry_size })
     }
 
-    pub(super) fn empty(paging_system: PagingSystem, level: PageTableLevel) -> Result<Self, Error> {
+    pub(super) fn empty(
+        paging_system: PagingSystem, level: PageTableLevel, reservation: &mut MemoryReservation,
+    ) -> Result<Self> {
         let number_of_pages = paging_system.configuration_pages(level);
-        let pages = MemoryTracker::acquire_continous_pages(number_of_pages, Self::PAGE_SIZE)?
+        let pages = reservation
+            .acquire_continous_pages(number_of_pages, Self::PAGE_SIZE)?
             .into_iter()
             .map(|f| f.zeroize())
             .collect();
@@ -64,37 +70,48 @@ impl PageTableMemory {
         Range { start: 0, end: self.number_of_entries }
     }
 
+    /// Returns the raw entry or None if the index is outside of the page table.
     pub(super) fn entry(&self, index: usize) -> Option<usize> {
-        self.resolve_index(index).and_then(|(page_id, index_in_page)| {
-            self.pages.get(page_id).and_then(|page| Some(page.read::<usize>(self.entry_size * index_in_page)))
-        })
+        self.resolve_index(index).map(|(page, offset)| page.read::<usize>(offset))
     }
 
-    pub(super) fn set_entry(&mut self, index: usize, entry: &PageTableEntry) {
-        self.resolve_index(index).and_then(|(page_id, index_in_page)| {
-            let offset = self.entry_size * index_in_page;
-            let value = entry.encode();
-            self.pages.get(page_id).and_then(|page| Some(page.write::<usize>(offset, value)))
-        });
+    /// Writes the encoding of the entry. Fails if the index is outside of the page table, so a bug computing the index
+    /// never overwrites the confidential memory next to the page table.
+    pub(super) fn set_entry(&mut self, index: usize, entry: &PageTableEntry) -> Result<()> {
+        let (page, offset) = self.resolve_index(index).ok_or(Error::PageTableIndexOutOfBounds(index))?;
+        page.write::<usize>(offset, entry.encode());
+        Ok(())
     }
 
-    fn resolve_index(&self, index: usize) -> Option<(usize, usize)> {
-        if index < self.number_of_entries {
-            // we can do this calculations because 1) pages are continous 2) vector stores pages
-            // in the correct order. Thus, we treat them as a continous array of memory.
-            let entries_per_page = Self::PAGE_SIZE.in_bytes() / self.entry_size;
-            let page_id = index / entries_per_page;
-            let index_in_page = index % entries_per_page;
-            Some((page_id, index_in_page))
-        } else {
-            None
+    /// Returns the page storing the entry with the given index and the offset of the entry within this page.
+    fn resolve_index(&self, index: usize) -> Option<(&Page<Allocated>, usize)> {
+        if index >= self.number_of_entries {
+            return None;
+        }
+        // we can do this calculations because 1) pages are continous 2) vector stores pages
+        // in the correct order. Thus, we treat them as a continous array of memory.
+        let entries_per_page = Self::PAGE_SIZE.in_bytes() / self.entry_size;
+        let page = self.pages.get(index / entries_per_page)?;
+        let offset = self.entry_size * (index % entries_per_page);
+        // the entry must lie entirely within the page, also when the entry size does not divide the page size.
+        match offset + self.entry_size <= page.size().in_bytes() {
+            true => Some((page, offset)),
+            false => None,
+        }
+    }
+
+    /// Returns the pages storing the page table configuration to the memory tracker. The owning page table calls it
+    /// after releasing its entries. Subsequent calls, including the one from Drop, do nothing.
+    pub(super) fn release(&mut self) {
+        let deallocated_pages: Vec<_> = self.pages.drain(..).map(|p| p.deallocate()).collect();
+        if !deallocated_pages.is_empty() {
+            MemoryTracker::release_pages(deallocated_pages);
         }
     }
 }
 
 impl Drop for PageTableMemory {
     fn drop(&mut self) {
-        let deallocated_pages: Vec<_> = self.pages.drain(..).map(|p| p.deallocate()).collect();
-        MemoryTracker::release_pages(deallocated_pages);
+        self.release();
     }
 }
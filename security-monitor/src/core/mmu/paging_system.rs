@@ -25,6 +25,13 @@ impl PagingSystem {
         }
     }
 
+    /// Returns the size of the confidential VM's physical address space translated by this paging system.
+    pub fn address_space_size(&self) -> usize {
+        match self {
+            PagingSystem::Sv57x4 => 1 << 59,
+        }
+    }
+
     pub fn levels(&self) -> PageTableLevel {
         match self {
             PagingSystem::Sv57x4 => PageTableLevel::Level5,
@@ -70,6 +77,20 @@ impl PagingSystem {
         }
     }
 
+    /// Returns the offset of the address space region that is mapped by the page table entry with the given virtual
+    /// page number. This is the inverse of `vpn()`.
+    pub fn vpn_address(&self, virtual_page_number: usize, level: PageTableLevel) -> usize {
+        match self {
+            PagingSystem::Sv57x4 => match level {
+                PageTableLevel::Level5 => virtual_page_number << 48,
+                PageTableLevel::Level4 => virtual_page_number << 39,
+                PageTableLevel::Level3 => virtual_page_number << 30,
+                PageTableLevel::Level2 => virtual_page_number << 21,
+                PageTableLevel::Level1 => virtual_page_number << 12,
+            },
+        }
+    }
+
     pub fn page_size(&self, level: PageTableLevel) -> PageSize {
         match level {
             PageTableLevel::Level5 => PageSize::Size128TiB,
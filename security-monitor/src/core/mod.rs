@@ -1,12 +1,15 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+pub mod attestation;
+pub mod bounded_lock;
 pub mod control_data;
 pub mod hart;
 mod heap;
 mod initialization;
 pub mod memory_tracker;
 pub mod mmu;
-mod panic;
+mod nested_trap;
+pub mod panic;
 pub mod pmp;
 pub mod transformations;
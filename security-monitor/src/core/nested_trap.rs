@@ -0,0 +1,24 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use core::sync::atomic::AtomicUsize;
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// Address at which `nested_trap_asm` resumes after a nested trap instead of panicking, or zero. Only the self-test
+/// sets it, to check that a fault of the security monitor reaches the nested trap handler. The handler clears it and
+/// resumes with mcause in a0, clobbering t0.
+#[export_name = "nested_trap_recovery"]
+static NESTED_TRAP_RECOVERY: AtomicUsize = AtomicUsize::new(0);
+
+/// Handles a trap taken while the security monitor executes, which always indicates a bug of the security monitor,
+/// e.g., an access to an invalid address. The trap entries store the state of the interrupted hart before they set
+/// the trap vector to `nested_trap_asm`, so the nested trap cannot overwrite this state. The security monitor cannot
+/// trust its state anymore, so it panics, which clears the confidential memory and applies the panic policy.
+#[no_mangle]
+extern "C" fn nested_trap(cause: usize, address: usize, value: usize) -> ! {
+    panic!("Nested trap in the security monitor: mcause={:x}, mepc={:x}, mtval={:x}", cause, address, value);
+}
+
+core::arch::global_asm!(include_str!("nested_trap.S"));
@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::error::{Error, Result};
+use core::sync::atomic::Ordering;
+
+/// Executes an illegal instruction with the trap vector set to `nested_trap_asm`, as the trap entries set it, and
+/// checks that the trap reaches the nested trap handler. The self-test runs before the security monitor handles any
+/// trap, so it restores OpenSBI's trap vector afterwards.
+pub fn run() -> Result<()> {
+    const ILLEGAL_INSTRUCTION: usize = 2;
+    let cause: usize;
+    unsafe {
+        core::arch::asm!(
+            "csrr t2, mtvec",
+            "la t1, nested_trap_asm",
+            "csrw mtvec, t1",
+            "la t1, 1f",
+            "sd t1, 0(t3)",
+            "unimp",
+            "1:",
+            "csrw mtvec, t2",
+            in("t3") NESTED_TRAP_RECOVERY.as_ptr(),
+            out("a0") cause,
+            out("t0") _,
+            out("t1") _,
+            out("t2") _,
+        )
+    };
+    let is_recovered = NESTED_TRAP_RECOVERY.load(Ordering::SeqCst) == 0;
+    assure!(is_recovered && cause == ILLEGAL_INSTRUCTION, Error::SelfTestFailed("nested trap"))
+}
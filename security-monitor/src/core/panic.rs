@@ -2,7 +2,67 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::core::memory_tracker::CONFIDENTIAL_MEMORY_RANGE;
+use crate::core::transformations::TrapReason;
 use crate::error::NOT_INITIALIZED_CONFIDENTIAL_MEMORY;
+use crate::ACE_EXT_ID;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
+
+/// Set when a physical hart panicked and the security monitor must not serve the hypervisor anymore.
+static HALTED: AtomicBool = AtomicBool::new(false);
+
+/// Action taken after a panic once the confidential memory has been cleared. It is selected at compile time with the
+/// `panic_reset_platform` and `panic_notify_host` features. Without them, the panicking hart spins.
+#[derive(Clone, Copy, PartialEq)]
+enum PanicPolicy {
+    /// Stops the panicking hart, so its state can be inspected with a debugger.
+    Spin,
+    /// Resets the platform with the SBI system reset extension, so the platform is never left in an ambiguous state.
+    ResetPlatform,
+    /// Stops the panicking hart and rejects all later calls that the hypervisor makes to the security monitor on other
+    /// physical harts, so the hypervisor learns that the security monitor halted. Physical harts executing
+    /// confidential VMs stop on their next trap.
+    NotifyHostAndHalt,
+}
+
+impl PanicPolicy {
+    /// Returns the policy selected by the enabled features. Resetting the platform takes precedence.
+    const fn configured() -> Self {
+        if cfg!(feature = "panic_reset_platform") {
+            Self::ResetPlatform
+        } else if cfg!(feature = "panic_notify_host") {
+            Self::NotifyHostAndHalt
+        } else {
+            Self::Spin
+        }
+    }
+}
+
+/// Returns true if the security monitor halted after a panic and must not handle the trap. The confidential memory
+/// was cleared, so a trap from a confidential VM is never handled and calls to the security monitor are rejected.
+/// Other traps of the hypervisor and non-confidential VMs are handled by OpenSBI, which does not depend on the state
+/// of the security monitor, so they are still served.
+pub fn is_blocked(is_confidential_vm: bool, trap_reason: &TrapReason) -> bool {
+    blocks(HALTED.load(Ordering::SeqCst), is_confidential_vm, trap_reason)
+}
+
+fn blocks(is_halted: bool, is_confidential_vm: bool, trap_reason: &TrapReason) -> bool {
+    let is_monitor_call =
+        matches!(trap_reason, TrapReason::HsEcall(ACE_EXT_ID, _) | TrapReason::VsEcall(ACE_EXT_ID, _));
+    is_halted && (is_confidential_vm || is_monitor_call)
+}
+
+/// Stops the physical hart. A confidential VM that executed on it can neither be resumed nor returned to the hypervisor
+/// after a panic, because its state was cleared with the confidential memory.
+pub fn stop_hart() -> ! {
+    loop {
+        unsafe {
+            core::arch::asm!("wfi");
+        }
+    }
+}
 
 /// This piece of code executes on a panic. Panic is a runtime error that
 /// indicates an implementation bug from which we cannot recover. Examples are
@@ -10,6 +70,7 @@ use crate::error::NOT_INITIALIZED_CONFIDENTIAL_MEMORY;
 /// expect().
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
+    crate::core::bounded_lock::mark_panicked();
     debug!("Ops security monitor panicked!");
     if let Some(p) = info.location() {
         debug!("Line {}, file {}: {}", p.line(), p.file(), info.message().unwrap());
@@ -22,10 +83,15 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
         unsafe { (address as *mut u8).write_volatile(0) };
     });
 
-    // sleep or loop forever since there is nothing else we can do
-    loop {
-        unsafe {
-            core::arch::asm!("wfi");
-        }
+    match PanicPolicy::configured() {
+        PanicPolicy::Spin => {}
+        PanicPolicy::ResetPlatform => unsafe {
+            use opensbi_sys::{SBI_SRST_RESET_REASON_SYSFAIL, SBI_SRST_RESET_TYPE_COLD_REBOOT};
+            opensbi_sys::sbi_system_reset(SBI_SRST_RESET_TYPE_COLD_REBOOT, SBI_SRST_RESET_REASON_SYSFAIL)
+        },
+        PanicPolicy::NotifyHostAndHalt => HALTED.store(true, Ordering::SeqCst),
     }
+
+    // sleep or loop forever since there is nothing else we can do
+    stop_hart()
 }
@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::error::{Error, Result};
+
+/// Checks that after a panic the security monitor stops handling its calls and the traps of confidential VMs, while
+/// the traps handled by OpenSBI are still served.
+pub fn run() -> Result<()> {
+    // traps handled by OpenSBI are served after a panic, calls to the security monitor and traps of confidential VMs
+    // are not.
+    let calls = [TrapReason::HsEcall(ACE_EXT_ID, 1010), TrapReason::VsEcall(ACE_EXT_ID, 1000)];
+    let other_traps = [TrapReason::Interrupt, TrapReason::HsEcall(0x54494D45, 0), TrapReason::LoadAccessFault];
+    let all_traps = || calls.iter().chain(other_traps.iter());
+    let is_served = all_traps().all(|trap| !blocks(false, false, trap) && !blocks(false, true, trap));
+    let is_halted = calls.iter().all(|trap| blocks(true, false, trap))
+        && other_traps.iter().all(|trap| !blocks(true, false, trap))
+        && all_traps().all(|trap| blocks(true, true, trap));
+    assure!(is_served && is_halted, Error::SelfTestFailed("halted security monitor"))
+}
@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to accept the page that the security monitor imported on the first access. The
+/// address is the start of the imported page.
+pub struct AcceptPageRequest {
+    confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
+}
+
+impl AcceptPageRequest {
+    pub fn new(address: usize) -> Self {
+        Self { confidential_vm_virtual_address: ConfidentialVmVirtualAddress::new(address) }
+    }
+
+    pub fn confidential_vm_virtual_address(&self) -> ConfidentialVmVirtualAddress {
+        self.confidential_vm_virtual_address
+    }
+}
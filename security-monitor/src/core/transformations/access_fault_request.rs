@@ -0,0 +1,26 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// Load or store of the confidential hart that the physical memory protection rejected after the address had been
+/// translated. Unlike a guest page fault, it never denotes MMIO, so it is reported back to the confidential VM instead
+/// of being forwarded to the hypervisor.
+pub struct AccessFaultRequest {
+    cause: usize,
+    // the faulting virtual address, like stval reported by the hardware.
+    address: usize,
+}
+
+impl AccessFaultRequest {
+    pub fn new(cause: usize, address: usize) -> Self {
+        Self { cause, address }
+    }
+
+    pub fn cause(&self) -> usize {
+        self.cause
+    }
+
+    pub fn address(&self) -> usize {
+        self.address
+    }
+}
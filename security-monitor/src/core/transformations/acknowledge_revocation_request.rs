@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to confirm that it stopped using the shared page revoked by the hypervisor.
+pub struct AcknowledgeRevocationRequest {
+    confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
+}
+
+impl AcknowledgeRevocationRequest {
+    pub fn new(address: usize) -> Self {
+        Self { confidential_vm_virtual_address: ConfidentialVmVirtualAddress::new(address) }
+    }
+
+    pub fn confidential_vm_virtual_address(&self) -> ConfidentialVmVirtualAddress {
+        self.confidential_vm_virtual_address
+    }
+}
@@ -0,0 +1,25 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to obtain the public part of the attestation key followed by the certificate chain
+/// endorsing it. Both are written into the buffer located in the confidential VM's memory.
+pub struct AttestationKeyRequest {
+    buffer_address: ConfidentialVmVirtualAddress,
+    buffer_size: usize,
+}
+
+impl AttestationKeyRequest {
+    pub fn new(buffer_address: usize, buffer_size: usize) -> Self {
+        Self { buffer_address: ConfidentialVmVirtualAddress::new(buffer_address), buffer_size }
+    }
+
+    pub fn buffer_address(&self) -> ConfidentialVmVirtualAddress {
+        self.buffer_address
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
@@ -0,0 +1,24 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// Call of the confidential VM to the `sbi_debug_console_write_byte` function of the SBI debug console extension. The
+/// byte is passed in a0, so the security monitor writes it to its console without reading the confidential VM's memory
+/// or sharing a buffer with the hypervisor.
+pub struct ConsoleWriteByteRequest {
+    byte: u8,
+}
+
+impl ConsoleWriteByteRequest {
+    pub const EXTID: usize = 0x4442434E;
+    pub const WRITE_BYTE_FID: usize = 2;
+
+    /// Only the lowest 8 bits of a0 are written, the upper bits are ignored as required by the SBI specification.
+    pub fn new(a0: usize) -> Self {
+        Self { byte: (a0 & 0xff) as u8 }
+    }
+
+    pub fn byte(&self) -> u8 {
+        self.byte
+    }
+}
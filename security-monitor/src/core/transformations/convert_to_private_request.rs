@@ -0,0 +1,40 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::mmu::{PageSize, RootPageTable};
+use crate::core::transformations::{ConfidentialVmVirtualAddress, EsmRequest};
+use crate::error::{Error, Result};
+
+/// Request from the confidential VM to convert a region of previously shared 4KiB pages back to confidential pages. The
+/// security monitor maps fresh zeroed pages from the confidential memory at the same addresses, so the content of the
+/// shared pages never becomes visible as confidential data.
+#[derive(PartialEq, Clone, Copy)]
+pub struct ConvertToPrivateRequest {
+    confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
+    number_of_pages: usize,
+}
+
+impl ConvertToPrivateRequest {
+    pub const MAX_NUMBER_OF_PAGES: usize = EsmRequest::DEFAULT_MAX_SHARED_PAGES;
+
+    pub fn new(address: usize, number_of_pages: usize) -> Result<Self> {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        assure!(address % page_size == 0, Error::AddressNotAligned())?;
+        // a confidential VM never shares more pages, so the cost of the conversion is bounded before it starts.
+        assure!((1..=Self::MAX_NUMBER_OF_PAGES).contains(&number_of_pages), Error::InvalidNumberOfPages())?;
+        let confidential_vm_virtual_address = ConfidentialVmVirtualAddress::new(address);
+        let size = number_of_pages.checked_mul(page_size).ok_or(Error::AddressOverflow())?;
+        let last_address = confidential_vm_virtual_address.checked_add(size - page_size)?;
+        let is_reserved = (address..=last_address.usize()).contains(&RootPageTable::MONITOR_PAGE_ADDRESS);
+        assure_not!(is_reserved, Error::ReservedAddress())?;
+        Ok(Self { confidential_vm_virtual_address, number_of_pages })
+    }
+
+    pub fn confidential_vm_virtual_address(&self) -> ConfidentialVmVirtualAddress {
+        self.confidential_vm_virtual_address
+    }
+
+    pub fn number_of_pages(&self) -> usize {
+        self.number_of_pages
+    }
+}
@@ -0,0 +1,26 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// Value of the monotonic counter and whether the counter is persistent. A counter that is not persistent starts from
+/// zero whenever the confidential VM is created, so it does not survive the hypervisor destroying and recreating the
+/// confidential VM nor a reboot of the platform. A confidential VM that relies on the counter to detect rollbacks of
+/// its persistent state must not trust a counter that is not persistent.
+pub struct CounterResult {
+    value: u64,
+    is_persistent: bool,
+}
+
+impl CounterResult {
+    pub fn new(value: u64, is_persistent: bool) -> Self {
+        Self { value, is_persistent }
+    }
+
+    pub fn value(&self) -> usize {
+        self.value as usize
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.is_persistent
+    }
+}
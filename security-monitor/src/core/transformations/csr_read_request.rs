@@ -0,0 +1,41 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::hart::GpRegister;
+
+/// Request to emulate the read of a CSR that the confidential VM cannot access directly, e.g., a counter whose value
+/// must be virtualized by the security monitor. Some CSRs are also written, e.g., writing stopei claims an interrupt.
+#[derive(Clone, Copy)]
+pub struct CsrReadRequest {
+    csr: usize,
+    result_gpr: GpRegister,
+    instruction_length: usize,
+    is_write: bool,
+}
+
+impl CsrReadRequest {
+    pub fn new(csr: usize, result_gpr: GpRegister, instruction_length: usize) -> Self {
+        Self { csr, result_gpr, instruction_length, is_write: false }
+    }
+
+    /// Creates the request to write the CSR and to read its value from before the write.
+    pub fn new_write(csr: usize, result_gpr: GpRegister, instruction_length: usize) -> Self {
+        Self { csr, result_gpr, instruction_length, is_write: true }
+    }
+
+    pub fn csr(&self) -> usize {
+        self.csr
+    }
+
+    pub fn result_gpr(&self) -> GpRegister {
+        self.result_gpr
+    }
+
+    pub fn instruction_length(&self) -> usize {
+        self.instruction_length
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.is_write
+    }
+}
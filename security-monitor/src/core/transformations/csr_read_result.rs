@@ -0,0 +1,29 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::hart::GpRegister;
+use crate::core::transformations::CsrReadRequest;
+
+pub struct CsrReadResult {
+    value: usize,
+    result_gpr: GpRegister,
+    instruction_length: usize,
+}
+
+impl CsrReadResult {
+    pub fn new(request: CsrReadRequest, value: usize) -> Self {
+        Self { value, result_gpr: request.result_gpr(), instruction_length: request.instruction_length() }
+    }
+
+    pub fn value(&self) -> usize {
+        self.value
+    }
+
+    pub fn result_gpr(&self) -> GpRegister {
+        self.result_gpr
+    }
+
+    pub fn instruction_length(&self) -> usize {
+        self.instruction_length
+    }
+}
@@ -0,0 +1,39 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::ConfidentialVmId;
+
+/// The hypervisor's request to copy the state of a confidential hart to its buffer. It exists only in builds with the
+/// `insecure_debug` feature because it exposes the confidential hart's registers to the hypervisor.
+#[derive(PartialEq)]
+pub struct DumpHartStateRequest {
+    confidential_vm_id: ConfidentialVmId,
+    confidential_hart_id: usize,
+    buffer_address: usize,
+    buffer_size: usize,
+}
+
+impl DumpHartStateRequest {
+    pub fn new(
+        confidential_vm_id: usize, confidential_hart_id: usize, buffer_address: usize, buffer_size: usize,
+    ) -> Self {
+        let confidential_vm_id = ConfidentialVmId::new(confidential_vm_id);
+        Self { confidential_vm_id, confidential_hart_id, buffer_address, buffer_size }
+    }
+
+    pub fn confidential_vm_id(&self) -> ConfidentialVmId {
+        self.confidential_vm_id
+    }
+
+    pub fn confidential_hart_id(&self) -> usize {
+        self.confidential_hart_id
+    }
+
+    pub fn buffer_address(&self) -> usize {
+        self.buffer_address
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
@@ -1,22 +1,121 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::hart::HartState;
+use crate::core::control_data::{SbiPolicy, SharingPolicy};
+use crate::core::hart::{GpRegister, HartState};
+use crate::core::mmu::PageTableCopyMode;
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
 use riscv::register::hgatp::Hgatp;
 
 pub struct EsmRequest {
     hgatp: Hgatp,
     hart_state: HartState,
+    copy_mode: PageTableCopyMode,
+    sharing_policy: SharingPolicy,
+    sbi_policy: SbiPolicy,
+    number_of_harts: usize,
+    image_manifest_address: Option<ConfidentialVmVirtualAddress>,
+    fdt_address: Option<ConfidentialVmVirtualAddress>,
+    reserved_pages: usize,
 }
 
 impl EsmRequest {
-    pub fn new(from_state: &HartState) -> Self {
+    // the VM requests that its pages are copied to the confidential memory on the first access.
+    const DEFERRED_IMPORT_FLAG: usize = 1 << 0;
+    // the VM requests that only the SBI extensions selected by the mask starting at bit 8 of the flags are forwarded to
+    // the hypervisor. Without this flag, calls of all extensions are forwarded.
+    const RESTRICTED_SBI_FLAG: usize = 1 << 2;
+    const SBI_EXTENSIONS_SHIFT: usize = 8;
+    // the upper half of the flags argument is the number of 4KiB pages of the confidential memory that the VM requests
+    // to reserve for its allocations after the creation.
+    const RESERVED_PAGES_SHIFT: usize = 32;
+    // the number of pages the VM can share with the hypervisor at the same time when the VM does not request a lower
+    // limit. No VM can share more pages.
+    pub(super) const DEFAULT_MAX_SHARED_PAGES: usize = 1024;
+
+    /// Reads the arguments of the ESM call. Fails if the VM requests a feature the security monitor does not know or if
+    /// the declared shareable region does not fit in the address space, so a malformed request is rejected before any
+    /// confidential memory is allocated.
+    pub fn new(from_state: &HartState) -> Result<Self> {
         let hart_state = HartState::from_existing(0, from_state);
         let hgatp = Hgatp::from(from_state.hgatp);
-        Self { hgatp, hart_state }
+        let flags = from_state.gpr(GpRegister::a0) & ((1 << Self::RESERVED_PAGES_SHIFT) - 1);
+        let reserved_pages = from_state.gpr(GpRegister::a0) >> Self::RESERVED_PAGES_SHIFT;
+        let sbi_extensions_mask = ((1 << SbiPolicy::NUMBER_OF_EXTENSIONS) - 1) << Self::SBI_EXTENSIONS_SHIFT;
+        let known_flags = Self::DEFERRED_IMPORT_FLAG | Self::RESTRICTED_SBI_FLAG | sbi_extensions_mask;
+        assure!(flags & !known_flags == 0, Error::InvalidEsmFlags(flags))?;
+        let copy_mode = match flags & Self::DEFERRED_IMPORT_FLAG {
+            0 => PageTableCopyMode::Eager,
+            _ => PageTableCopyMode::Deferred,
+        };
+        let max_shared_pages = match from_state.gpr(GpRegister::a1) {
+            0 => Self::DEFAULT_MAX_SHARED_PAGES,
+            max_shared_pages => core::cmp::min(max_shared_pages, Self::DEFAULT_MAX_SHARED_PAGES),
+        };
+        // the VM can share pages only within the declared region. The VM that declares no region can share any page.
+        let shareable_region = match from_state.gpr(GpRegister::a3) {
+            0 => 0..usize::MAX,
+            size => {
+                let start = from_state.gpr(GpRegister::a2);
+                start..start.checked_add(size).ok_or(Error::InvalidShareableRegion())?
+            }
+        };
+        let sharing_policy = SharingPolicy::new(max_shared_pages, shareable_region);
+        let sbi_policy = match flags & Self::RESTRICTED_SBI_FLAG {
+            0 => SbiPolicy::unrestricted(),
+            _ => SbiPolicy::restricted((flags & sbi_extensions_mask) >> Self::SBI_EXTENSIONS_SHIFT),
+        };
+        // the VM that does not declare the number of harts runs on a single hart.
+        let number_of_harts = core::cmp::max(from_state.gpr(GpRegister::a4), 1);
+        // the VM that does not supply the address of the image manifest signed by its owner is launched from any image.
+        // The manifest is a part of the VM's image, so its address is the VM's physical address.
+        let image_manifest_address = match from_state.gpr(GpRegister::a5) {
+            0 => None,
+            address => Some(ConfidentialVmVirtualAddress::new(address)),
+        };
+        // the VM passes the address of the device tree it received at boot in t0, because the other arguments occupy
+        // all argument registers. The VM that does not supply it has no device tree measured.
+        let fdt_address = match from_state.gpr(GpRegister::t0) {
+            0 => None,
+            address => Some(ConfidentialVmVirtualAddress::new(address)),
+        };
+        Ok(Self {
+            hgatp,
+            hart_state,
+            copy_mode,
+            sharing_policy,
+            sbi_policy,
+            number_of_harts,
+            image_manifest_address,
+            fdt_address,
+            reserved_pages,
+        })
     }
 
-    pub fn into(self) -> (Hgatp, HartState) {
-        (self.hgatp, self.hart_state)
+    pub fn into(
+        self,
+    ) -> (
+        Hgatp,
+        HartState,
+        PageTableCopyMode,
+        SharingPolicy,
+        SbiPolicy,
+        usize,
+        Option<ConfidentialVmVirtualAddress>,
+        Option<ConfidentialVmVirtualAddress>,
+        usize,
+    ) {
+        (
+            self.hgatp,
+            self.hart_state,
+            self.copy_mode,
+            self.sharing_policy,
+            self.sbi_policy,
+            self.number_of_harts,
+            self.image_manifest_address,
+            self.fdt_address,
+            self.reserved_pages,
+        )
     }
 }
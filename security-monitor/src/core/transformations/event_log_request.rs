@@ -0,0 +1,25 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to read the log of events it recorded. The log is written into the buffer located
+/// in the confidential VM's memory.
+pub struct EventLogRequest {
+    buffer_address: ConfidentialVmVirtualAddress,
+    buffer_size: usize,
+}
+
+impl EventLogRequest {
+    pub fn new(buffer_address: usize, buffer_size: usize) -> Self {
+        Self { buffer_address: ConfidentialVmVirtualAddress::new(buffer_address), buffer_size }
+    }
+
+    pub fn buffer_address(&self) -> ConfidentialVmVirtualAddress {
+        self.buffer_address
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
@@ -0,0 +1,31 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
+
+/// Request from the confidential VM to read the faults that the security monitor detected while completing its
+/// operations asynchronously. The security monitor moves the oldest records as (code, address, context) triples of
+/// 64-bit values into the buffer located in the confidential VM's memory.
+pub struct FaultRecordsRequest {
+    buffer_address: ConfidentialVmVirtualAddress,
+    number_of_entries: usize,
+}
+
+impl FaultRecordsRequest {
+    pub const ENTRY_SIZE: usize = 3 * core::mem::size_of::<u64>();
+
+    pub fn new(buffer_address: usize, number_of_entries: usize) -> Result<Self> {
+        assure!(buffer_address % core::mem::size_of::<u64>() == 0, Error::AddressNotAligned())?;
+        let buffer_address = ConfidentialVmVirtualAddress::new(buffer_address);
+        Ok(Self { buffer_address, number_of_entries })
+    }
+
+    pub fn buffer_address(&self) -> ConfidentialVmVirtualAddress {
+        self.buffer_address
+    }
+
+    pub fn number_of_entries(&self) -> usize {
+        self.number_of_entries
+    }
+}
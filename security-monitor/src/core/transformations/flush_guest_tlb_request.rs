@@ -0,0 +1,28 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
+use core::ops::Range;
+
+/// Request from the confidential VM to remove cached second-stage translations of its physical address range, e.g.,
+/// after converting shared pages to private. The request without a range flushes all translations of the VM.
+pub struct FlushGuestTlbRequest {
+    range: Option<Range<usize>>,
+}
+
+impl FlushGuestTlbRequest {
+    pub fn new(address: usize, size: usize) -> Result<Self> {
+        assure!(size > 0, Error::InvalidNumberOfPages())?;
+        let end_address = ConfidentialVmVirtualAddress::new(address).checked_add(size)?;
+        Ok(Self { range: Some(address..end_address.usize()) })
+    }
+
+    pub fn all() -> Self {
+        Self { range: None }
+    }
+
+    pub fn range(&self) -> Option<&Range<usize>> {
+        self.range.as_ref()
+    }
+}
@@ -1,17 +1,22 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::hart::GpRegister;
+use crate::core::hart::{Endianness, GpRegister};
 
 #[derive(PartialEq)]
 pub struct GuestLoadPageFaultRequest {
     instruction_length: usize,
     result_gpr: GpRegister,
+    access_width: usize,
+    is_signed: bool,
+    endianness: Endianness,
 }
 
 impl GuestLoadPageFaultRequest {
-    pub fn new(instruction_length: usize, result_gpr: GpRegister) -> Self {
-        Self { instruction_length, result_gpr }
+    pub fn new(
+        instruction_length: usize, result_gpr: GpRegister, access_width: usize, is_signed: bool, endianness: Endianness,
+    ) -> Self {
+        Self { instruction_length, result_gpr, access_width, is_signed, endianness }
     }
 
     pub fn instruction_length(&self) -> usize {
@@ -21,4 +26,15 @@ impl GuestLoadPageFaultRequest {
     pub fn result_gpr(&self) -> GpRegister {
         self.result_gpr
     }
+    pub fn access_width(&self) -> usize {
+        self.access_width
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.is_signed
+    }
+
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
 }
@@ -11,10 +11,17 @@ pub struct GuestLoadPageFaultResult {
 }
 
 impl GuestLoadPageFaultResult {
+    /// Only the loaded value comes from the hypervisor. The result register, the access width, and the instruction
+    /// length, which advances mepc, come from the request recorded when the confidential hart faulted, so the
+    /// hypervisor cannot make the confidential hart skip or re-execute instructions.
     pub fn new(hart_state: &HartState, request: GuestLoadPageFaultRequest) -> Self {
         Self {
             result_gpr: request.result_gpr(),
-            value: hart_state.gpr(request.result_gpr()),
+            value: request.endianness().from_canonical(
+                hart_state.gpr(request.result_gpr()),
+                request.access_width(),
+                request.is_signed(),
+            ),
             instruction_length: request.instruction_length(),
         }
     }
@@ -1,18 +1,31 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::GuestStorePageFaultResult;
+use crate::error::{Error, Result};
 
 #[derive(PartialEq)]
 pub struct GuestStorePageFaultRequest {
     instruction_length: usize,
+    // address of the store instruction that trapped.
+    mepc: usize,
 }
 
 impl GuestStorePageFaultRequest {
-    pub fn new(instruction_length: usize) -> Self {
-        Self { instruction_length }
+    pub fn new(instruction_length: usize, mepc: usize) -> Self {
+        Self { instruction_length, mepc }
     }
 
     pub fn instruction_length(&self) -> usize {
         self.instruction_length
     }
+
+    pub fn mepc(&self) -> usize {
+        self.mepc
+    }
+
+    /// Fails if the hypervisor emulated an instruction of a different length than the store instruction that trapped.
+    pub fn assure_completed_by(&self, result: &GuestStorePageFaultResult) -> Result<()> {
+        assure!(result.instruction_length() == self.instruction_length, Error::UnexpectedResult())
+    }
 }
@@ -1,16 +1,19 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+use crate::core::hart::HartState;
 use crate::core::transformations::GuestStorePageFaultRequest;
 
-#[derive(PartialEq)]
 pub struct GuestStorePageFaultResult {
     instruction_length: usize,
 }
 
 impl GuestStorePageFaultResult {
-    pub fn new(request: GuestStorePageFaultRequest) -> Self {
-        Self { instruction_length: request.instruction_length() }
+    /// The instruction length is the distance by which the hypervisor advanced sepc from the address of the store
+    /// instruction, i.e., the length of the instruction that the hypervisor emulated. The security monitor compares it
+    /// with the length it decoded when the confidential hart faulted.
+    pub fn new(hart_state: &HartState, request: &GuestStorePageFaultRequest) -> Self {
+        Self { instruction_length: hart_state.sepc.wrapping_sub(request.mepc()) }
     }
 
     pub fn instruction_length(&self) -> usize {
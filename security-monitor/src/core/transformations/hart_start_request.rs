@@ -0,0 +1,41 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::SbiRequest;
+
+/// Call of the confidential VM to start one of its harts with the SBI HSM extension. The hypervisor starts the hart,
+/// but only harts that the confidential VM declared when entering the secure mode exist, so the security monitor
+/// rejects the others without involving the hypervisor. The hypervisor cannot set the state of a confidential hart, so
+/// the security monitor sets the state in which the hart starts.
+pub struct HartStartRequest {
+    confidential_hart_id: usize,
+    start_address: usize,
+    opaque: usize,
+    sbi_request: SbiRequest,
+}
+
+impl HartStartRequest {
+    pub const EXTID: usize = 0x48534D;
+    pub const HART_START_FID: usize = 0;
+    pub const HART_STOP_FID: usize = 1;
+
+    pub fn new(confidential_hart_id: usize, start_address: usize, opaque: usize, sbi_request: SbiRequest) -> Self {
+        Self { confidential_hart_id, start_address, opaque, sbi_request }
+    }
+
+    pub fn confidential_hart_id(&self) -> usize {
+        self.confidential_hart_id
+    }
+
+    pub fn start_address(&self) -> usize {
+        self.start_address
+    }
+
+    pub fn opaque(&self) -> usize {
+        self.opaque
+    }
+
+    pub fn into_sbi_request(self) -> SbiRequest {
+        self.sbi_request
+    }
+}
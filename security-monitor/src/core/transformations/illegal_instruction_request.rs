@@ -0,0 +1,26 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::CsrReadRequest;
+
+/// Instruction that the confidential hart could not execute, either because it is illegal or because the hardware
+/// requires the hypervisor to emulate it. The request carries the CSR access that the security monitor can emulate, if
+/// the instruction is one.
+pub struct IllegalInstructionRequest {
+    instruction: usize,
+    csr_read_request: Option<CsrReadRequest>,
+}
+
+impl IllegalInstructionRequest {
+    pub fn new(instruction: usize, csr_read_request: Option<CsrReadRequest>) -> Self {
+        Self { instruction, csr_read_request }
+    }
+
+    pub fn instruction(&self) -> usize {
+        self.instruction
+    }
+
+    pub fn csr_read_request(&self) -> Option<&CsrReadRequest> {
+        self.csr_read_request.as_ref()
+    }
+}
@@ -0,0 +1,73 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::{ConsoleWriteByteRequest, SbiRequest};
+
+/// Call from the confidential VM to one of the legacy SBI v0.1 extensions, in which the extension id identifies the
+/// function. Legacy calls are never forwarded to the hypervisor as they are. The supported ones are translated into
+/// calls to the extensions that replaced them, so the hypervisor services the same interface for all confidential VMs.
+pub struct LegacySbiRequest {
+    extension_id: usize,
+    a0: usize,
+}
+
+impl LegacySbiRequest {
+    const SET_TIMER_EXTID: usize = 0x00;
+    const CONSOLE_PUTCHAR_EXTID: usize = 0x01;
+    const SHUTDOWN_EXTID: usize = 0x08;
+    const TIME_EXTID: usize = 0x54494D45;
+    const TIME_SET_TIMER_FID: usize = 0;
+    const SRST_EXTID: usize = 0x53525354;
+    const SRST_SYSTEM_RESET_FID: usize = 0;
+    const SRST_SHUTDOWN_TYPE: usize = 0;
+    const SRST_NO_REASON: usize = 0;
+
+    pub fn new(extension_id: usize, a0: usize) -> Self {
+        Self { extension_id, a0 }
+    }
+
+    /// Returns true if the extension id belongs to the legacy extensions 0x00-0x0F reserved by the SBI specification.
+    pub fn is_legacy(extension_id: usize) -> bool {
+        extension_id <= 0x0F
+    }
+
+    /// Returns the id of the extension replacing the legacy extension, or None if the security monitor does not support
+    /// the legacy extension.
+    pub fn replacement_extension_id(extension_id: usize) -> Option<usize> {
+        match extension_id {
+            Self::SET_TIMER_EXTID => Some(Self::TIME_EXTID),
+            Self::CONSOLE_PUTCHAR_EXTID => Some(ConsoleWriteByteRequest::EXTID),
+            Self::SHUTDOWN_EXTID => Some(Self::SRST_EXTID),
+            _ => None,
+        }
+    }
+
+    /// Returns the debug console write of the byte that the legacy putchar passes in a0. The security monitor writes it
+    /// to its own console like the single-byte debug console write, without involving the hypervisor.
+    pub fn console_write_byte_request(&self) -> Option<ConsoleWriteByteRequest> {
+        match self.extension_id {
+            Self::CONSOLE_PUTCHAR_EXTID => Some(ConsoleWriteByteRequest::new(self.a0)),
+            _ => None,
+        }
+    }
+
+    /// Returns the call to the extension replacing the legacy one, or None if the security monitor does not support
+    /// the legacy call, e.g., getchar and the IPI and remote fence calls that take pointers to hart masks. The legacy
+    /// putchar is not forwarded, see `console_write_byte_request`.
+    pub fn replacement(&self) -> Option<SbiRequest> {
+        match self.extension_id {
+            Self::SET_TIMER_EXTID => Some(Self::call(Self::TIME_EXTID, Self::TIME_SET_TIMER_FID, self.a0, 0)),
+            Self::SHUTDOWN_EXTID => Some(Self::call(
+                Self::SRST_EXTID,
+                Self::SRST_SYSTEM_RESET_FID,
+                Self::SRST_SHUTDOWN_TYPE,
+                Self::SRST_NO_REASON,
+            )),
+            _ => None,
+        }
+    }
+
+    fn call(extension_id: usize, function_id: usize, a0: usize, a1: usize) -> SbiRequest {
+        SbiRequest::new(extension_id, function_id, a0, a1, 0, 0, 0, 0)
+    }
+}
@@ -0,0 +1,31 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to record the event described by the data located in its memory and to extend the
+/// measurement register with the hash of this description.
+pub struct LogEventRequest {
+    description_address: ConfidentialVmVirtualAddress,
+    description_size: usize,
+    measurement_index: usize,
+}
+
+impl LogEventRequest {
+    pub fn new(description_address: usize, description_size: usize, measurement_index: usize) -> Self {
+        let description_address = ConfidentialVmVirtualAddress::new(description_address);
+        Self { description_address, description_size, measurement_index }
+    }
+
+    pub fn description_address(&self) -> ConfidentialVmVirtualAddress {
+        self.description_address
+    }
+
+    pub fn description_size(&self) -> usize {
+        self.description_size
+    }
+
+    pub fn measurement_index(&self) -> usize {
+        self.measurement_index
+    }
+}
@@ -0,0 +1,36 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
+
+/// Request from the confidential VM to read its current measurement registers without the signed attestation report.
+/// The registers, optionally followed by the event log, are written into the buffer in the confidential VM's memory.
+pub struct MeasurementsRequest {
+    buffer_address: ConfidentialVmVirtualAddress,
+    buffer_size: usize,
+    include_event_log: bool,
+}
+
+impl MeasurementsRequest {
+    // the VM requests that the event log follows the measurement registers in the report.
+    const INCLUDE_EVENT_LOG_FLAG: usize = 1 << 0;
+
+    pub fn new(buffer_address: usize, buffer_size: usize, flags: usize) -> Result<Self> {
+        assure!(flags & !Self::INCLUDE_EVENT_LOG_FLAG == 0, Error::InvalidMeasurementsFlags(flags))?;
+        let include_event_log = flags & Self::INCLUDE_EVENT_LOG_FLAG != 0;
+        Ok(Self { buffer_address: ConfidentialVmVirtualAddress::new(buffer_address), buffer_size, include_event_log })
+    }
+
+    pub fn buffer_address(&self) -> ConfidentialVmVirtualAddress {
+        self.buffer_address
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    pub fn include_event_log(&self) -> bool {
+        self.include_event_log
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::ConfidentialVmId;
+
+/// The hypervisor's request to copy the memory audit records of a confidential VM to its buffer. It exists only in
+/// builds with the `memory_audit` feature.
+pub struct MemoryAuditRequest {
+    confidential_vm_id: ConfidentialVmId,
+    buffer_address: usize,
+    buffer_size: usize,
+}
+
+impl MemoryAuditRequest {
+    pub fn new(confidential_vm_id: usize, buffer_address: usize, buffer_size: usize) -> Self {
+        Self { confidential_vm_id: ConfidentialVmId::new(confidential_vm_id), buffer_address, buffer_size }
+    }
+
+    pub fn confidential_vm_id(&self) -> ConfidentialVmId {
+        self.confidential_vm_id
+    }
+
+    pub fn buffer_address(&self) -> usize {
+        self.buffer_address
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
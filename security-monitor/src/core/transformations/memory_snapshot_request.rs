@@ -0,0 +1,23 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// The hypervisor's request to copy a snapshot of the confidential memory to its buffer.
+pub struct MemorySnapshotRequest {
+    buffer_address: usize,
+    buffer_size: usize,
+}
+
+impl MemorySnapshotRequest {
+    pub fn new(buffer_address: usize, buffer_size: usize) -> Self {
+        Self { buffer_address, buffer_size }
+    }
+
+    pub fn buffer_address(&self) -> usize {
+        self.buffer_address
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
@@ -10,11 +10,16 @@ pub struct MmioStoreRequest {
     instruction: usize,
     gpr: GpRegister,
     gpr_value: usize,
+    // address of the store instruction, from which the hypervisor advances the program counter once it emulated the
+    // store.
+    sepc: usize,
 }
 
 impl MmioStoreRequest {
-    pub fn new(code: usize, stval: usize, htval: usize, instruction: usize, gpr: GpRegister, gpr_value: usize) -> Self {
-        Self { code, stval, htval, instruction, gpr, gpr_value: gpr_value }
+    pub fn new(
+        code: usize, stval: usize, htval: usize, instruction: usize, gpr: GpRegister, gpr_value: usize, sepc: usize,
+    ) -> Self {
+        Self { code, stval, htval, instruction, gpr, gpr_value: gpr_value, sepc }
     }
 
     pub fn code(&self) -> usize {
@@ -40,4 +45,8 @@ impl MmioStoreRequest {
     pub fn gpr_value(&self) -> usize {
         self.gpr_value
     }
+
+    pub fn sepc(&self) -> usize {
+        self.sepc
+    }
 }
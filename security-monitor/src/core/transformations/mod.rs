@@ -1,41 +1,111 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+pub use accept_page_request::AcceptPageRequest;
+pub use access_fault_request::AccessFaultRequest;
+pub use acknowledge_revocation_request::AcknowledgeRevocationRequest;
+pub use attestation_key_request::AttestationKeyRequest;
+pub use console_write_byte_request::ConsoleWriteByteRequest;
+pub use convert_to_private_request::ConvertToPrivateRequest;
+pub use counter_request::CounterRequest;
+pub use counter_result::CounterResult;
+pub use csr_read_request::CsrReadRequest;
+pub use csr_read_result::CsrReadResult;
+#[cfg(feature = "insecure_debug")]
+pub use dump_hart_state_request::DumpHartStateRequest;
 pub use esm_request::EsmRequest;
+pub use event_log_request::EventLogRequest;
+pub use fault_records_request::FaultRecordsRequest;
+pub use flush_guest_tlb_request::FlushGuestTlbRequest;
 pub use guest_load_page_fault_request::GuestLoadPageFaultRequest;
 pub use guest_load_page_fault_result::GuestLoadPageFaultResult;
 pub use guest_store_page_fault_request::GuestStorePageFaultRequest;
 pub use guest_store_page_fault_result::GuestStorePageFaultResult;
+pub use hart_start_request::HartStartRequest;
+pub use illegal_instruction_request::IllegalInstructionRequest;
 pub use interrupt_request::InterruptRequest;
+pub use legacy_sbi_request::LegacySbiRequest;
+pub use log_event_request::LogEventRequest;
+pub use measurements_request::MeasurementsRequest;
+#[cfg(feature = "memory_audit")]
+pub use memory_audit_request::MemoryAuditRequest;
+pub use memory_regions_request::MemoryRegionsRequest;
+pub use memory_snapshot_request::MemorySnapshotRequest;
 pub use mmio_load_request::MmioLoadRequest;
 pub use mmio_store_request::MmioStoreRequest;
 pub use opensbi_request::OpensbiRequest;
+pub use page_import_request::PageImportRequest;
+pub use page_status_request::PageStatusRequest;
+pub use pin_private_request::PinPrivateRequest;
+pub use remap_page_request::RemapPageRequest;
+pub use reservation_limit_request::ReservationLimitRequest;
 pub use resume_request::ResumeRequest;
+pub use revoke_shared_page_request::RevokeSharedPageRequest;
+pub use sbi_base_request::SbiBaseRequest;
 pub use sbi_request::SbiRequest;
 pub use sbi_result::SbiResult;
 pub use sbi_vm_request::SbiVmRequest;
+pub use sealing_request::SealingRequest;
+pub use secure_timer_request::SecureTimerRequest;
 pub use share_page_request::{ConfidentialVmVirtualAddress, SharePageRequest};
 pub use share_page_result::SharePageResult;
+pub use shared_memcpy_request::SharedMemcpyRequest;
 pub use terminate_request::TerminateRequest;
 pub use trap_reason::TrapReason;
+pub use vm_info_result::VmInfoResult;
 
+mod accept_page_request;
+mod access_fault_request;
+mod acknowledge_revocation_request;
+mod attestation_key_request;
+mod console_write_byte_request;
+mod convert_to_private_request;
+mod counter_request;
+mod counter_result;
+mod csr_read_request;
+mod csr_read_result;
+#[cfg(feature = "insecure_debug")]
+mod dump_hart_state_request;
 mod esm_request;
+mod event_log_request;
+mod fault_records_request;
+mod flush_guest_tlb_request;
 mod guest_load_page_fault_request;
 mod guest_load_page_fault_result;
 mod guest_store_page_fault_request;
 mod guest_store_page_fault_result;
+mod hart_start_request;
+mod illegal_instruction_request;
 mod interrupt_request;
+mod legacy_sbi_request;
+mod log_event_request;
+mod measurements_request;
+#[cfg(feature = "memory_audit")]
+mod memory_audit_request;
+mod memory_regions_request;
+mod memory_snapshot_request;
 mod mmio_load_request;
 mod mmio_store_request;
 mod opensbi_request;
+mod page_import_request;
+mod page_status_request;
+mod pin_private_request;
+mod remap_page_request;
+mod reservation_limit_request;
 mod resume_request;
+mod revoke_shared_page_request;
+mod sbi_base_request;
 mod sbi_request;
 mod sbi_result;
 mod sbi_vm_request;
+mod sealing_request;
+mod secure_timer_request;
 mod share_page_request;
 mod share_page_result;
+mod shared_memcpy_request;
 mod terminate_request;
 mod trap_reason;
+mod vm_info_result;
 
 pub enum ExposeToHypervisor {
     SbiRequest(SbiRequest),
@@ -50,12 +120,18 @@ pub enum ExposeToConfidentialVm {
     SbiResult(SbiResult),
     GuestLoadPageFaultResult(GuestLoadPageFaultResult),
     GuestStorePageFaultResult(GuestStorePageFaultResult),
+    CsrReadResult(CsrReadResult),
+    VmInfoResult(VmInfoResult),
+    CounterResult(CounterResult),
+    AccessFault(AccessFaultRequest),
+    IllegalInstruction(IllegalInstructionRequest),
     Resume(),
 }
 
 #[derive(PartialEq)]
 pub enum PendingRequest {
     SharePage(SharePageRequest),
+    ConvertToPrivate(),
     GuestLoadPageFault(GuestLoadPageFaultRequest),
     GuestStorePageFault(GuestStorePageFaultRequest),
     SbiRequest(),
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::{AccessFaultRequest, ConfidentialVmVirtualAddress};
+
+/// Request to copy the page containing the faulting address from the hypervisor's memory to the confidential memory.
+/// It is created when the confidential VM accesses a page whose import was deferred or that it has not accepted yet.
+#[derive(Clone, Copy)]
+pub struct PageImportRequest {
+    confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
+    // the access fault raised in the confidential VM, because the page is not accessible until it is accepted.
+    access_fault_cause: usize,
+    faulting_address: usize,
+}
+
+impl PageImportRequest {
+    pub fn new(address: usize, access_fault_cause: usize, faulting_address: usize) -> Self {
+        Self {
+            confidential_vm_virtual_address: ConfidentialVmVirtualAddress::new(address),
+            access_fault_cause,
+            faulting_address,
+        }
+    }
+
+    pub fn confidential_vm_virtual_address(&self) -> ConfidentialVmVirtualAddress {
+        self.confidential_vm_virtual_address
+    }
+
+    pub fn access_fault_request(&self) -> AccessFaultRequest {
+        AccessFaultRequest::new(self.access_fault_cause, self.faulting_address)
+    }
+}
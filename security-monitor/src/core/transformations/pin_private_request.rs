@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to pin or unpin its confidential page, so that the page at this address can never
+/// be shared with the hypervisor while it is pinned.
+pub struct PinPrivateRequest {
+    confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
+}
+
+impl PinPrivateRequest {
+    pub fn new(address: usize) -> Self {
+        Self { confidential_vm_virtual_address: ConfidentialVmVirtualAddress::new(address) }
+    }
+
+    pub fn confidential_vm_virtual_address(&self) -> ConfidentialVmVirtualAddress {
+        self.confidential_vm_virtual_address
+    }
+}
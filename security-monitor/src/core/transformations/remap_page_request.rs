@@ -0,0 +1,34 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::mmu::{PageSize, RootPageTable};
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+use crate::error::{Error, Result};
+
+/// Request from the confidential VM to move one of its confidential 4KiB pages to another address of its physical
+/// address space. The content of the page is not copied, so the confidential VM can relocate buffers at no cost.
+pub struct RemapPageRequest {
+    old_address: ConfidentialVmVirtualAddress,
+    new_address: ConfidentialVmVirtualAddress,
+}
+
+impl RemapPageRequest {
+    pub fn new(old_address: usize, new_address: usize) -> Result<Self> {
+        let page_size = PageSize::Size4KiB.in_bytes();
+        assure!(old_address % page_size == 0 && new_address % page_size == 0, Error::AddressNotAligned())?;
+        assure_not!(old_address == new_address, Error::MemoryAccessAuthorization())?;
+        let old_address = ConfidentialVmVirtualAddress::new(old_address);
+        let new_address = ConfidentialVmVirtualAddress::new(new_address);
+        let is_reserved = RootPageTable::is_reserved(old_address) || RootPageTable::is_reserved(new_address);
+        assure_not!(is_reserved, Error::ReservedAddress())?;
+        Ok(Self { old_address, new_address })
+    }
+
+    pub fn old_address(&self) -> ConfidentialVmVirtualAddress {
+        self.old_address
+    }
+
+    pub fn new_address(&self) -> ConfidentialVmVirtualAddress {
+        self.new_address
+    }
+}
@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// Request from the hypervisor to set the largest number of 4KiB pages of the confidential memory that a confidential
+/// VM can reserve at its creation.
+pub struct ReservationLimitRequest {
+    number_of_pages: usize,
+}
+
+impl ReservationLimitRequest {
+    pub fn new(number_of_pages: usize) -> Self {
+        Self { number_of_pages }
+    }
+
+    pub fn number_of_pages(&self) -> usize {
+        self.number_of_pages
+    }
+}
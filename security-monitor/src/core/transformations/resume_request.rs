@@ -7,12 +7,16 @@ use crate::core::control_data::ConfidentialVmId;
 pub struct ResumeRequest {
     confidential_vm_id: ConfidentialVmId,
     confidential_hart_id: usize,
+    pending_interrupts: usize,
+    external_interrupt: usize,
 }
 
 impl ResumeRequest {
-    pub fn new(confidential_vm_id: usize, confidential_hart_id: usize) -> Self {
+    pub fn new(
+        confidential_vm_id: usize, confidential_hart_id: usize, pending_interrupts: usize, external_interrupt: usize,
+    ) -> Self {
         let confidential_vm_id = ConfidentialVmId::new(confidential_vm_id);
-        Self { confidential_vm_id, confidential_hart_id }
+        Self { confidential_vm_id, confidential_hart_id, pending_interrupts, external_interrupt }
     }
 
     pub fn confidential_vm_id(&self) -> ConfidentialVmId {
@@ -22,4 +26,15 @@ impl ResumeRequest {
     pub fn confidential_hart_id(&self) -> usize {
         self.confidential_hart_id
     }
+
+    /// Returns the interrupts that the hypervisor marked pending for the confidential hart, encoded like hvip.
+    pub fn pending_interrupts(&self) -> usize {
+        self.pending_interrupts
+    }
+
+    /// Returns the identity of the external interrupt that the hypervisor routes to the confidential hart's virtual
+    /// interrupt file, 0 if none.
+    pub fn external_interrupt(&self) -> usize {
+        self.external_interrupt
+    }
 }
@@ -0,0 +1,28 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::ConfidentialVmId;
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the hypervisor to take back the page it shared with the confidential VM at the given address.
+pub struct RevokeSharedPageRequest {
+    confidential_vm_id: ConfidentialVmId,
+    confidential_vm_virtual_address: ConfidentialVmVirtualAddress,
+}
+
+impl RevokeSharedPageRequest {
+    pub fn new(confidential_vm_id: usize, address: usize) -> Self {
+        Self {
+            confidential_vm_id: ConfidentialVmId::new(confidential_vm_id),
+            confidential_vm_virtual_address: ConfidentialVmVirtualAddress::new(address),
+        }
+    }
+
+    pub fn confidential_vm_id(&self) -> ConfidentialVmId {
+        self.confidential_vm_id
+    }
+
+    pub fn confidential_vm_virtual_address(&self) -> ConfidentialVmVirtualAddress {
+        self.confidential_vm_virtual_address
+    }
+}
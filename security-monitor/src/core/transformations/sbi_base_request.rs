@@ -0,0 +1,89 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::SbiPolicy;
+use crate::core::transformations::{LegacySbiRequest, SbiResult};
+use crate::ACE_EXT_ID;
+
+// SBI specification v1.0
+const SPEC_VERSION: usize = 1 << 24;
+// "ACE" in ASCII. The same value is returned to all confidential VMs on all hosts.
+const ACE_IMPL_ID: usize = 0x414345;
+// security monitor version 0.2
+const ACE_IMPL_VERSION: usize = 0x2;
+// the confidential VM does not learn the vendor, architecture, and implementation of the physical processor.
+const VIRTUAL_MVENDORID: usize = 0;
+const VIRTUAL_MARCHID: usize = 0;
+const VIRTUAL_MIMPID: usize = 0;
+
+// answers to the calls that do not depend on their arguments, indexed by the function id.
+const PRECOMPUTED_RESULTS: [Option<usize>; 7] = [
+    Some(SPEC_VERSION),
+    Some(ACE_IMPL_ID),
+    Some(ACE_IMPL_VERSION),
+    None,
+    Some(VIRTUAL_MVENDORID),
+    Some(VIRTUAL_MARCHID),
+    Some(VIRTUAL_MIMPID),
+];
+
+/// Call of the confidential VM to the SBI base extension. The security monitor answers it without involving the
+/// hypervisor or the firmware, so the confidential VM observes an identity that does not depend on the host.
+pub struct SbiBaseRequest {
+    function_id: usize,
+    a0: usize,
+    sbi_policy: SbiPolicy,
+}
+
+impl SbiBaseRequest {
+    pub const EXTID: usize = 0x10;
+    pub const GET_SPEC_VERSION_FID: usize = 0;
+    pub const GET_IMPL_ID_FID: usize = 1;
+    pub const GET_IMPL_VERSION_FID: usize = 2;
+    pub const PROBE_EXTENSION_FID: usize = 3;
+    pub const GET_MVENDORID_FID: usize = 4;
+    pub const GET_MARCHID_FID: usize = 5;
+    pub const GET_MIMPID_FID: usize = 6;
+
+    pub fn new(function_id: usize, a0: usize, sbi_policy: SbiPolicy) -> Self {
+        Self { function_id, a0, sbi_policy }
+    }
+
+    pub fn function_id(&self) -> usize {
+        self.function_id
+    }
+
+    /// Id of the extension whose availability the confidential VM probes.
+    pub fn probed_extension_id(&self) -> usize {
+        self.a0
+    }
+
+    /// Returns the answer to the call. Calls to the base extension have no side effects.
+    pub fn result(&self) -> SbiResult {
+        match (Self::precomputed_result(self.function_id), self.function_id) {
+            (Some(result), _) => result,
+            (None, Self::PROBE_EXTENSION_FID) => SbiResult::success(self.is_supported() as usize),
+            (None, _) => SbiResult::not_supported(),
+        }
+    }
+
+    /// Returns true if the confidential VM can use the probed extension. The security monitor implements the base and
+    /// ACE extensions for every confidential VM. Other extensions are available only if the security monitor forwards
+    /// or emulates their calls and the SBI policy of the confidential VM allows them, so the confidential VM does not
+    /// discover an extension whose calls would be denied. Legacy extensions follow the extensions that replace them.
+    fn is_supported(&self) -> bool {
+        match self.probed_extension_id() {
+            Self::EXTID | ACE_EXT_ID => true,
+            extension_id if LegacySbiRequest::is_legacy(extension_id) => {
+                LegacySbiRequest::replacement_extension_id(extension_id).is_some_and(|id| self.sbi_policy.exposes(id))
+            }
+            extension_id => self.sbi_policy.exposes(extension_id),
+        }
+    }
+
+    /// Returns the answer to the call with the given function id if the answer does not depend on the arguments, so
+    /// the security monitor can answer it without building the request.
+    pub fn precomputed_result(function_id: usize) -> Option<SbiResult> {
+        PRECOMPUTED_RESULTS.get(function_id).copied().flatten().map(|value| SbiResult::success(value))
+    }
+}
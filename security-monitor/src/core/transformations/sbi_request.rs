@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::core::control_data::ConfidentialVmId;
 use crate::core::hart::{GpRegister, HartState};
+use crate::core::transformations::ConfidentialVmVirtualAddress;
 
 pub struct SbiRequest {
     extension_id: usize,
@@ -19,6 +20,12 @@ impl SbiRequest {
     const KVM_ACE_EXTID: usize = 0x509999;
     const KVM_ACE_REGISTER_FID: usize = 1;
     const KVM_ACE_PAGE_IN_FID: usize = 2;
+    const KVM_ACE_PAGE_OUT_FID: usize = 3;
+    const DBCN_EXTID: usize = 0x4442434E;
+    const DBCN_WRITE_FID: usize = 0;
+    const DBCN_READ_FID: usize = 1;
+    const TIME_EXTID: usize = 0x54494D45;
+    const TIME_SET_TIMER_FID: usize = 0;
 
     pub fn kvm_ace_register(confidential_vm_id: ConfidentialVmId, confidential_hart_id: usize) -> Self {
         Self::new(
@@ -37,6 +44,12 @@ impl SbiRequest {
         Self::new(Self::KVM_ACE_EXTID, Self::KVM_ACE_PAGE_IN_FID, page_address, 0, 0, 0, 0, 0)
     }
 
+    /// Informs the hypervisor that the confidential VM no longer uses the shared pages starting at the given address,
+    /// so the hypervisor can reclaim the memory backing them.
+    pub fn kvm_ace_page_out(page_address: usize, number_of_pages: usize) -> Self {
+        Self::new(Self::KVM_ACE_EXTID, Self::KVM_ACE_PAGE_OUT_FID, page_address, number_of_pages, 0, 0, 0, 0)
+    }
+
     // only ConfidentialHart or HardwareHart can invoke this function because only they have access to the HartState
     // storing confidential information
     pub fn from_hart_state(hart_state: &HartState) -> Self {
@@ -58,6 +71,29 @@ impl SbiRequest {
         Self { extension_id, function_id, a0, a1, a2, a3, a4, a5 }
     }
 
+    /// Returns the address and size of the buffer that the hypervisor accesses to service a debug console write or
+    /// read. The upper half of the address is passed in a2 and must be zero on RV64.
+    pub fn debug_console_buffer(&self) -> Option<(ConfidentialVmVirtualAddress, usize)> {
+        match (self.extension_id, self.function_id) {
+            (Self::DBCN_EXTID, Self::DBCN_WRITE_FID | Self::DBCN_READ_FID) => {
+                Some((ConfidentialVmVirtualAddress::new(self.a1), self.a0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the deadline requested by an SBI set_timer call. The legacy set_timer call is translated to this call.
+    pub fn timer_deadline(&self) -> Option<usize> {
+        match (self.extension_id, self.function_id) {
+            (Self::TIME_EXTID, Self::TIME_SET_TIMER_FID) => Some(self.a0),
+            _ => None,
+        }
+    }
+
+    pub fn with_timer_deadline(self, deadline: usize) -> Self {
+        Self { a0: deadline, ..self }
+    }
+
     pub fn extension_id(&self) -> usize {
         self.extension_id
     }
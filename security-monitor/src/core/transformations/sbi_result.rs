@@ -8,14 +8,23 @@ use crate::core::hart::{GpRegister, HartState};
 pub struct SbiResult {
     a0: usize,
     a1: usize,
+    // values returned in a2 and the following registers by calls that return more than one value.
+    additional_values: [usize; Self::MAX_ADDITIONAL_VALUES],
+    number_of_additional_values: usize,
     pc_offset: usize,
 }
 
 impl SbiResult {
     const ECALL_INSTRUCTION_LENGTH: usize = 4;
+    const SBI_ERR_NOT_SUPPORTED: isize = -2;
+    const SBI_ERR_INVALID_PARAM: isize = -3;
+    const SBI_ERR_DENIED: isize = -4;
+    const MAX_ADDITIONAL_VALUES: usize = 4;
+    const ADDITIONAL_VALUE_REGISTERS: [GpRegister; Self::MAX_ADDITIONAL_VALUES] =
+        [GpRegister::a2, GpRegister::a3, GpRegister::a4, GpRegister::a5];
 
     pub fn with_mstatus(a0: usize, a1: usize, pc_offset: usize) -> Self {
-        Self { a0, a1, pc_offset }
+        Self::new(a0, a1, pc_offset)
     }
 
     pub fn ecall(hart_state: &HartState) -> Self {
@@ -30,8 +39,33 @@ impl SbiResult {
         Self::new(code, 0, Self::ECALL_INSTRUCTION_LENGTH)
     }
 
+    /// Result of an SBI call that is not implemented, as defined by the SBI specification.
+    pub fn not_supported() -> Self {
+        Self::failure(Self::SBI_ERR_NOT_SUPPORTED as usize)
+    }
+
+    /// Result of an SBI call with an invalid argument, as defined by the SBI specification.
+    pub fn invalid_param() -> Self {
+        Self::failure(Self::SBI_ERR_INVALID_PARAM as usize)
+    }
+
+    /// Result of an SBI call that the caller is not permitted to make, as defined by the SBI specification.
+    pub fn denied() -> Self {
+        Self::failure(Self::SBI_ERR_DENIED as usize)
+    }
+
     fn new(a0: usize, a1: usize, pc_offset: usize) -> Self {
-        Self { a0, a1, pc_offset }
+        Self { a0, a1, additional_values: [0; Self::MAX_ADDITIONAL_VALUES], number_of_additional_values: 0, pc_offset }
+    }
+
+    /// Returns the result that also sets a2 and the following registers to the given values. Only registers a2-a5
+    /// are available, so calls returning more values must write them into memory. The values that do not fit are
+    /// ignored.
+    pub fn with_additional_values(mut self, values: &[usize]) -> Self {
+        self.number_of_additional_values = core::cmp::min(values.len(), Self::MAX_ADDITIONAL_VALUES);
+        self.additional_values[..self.number_of_additional_values]
+            .copy_from_slice(&values[..self.number_of_additional_values]);
+        self
     }
 
     pub fn a0(&self) -> usize {
@@ -42,6 +76,14 @@ impl SbiResult {
         self.a1
     }
 
+    /// Returns the registers following a1 that this result sets, together with their values.
+    pub fn additional_values(&self) -> impl Iterator<Item = (GpRegister, usize)> + '_ {
+        Self::ADDITIONAL_VALUE_REGISTERS
+            .iter()
+            .copied()
+            .zip(self.additional_values[..self.number_of_additional_values].iter().copied())
+    }
+
     pub fn pc_offset(&self) -> usize {
         self.pc_offset
     }
@@ -0,0 +1,43 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to seal or unseal data. The input is read from and the output is written into
+/// buffers located in the confidential VM's memory.
+pub struct SealingRequest {
+    input_address: ConfidentialVmVirtualAddress,
+    input_size: usize,
+    output_address: ConfidentialVmVirtualAddress,
+    output_capacity: usize,
+}
+
+impl SealingRequest {
+    /// Bounds the amount of the confidential VM's memory that the security monitor copies to its heap.
+    pub const MAX_INPUT_SIZE: usize = 64 * 1024;
+
+    pub fn new(input_address: usize, input_size: usize, output_address: usize, output_capacity: usize) -> Self {
+        Self {
+            input_address: ConfidentialVmVirtualAddress::new(input_address),
+            input_size,
+            output_address: ConfidentialVmVirtualAddress::new(output_address),
+            output_capacity,
+        }
+    }
+
+    pub fn input_address(&self) -> ConfidentialVmVirtualAddress {
+        self.input_address
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    pub fn output_address(&self) -> ConfidentialVmVirtualAddress {
+        self.output_address
+    }
+
+    pub fn output_capacity(&self) -> usize {
+        self.output_capacity
+    }
+}
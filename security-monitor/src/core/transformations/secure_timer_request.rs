@@ -0,0 +1,24 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+
+/// Request from the confidential VM to arm one of its secure timers. The deadline is expressed in the units of the
+/// `time` counter, the maximum value cancels the timer.
+pub struct SecureTimerRequest {
+    index: usize,
+    deadline: usize,
+}
+
+impl SecureTimerRequest {
+    pub fn new(index: usize, deadline: usize) -> Self {
+        Self { index, deadline }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn deadline(&self) -> usize {
+        self.deadline
+    }
+}
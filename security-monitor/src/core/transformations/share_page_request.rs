@@ -1,8 +1,8 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::mmu::PageSize;
-use crate::error::Error;
+use crate::core::mmu::{PageSize, RootPageTable};
+use crate::error::{Error, Result};
 
 #[derive(PartialEq)]
 pub struct SharePageRequest {
@@ -11,8 +11,9 @@ pub struct SharePageRequest {
 }
 
 impl SharePageRequest {
-    pub fn new(address: usize) -> Result<Self, Error> {
+    pub fn new(address: usize) -> Result<Self> {
         let confidential_vm_virtual_address = ConfidentialVmVirtualAddress(address);
+        assure_not!(RootPageTable::is_reserved(confidential_vm_virtual_address), Error::ReservedAddress())?;
         Ok(Self { confidential_vm_virtual_address, page_size: PageSize::Size4KiB })
     }
 
@@ -29,7 +30,15 @@ impl SharePageRequest {
 pub struct ConfidentialVmVirtualAddress(usize);
 
 impl ConfidentialVmVirtualAddress {
+    pub fn new(address: usize) -> Self {
+        Self(address)
+    }
+
     pub fn usize(&self) -> usize {
         self.0
     }
+
+    pub fn checked_add(&self, offset: usize) -> Result<Self> {
+        self.0.checked_add(offset).map(|address| Self(address)).ok_or(Error::AddressOverflow())
+    }
 }
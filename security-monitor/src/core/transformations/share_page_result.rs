@@ -5,11 +5,17 @@
 pub struct SharePageResult {
     response_code: usize,
     hypervisor_page_address: usize,
+    is_read_only: bool,
 }
 
 impl SharePageResult {
+    // the hypervisor exports the page without the write permission. The flag is stored in the lowest bit of the page
+    // address, which is always zero because the address is page aligned.
+    const READ_ONLY_FLAG: usize = 1 << 0;
+
     pub fn new(response_code: usize, hypervisor_page_address: usize) -> Self {
-        Self { response_code, hypervisor_page_address }
+        let is_read_only = hypervisor_page_address & Self::READ_ONLY_FLAG != 0;
+        Self { response_code, hypervisor_page_address: hypervisor_page_address & !Self::READ_ONLY_FLAG, is_read_only }
     }
 
     pub fn is_error(&self) -> bool {
@@ -23,4 +29,8 @@ impl SharePageResult {
     pub fn hypervisor_page_address(&self) -> usize {
         self.hypervisor_page_address
     }
+
+    pub fn is_read_only(&self) -> bool {
+        self.is_read_only
+    }
 }
@@ -0,0 +1,37 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::transformations::ConfidentialVmVirtualAddress;
+
+/// Request from the confidential VM to copy data between two of its buffers shared with the hypervisor. The buffers may
+/// overlap.
+pub struct SharedMemcpyRequest {
+    destination_address: ConfidentialVmVirtualAddress,
+    source_address: ConfidentialVmVirtualAddress,
+    size: usize,
+}
+
+impl SharedMemcpyRequest {
+    /// The copy runs while the confidential VM is locked, so its size is bounded.
+    pub const MAX_SIZE: usize = 64 * 1024;
+
+    pub fn new(destination_address: usize, source_address: usize, size: usize) -> Self {
+        Self {
+            destination_address: ConfidentialVmVirtualAddress::new(destination_address),
+            source_address: ConfidentialVmVirtualAddress::new(source_address),
+            size,
+        }
+    }
+
+    pub fn destination_address(&self) -> ConfidentialVmVirtualAddress {
+        self.destination_address
+    }
+
+    pub fn source_address(&self) -> ConfidentialVmVirtualAddress {
+        self.source_address
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
@@ -8,17 +8,25 @@ pub enum TrapReason {
     Interrupt,
     VsEcall(usize, usize),
     HsEcall(usize, usize),
+    GuestInstructionPageFault,
     GuestLoadPageFault,
     GuestStorePageFault,
+    IllegalInstruction,
+    VirtualInstruction,
+    LoadAccessFault,
     StoreAccessFault,
     Unknown(usize, usize),
 }
 
 impl TrapReason {
+    const ILLEGAL_INSTRUCTION: usize = 2;
+    const LOAD_ACCESS_FAULT: usize = 5;
     const STORE_ACCESS_FAULT: usize = 7;
     const HS_ECALL: usize = 9;
     const VS_ECALL: usize = 10;
+    const GUEST_INSTRUCTION_PAGE_FAULT: usize = 20;
     const GUEST_LOAD_PAGE_FAULT: usize = 21;
+    const VIRTUAL_INSTRUCTION: usize = 22;
     const GUEST_STORE_PAGE_FAULT: usize = 23;
 
     pub fn from_hart_state(hart_state: &HartState) -> TrapReason {
@@ -26,11 +34,21 @@ impl TrapReason {
         if mcause.is_interrupt() {
             return TrapReason::Interrupt;
         }
-        match mcause.code() {
+        Self::from_exception(mcause.code(), hart_state)
+    }
+
+    /// Decodes the exception code from mcause. Access faults are raised by the physical memory protection after the
+    /// address was translated, unlike guest page faults raised by the second-stage translation, so they are separated.
+    pub fn from_exception(code: usize, hart_state: &HartState) -> TrapReason {
+        match code {
+            Self::ILLEGAL_INSTRUCTION => TrapReason::IllegalInstruction,
+            Self::LOAD_ACCESS_FAULT => TrapReason::LoadAccessFault,
             Self::STORE_ACCESS_FAULT => TrapReason::StoreAccessFault,
             Self::HS_ECALL => TrapReason::HsEcall(hart_state.gpr(GpRegister::a7), hart_state.gpr(GpRegister::a6)),
             Self::VS_ECALL => TrapReason::VsEcall(hart_state.gpr(GpRegister::a7), hart_state.gpr(GpRegister::a6)),
+            Self::GUEST_INSTRUCTION_PAGE_FAULT => TrapReason::GuestInstructionPageFault,
             Self::GUEST_LOAD_PAGE_FAULT => TrapReason::GuestLoadPageFault,
+            Self::VIRTUAL_INSTRUCTION => TrapReason::VirtualInstruction,
             Self::GUEST_STORE_PAGE_FAULT => TrapReason::GuestStorePageFault,
             _ => TrapReason::Unknown(hart_state.gpr(GpRegister::a7), hart_state.gpr(GpRegister::a6)),
         }
@@ -0,0 +1,36 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::ConfidentialVmId;
+
+/// Identifies the confidential VM and the confidential hart that made the call. The values come from the control data
+/// of the security monitor, so the confidential VM can rely on them instead of the ones the hypervisor provides, e.g.,
+/// in the device tree. It also carries how many times the confidential hart returned to the hypervisor, which lets the
+/// confidential VM check that a call was serviced without exiting to the hypervisor.
+pub struct VmInfoResult {
+    confidential_vm_id: ConfidentialVmId,
+    confidential_hart_id: usize,
+    hart_exits: usize,
+}
+
+impl VmInfoResult {
+    pub fn new(confidential_vm_id: ConfidentialVmId, confidential_hart_id: usize) -> Self {
+        Self { confidential_vm_id, confidential_hart_id, hart_exits: 0 }
+    }
+
+    pub fn with_hart_exits(self, hart_exits: usize) -> Self {
+        Self { hart_exits, ..self }
+    }
+
+    pub fn confidential_vm_id(&self) -> usize {
+        self.confidential_vm_id.usize()
+    }
+
+    pub fn confidential_hart_id(&self) -> usize {
+        self.confidential_hart_id
+    }
+
+    pub fn hart_exits(&self) -> usize {
+        self.hart_exits
+    }
+}
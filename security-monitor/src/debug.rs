@@ -99,13 +99,22 @@ macro_rules! debug {
     ($( $args:expr ),*) => {};
 }
 
-pub(crate) use {_debug, debug};
+pub(crate) use _debug;
+pub(crate) use debug;
 
-#[cfg(feature = "verbose")]
-pub struct Console {}
+#[cfg(feature = "init_self_test")]
+pub mod self_test;
 
-impl Console {
-    pub fn put(c: u8) {
+/// Destination of the debug output of the security monitor.
+pub trait ConsoleSink {
+    fn put(&self, c: u8);
+}
+
+/// Writes the debug output to the console of OpenSBI. This is the default sink.
+pub struct OpenSbiSink;
+
+impl ConsoleSink for OpenSbiSink {
+    fn put(&self, c: u8) {
         let ci8: Option<i8> = c.try_into().ok();
         if let Some(v) = ci8 {
             unsafe {
@@ -115,18 +124,43 @@ impl Console {
     }
 }
 
+/// The sink used by the debug!() macro and by the console writes of confidential VMs. The self-test replaces it to
+/// capture the output.
+static CONSOLE_SINK: spin::RwLock<&'static (dyn ConsoleSink + Sync)> = spin::RwLock::new(&OpenSbiSink);
+
+/// Writes a single byte to the configured sink. Unlike the debug!() macro, it is available in all builds because
+/// confidential VMs write their console output through it.
+pub fn console_put(c: u8) {
+    CONSOLE_SINK.read().put(c);
+}
+
+/// Writes the string to the configured sink, so the self-test reports its results also in builds without the verbose
+/// feature.
+#[cfg(feature = "init_self_test")]
+pub fn console_write(s: &str) {
+    s.bytes().for_each(console_put);
+}
+
+#[cfg(feature = "verbose")]
+pub struct Console {
+    sink: &'static (dyn ConsoleSink + Sync),
+}
+
+#[cfg(feature = "verbose")]
+impl Console {
+    /// Returns the console writing to the configured sink.
+    pub fn new() -> Self {
+        Self { sink: *CONSOLE_SINK.read() }
+    }
+}
+
+#[cfg(feature = "verbose")]
 impl Write for Console {
     fn write_str(&mut self, s: &str) -> Result<(), Error> {
         for i in s.bytes() {
-            Self::put(i);
+            self.sink.put(i);
         }
 
         Ok(())
     }
 }
-
-impl Console {
-    pub fn new() -> Self {
-        Console {}
-    }
-}
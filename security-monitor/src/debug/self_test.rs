@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use super::*;
+use crate::core::transformations::ConsoleWriteByteRequest;
+use crate::error::{Error, Result};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Replaces the sink and returns the previous one.
+fn set_console_sink(sink: &'static (dyn ConsoleSink + Sync)) -> &'static (dyn ConsoleSink + Sync) {
+    core::mem::replace(&mut *CONSOLE_SINK.write(), sink)
+}
+
+/// Checks that a byte written by a confidential VM reaches the configured sink. The byte is taken from the register
+/// passed in the call, so the check does not need a confidential VM.
+pub fn run() -> Result<()> {
+    struct CaptureSink(AtomicUsize);
+    impl ConsoleSink for CaptureSink {
+        fn put(&self, c: u8) {
+            self.0.store(c as usize, Ordering::SeqCst);
+        }
+    }
+    static CAPTURE_SINK: CaptureSink = CaptureSink(AtomicUsize::new(0));
+
+    // the upper bits of a0 must be ignored
+    let request = ConsoleWriteByteRequest::new(0x100 | b'A' as usize);
+    let previous_sink = set_console_sink(&CAPTURE_SINK);
+    console_put(request.byte());
+    set_console_sink(previous_sink);
+    let is_written = CAPTURE_SINK.0.load(Ordering::SeqCst) == b'A' as usize;
+    assure!(is_written, Error::SelfTestFailed("console sink"))
+}
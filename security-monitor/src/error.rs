@@ -4,6 +4,7 @@
 use crate::core::transformations::{ExposeToConfidentialVm, ExposeToHypervisor, SbiResult};
 use core::num::TryFromIntError;
 use fdt_rs::error::DevTreeError;
+use riscv_decode::DecodingError;
 use thiserror_no_std::Error;
 
 pub const CTX_SWITCH_ERROR_MSG: &str =
@@ -21,18 +22,30 @@ pub const NOT_INITIALIZED_MEMORY_TRACKER: &str = "Bug. Could not access memory t
 pub const NOT_INITIALIZED_CONFIDENTIAL_MEMORY: &str =
     "Bug. Could not access confidential memory start/end addresses because they were not initialized";
 
+/// Result of the security monitor's operations. The error type defaults to the security monitor's error, so only
+/// results with other error types need to name it.
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("security monitor initialization error")]
     InitializationError(InitializationErrorType),
     #[error("FDT parsing error")]
     FdtParsing(#[from] DevTreeError),
+    #[error("Invalid FDT header")]
+    InvalidFdt(),
     #[error("Could not convert SBI argument to usize: {0}")]
     SbiArgument(#[from] TryFromIntError),
     #[error("Not enough memory to allocate")]
     OutOfMemory(),
-    #[error("Could not get lock access to a share object")]
-    OptimisticLocking(),
+    #[error("Not enough heap to reserve for the confidential VM")]
+    OutOfHeap(),
+    #[error("Heap reservation of the confidential VM exhausted")]
+    HeapReservationExhausted(),
+    #[error("Timed out acquiring a lock to a shared object")]
+    ContentionTimeout(),
+    #[error("Lock might be held by a hart that panicked")]
+    LockHeldAcrossPanic(),
     #[error("Page table error")]
     PageTableConfiguration(),
     #[error("Page Table is corrupted")]
@@ -41,24 +54,144 @@ pub enum Error {
     ReachedMaximumNumberOfCvms(),
     #[error("Unsupported paging mode")]
     UnsupportedPagingMode(),
+    #[error("Page table is not aligned to its size")]
+    MisalignedPageTableRoot(),
+    #[error("Root page table is not in the non-confidential memory")]
+    PageTableRootNotInNonConfidentialMemory(),
+    #[error("Page table is referenced more than once")]
+    AliasedPageTable(),
+    #[error("Page table entry grants write without read permission")]
+    ReservedPagePermission(),
+    #[error("Invalid secure timer {0}")]
+    InvalidSecureTimer(usize),
     #[error("Memory access not authorized")]
     MemoryAccessAuthorization(),
+    #[error("Confidential VM's memory cannot be accessed at the given address")]
+    GuestAccessFault(),
+    #[error("Confidential VM's first-stage page table does not translate the address {0:x}")]
+    GuestPageFault(usize),
+    #[error("Security monitor halted after a panic")]
+    MonitorHalted(),
     #[error("There is a pending request")]
     PendingRequest(),
+    #[error("Too many pending requests")]
+    PendingRequestQueueFull(),
+    #[error("Result does not match the pending request")]
+    UnexpectedResult(),
+    #[error("Page was not imported or has already been accepted")]
+    PageNotPendingAccept(),
+    #[error("Remapping the shared page would widen its permissions")]
+    SharePermissionWidened(),
+    #[error("Cannot share the page owned by the confidential VM")]
+    CannotShareConfidentialLeaf(),
+    #[error("Released page is outside the tracked memory or already free")]
+    InvalidPageRelease(),
+    #[error("Could not seal the data")]
+    SealingFailed(),
+    #[error("Sealed data was modified or sealed by a different confidential VM")]
+    UnsealingFailed(),
     #[error("Invalid Hart ID")]
     InvalidHartId(),
+    #[error("Too many harts requested for the confidential VM")]
+    TooManyHarts(),
+    #[error("Unknown ESM flags: {0:x}")]
+    InvalidEsmFlags(usize),
+    #[error("Shareable region does not fit in the address space")]
+    InvalidShareableRegion(),
     #[error("Invalid confidential VM ID")]
     InvalidConfidentialVmId(),
+    #[error("The confidential VM is being quiesced")]
+    ConfidentialVmQuiesced(),
+    #[error("Harts of the confidential VM did not stop in time")]
+    QuiesceTimeout(),
     #[error("vHart is running")]
     RunningVHart(),
     #[error("Invalid riscv instruction: {0:x}")]
     InvalidRiscvInstruction(usize),
+    #[error("Decoded form of riscv instruction {0:x} differs in length from its encoding")]
+    InconsistentInstruction(usize),
     #[error("Not supported interrupt")]
     NotSupportedInterrupt(),
+    #[error("Not supported CSR: {0:x}")]
+    NotSupportedCsr(usize),
+    #[error("Invalid hgatp configuration")]
+    InvalidHgatp(),
+    #[error("Hgatp does not point to the root page table in the confidential memory")]
+    HgatpNotPointingToConfidentialRoot(),
+    #[error("Address is reserved for the security monitor")]
+    ReservedAddress(),
+    #[error("Address arithmetic overflow")]
+    AddressOverflow(),
+    #[error("Address is not aligned")]
+    AddressNotAligned(),
+    #[error("Invalid number of pages")]
+    InvalidNumberOfPages(),
+    #[error("Reached the maximum number of shared pages")]
+    ShareLimitExceeded(),
+    #[error("Fewer shared pages are accounted than the confidential VM converts or unmaps")]
+    SharedPagesMiscounted(),
+    #[error("Sharing the page is not permitted by the sharing policy")]
+    ShareOutsidePolicy(),
+    #[error("The VM image does not match the manifest signed by the VM owner")]
+    ManifestVerificationFailed(),
+    #[error("Page is not shared with the hypervisor")]
+    PageNotShared(),
+    #[error("There is no pending revocation of the shared page")]
+    NoPendingRevocation(),
+    #[error("The event log cannot store more events")]
+    EventLogFull(),
+    #[error("The event description is empty or too large")]
+    InvalidEventSize(),
+    #[error("The confidential VM cannot extend the measurement register {0}")]
+    InvalidMeasurementIndex(usize),
+    #[error("The page is pinned as private by the confidential VM")]
+    PagePinnedPrivate(),
+    #[error("Only pages owned by the confidential VM can be pinned")]
+    CannotPinNonConfidentialPage(),
+    #[error("The confidential VM pinned the maximum number of pages")]
+    PinLimitExceeded(),
+    #[error("The confidential VM executes an instruction that is not in its confidential memory")]
+    ExecutingFromSharedMemory(),
+    #[error("Self-test failed: {0}")]
+    SelfTestFailed(&'static str),
+    #[error("Page table entry is not allowed at its level")]
+    InvalidPageTableEntryLevel(),
+    #[error("Invalid monotonic counter index: {0}")]
+    InvalidCounterIndex(usize),
+    #[error("Monotonic counter reached its maximum value")]
+    CounterOverflow(),
+    #[error("Page table entry index {0} is outside of the page table")]
+    PageTableIndexOutOfBounds(usize),
+    #[error("Shared page is backed by the memory of the security monitor")]
+    ShareIntoMonitorMemory(),
+    #[error("Attestation key is not available")]
+    AttestationKeyNotAvailable(),
+    #[error("Content of the deferred page differs from the measured content")]
+    DeferredPageModified(),
+    #[error("Sealing key is not available")]
+    SealingKeyNotAvailable(),
+    #[error("Timebase frequency is not available")]
+    TimebaseFrequencyNotAvailable(),
+    #[error("Reservation of {0} pages exceeds the limit of {1} pages approved by the hypervisor")]
+    ReservationLimitExceeded(usize, usize),
+    #[error("Copy of {0} bytes within the shared memory exceeds the limit of {1} bytes")]
+    SharedMemcpyTooLarge(usize, usize),
+    #[error("Buffer of the hypervisor overlaps the memory of the security monitor")]
+    BufferInMonitorMemory(),
+    #[error("Unknown measurements flags: {0:x}")]
+    InvalidMeasurementsFlags(usize),
     #[error("Invalid call cause: {0}, extid: {1:x}, fid: {2:x}")]
     InvalidCall(usize, usize, usize),
 }
 
+// The decoding error does not implement the error trait, so it cannot be converted with the `#[from]` attribute. It
+// does not carry the instruction either, so the converted error reports the instruction as 0.
+impl From<DecodingError> for Error {
+    fn from(_: DecodingError) -> Self {
+        Self::InvalidRiscvInstruction(0)
+    }
+}
+
 impl Error {
     pub fn into_non_confidential_transformation(self) -> ExposeToHypervisor {
         let error_code = 0x1000;
@@ -85,4 +218,10 @@ pub enum InitializationErrorType {
     InvalidMemoryBoundaries,
     #[error("Invalid assembly address")]
     InvalidAssemblyAddress,
+    #[error("Entropy source not available")]
+    NoEntropySource,
+    #[error("Invalid size of the FDT's sealing secret")]
+    FdtSealingSecret,
+    #[error("FDT's timebase frequency not found")]
+    FdtTimebaseFrequency,
 }
@@ -18,14 +18,13 @@
 extern crate alloc;
 // pub use declarations
 // use declarations
+use build_config::ACE_EXT_ID;
 // pub mod declarations
 // mod declarations
 #[macro_use]
 mod debug;
+mod build_config;
 mod confidential_flow;
 mod core;
 mod error;
 mod non_confidential_flow;
-
-// TODO: replace with an identifier registered in the RISC-V fundation
-const ACE_EXT_ID: usize = 0x510000;
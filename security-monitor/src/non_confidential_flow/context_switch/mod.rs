@@ -64,6 +64,11 @@ core::arch::global_asm!(
     HART_HGATP_OFFSET = const crate::core::control_data::HART_HGATP_OFFSET,
     HART_HEDELEG_OFFSET = const crate::core::control_data::HART_HEDELEG_OFFSET,
     HART_HIDELEG_OFFSET = const crate::core::control_data::HART_HIDELEG_OFFSET,
+    HART_HCOUNTEREN_OFFSET = const crate::core::control_data::HART_HCOUNTEREN_OFFSET,
+    HART_HIE_OFFSET = const crate::core::control_data::HART_HIE_OFFSET,
+    HART_HIP_OFFSET = const crate::core::control_data::HART_HIP_OFFSET,
+    HART_HGEIE_OFFSET = const crate::core::control_data::HART_HGEIE_OFFSET,
+    HART_HENVCFG_OFFSET = const crate::core::control_data::HART_HENVCFG_OFFSET,
     HART_HTINST_OFFSET = const crate::core::control_data::HART_HTINST_OFFSET,
     HART_MEPC_OFFSET = const crate::core::control_data::HART_MEPC_OFFSET,
     HART_MSTATUS_OFFSET = const crate::core::control_data::HART_MSTATUS_OFFSET,
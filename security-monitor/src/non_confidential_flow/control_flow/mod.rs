@@ -2,6 +2,7 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::confidential_flow::ConfidentialFlow;
+use crate::core::bounded_lock::{self, MAX_LOCK_ATTEMPTS};
 use crate::core::control_data::{ControlData, HardwareHart};
 use crate::core::transformations::{ExposeToHypervisor, ResumeRequest};
 use crate::error::Error;
@@ -27,10 +28,17 @@ impl<'a> NonConfidentialFlow<'a> {
     ) -> Result<ConfidentialFlow<'a>, (NonConfidentialFlow<'a>, Error)> {
         let confidential_vm_id = resume_request.confidential_vm_id();
         let confidential_hart_id = resume_request.confidential_hart_id();
-        match ControlData::try_confidential_vm(confidential_vm_id, |mut cvm| {
-            cvm.steal_confidential_hart(confidential_hart_id, self.hardware_hart)
+        // the confidential flow accesses the confidential VM via the handle, without looking it up in the control data.
+        match ControlData::confidential_vm_handle(confidential_vm_id).and_then(|confidential_vm| {
+            bounded_lock::acquire(MAX_LOCK_ATTEMPTS, || confidential_vm.try_lock())?
+                .steal_confidential_hart(confidential_hart_id, self.hardware_hart)?;
+            Ok(confidential_vm)
         }) {
-            Ok(_) => {
+            Ok(confidential_vm) => {
+                self.hardware_hart.cache_confidential_vm(confidential_vm);
+                let confidential_hart = self.hardware_hart.confidential_hart_mut();
+                confidential_hart.inject_interrupts(resume_request.pending_interrupts());
+                confidential_hart.inject_external_interrupt(resume_request.external_interrupt());
                 crate::core::pmp::open_access_to_confidential_memory();
                 Ok(ConfidentialFlow::create(self.hardware_hart))
             }
@@ -40,13 +48,29 @@ impl<'a> NonConfidentialFlow<'a> {
 
     pub fn route(self) -> ! {
         use crate::core::transformations::TrapReason;
-        use crate::non_confidential_flow::handlers::{esm, invalid_call, opensbi, resume, terminate, vm_hypercall};
+        use crate::non_confidential_flow::handlers::{
+            esm, invalid_call, memory_snapshot, opensbi, reservation_limit, resume, revoke_shared_page, terminate,
+            vm_hypercall,
+        };
         use crate::ACE_EXT_ID;
         const ESM_FID: usize = 1000;
         const RESUME_FID: usize = 1010;
         const TERMINATE_FID: usize = 3001;
+        const REVOKE_SHARED_PAGE_FID: usize = 3002;
+        const RESERVATION_LIMIT_FID: usize = 3003;
+        const MEMORY_SNAPSHOT_FID: usize = 3004;
+        #[cfg(feature = "insecure_debug")]
+        const DUMP_HART_STATE_FID: usize = 9000;
+        #[cfg(feature = "memory_audit")]
+        const MEMORY_AUDIT_FID: usize = 9001;
+        const SBI_TIME_EXTID: usize = 0x54494D45;
+        const SBI_SET_TIMER_FID: usize = 0;
 
         match self.hardware_hart.trap_reason() {
+            // after a panic on another physical hart, the state of the security monitor might be inconsistent.
+            trap_reason if crate::core::panic::is_blocked(false, &trap_reason) => {
+                self.exit_to_hypervisor(Error::MonitorHalted().into_non_confidential_transformation())
+            }
             TrapReason::Interrupt => opensbi::handle(self.hardware_hart.opensbi_request(), self),
             TrapReason::VsEcall(ACE_EXT_ID, ESM_FID) => esm::handle(self.hardware_hart.esm_request(), self),
             TrapReason::VsEcall(ACE_EXT_ID, function_id) => invalid_call::handle(self, ACE_EXT_ID, function_id),
@@ -55,9 +79,41 @@ impl<'a> NonConfidentialFlow<'a> {
             TrapReason::HsEcall(ACE_EXT_ID, TERMINATE_FID) => {
                 terminate::handle(self.hardware_hart.terminate_request(), self)
             }
+            TrapReason::HsEcall(ACE_EXT_ID, REVOKE_SHARED_PAGE_FID) => {
+                revoke_shared_page::handle(self.hardware_hart.revoke_shared_page_request(), self)
+            }
+            TrapReason::HsEcall(ACE_EXT_ID, RESERVATION_LIMIT_FID) => {
+                reservation_limit::handle(self.hardware_hart.reservation_limit_request(), self)
+            }
+            TrapReason::HsEcall(ACE_EXT_ID, MEMORY_SNAPSHOT_FID) => {
+                memory_snapshot::handle(self.hardware_hart.memory_snapshot_request(), self)
+            }
+            #[cfg(feature = "insecure_debug")]
+            TrapReason::HsEcall(ACE_EXT_ID, DUMP_HART_STATE_FID) => {
+                use crate::non_confidential_flow::handlers::dump_hart_state;
+                dump_hart_state::handle(self.hardware_hart.dump_hart_state_request(), self)
+            }
+            #[cfg(feature = "memory_audit")]
+            TrapReason::HsEcall(ACE_EXT_ID, MEMORY_AUDIT_FID) => {
+                use crate::non_confidential_flow::handlers::memory_audit;
+                memory_audit::handle(self.hardware_hart.memory_audit_request(), self)
+            }
             TrapReason::HsEcall(ACE_EXT_ID, function_id) => invalid_call::handle(self, ACE_EXT_ID, function_id),
+            TrapReason::HsEcall(SBI_TIME_EXTID, SBI_SET_TIMER_FID) => {
+                self.hardware_hart.record_hypervisor_timer();
+                opensbi::handle(self.hardware_hart.opensbi_request(), self)
+            }
             TrapReason::HsEcall(_, _) => opensbi::handle(self.hardware_hart.opensbi_request(), self),
-            TrapReason::StoreAccessFault => opensbi::handle(self.hardware_hart.opensbi_request(), self),
+            TrapReason::LoadAccessFault | TrapReason::StoreAccessFault => {
+                opensbi::handle(self.hardware_hart.opensbi_request(), self)
+            }
+            // OpenSBI emulates instructions that the hardware does not implement for the hypervisor.
+            TrapReason::IllegalInstruction | TrapReason::VirtualInstruction => {
+                opensbi::handle(self.hardware_hart.opensbi_request(), self)
+            }
+            TrapReason::GuestInstructionPageFault => {
+                panic!("Bug: Incorrect interrupt delegation configuration")
+            }
             TrapReason::GuestLoadPageFault => {
                 panic!("Bug: Incorrect interrupt delegation configuration")
             }
@@ -76,4 +132,8 @@ impl<'a> NonConfidentialFlow<'a> {
     pub fn swap_mscratch(&mut self) {
         self.hardware_hart.swap_mscratch()
     }
+
+    pub fn interrupt_physical_hart(&mut self, physical_hart_id: usize) {
+        self.hardware_hart.interrupt_physical_hart(physical_hart_id)
+    }
 }
@@ -0,0 +1,34 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::ControlData;
+use crate::core::memory_tracker::{MemoryTracker, NonConfidentialMemoryAddress};
+use crate::core::transformations::{DumpHartStateRequest, ExposeToHypervisor, SbiResult};
+use crate::error::{Error, Result};
+use crate::non_confidential_flow::NonConfidentialFlow;
+
+/// Copies the registers of a stopped confidential hart to the hypervisor's buffer, so a developer can inspect a
+/// confidential VM that stopped making progress. The call breaks the confidentiality of the VM and is compiled only
+/// with the `insecure_debug` feature. The hypervisor always learns the number of bytes required to store the registers.
+/// Nothing is written if the buffer is too small.
+pub fn handle(request: DumpHartStateRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
+    let transformation = dump_hart_state(&request)
+        .map(|size| ExposeToHypervisor::SbiResult(SbiResult::success(size)))
+        .unwrap_or_else(|error| error.into_non_confidential_transformation());
+    non_confidential_flow.exit_to_hypervisor(transformation)
+}
+
+fn dump_hart_state(request: &DumpHartStateRequest) -> Result<usize> {
+    let registers = ControlData::try_confidential_vm(request.confidential_vm_id(), |confidential_vm| {
+        confidential_vm.dump_confidential_hart(request.confidential_hart_id())
+    })?;
+    let size = registers.len() * core::mem::size_of::<usize>();
+    if size <= request.buffer_size() {
+        assure!(request.buffer_address() % core::mem::size_of::<usize>() == 0, Error::AddressNotAligned())?;
+        let buffer = NonConfidentialMemoryAddress::new_region(request.buffer_address(), size)?;
+        MemoryTracker::assure_hypervisor_memory(buffer.usize(), size)?;
+        let pointer = buffer.usize() as *mut usize;
+        registers.iter().enumerate().for_each(|(i, value)| unsafe { pointer.add(i).write_volatile(*value) });
+    }
+    Ok(size)
+}
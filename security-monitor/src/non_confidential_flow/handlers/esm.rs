@@ -1,49 +1,81 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
-use crate::core::control_data::{ConfidentialHart, ConfidentialVmId, ControlData};
-use crate::core::memory_tracker::NonConfidentialMemoryAddress;
+use crate::core::attestation::ImageManifest;
+use crate::core::control_data::{ConfidentialHart, ConfidentialVm, ConfidentialVmId, ControlData};
+use crate::core::memory_tracker::{MemoryTracker, NonConfidentialMemoryAddress};
 use crate::core::mmu::{PagingSystem, RootPageTable};
 use crate::core::transformations::{EsmRequest, ExposeToHypervisor, SbiRequest};
-use crate::error::Error;
+use crate::error::{Error, Result};
 use crate::non_confidential_flow::NonConfidentialFlow;
 
 const BOOT_HART_ID: usize = 0;
 
-pub fn handle(esm_request: EsmRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
+pub fn handle(esm_request: Result<EsmRequest>, non_confidential_flow: NonConfidentialFlow) -> ! {
     debug!("Handling enter secure mode (ESM) SM-call");
-    let transformation = match create_confidential_vm(esm_request) {
+    let transformation = match esm_request.and_then(create_confidential_vm) {
         Ok(id) => ExposeToHypervisor::SbiRequest(SbiRequest::kvm_ace_register(id, BOOT_HART_ID)),
         Err(error) => error.into_non_confidential_transformation(),
     };
     non_confidential_flow.exit_to_hypervisor(transformation)
 }
 
-fn create_confidential_vm(esm_request: EsmRequest) -> Result<ConfidentialVmId, Error> {
-    let (hgatp, hart_state) = esm_request.into();
+fn create_confidential_vm(esm_request: EsmRequest) -> Result<ConfidentialVmId> {
+    let (
+        hgatp,
+        hart_state,
+        copy_mode,
+        sharing_policy,
+        sbi_policy,
+        confidential_harts_count,
+        image_manifest_address,
+        fdt_address,
+        reserved_pages,
+    ) = esm_request.into();
+    // reject the request before copying the VM's memory to the confidential memory.
+    assure!(confidential_harts_count <= ConfidentialVm::MAX_HARTS_PER_VM, Error::TooManyHarts())?;
     let paging_mode = hgatp.mode().ok_or_else(|| Error::UnsupportedPagingMode())?;
     let paging_system = PagingSystem::from(&paging_mode).ok_or_else(|| Error::UnsupportedPagingMode())?;
-    let root_page_address = NonConfidentialMemoryAddress::new(hgatp.address())?;
-    // TODO: read number of harts from fdt
-    let confidential_harts_count = 1;
+    let root_page_size = paging_system.size_in_bytes(paging_system.levels());
+    assure!(hgatp.address() % root_page_size == 0, Error::MisalignedPageTableRoot())?;
+    let root_page_address = NonConfidentialMemoryAddress::new_region(hgatp.address(), root_page_size)
+        .map_err(|_| Error::PageTableRootNotInNonConfidentialMemory())?;
 
-    let root_page_table = RootPageTable::copy_from_non_confidential_memory(root_page_address, paging_system)?;
+    // the VM is not admitted if its reservation cannot be met. The reservation is released if the creation fails.
+    let reservation = MemoryTracker::reserve(reserved_pages)?;
+    let root_page_table =
+        RootPageTable::copy_from_non_confidential_memory(root_page_address, paging_system, copy_mode, reservation)?;
+
+    // the image is verified after it has been copied to the confidential memory, so the hypervisor cannot change it.
+    let image_manifest = match image_manifest_address {
+        Some(address) => {
+            let image_manifest = ImageManifest::copy_from_confidential_vm(&root_page_table, address)?;
+            root_page_table.verify_image(&image_manifest)?;
+            Some(image_manifest)
+        }
+        None => None,
+    };
 
     // create virtual processor for this confidential VM
     let confidential_harts = (0..confidential_harts_count)
         .map(|confidential_hart_id| match confidential_hart_id {
             0 => ConfidentialHart::from_vm_hart(confidential_hart_id, &hart_state),
-            _ => ConfidentialHart::from_vm_hart_reset(confidential_hart_id, &hart_state),
+            _ => Ok(ConfidentialHart::from_vm_hart_reset(confidential_hart_id, &hart_state)),
         })
-        .collect();
-
-    // TODO: measure the confidential VM
+        .collect::<Result<_>>()?;
 
     // TODO: perform local attestation (optional)
 
-    let confidential_vm_id = ControlData::store_confidential_vm(confidential_harts, root_page_table)?;
+    let confidential_vm_id = ControlData::store_confidential_vm(
+        confidential_harts,
+        root_page_table,
+        sharing_policy,
+        sbi_policy,
+        image_manifest.as_ref(),
+        fdt_address,
+    )?;
 
-    debug!("Created new confidential VM[id={:?}]", confidential_vm_id);
+    debug!("Created new confidential VM[id={:?}], memory: {:?}", confidential_vm_id, MemoryTracker::snapshot());
 
     Ok(confidential_vm_id)
 }
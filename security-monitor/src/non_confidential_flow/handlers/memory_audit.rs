@@ -0,0 +1,37 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::{AuditRecord, ControlData};
+use crate::core::memory_tracker::{MemoryTracker, NonConfidentialMemoryAddress};
+use crate::core::transformations::{ExposeToHypervisor, MemoryAuditRequest, SbiResult};
+use crate::error::{Error, Result};
+use crate::non_confidential_flow::NonConfidentialFlow;
+
+/// Copies the memory audit records of a confidential VM, ordered from the oldest one, to the hypervisor's buffer. Every
+/// record consists of the access type, the confidential VM's physical address, and the size as 64-bit values. The call
+/// is compiled only with the `memory_audit` feature. The hypervisor always learns the number of bytes required to store
+/// the records. Nothing is written if the buffer is too small.
+pub fn handle(request: MemoryAuditRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
+    let transformation = copy_memory_audit(&request)
+        .map(|size| ExposeToHypervisor::SbiResult(SbiResult::success(size)))
+        .unwrap_or_else(|error| error.into_non_confidential_transformation());
+    non_confidential_flow.exit_to_hypervisor(transformation)
+}
+
+fn copy_memory_audit(request: &MemoryAuditRequest) -> Result<usize> {
+    let records = ControlData::try_confidential_vm(request.confidential_vm_id(), |confidential_vm| {
+        Ok(confidential_vm.memory_audit().records())
+    })?;
+    let size = records.len() * AuditRecord::SIZE;
+    if size <= request.buffer_size() {
+        assure!(request.buffer_address() % core::mem::size_of::<usize>() == 0, Error::AddressNotAligned())?;
+        let buffer = NonConfidentialMemoryAddress::new_region(request.buffer_address(), size)?;
+        MemoryTracker::assure_hypervisor_memory(buffer.usize(), size)?;
+        let pointer = buffer.usize() as *mut usize;
+        records.iter().flat_map(|record| record.values()).enumerate().for_each(|(i, value)| {
+            // Safety: the buffer is aligned, large enough to store all records, and located in the hypervisor's memory.
+            unsafe { pointer.add(i).write_volatile(value) }
+        });
+    }
+    Ok(size)
+}
@@ -0,0 +1,38 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::memory_tracker::{MemoryTracker, NonConfidentialMemoryAddress};
+use crate::core::transformations::{ExposeToHypervisor, MemorySnapshotRequest, SbiResult};
+use crate::error::{Error, Result};
+use crate::non_confidential_flow::NonConfidentialFlow;
+
+/// Copies a snapshot of the confidential memory to the hypervisor's buffer, so the hypervisor can diagnose leaks and
+/// the usage of the confidential memory by confidential VMs. The snapshot consists of the numbers of total, free, and
+/// reserved 4KiB pages, the largest number of contiguous free pages, and pairs of a reservation id and the number of
+/// pages allocated through the reservation, all as 64-bit values. It contains only page counts, never addresses. The
+/// hypervisor always learns the number of bytes required to store the snapshot. Nothing is written if the buffer is
+/// too small.
+pub fn handle(request: MemorySnapshotRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
+    let transformation = copy_memory_snapshot(&request)
+        .map(|size| ExposeToHypervisor::SbiResult(SbiResult::success(size)))
+        .unwrap_or_else(|error| error.into_non_confidential_transformation());
+    non_confidential_flow.exit_to_hypervisor(transformation)
+}
+
+fn copy_memory_snapshot(request: &MemorySnapshotRequest) -> Result<usize> {
+    let snapshot = MemoryTracker::snapshot()?;
+    let values = [snapshot.total_pages, snapshot.free_pages, snapshot.reserved_pages, snapshot.largest_free_run];
+    let usage = snapshot.usage.iter().flat_map(|(id, pages)| [*id, *pages]);
+    let size = (values.len() + 2 * snapshot.usage.len()) * core::mem::size_of::<usize>();
+    if size <= request.buffer_size() {
+        assure!(request.buffer_address() % core::mem::size_of::<usize>() == 0, Error::AddressNotAligned())?;
+        let buffer = NonConfidentialMemoryAddress::new_region(request.buffer_address(), size)?;
+        MemoryTracker::assure_hypervisor_memory(buffer.usize(), size)?;
+        let pointer = buffer.usize() as *mut usize;
+        values.into_iter().chain(usage).enumerate().for_each(|(i, value)| {
+            // Safety: the buffer is aligned, large enough for the snapshot, and located in the hypervisor's memory.
+            unsafe { pointer.add(i).write_volatile(value) }
+        });
+    }
+    Ok(size)
+}
@@ -1,9 +1,16 @@
 // SPDX-FileCopyrightText: 2023 IBM Corporation
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
+#[cfg(feature = "insecure_debug")]
+pub mod dump_hart_state;
 pub mod esm;
 pub mod invalid_call;
+#[cfg(feature = "memory_audit")]
+pub mod memory_audit;
+pub mod memory_snapshot;
 pub mod opensbi;
+pub mod reservation_limit;
 pub mod resume;
+pub mod revoke_shared_page;
 pub mod terminate;
 pub mod vm_hypercall;
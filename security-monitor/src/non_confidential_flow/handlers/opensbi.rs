@@ -2,7 +2,7 @@
 // SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
 // SPDX-License-Identifier: Apache-2.0
 use crate::core::transformations::{ExposeToHypervisor, OpensbiRequest, SbiResult};
-use crate::error::Error;
+use crate::error::{Error, Result};
 use crate::non_confidential_flow::NonConfidentialFlow;
 use opensbi_sys::sbi_trap_regs;
 
@@ -26,7 +26,7 @@ pub fn handle(mut opensbi_request: OpensbiRequest, mut non_confidential_flow: No
     non_confidential_flow.exit_to_hypervisor(transformation)
 }
 
-fn create_transformation(regs: &opensbi_sys::sbi_trap_regs, previous_mepc: usize) -> Result<ExposeToHypervisor, Error> {
+fn create_transformation(regs: &opensbi_sys::sbi_trap_regs, previous_mepc: usize) -> Result<ExposeToHypervisor> {
     let a0 = regs.a0.try_into().map_err(|e| Error::SbiArgument(e))?;
     let a1 = regs.a1.try_into().map_err(|e| Error::SbiArgument(e))?;
     let pc_offset = regs.mepc as usize - previous_mepc;
@@ -0,0 +1,13 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::memory_tracker::MemoryTracker;
+use crate::core::transformations::{ExposeToHypervisor, ReservationLimitRequest, SbiResult};
+use crate::non_confidential_flow::NonConfidentialFlow;
+
+/// The hypervisor approves how much confidential memory a single confidential VM can reserve, so a confidential VM
+/// cannot exhaust the confidential memory with its reservation. The limit applies to confidential VMs created later.
+pub fn handle(request: ReservationLimitRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
+    MemoryTracker::set_reservation_limit(request.number_of_pages());
+    non_confidential_flow.exit_to_hypervisor(ExposeToHypervisor::SbiResult(SbiResult::success(0)))
+}
@@ -0,0 +1,20 @@
+// SPDX-FileCopyrightText: 2023 IBM Corporation
+// SPDX-FileContributor: Wojciech Ozga <woz@zurich.ibm.com>, IBM Research - Zurich
+// SPDX-License-Identifier: Apache-2.0
+use crate::core::control_data::ControlData;
+use crate::core::transformations::{ExposeToHypervisor, RevokeSharedPageRequest, SbiResult};
+use crate::non_confidential_flow::NonConfidentialFlow;
+
+/// The hypervisor's request to take back the page it shared with the confidential VM. Returns 1 if the page has been
+/// unmapped and no confidential hart can still access it, so the hypervisor can reuse it, or 0 if the revocation awaits
+/// the acknowledgement of the confidential VM or confidential harts running when the page was unmapped have not
+/// returned to the hypervisor yet. The hypervisor should repeat the request later.
+pub fn handle(request: RevokeSharedPageRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
+    let now = riscv::register::time::read();
+    let transformation = ControlData::try_confidential_vm_mut(request.confidential_vm_id(), |mut confidential_vm| {
+        confidential_vm.revoke_shared_page(request.confidential_vm_virtual_address(), now)
+    })
+    .map(|is_unmapped| ExposeToHypervisor::SbiResult(SbiResult::success(is_unmapped as usize)))
+    .unwrap_or_else(|error| error.into_non_confidential_transformation());
+    non_confidential_flow.exit_to_hypervisor(transformation)
+}
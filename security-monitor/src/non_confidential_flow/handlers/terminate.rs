@@ -3,16 +3,27 @@
 // SPDX-License-Identifier: Apache-2.0
 use crate::core::control_data::{ConfidentialVmId, ControlData};
 use crate::core::transformations::{ExposeToHypervisor, SbiResult, TerminateRequest};
-use crate::error::Error;
+use crate::error::{Error, Result};
 use crate::non_confidential_flow::NonConfidentialFlow;
 
 /// The hypervisor command to terminate the confidential VM and remove it from the memory.
-pub fn handle(terminate_request: TerminateRequest, non_confidential_flow: NonConfidentialFlow) -> ! {
-    let transformation = ControlData::try_write(|control_data| {
-        let confidential_vm_id = terminate_request.confidential_vm_id();
-        ensure_confidential_vm_can_be_terminated(control_data, confidential_vm_id)?;
-        debug!("Terminating the confidential VM[id={:?}]", confidential_vm_id);
-        control_data.remove_confidential_vm(confidential_vm_id)
+pub fn handle(terminate_request: TerminateRequest, mut non_confidential_flow: NonConfidentialFlow) -> ! {
+    let confidential_vm_id = terminate_request.confidential_vm_id();
+    // the confidential harts must stop before the write lock is taken, because they need the control data to stop.
+    let transformation = ControlData::quiesce_confidential_vm(confidential_vm_id, |physical_hart_id| {
+        non_confidential_flow.interrupt_physical_hart(physical_hart_id)
+    })
+    .and_then(|_| {
+        ControlData::try_write(|control_data| {
+            ensure_confidential_vm_can_be_terminated(control_data, confidential_vm_id)?;
+            debug!("Terminating the confidential VM[id={:?}]", confidential_vm_id);
+            control_data.remove_confidential_vm(confidential_vm_id)
+        })
+        .or_else(|error| {
+            // the confidential VM has not been removed, so the hypervisor can run it again.
+            ControlData::unquiesce_confidential_vm(confidential_vm_id)?;
+            Err(error)
+        })
     })
     .and_then(|_| Ok(ExposeToHypervisor::SbiResult(SbiResult::success(0))))
     .unwrap_or_else(|error| error.into_non_confidential_transformation());
@@ -22,8 +33,8 @@ pub fn handle(terminate_request: TerminateRequest, non_confidential_flow: NonCon
 
 fn ensure_confidential_vm_can_be_terminated(
     control_data: &ControlData, confidential_vm_id: ConfidentialVmId,
-) -> Result<(), Error> {
-    let cvm = control_data.confidential_vm(confidential_vm_id).ok_or(Error::InvalidConfidentialVmId())?;
+) -> Result<()> {
+    let cvm = control_data.confidential_vm(confidential_vm_id)?;
     assure_not!(cvm.is_running(), Error::RunningVHart())?;
     Ok(())
 }